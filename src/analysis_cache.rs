@@ -0,0 +1,227 @@
+use anyhow::{Context, Result};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+use crate::file_analysis::{DirectoryMap, FileInfo, is_skipped, process_file_info};
+
+/// Cache of the last analysis, stored in the start directory
+pub const ANALYSIS_CACHE_FILE_NAME: &str = ".claude-analysis";
+/// Format of the analysis cache; a cache of another version is rebuilt.
+/// Version 3 fingerprints directories by their entries instead of their files
+const ANALYSIS_CACHE_VERSION: u32 = 3;
+
+/// A directory as last analyzed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DirRecord {
+    /// Hash of the directory's modification time and the names of its
+    /// entries, which changes whenever a file is added, removed or replaced
+    stamp: u64,
+    /// Hash of `stamp` and the `tree` hashes of its subdirectories, so it
+    /// changes when anything below the directory does
+    tree: u64,
+    files: Vec<FileInfo>,
+}
+
+/// Per-directory fingerprints and file details from the last analysis
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnalysisCache {
+    #[serde(default)]
+    version: u32,
+    dirs: HashMap<PathBuf, DirRecord>,
+}
+
+impl Default for AnalysisCache {
+    fn default() -> Self {
+        Self {
+            version: ANALYSIS_CACHE_VERSION,
+            dirs: HashMap::new(),
+        }
+    }
+}
+
+/// Load the analysis cache if it exists
+pub fn load_analysis_cache(directory: &Path) -> Result<AnalysisCache> {
+    let cache_path = directory.join(ANALYSIS_CACHE_FILE_NAME);
+    if !cache_path.exists() {
+        return Ok(AnalysisCache::default());
+    }
+
+    let content = fs::read_to_string(&cache_path).context("Failed to read analysis cache")?;
+    match serde_json::from_str::<AnalysisCache>(&content) {
+        Ok(cache) if cache.version == ANALYSIS_CACHE_VERSION => Ok(cache),
+        Ok(_) => {
+            info!("Analysis cache is from another version; analyzing afresh");
+            Ok(AnalysisCache::default())
+        }
+        Err(e) => {
+            warn!("Invalid analysis cache format. Creating a new one: {}", e);
+            Ok(AnalysisCache::default())
+        }
+    }
+}
+
+/// Save the analysis cache
+pub fn save_analysis_cache(directory: &Path, cache: &AnalysisCache) -> Result<()> {
+    let content = serde_json::to_string(cache).context("Failed to serialize analysis cache")?;
+    fs::write(directory.join(ANALYSIS_CACHE_FILE_NAME), content).context("Failed to write analysis cache")
+}
+
+/// Whether a directory entry is a directory, following symlinks. Only
+/// symlinks are stat'ed; the type of anything else comes from the listing
+fn is_dir_entry(entry: &fs::DirEntry) -> bool {
+    match entry.file_type() {
+        Ok(file_type) if file_type.is_symlink() => entry.path().is_dir(),
+        Ok(file_type) => file_type.is_dir(),
+        Err(_) => false,
+    }
+}
+
+/// Analyze a directory like `analyze_directory`, reusing the cached details
+/// of directories whose entries haven't changed. Each directory is
+/// fingerprinted from its own modification time and entry names, so only
+/// directories are stat'ed: the files of an unchanged directory are neither
+/// stat'ed nor checked for binary content. Saving a file through a rename,
+/// as editors do, changes its directory; a file rewritten in place doesn't,
+/// and is only picked up by analyzing without the cache. Fingerprints are
+/// Merkle-style, each covering a directory's stamp and its subdirectories'
+/// fingerprints, so an unchanged tree is recognized from its root alone. The
+/// cache is replaced with the new analysis.
+pub fn analyze_directory_cached(
+    directory: &Path,
+    exclude_patterns: &[String],
+    cache: &mut AnalysisCache,
+) -> Result<DirectoryMap> {
+    let mut result = DirectoryMap::new();
+    let mut files: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    let mut stamps: HashMap<PathBuf, u64> = HashMap::new();
+
+    for entry in WalkDir::new(directory)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|e| !is_skipped(e.path(), directory, e.file_type().is_dir(), exclude_patterns))
+    {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                error!("Error accessing entry: {}", e);
+                continue;
+            }
+        };
+        let path = entry.path();
+
+        if entry.file_type().is_dir() {
+            let info = result.entry(path.to_path_buf()).or_default();
+            let mut names = Vec::new();
+            if let Ok(entries) = fs::read_dir(path) {
+                for child in entries.filter_map(Result::ok) {
+                    let child_path = child.path();
+                    if is_dir_entry(&child) && !is_skipped(&child_path, directory, true, exclude_patterns) {
+                        info.subdirs.push(child_path);
+                    }
+                    names.push(child.file_name());
+                }
+            }
+            names.sort();
+            let modified = entry
+                .metadata()
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_nanos());
+            let mut hasher = DefaultHasher::new();
+            (modified, names).hash(&mut hasher);
+            stamps.insert(path.to_path_buf(), hasher.finish());
+        } else if entry.file_type().is_file() {
+            let Some(parent) = path.parent() else {
+                error!("Failed to get parent dir for: {}", path.display());
+                continue;
+            };
+            // The files of an unchanged directory come from the cache
+            let unchanged = cache
+                .dirs
+                .get(parent)
+                .is_some_and(|record| stamps.get(parent) == Some(&record.stamp));
+            if unchanged {
+                continue;
+            }
+            files.entry(parent.to_path_buf()).or_default().push(path.to_path_buf());
+        }
+    }
+
+    // Fingerprint directories deepest first, so subdirectories are done
+    // before their parents
+    let mut dirs: Vec<PathBuf> = result.keys().cloned().collect();
+    dirs.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
+    let mut trees: HashMap<PathBuf, u64> = HashMap::new();
+    for dir in &dirs {
+        let mut hasher = DefaultHasher::new();
+        stamps.get(dir).hash(&mut hasher);
+        let mut subdirs = result[dir].subdirs.clone();
+        subdirs.sort();
+        for sub in &subdirs {
+            (sub, trees.get(sub)).hash(&mut hasher);
+        }
+        trees.insert(dir.clone(), hasher.finish());
+    }
+
+    let unchanged = cache
+        .dirs
+        .get(directory)
+        .is_some_and(|record| trees.get(directory) == Some(&record.tree));
+    if unchanged {
+        info!("Nothing changed since the last analysis");
+    }
+
+    let mut reused = 0;
+    let mut records = HashMap::new();
+    for (dir, info) in result.iter_mut() {
+        let stamp = stamps.get(dir).copied().unwrap_or_default();
+        let dir_files = match cache.dirs.remove(dir) {
+            Some(record) if record.stamp == stamp => {
+                reused += 1;
+                record.files
+            }
+            _ => files
+                .remove(dir)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|path| match process_file_info(&path) {
+                    Ok(file_info) => Some(file_info),
+                    Err(e) => {
+                        error!("Error processing file {}: {}", path.display(), e);
+                        None
+                    }
+                })
+                .collect(),
+        };
+
+        for file in &dir_files {
+            info.total_files += 1;
+            if file.binary {
+                info.binary_files += 1;
+            } else {
+                info.tokens += file.tokens;
+            }
+        }
+        info.files = dir_files.clone();
+        records.insert(
+            dir.clone(),
+            DirRecord {
+                stamp,
+                tree: trees[dir],
+                files: dir_files,
+            },
+        );
+    }
+    info!("Reused the analysis of {} of {} directories", reused, result.len());
+
+    cache.dirs = records;
+    Ok(result)
+}
@@ -0,0 +1,275 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::context_files::{CONTINUED_PREFIX, PART_HEADER_PREFIX, shown_path};
+use crate::formats::{block_end, part_format};
+use crate::processing::{Action, folded_lines, render_file_block, render_read_block};
+use crate::summary_cache::{SummaryCache, load_summary_cache};
+use crate::tokenizer::count_tokens;
+
+/// Start of the anchor line emitted before each file block
+pub const ANCHOR_PREFIX: &str = "===== ANCHOR: ";
+const ANCHOR_SUFFIX: &str = " =====";
+
+/// Number of hex digits kept in short hashes
+pub const SHORT_HASH_LEN: usize = 12;
+
+/// Number of hex digits in a citation ID after its `F`
+const CITATION_LEN: usize = 6;
+
+/// Header line recording the directory a context file was built from
+const SOURCE_DIR_PREFIX: &str = "Source directory: ";
+
+/// A parsed anchor line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anchor {
+    /// Content hash of the file when the block was written
+    pub hash: String,
+    /// Action that produced the block
+    pub action: Action,
+    /// Path relative to the source directory
+    pub path: PathBuf,
+    /// Citation ID, absent from anchors written before they were added
+    pub citation: Option<String>,
+}
+
+/// Short ID for citing a file, e.g. `F3A9C1`. It comes from the path alone,
+/// so a file keeps its ID across runs, updates and merged bundles.
+pub fn citation_id(rel_path: &Path) -> String {
+    let path = rel_path.to_string_lossy().replace('\\', "/");
+    let mut hasher = sha1_smol::Sha1::new();
+    hasher.update(path.as_bytes());
+    format!("F{}", &hasher.digest().to_string()[..CITATION_LEN].to_uppercase())
+}
+
+/// Format the anchor line for a file block, led by the file's citation ID
+pub fn format_anchor(rel_path: &Path, hash: &str, action: &Action) -> String {
+    format!(
+        "{}[{}] {} {} {}{}",
+        ANCHOR_PREFIX,
+        citation_id(rel_path),
+        hash,
+        action.spec(),
+        rel_path.display(),
+        ANCHOR_SUFFIX
+    )
+}
+
+/// A line without the HTML comment markdown and XML parts keep markers in
+pub fn uncommented(line: &str) -> &str {
+    line.strip_prefix("<!-- ")
+        .and_then(|l| l.strip_suffix(" -->"))
+        .unwrap_or(line)
+}
+
+/// Parse an anchor line; the path comes last so it may contain spaces
+pub fn parse_anchor(line: &str) -> Option<Anchor> {
+    let rest = uncommented(line).strip_prefix(ANCHOR_PREFIX)?.strip_suffix(ANCHOR_SUFFIX)?;
+    let (citation, rest) = match rest.strip_prefix('[').and_then(|r| r.split_once("] ")) {
+        Some((citation, rest)) => (Some(citation.to_string()), rest),
+        None => (None, rest),
+    };
+    let (hash, rest) = rest.split_once(' ')?;
+    let (action, path) = rest.split_once(' ')?;
+
+    Some(Anchor {
+        hash: hash.to_string(),
+        action: Action::parse_str(action)?,
+        path: PathBuf::from(path),
+        citation,
+    })
+}
+
+/// Git blob hash of some content, so `git hash-object <file>` can verify it
+pub fn git_blob_hash(bytes: &[u8]) -> String {
+    let mut hasher = sha1_smol::Sha1::new();
+    hasher.update(format!("blob {}\0", bytes.len()).as_bytes());
+    hasher.update(bytes);
+    hasher.digest().to_string()
+}
+
+/// Short content hash of a file as recorded in its anchor and FILE START marker
+pub fn file_hash(path: &Path) -> String {
+    match fs::read(path) {
+        Ok(bytes) => git_blob_hash(&bytes)[..SHORT_HASH_LEN].to_string(),
+        Err(_) => "unreadable".to_string(),
+    }
+}
+
+/// Source directory recorded in a context part's header, which markdown
+/// parts list as a bullet
+pub fn source_dir(content: &str) -> Option<PathBuf> {
+    content
+        .lines()
+        .find_map(|line| line.trim_start_matches("- ").strip_prefix(SOURCE_DIR_PREFIX))
+        .map(PathBuf::from)
+}
+
+/// Whether a line ends the file collection of a part
+pub fn is_collection_end(line: &str) -> bool {
+    uncommented(line).starts_with("===== END OF FILE COLLECTION")
+}
+
+/// Whether a line ends the file block before it
+pub fn is_block_end(line: &str) -> bool {
+    let line = uncommented(line);
+    line.starts_with(ANCHOR_PREFIX) || line.starts_with("## DIRECTORY: ") || is_collection_end(line)
+}
+
+/// Name under which part headers, directory blocks and footers are counted
+pub const HEADERS_BREAKDOWN_KEY: &str = "headers";
+
+/// Estimated tokens of a context part by the action that produced each file
+/// block; everything outside file blocks counts under `headers`
+pub fn action_breakdown(content: &str) -> BTreeMap<&'static str, usize> {
+    let mut tokens: BTreeMap<&'static str, usize> = BTreeMap::new();
+    let mut current = HEADERS_BREAKDOWN_KEY;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_end_matches(['\n', '\r']);
+        if is_block_end(trimmed) {
+            current = parse_anchor(trimmed).map_or(HEADERS_BREAKDOWN_KEY, |a| a.action.as_str());
+        }
+        *tokens.entry(current).or_default() += count_tokens(line);
+    }
+    tokens
+}
+
+/// Re-render the blocks of one context file whose source files changed.
+/// Returns the number of blocks replaced.
+pub fn update_context_file(path: &Path, summary_cache: Option<&SummaryCache>) -> Result<usize> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read context file {}", path.display()))?;
+
+    let Some(source_dir) = source_dir(&content) else {
+        warn!("No source directory recorded in {}", path.display());
+        return Ok(0);
+    };
+    // Blocks are rewritten in the format the part was written in
+    let format = part_format(&content);
+
+    // Byte offset of each line start, with the line itself
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        lines.push((offset, line.trim_end_matches(['\n', '\r'])));
+        offset += line.len();
+    }
+
+    let texts: Vec<&str> = lines.iter().map(|(_, line)| *line).collect();
+
+    let mut replacements = Vec::new();
+    for (idx, (start, line)) in lines.iter().enumerate() {
+        let Some(anchor) = parse_anchor(line) else {
+            continue;
+        };
+
+        let file_path = source_dir.join(&anchor.path);
+        if !file_path.is_file() {
+            warn!("Skipping block for missing file: {}", anchor.path.display());
+            continue;
+        }
+
+        let hash = file_hash(&file_path);
+        if hash == anchor.hash {
+            continue;
+        }
+
+        // The block runs until the next anchor, directory header or footer;
+        // the blank lines separating it from that belong to the separator
+        let end = lines
+            .get(block_end(&texts, idx, format))
+            .map_or(content.len(), |(s, _)| *s);
+        let end = start + content[*start..end].trim_end_matches('\n').len();
+
+        // The rest of a split block is in the next part, which this can't rewrite
+        if content[*start..end].contains(CONTINUED_PREFIX) {
+            warn!(
+                "Skipping block split across parts: {} (regenerate the context to refresh it)",
+                anchor.path.display()
+            );
+            continue;
+        }
+
+        // Paths stay absolute in blocks that were written with them
+        let absolute = lines[idx + 1..]
+            .iter()
+            .take(4)
+            .any(|(_, next)| next.contains(&file_path.display().to_string()));
+        let rel_path = &shown_path(&file_path, &source_dir, absolute);
+        // Folded blocks stay folded at the threshold they were built with
+        let body = match folded_lines(&content[*start..end]) {
+            Some(min_lines) if anchor.action == Action::Read => {
+                render_read_block(&file_path, rel_path, Some(min_lines), &[])
+            }
+            _ => render_file_block(&file_path, rel_path, &anchor.action, summary_cache)?,
+        };
+        let block = format!(
+            "{}\n{}",
+            format_anchor(&anchor.path, &hash, &anchor.action),
+            body
+        );
+        let block = format.formatter().file(&block, None);
+
+        info!("Updating block for: {}", anchor.path.display());
+        replacements.push((*start, end, block.trim_matches('\n').to_string()));
+    }
+
+    if replacements.is_empty() {
+        return Ok(0);
+    }
+
+    let mut updated = content.clone();
+    for (start, end, block) in replacements.iter().rev() {
+        updated.replace_range(*start..*end, block);
+    }
+    fs::write(path, updated).context("Failed to write context file")?;
+
+    Ok(replacements.len())
+}
+
+/// The context part files in a directory, in part order
+pub fn context_part_paths(context_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut parts: Vec<(usize, PathBuf)> = fs::read_dir(context_dir)
+        .with_context(|| format!("Failed to read context directory {}", context_dir.display()))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|p| p.is_file())
+        .filter_map(|p| part_number(&p).map(|num| (num, p)))
+        .collect();
+    parts.sort();
+    Ok(parts.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Part number from a context part's header line, or None if the file is not a part
+fn part_number(path: &Path) -> Option<usize> {
+    let mut first_line = String::new();
+    BufReader::new(fs::File::open(path).ok()?)
+        .read_line(&mut first_line)
+        .ok()?;
+    let rest = first_line.strip_prefix(PART_HEADER_PREFIX)?;
+    rest.split_whitespace().next()?.parse().ok()
+}
+
+/// Update every context file in a directory, returning each file with the
+/// number of blocks replaced in it
+pub fn update_context_dir(context_dir: &Path) -> Result<Vec<(PathBuf, usize)>> {
+    let parts = context_part_paths(context_dir)?;
+    let mut results = Vec::new();
+    for file in parts {
+        // Summaries are cached next to the source directory of each part
+        let summary_cache = fs::read_to_string(&file)
+            .ok()
+            .and_then(|c| source_dir(&c))
+            .and_then(|dir| load_summary_cache(&dir).ok());
+
+        let count = update_context_file(&file, summary_cache.as_ref())?;
+        results.push((file, count));
+    }
+
+    Ok(results)
+}
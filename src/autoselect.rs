@@ -0,0 +1,99 @@
+use log::info;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::budget::{BudgetPlan, Ladder, action_cost};
+use crate::coverage::CoverageMap;
+use crate::file_analysis::{DirectoryMap, file_tokens};
+use crate::manifest::Manifest;
+use crate::recency::RecencyMap;
+use crate::suggestions::SuggestionMap;
+
+/// Build a selection without prompting. Priority files come first, then files
+/// ordered by executed lines (when coverage is given) and recency. Each file
+/// takes its suggested action (read by default) while it fits the budget, and
+/// otherwise steps down the ladder; downgrades are noted in the entry's options.
+/// Category ceilings in `plan` apply the same way. With coverage, only files that ran (and priority files)
+/// are considered.
+#[allow(clippy::too_many_arguments)]
+pub fn auto_select(
+    start_dir: &Path,
+    dir_info: &DirectoryMap,
+    suggestions: &SuggestionMap,
+    priority: &[PathBuf],
+    recency: &RecencyMap,
+    coverage: Option<&CoverageMap>,
+    budget: usize,
+    plan: &BudgetPlan,
+    ladder: &Ladder,
+) -> Manifest {
+    let hits = |path: &PathBuf| coverage.and_then(|c| c.get(path)).map_or(0, |c| c.lines_hit);
+
+    let mut candidates: Vec<(&PathBuf, usize)> = dir_info
+        .values()
+        .flat_map(|info| info.files.iter())
+        .filter(|f| !f.binary && !priority.contains(&f.path))
+        .filter(|f| coverage.is_none() || hits(&f.path) > 0)
+        .map(|f| (&f.path, f.tokens))
+        .collect();
+    candidates.sort_by_key(|(path, _)| {
+        (
+            Reverse(hits(path)),
+            Reverse(recency.get(*path).copied().unwrap_or(0)),
+            (*path).clone(),
+        )
+    });
+
+    let ordered = priority
+        .iter()
+        .filter_map(|p| file_tokens(dir_info, p).map(|t| (p, t)))
+        .chain(candidates);
+
+    let mut manifest = Manifest::new();
+    let mut plan = plan.clone();
+    let mut used = 0;
+    for (path, tokens) in ordered {
+        let suggested = suggestions.get(path).map_or("read", |s| s.action.as_str());
+        if suggested == "exclude" || suggested == "enter" {
+            continue;
+        }
+
+        let fits = |action: &str| {
+            let cost = action_cost(tokens, action);
+            used + cost <= budget && plan.fits(path, cost)
+        };
+        let action = ladder.downgrade(suggested, fits);
+
+        let mut options = BTreeMap::new();
+        if action != suggested {
+            options.insert("requested".to_string(), suggested.to_string());
+            options.insert("reason".to_string(), "over budget".to_string());
+        }
+        if action != "exclude" {
+            used += action_cost(tokens, action);
+            plan.record(path, action_cost(tokens, action));
+        }
+        manifest.record_with_options(path, start_dir, action, options);
+    }
+
+    info!(
+        "Auto-selected {} files (~{} of {} tokens)",
+        manifest.entries.iter().filter(|e| e.action != "exclude").count(),
+        used,
+        budget
+    );
+    manifest
+}
+
+/// Text files ordered by estimated tokens, largest first
+pub fn largest_files(dir_info: &DirectoryMap) -> Vec<(PathBuf, usize)> {
+    let mut files: Vec<(PathBuf, usize)> = dir_info
+        .values()
+        .flat_map(|info| info.files.iter())
+        .filter(|f| !f.binary)
+        .map(|f| (f.path.clone(), f.tokens))
+        .collect();
+    files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    files
+}
@@ -0,0 +1,353 @@
+use anyhow::{Result, bail};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::file_analysis::DirectoryMap;
+use crate::ui::{print_field, print_title};
+use crate::vendored::vendor_dir;
+
+/// Class of files of no recognized type
+pub const UNKNOWN_CLASS: &str = "unknown";
+/// Directory names whose contents count as tests
+const TEST_DIRS: [&str; 5] = ["test", "tests", "spec", "specs", "__tests__"];
+/// Directory names whose contents count as docs
+const DOC_DIRS: [&str; 2] = ["doc", "docs"];
+/// Extensions of documentation files
+const DOC_EXTENSIONS: [&str; 6] = ["md", "markdown", "rst", "txt", "adoc", "org"];
+/// Extensions of configuration and data files
+const CONFIG_EXTENSIONS: [&str; 11] = [
+    "toml", "yaml", "yml", "json", "ini", "cfg", "conf", "lock", "env", "xml", "properties",
+];
+/// Extensionless configuration files
+const CONFIG_FILE_NAMES: [&str; 3] = ["Makefile", "Dockerfile", "Procfile"];
+
+/// Extensions recognized as source code; other files that would count as
+/// source are of unknown type
+const SOURCE_EXTENSIONS: [&str; 36] = [
+    "rs", "py", "pyi", "js", "jsx", "ts", "tsx", "mjs", "cjs", "go", "c", "h", "cpp", "hpp", "cc", "java", "kt",
+    "scala", "cs", "rb", "php", "swift", "sh", "bash", "zsh", "lua", "ex", "exs", "sql", "css", "scss", "sass",
+    "less", "html", "vue", "svelte",
+];
+
+/// Kind of file a share of the budget can be set aside for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Source,
+    Docs,
+    Tests,
+    Config,
+    /// Third-party code under a vendor directory
+    Vendored,
+}
+
+impl Category {
+    pub fn parse_str(s: &str) -> Option<Self> {
+        match s {
+            "source" | "src" => Some(Category::Source),
+            "docs" | "doc" => Some(Category::Docs),
+            "tests" | "test" => Some(Category::Tests),
+            "config" => Some(Category::Config),
+            "vendored" => Some(Category::Vendored),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Source => "source",
+            Category::Docs => "docs",
+            Category::Tests => "tests",
+            Category::Config => "config",
+            Category::Vendored => "vendored",
+        }
+    }
+
+    /// Category of a file from its path relative to the start directory
+    pub fn of(rel_path: &Path) -> Self {
+        let name = rel_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let stem = rel_path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let ext = rel_path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        let in_dir = |dirs: &[&str]| {
+            rel_path
+                .parent()
+                .into_iter()
+                .flat_map(|p| p.components())
+                .any(|c| dirs.contains(&c.as_os_str().to_str().unwrap_or("")))
+        };
+
+        if vendor_dir(rel_path).is_some() {
+            Category::Vendored
+        } else if in_dir(&TEST_DIRS)
+            || stem.starts_with("test_")
+            || stem.ends_with("_test")
+            || stem.ends_with(".test")
+            || stem.ends_with(".spec")
+        {
+            Category::Tests
+        } else if in_dir(&DOC_DIRS) || DOC_EXTENSIONS.contains(&ext.as_str()) {
+            Category::Docs
+        } else if CONFIG_EXTENSIONS.contains(&ext.as_str())
+            || CONFIG_FILE_NAMES.contains(&name)
+            || name.starts_with('.')
+        {
+            Category::Config
+        } else {
+            Category::Source
+        }
+    }
+}
+
+/// Class of a file for configured default actions: its category, or
+/// `unknown` for a file counted as source without a recognized source extension
+pub fn file_class(rel_path: &Path) -> &'static str {
+    let ext = rel_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    match Category::of(rel_path) {
+        Category::Source if !SOURCE_EXTENSIONS.contains(&ext.as_str()) => UNKNOWN_CLASS,
+        category => category.name(),
+    }
+}
+
+/// Estimated tokens a file adds to the context under an action
+pub fn action_cost(tokens: usize, action: &str) -> usize {
+    match action {
+        "read" => tokens,
+        "summarize" => tokens / 4,
+        "sample" => tokens.min(500),
+        "skeleton" => tokens / 3,
+        "docs" => tokens / 6,
+        "stats" => 0,
+        _ => tokens,
+    }
+}
+
+/// What including a node would do to the run: the share of the remaining
+/// budget it takes and the parts it adds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadImpact {
+    /// Percent of the tokens left under the budget, rounded up
+    pub percent_of_remaining: usize,
+    /// Parts started to hold it
+    pub new_parts: usize,
+}
+
+impl ReadImpact {
+    /// The impact of adding `cost` tokens with `used` of `max_tokens` spent
+    /// and `part_tokens` of `part_limit` in the current part
+    pub fn new(cost: usize, used: usize, max_tokens: usize, part_tokens: usize, part_limit: usize) -> Self {
+        let remaining = max_tokens.saturating_sub(used).max(1);
+        let filled = part_tokens + cost;
+        ReadImpact {
+            percent_of_remaining: (cost * 100).div_ceil(remaining),
+            new_parts: if filled > part_limit { filled.div_ceil(part_limit.max(1)) - 1 } else { 0 },
+        }
+    }
+
+    /// E.g. `this will consume 31% of remaining budget and force 2 new parts`
+    pub fn describe(&self) -> String {
+        let mut text = format!("this will consume {}% of remaining budget", self.percent_of_remaining);
+        match self.new_parts {
+            0 => {}
+            1 => text.push_str(" and force a new part"),
+            n => text.push_str(&format!(" and force {} new parts", n)),
+        }
+        text
+    }
+}
+
+/// Default order in which actions are downgraded when over budget
+pub const DEFAULT_LADDER: &str = "read,sample,summarize,stats,exclude";
+/// Actions from most to least costly, for ladders that list them in another order
+const COST_ORDER: [&str; 7] = ["read", "skeleton", "docs", "sample", "summarize", "stats", "exclude"];
+
+/// Actions tried in turn for a node that doesn't fit the remaining budget
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ladder {
+    rungs: Vec<String>,
+}
+
+impl Default for Ladder {
+    fn default() -> Self {
+        Self::parse(DEFAULT_LADDER).expect("default ladder is valid")
+    }
+}
+
+impl Ladder {
+    /// Parse a comma-separated list of actions (`truncate` is taken as `sample`)
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut rungs = Vec::new();
+        for rung in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let action = match rung {
+                "truncate" => "sample",
+                "read" | "skeleton" | "docs" | "sample" | "summarize" | "stats" | "exclude" => rung,
+                _ => bail!(
+                    "Invalid ladder action '{}' (expected read, skeleton, docs, truncate/sample, summarize, stats or exclude)",
+                    rung
+                ),
+            };
+            rungs.push(action.to_string());
+        }
+        if rungs.is_empty() {
+            bail!("The over-budget ladder needs at least one action");
+        }
+        Ok(Self { rungs })
+    }
+
+    /// The requested action if it fits, otherwise the first rung after it that
+    /// does and that is cheaper in COST_ORDER. Excluding always fits,
+    /// and is the last resort.
+    pub fn downgrade<'a>(&'a self, action: &'a str, fits: impl Fn(&str) -> bool) -> &'a str {
+        if action == "exclude" || fits(action) {
+            return action;
+        }
+        let rank = |a: &str| COST_ORDER.iter().position(|r| *r == a);
+        let below = self
+            .rungs
+            .iter()
+            .position(|r| r == action)
+            .map_or(0, |idx| idx + 1);
+        self.rungs[below..]
+            .iter()
+            .map(String::as_str)
+            .filter(|r| rank(r) > rank(action))
+            .find(|r| *r == "exclude" || fits(r))
+            .unwrap_or("exclude")
+    }
+}
+
+/// A share of the token budget for one or more categories
+#[derive(Debug, Clone)]
+pub struct Bucket {
+    pub name: String,
+    pub categories: Vec<Category>,
+    pub limit: usize,
+    pub used: usize,
+}
+
+/// Per-category token ceilings, e.g. `source=70,docs=20,tests+config=10`.
+/// Categories without a bucket have no ceiling of their own.
+#[derive(Debug, Clone, Default)]
+pub struct BudgetPlan {
+    start_dir: PathBuf,
+    pub buckets: Vec<Bucket>,
+}
+
+impl BudgetPlan {
+    /// Parse comma-separated `category=percent` shares of `max_tokens`;
+    /// categories joined with `+` share one bucket
+    pub fn parse(spec: &str, max_tokens: usize, start_dir: &Path) -> Result<Self> {
+        let mut buckets = Vec::new();
+        let mut total = 0;
+
+        for share in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let Some((names, percent)) = share.split_once('=') else {
+                bail!("Invalid budget share '{}' (expected category=percent)", share);
+            };
+            let Ok(percent) = percent.trim().trim_end_matches('%').parse::<usize>() else {
+                bail!("Invalid percentage in budget share '{}'", share);
+            };
+
+            let mut categories = Vec::new();
+            for name in names.split('+').map(str::trim) {
+                match Category::parse_str(name) {
+                    Some(category) => categories.push(category),
+                    None => bail!(
+                        "Unknown budget category '{}' (expected source, docs, tests, config or vendored)",
+                        name
+                    ),
+                }
+            }
+
+            total += percent;
+            buckets.push(Bucket {
+                name: names.trim().to_string(),
+                categories,
+                limit: max_tokens * percent / 100,
+                used: 0,
+            });
+        }
+
+        if total > 100 {
+            bail!("Budget shares add up to {}%, more than 100%", total);
+        }
+
+        Ok(Self {
+            start_dir: start_dir.to_path_buf(),
+            buckets,
+        })
+    }
+
+    /// Index of the bucket a file counts against
+    fn bucket_for(&self, path: &Path) -> Option<usize> {
+        let category = Category::of(path.strip_prefix(&self.start_dir).unwrap_or(path));
+        self.buckets
+            .iter()
+            .position(|b| b.categories.contains(&category))
+    }
+
+    /// The first bucket that adding these file costs would overflow
+    pub fn overflow(&self, costs: &[(PathBuf, usize)]) -> Option<&Bucket> {
+        let mut added = vec![0; self.buckets.len()];
+        for (path, cost) in costs {
+            if let Some(idx) = self.bucket_for(path) {
+                added[idx] += cost;
+            }
+        }
+        self.buckets
+            .iter()
+            .zip(added)
+            .find(|(bucket, added)| *added > 0 && bucket.used + added > bucket.limit)
+            .map(|(bucket, _)| bucket)
+    }
+
+    /// Whether a file's cost fits in its bucket
+    pub fn fits(&self, path: &Path, cost: usize) -> bool {
+        self.overflow(&[(path.to_path_buf(), cost)]).is_none()
+    }
+
+    /// Count a file's cost against its bucket
+    pub fn record(&mut self, path: &Path, cost: usize) {
+        if let Some(idx) = self.bucket_for(path) {
+            self.buckets[idx].used += cost;
+        }
+    }
+
+    /// Print how much of each bucket was used
+    pub fn print_utilization(&self) {
+        if self.buckets.is_empty() {
+            return;
+        }
+
+        print_title("Budget utilization:");
+        for bucket in &self.buckets {
+            let percent = (bucket.used * 100).checked_div(bucket.limit).unwrap_or(0);
+            print_field(
+                &bucket.name,
+                format!("{} of {} tokens ({}%)", bucket.used, bucket.limit, percent),
+            );
+        }
+    }
+}
+
+/// Cost of each not-yet-included text file under a node for an action
+pub fn node_costs(
+    path: &Path,
+    dir_info: &DirectoryMap,
+    action: &str,
+    included: &HashSet<PathBuf>,
+) -> Vec<(PathBuf, usize)> {
+    dir_info
+        .iter()
+        .filter(|(dir, _)| dir.starts_with(path) || path.parent() == Some(dir.as_path()))
+        .flat_map(|(_, info)| info.files.iter())
+        .filter(|f| !f.binary && f.path.starts_with(path) && !included.contains(&f.path))
+        .map(|f| (f.path.clone(), action_cost(f.tokens, action)))
+        .collect()
+}
@@ -0,0 +1,193 @@
+use anyhow::{Context, Result, bail};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::anchors::{context_part_paths, source_dir};
+use crate::index::INDEX_FILE_NAME;
+
+/// Extension of packed bundles
+pub const BUNDLE_EXTENSION: &str = ".ctx.tar.zst";
+/// Name of the listing with integrity hashes inside a bundle
+pub const BUNDLE_LISTING: &str = "bundle.json";
+/// Version of the bundle listing written by this build
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Files by name, in bundle order
+pub type BundleFiles = Vec<(String, Vec<u8>)>;
+
+/// A file in a bundle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleFile {
+    pub name: String,
+    pub size: u64,
+    /// SHA-1 of the content, hex
+    pub sha1: String,
+}
+
+/// What a bundle holds, stored first in it as `bundle.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleListing {
+    pub version: u32,
+    /// When the bundle was packed (RFC 3339, UTC)
+    pub created: String,
+    /// Directory the parts were built from, as recorded in them
+    pub source_dir: Option<PathBuf>,
+    pub files: Vec<BundleFile>,
+}
+
+fn sha1_hex(bytes: &[u8]) -> String {
+    let mut hasher = sha1_smol::Sha1::new();
+    hasher.update(bytes);
+    hasher.digest().to_string()
+}
+
+/// The files of a context directory to pack: its parts in order, its
+/// index.json and, if given, a manifest
+pub fn bundle_contents(context_dir: &Path, manifest: Option<&Path>) -> Result<BundleFiles> {
+    let mut paths = context_part_paths(context_dir)?;
+    if paths.is_empty() {
+        bail!("No context parts in {}", context_dir.display());
+    }
+    let index = context_dir.join(INDEX_FILE_NAME);
+    if index.exists() {
+        paths.push(index);
+    }
+    paths.extend(manifest.map(Path::to_path_buf));
+
+    let mut contents = Vec::new();
+    for path in paths {
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .with_context(|| format!("Unusable file name: {}", path.display()))?
+            .to_string();
+        let bytes = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        contents.push((name, bytes));
+    }
+    Ok(contents)
+}
+
+/// Pack files into a zstd-compressed tar at `out`, with a listing of their
+/// hashes in front
+pub fn pack(files: &[(String, Vec<u8>)], out: &Path) -> Result<BundleListing> {
+    let source_dir = files
+        .first()
+        .and_then(|(_, bytes)| source_dir(&String::from_utf8_lossy(bytes)));
+    let listing = BundleListing {
+        version: BUNDLE_VERSION,
+        created: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        source_dir,
+        files: files
+            .iter()
+            .map(|(name, bytes)| BundleFile {
+                name: name.clone(),
+                size: bytes.len() as u64,
+                sha1: sha1_hex(bytes),
+            })
+            .collect(),
+    };
+
+    let file = File::create(out).with_context(|| format!("Failed to create {}", out.display()))?;
+    let encoder = zstd::Encoder::new(file, 0).context("Failed to start compression")?;
+    let mut tar = tar::Builder::new(encoder);
+    let listing_json = serde_json::to_vec_pretty(&listing).context("Failed to serialize bundle listing")?;
+    append_tar_entry(&mut tar, BUNDLE_LISTING, &listing_json)?;
+    for (name, bytes) in files {
+        append_tar_entry(&mut tar, name, bytes)?;
+    }
+    let encoder = tar.into_inner().context("Failed to finish the bundle's archive")?;
+    encoder
+        .finish()
+        .and_then(|mut file| file.flush())
+        .with_context(|| format!("Failed to write {}", out.display()))?;
+    Ok(listing)
+}
+
+/// Append one file to a tar archive
+fn append_tar_entry<W: Write>(tar: &mut tar::Builder<W>, name: &str, bytes: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_ustar();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(Utc::now().timestamp().max(0) as u64);
+    tar.append_data(&mut header, name, bytes)
+        .with_context(|| format!("Failed to add {} to the bundle", name))
+}
+
+/// The regular files in a zstd-compressed tar, in order
+fn read_tar(path: &Path) -> Result<BundleFiles> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let decoder = zstd::Decoder::new(file).context("Failed to start decompression")?;
+    let mut archive = tar::Archive::new(decoder);
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes)?;
+        files.push((name, bytes));
+    }
+    Ok(files)
+}
+
+/// Read a bundle, checking every file against the listing's hashes
+pub fn read_bundle(path: &Path) -> Result<(BundleListing, BundleFiles)> {
+    let mut files = read_tar(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if files.first().is_none_or(|(name, _)| name != BUNDLE_LISTING) {
+        bail!("{} is not a context bundle (no {})", path.display(), BUNDLE_LISTING);
+    }
+    let (_, listing_json) = files.remove(0);
+    let listing: BundleListing = serde_json::from_slice(&listing_json).context("Invalid bundle listing")?;
+    if listing.version > BUNDLE_VERSION {
+        bail!(
+            "Bundle version {} is newer than this build supports ({})",
+            listing.version,
+            BUNDLE_VERSION
+        );
+    }
+
+    if files.len() != listing.files.len() {
+        bail!("Bundle lists {} files but holds {}", listing.files.len(), files.len());
+    }
+    for ((name, bytes), expected) in files.iter().zip(&listing.files) {
+        if *name != expected.name || sha1_hex(bytes) != expected.sha1 {
+            bail!("Integrity check failed for {} in {}", expected.name, path.display());
+        }
+    }
+    Ok((listing, files))
+}
+
+/// Verify a bundle and write its files into a directory. Existing files
+/// are only replaced with `overwrite`.
+pub fn unpack(path: &Path, out_dir: &Path, overwrite: bool) -> Result<BundleListing> {
+    let (listing, files) = read_bundle(path)?;
+    for (name, _) in &files {
+        // Names are bare file names; anything else could escape the directory
+        if Path::new(name).file_name().and_then(|n| n.to_str()) != Some(name.as_str()) {
+            bail!("Refusing to unpack {} from {}", name, path.display());
+        }
+        if !overwrite && out_dir.join(name).exists() {
+            bail!("{} already exists; use --force to replace it", out_dir.join(name).display());
+        }
+    }
+
+    fs::create_dir_all(out_dir).with_context(|| format!("Failed to create {}", out_dir.display()))?;
+    for (name, bytes) in &files {
+        fs::write(out_dir.join(name), bytes).with_context(|| format!("Failed to write {}", name))?;
+    }
+    Ok(listing)
+}
+
+/// Directory a bundle unpacks to by default: its name without the extension
+pub fn default_unpack_dir(path: &Path) -> PathBuf {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("bundle");
+    match name.strip_suffix(BUNDLE_EXTENSION) {
+        Some(stem) if !stem.is_empty() => path.with_file_name(stem),
+        _ => path.with_file_name(format!("{}-unpacked", name)),
+    }
+}
@@ -83,3 +83,31 @@ pub fn should_prompt_for_directory(
 
     false
 }
+
+/// Directories below `directory` cached as entered, parents before their
+/// children; a directory that isn't entered hides the ones below it
+pub fn cached_enters(
+    directory: &Path,
+    dir_info: &HashMap<PathBuf, crate::file_analysis::DirInfo>,
+    cache: &CacheMap,
+) -> Vec<PathBuf> {
+    let mut entered = Vec::new();
+    let mut pending: Vec<&PathBuf> = dir_info
+        .get(directory)
+        .map(|info| info.subdirs.iter().collect())
+        .unwrap_or_default();
+    pending.sort();
+    pending.reverse();
+    while let Some(dir) = pending.pop() {
+        if cache.get(dir).map(String::as_str) != Some("enter") {
+            continue;
+        }
+        entered.push(dir.clone());
+        if let Some(info) = dir_info.get(dir) {
+            let mut subdirs: Vec<&PathBuf> = info.subdirs.iter().collect();
+            subdirs.sort();
+            pending.extend(subdirs.into_iter().rev());
+        }
+    }
+    entered
+}
@@ -0,0 +1,182 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// A `#[cfg(...)]` predicate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cfg {
+    /// A bare option such as `unix` or `test`
+    Option(String),
+    /// A key and value such as `feature = "serde"` or `target_os = "linux"`
+    KeyValue(String, String),
+    All(Vec<Cfg>),
+    Any(Vec<Cfg>),
+    Not(Box<Cfg>),
+}
+
+impl fmt::Display for Cfg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |items: &[Cfg]| items.iter().map(Cfg::to_string).collect::<Vec<_>>().join(", ");
+        match self {
+            Cfg::Option(name) => write!(f, "{}", name),
+            Cfg::KeyValue(key, value) => write!(f, "{} = \"{}\"", key, value),
+            Cfg::All(items) => write!(f, "all({})", list(items)),
+            Cfg::Any(items) => write!(f, "any({})", list(items)),
+            Cfg::Not(item) => write!(f, "not({})", item),
+        }
+    }
+}
+
+impl Cfg {
+    /// Parse the predicate inside `cfg(...)`
+    pub fn parse(text: &str) -> Option<Cfg> {
+        let tokens = tokenize(text)?;
+        let mut pos = 0;
+        let cfg = parse_predicate(&tokens, &mut pos)?;
+        (pos == tokens.len()).then_some(cfg)
+    }
+
+    /// Every one of some predicates, flattened
+    pub fn all(cfgs: Vec<Cfg>) -> Cfg {
+        let mut items = Vec::new();
+        for cfg in cfgs {
+            match cfg {
+                Cfg::All(inner) => items.extend(inner),
+                cfg if !items.contains(&cfg) => items.push(cfg),
+                _ => {}
+            }
+        }
+        match items.len() {
+            1 => items.remove(0),
+            _ => Cfg::All(items),
+        }
+    }
+
+    /// Whether the predicate holds with some features enabled. Only features
+    /// are known; None when the answer depends on anything else (target,
+    /// `test`, other `--cfg` flags).
+    pub fn eval(&self, features: &BTreeSet<String>) -> Option<bool> {
+        match self {
+            Cfg::KeyValue(key, value) if key == "feature" => Some(features.contains(value)),
+            Cfg::Option(_) | Cfg::KeyValue(..) => None,
+            Cfg::All(items) => {
+                let results: Vec<Option<bool>> = items.iter().map(|c| c.eval(features)).collect();
+                if results.contains(&Some(false)) {
+                    Some(false)
+                } else {
+                    results.iter().all(|r| *r == Some(true)).then_some(true)
+                }
+            }
+            Cfg::Any(items) => {
+                let results: Vec<Option<bool>> = items.iter().map(|c| c.eval(features)).collect();
+                if results.contains(&Some(true)) {
+                    Some(true)
+                } else {
+                    results.iter().all(|r| *r == Some(false)).then_some(false)
+                }
+            }
+            Cfg::Not(item) => item.eval(features).map(|b| !b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Open,
+    Close,
+    Comma,
+    Equals,
+}
+
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | ',' | '=' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    ',' => Token::Comma,
+                    _ => Token::Equals,
+                });
+            }
+            '"' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => value.push(chars.next()?),
+                        c => value.push(c),
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut ident = String::new();
+                while let Some(&c) = chars.peek()
+                    && (c.is_alphanumeric() || c == '_')
+                {
+                    ident.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            _ => return None,
+        }
+    }
+    Some(tokens)
+}
+
+fn parse_predicate(tokens: &[Token], pos: &mut usize) -> Option<Cfg> {
+    let Token::Ident(name) = tokens.get(*pos)? else {
+        return None;
+    };
+    *pos += 1;
+    match tokens.get(*pos) {
+        Some(Token::Equals) => {
+            let Token::Str(value) = tokens.get(*pos + 1)? else {
+                return None;
+            };
+            *pos += 2;
+            Some(Cfg::KeyValue(name.clone(), value.clone()))
+        }
+        Some(Token::Open) if matches!(name.as_str(), "all" | "any" | "not") => {
+            *pos += 1;
+            let mut items = Vec::new();
+            while tokens.get(*pos) != Some(&Token::Close) {
+                items.push(parse_predicate(tokens, pos)?);
+                if tokens.get(*pos) == Some(&Token::Comma) {
+                    *pos += 1;
+                }
+            }
+            *pos += 1;
+            match name.as_str() {
+                "all" => Some(Cfg::All(items)),
+                "any" => Some(Cfg::Any(items)),
+                _ if items.len() == 1 => Some(Cfg::Not(Box::new(items.remove(0)))),
+                _ => None,
+            }
+        }
+        _ => Some(Cfg::Option(name.clone())),
+    }
+}
+
+/// The predicate of a `#[cfg(...)]` line, and whether it is the inner
+/// `#![cfg(...)]` form gating the whole file or module
+pub fn cfg_attribute(line: &str) -> Option<(Cfg, bool)> {
+    let trimmed = line.trim();
+    let (rest, inner) = match trimmed.strip_prefix("#![") {
+        Some(rest) => (rest, true),
+        None => (trimmed.strip_prefix("#[")?, false),
+    };
+    let predicate = rest.trim_start().strip_prefix("cfg")?.trim_start().strip_prefix('(')?;
+    let predicate = predicate.trim_end().strip_suffix(']')?.trim_end().strip_suffix(')')?;
+    Some((Cfg::parse(predicate)?, inner))
+}
@@ -0,0 +1,120 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::path::Path;
+
+use crate::file_analysis::DirectoryMap;
+use crate::suggestions::{Suggestion, SuggestionMap};
+
+/// File names (without extension) of changelogs and release notes
+pub const CHANGELOG_NAMES: [&str; 7] = [
+    "changelog",
+    "changes",
+    "history",
+    "news",
+    "releases",
+    "release-notes",
+    "release_notes",
+];
+/// Releases included in full when a changelog is sampled
+pub const RECENT_RELEASES: usize = 3;
+/// Default action for changelogs: the recent releases plus a list of the rest
+pub const CHANGELOG_ACTION: &str = "sample";
+
+/// Most older releases listed before the rest are counted
+const MAX_LISTED_RELEASES: usize = 30;
+
+lazy_static! {
+    static ref RELEASE: Regex =
+        Regex::new(r"(?i)\bv?\d+\.\d+(?:\.\d+)?(?:[-+][\w.]+)?\b|\bunreleased\b").unwrap();
+    static ref MD_HEADING: Regex = Regex::new(r"^(#{1,4})\s+(.+)$").unwrap();
+    static ref SETEXT_UNDERLINE: Regex = Regex::new(r"^(=+|-+)\s*$").unwrap();
+    static ref ENTRY: Regex = Regex::new(r"^\s*[-*+]\s").unwrap();
+}
+
+/// Whether a file is a changelog or release notes, by name
+pub fn is_changelog(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .is_some_and(|stem| CHANGELOG_NAMES.contains(&stem.to_lowercase().as_str()))
+}
+
+/// Line index and title of each release heading. Only the shallowest heading
+/// level naming versions counts, so sections inside a release are skipped.
+fn release_headings(lines: &[&str]) -> Vec<(usize, String)> {
+    let mut headings: Vec<(usize, usize, String)> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(c) = MD_HEADING.captures(line) {
+            if RELEASE.is_match(&c[2]) {
+                headings.push((c[1].len(), i, c[2].trim().to_string()));
+            }
+        } else if RELEASE.is_match(line)
+            && !line.starts_with(char::is_whitespace)
+            && let Some(next) = lines.get(i + 1)
+            && let Some(c) = SETEXT_UNDERLINE.captures(next)
+        {
+            let level = if c[1].starts_with('=') { 1 } else { 2 };
+            headings.push((level, i, line.trim().to_string()));
+        }
+    }
+
+    let Some(level) = headings.iter().map(|(level, _, _)| *level).min() else {
+        return Vec::new();
+    };
+    headings
+        .into_iter()
+        .filter(|(l, _, _)| *l == level)
+        .map(|(_, i, title)| (i, title))
+        .collect()
+}
+
+/// The most recent `count` releases of a changelog in full, followed by the
+/// older releases' versions and entry counts. None when no release headings
+/// are found.
+pub fn recent_releases(content: &str, count: usize) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let headings = release_headings(&lines);
+    if headings.is_empty() {
+        return None;
+    }
+
+    let shown = count.min(headings.len());
+    let end = headings.get(shown).map_or(lines.len(), |(i, _)| *i);
+    let mut output = format!("Most recent {} of {} releases:\n", shown, headings.len());
+    for line in &lines[headings[0].0..end] {
+        output.push_str(line);
+        output.push('\n');
+    }
+
+    let older = &headings[shown..];
+    if !older.is_empty() {
+        output.push_str(&format!("\nOlder releases ({}):\n", older.len()));
+        for (idx, (start, title)) in older.iter().enumerate().take(MAX_LISTED_RELEASES) {
+            let stop = older.get(idx + 1).map_or(lines.len(), |(i, _)| *i);
+            let entries = lines[*start..stop].iter().filter(|l| ENTRY.is_match(l)).count();
+            let version = RELEASE.find(title).map_or(title.as_str(), |m| m.as_str());
+            output.push_str(&format!("  {} ({} entries)\n", version, entries));
+        }
+        if older.len() > MAX_LISTED_RELEASES {
+            output.push_str(&format!("  +{} more\n", older.len() - MAX_LISTED_RELEASES));
+        }
+    }
+    Some(output)
+}
+
+/// Suggest sampling changelogs, which keeps only their recent releases
+pub fn changelog_suggestions(dir_info: &DirectoryMap) -> SuggestionMap {
+    dir_info
+        .values()
+        .flat_map(|info| info.files.iter())
+        .filter(|f| !f.binary && is_changelog(&f.path))
+        .map(|f| {
+            (
+                f.path.clone(),
+                Suggestion {
+                    action: CHANGELOG_ACTION.to_string(),
+                    reason: format!("changelog (latest {} releases)", RECENT_RELEASES),
+                },
+            )
+        })
+        .collect()
+}
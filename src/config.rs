@@ -0,0 +1,391 @@
+use anyhow::{Context, Result, bail};
+use globset::{GlobBuilder, GlobMatcher};
+use log::{info, warn};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::budget::{Category, UNKNOWN_CLASS, file_class};
+use crate::file_analysis::DirectoryMap;
+use crate::processing::Action;
+use crate::suggestions::{Suggestion, SuggestionMap, propagate_to_directories};
+use crate::summarizer::SummarizerKind;
+
+/// Directory under the user's config directory holding the config file
+pub const CONFIG_DIR_NAME: &str = "llm-context-loader";
+/// Name of the user-wide config file
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+/// Name of the project config file, in the start directory
+pub const PROJECT_CONFIG_FILE_NAME: &str = "llm-context.toml";
+/// Environment variable overriding the configured token limit
+pub const MAX_TOKENS_VAR: &str = "LLM_CONTEXT_MAX_TOKENS";
+
+/// Actions by file class for --non-interactive runs, where no class action
+/// is configured: code is read, everything else reduced to stats
+pub const NON_INTERACTIVE_CLASS_ACTIONS: [(&str, &str); 6] = [
+    ("source", "read"),
+    ("tests", "read"),
+    ("docs", "stats"),
+    ("config", "stats"),
+    ("vendored", "stats"),
+    (UNKNOWN_CLASS, "stats"),
+];
+
+/// A default action for files matching a glob, relative to the start directory
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub pattern: String,
+    pub action: String,
+}
+
+/// Named settings selected with --model
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModelPreset {
+    pub max_tokens: Option<usize>,
+    pub token_model: Option<String>,
+}
+
+/// Settings tuned for a kind of task, selected with --task; each field set
+/// here fills in the one the command line leaves out
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TaskPreset {
+    pub max_tokens: Option<usize>,
+    /// Per-category ceilings, as for --budget
+    pub budget: Option<String>,
+    pub auto_select: Option<bool>,
+    /// Default actions by file class, over the config's own
+    pub class_actions: BTreeMap<String, String>,
+    /// Text opening the first part; `{project}` is replaced with the start
+    /// directory's name
+    pub preamble: Option<String>,
+}
+
+impl TaskPreset {
+    /// This preset with the fields another one sets replaced
+    pub fn overridden_by(mut self, other: &TaskPreset) -> TaskPreset {
+        self.max_tokens = other.max_tokens.or(self.max_tokens);
+        self.budget = other.budget.clone().or(self.budget);
+        self.auto_select = other.auto_select.or(self.auto_select);
+        self.class_actions
+            .extend(other.class_actions.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self.preamble = other.preamble.clone().or(self.preamble);
+        self
+    }
+}
+
+/// Which model summaries come from, its credentials, and how they are asked for
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SummarizerConfig {
+    /// Used when ANTHROPIC_API_KEY is not set
+    pub api_key: Option<String>,
+    /// Instruction the file content is appended to, replacing the default
+    pub prompt: Option<String>,
+    /// Where summaries come from, when --summarizer isn't given
+    pub backend: Option<SummarizerKind>,
+    /// Model summaries are asked of, replacing the backend's default
+    pub model: Option<String>,
+    /// Tokens the model accepts, replacing the provider's default; longer
+    /// files are cut to fit
+    pub context_window: Option<usize>,
+    /// Ollama server, when OLLAMA_HOST doesn't name the one to use; only
+    /// read from the user config
+    pub ollama_url: Option<String>,
+    /// Retries of a failed summary call (rate limits, overload, network)
+    pub max_retries: Option<u32>,
+    /// Growth of the wait between retries, starting at one second
+    pub backoff_multiplier: Option<f64>,
+}
+
+/// Shell commands run around a build, with its paths in LLM_CONTEXT_* variables
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Run before the directory is analyzed (e.g. generating docs)
+    pub pre_build: Option<String>,
+    /// Run after the parts are written (e.g. copying them elsewhere)
+    pub post_build: Option<String>,
+}
+
+/// Settings read from a config file
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Base config this one layers over, relative to this file; read in
+    /// project configs so packages of a monorepo can share one
+    pub extends: Option<PathBuf>,
+    /// Token limit used when --max-tokens isn't given
+    pub max_tokens: Option<usize>,
+    /// Output directory used when --output-dir isn't given; in the project
+    /// config, relative to the project root
+    pub output_dir: Option<PathBuf>,
+    /// Patterns excluded in addition to the defaults
+    pub exclude: Vec<String>,
+    /// Default actions by glob; the first matching rule wins
+    pub rules: Vec<Rule>,
+    /// Default actions by file class (source, docs, tests, config, vendored,
+    /// or unknown for unrecognized types), for files nothing else suggests
+    pub class_actions: BTreeMap<String, String>,
+    pub models: BTreeMap<String, ModelPreset>,
+    /// Task presets added, or overriding the built-in ones field by field
+    pub tasks: BTreeMap<String, TaskPreset>,
+    pub summarizer: SummarizerConfig,
+    /// Marker that opts a file out of bundles, replacing the default
+    pub opt_out_marker: Option<String>,
+    pub hooks: HooksConfig,
+}
+
+impl Config {
+    /// This config with the settings of a more specific one (the project's)
+    /// layered over it: values it sets replace these, its rules are tried
+    /// first, and lists and tables are combined, with its own entries
+    /// winning and its task presets overriding these field by field
+    pub fn merge(mut self, other: Config) -> Config {
+        self.max_tokens = other.max_tokens.or(self.max_tokens);
+        self.output_dir = other.output_dir.or(self.output_dir);
+        for pattern in other.exclude {
+            if !self.exclude.contains(&pattern) {
+                self.exclude.push(pattern);
+            }
+        }
+        self.rules.splice(0..0, other.rules);
+        self.class_actions.extend(other.class_actions);
+        self.models.extend(other.models);
+        for (name, task) in other.tasks {
+            let merged = match self.tasks.remove(&name) {
+                Some(base) => base.overridden_by(&task),
+                None => task,
+            };
+            self.tasks.insert(name, merged);
+        }
+        self.summarizer.api_key = other.summarizer.api_key.or(self.summarizer.api_key);
+        self.summarizer.prompt = other.summarizer.prompt.or(self.summarizer.prompt);
+        self.summarizer.backend = other.summarizer.backend.or(self.summarizer.backend);
+        self.summarizer.model = other.summarizer.model.or(self.summarizer.model);
+        self.summarizer.context_window = other.summarizer.context_window.or(self.summarizer.context_window);
+        self.summarizer.ollama_url = other.summarizer.ollama_url.or(self.summarizer.ollama_url);
+        self.summarizer.max_retries = other.summarizer.max_retries.or(self.summarizer.max_retries);
+        self.summarizer.backoff_multiplier = other
+            .summarizer
+            .backoff_multiplier
+            .or(self.summarizer.backoff_multiplier);
+        self.opt_out_marker = other.opt_out_marker.or(self.opt_out_marker);
+        self.hooks.pre_build = other.hooks.pre_build.or(self.hooks.pre_build);
+        self.hooks.post_build = other.hooks.post_build.or(self.hooks.post_build);
+        self
+    }
+
+    /// Apply settings from the environment, which take precedence over
+    /// config files but not over the command line
+    pub fn with_env(mut self) -> Result<Config> {
+        if let Some(value) = std::env::var_os(MAX_TOKENS_VAR).filter(|v| !v.is_empty()) {
+            let value = value.to_string_lossy();
+            self.max_tokens = Some(
+                value
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("Invalid {} '{}'", MAX_TOKENS_VAR, value))?,
+            );
+        }
+        Ok(self)
+    }
+
+    /// The preset with this name
+    pub fn model(&self, name: &str) -> Result<&ModelPreset> {
+        match self.models.get(name) {
+            Some(preset) => Ok(preset),
+            None => bail!(
+                "Unknown model preset '{}' (defined: {})",
+                name,
+                self.models.keys().cloned().collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+}
+
+/// Path of the user-wide config file: `$XDG_CONFIG_HOME` or `~/.config`,
+/// then `llm-context-loader/config.toml`
+pub fn user_config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+    Some(base.join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME))
+}
+
+/// Parse a config file, checking that its rules are usable
+pub fn load_config_file(path: &Path) -> Result<Config> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    let config: Config = toml::from_str(&content)
+        .with_context(|| format!("Invalid config file {}", path.display()))?;
+
+    for rule in &config.rules {
+        if Action::parse_str(&rule.action).is_none() || rule.action == "enter" {
+            bail!(
+                "Invalid action '{}' for rule '{}' in {}",
+                rule.action,
+                rule.pattern,
+                path.display()
+            );
+        }
+        rule_matcher(&rule.pattern)?;
+    }
+    check_class_actions(&config.class_actions, path)?;
+    for task in config.tasks.values() {
+        check_class_actions(&task.class_actions, path)?;
+    }
+    Ok(config)
+}
+
+/// Check that class actions name known classes and usable actions
+fn check_class_actions(class_actions: &BTreeMap<String, String>, path: &Path) -> Result<()> {
+    for (class, action) in class_actions {
+        if Category::parse_str(class).is_none() && class != UNKNOWN_CLASS {
+            bail!("Unknown file class '{}' in {}", class, path.display());
+        }
+        if Action::parse_str(action).is_none() || action == "enter" {
+            bail!("Invalid action '{}' for class '{}' in {}", action, class, path.display());
+        }
+    }
+    Ok(())
+}
+
+/// A config file layered over the chain of configs it extends. `seen`
+/// holds the files already in the chain, to catch cycles.
+fn load_extended_config(path: &Path, seen: &mut Vec<PathBuf>) -> Result<Config> {
+    let canonical = path
+        .canonicalize()
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    if seen.contains(&canonical) {
+        bail!("Config {} extends itself", path.display());
+    }
+    seen.push(canonical);
+
+    let mut config = load_config_file(path)?;
+    match config.extends.take() {
+        Some(base) => {
+            let base = path.parent().unwrap_or(Path::new(".")).join(base);
+            info!("{} extends {}", path.display(), base.display());
+            Ok(load_extended_config(&base, seen)?.merge(config))
+        }
+        None => Ok(config),
+    }
+}
+
+/// The project's llm-context.toml, if it has one, over any configs it
+/// extends. Its output directory, wherever set, is resolved against the
+/// project root. Hooks, API keys and the Ollama server are only taken
+/// from the user config, so a checked-out project can't run commands,
+/// swap credentials or send file contents elsewhere.
+pub fn load_project_config(start_dir: &Path) -> Result<Option<Config>> {
+    let path = start_dir.join(PROJECT_CONFIG_FILE_NAME);
+    if !path.exists() {
+        return Ok(None);
+    }
+    info!("Loading project config from {}", path.display());
+    let mut config = load_extended_config(&path, &mut Vec::new())?;
+
+    config.output_dir = config.output_dir.map(|dir| start_dir.join(dir));
+    if config.hooks != HooksConfig::default() {
+        warn!("Ignoring [hooks] in {}; hooks are only read from the user config", path.display());
+        config.hooks = HooksConfig::default();
+    }
+    if config.summarizer.api_key.take().is_some() {
+        warn!("Ignoring api_key in {}; set it in the user config or ANTHROPIC_API_KEY", path.display());
+    }
+    if config.summarizer.ollama_url.take().is_some() {
+        warn!("Ignoring ollama_url in {}; set it in the user config or OLLAMA_HOST", path.display());
+    }
+    Ok(Some(config))
+}
+
+/// The user-wide config, or an empty one when there is no file
+pub fn load_user_config() -> Result<Config> {
+    match user_config_path() {
+        Some(path) if path.exists() => {
+            info!("Loading user config from {}", path.display());
+            let config = load_config_file(&path)?;
+            if config.extends.is_some() {
+                warn!("Ignoring extends in {}; only project configs extend others", path.display());
+            }
+            Ok(config)
+        }
+        _ => Ok(Config::default()),
+    }
+}
+
+/// Build a matcher for a rule pattern; patterns without a slash match at any depth
+pub fn rule_matcher(pattern: &str) -> Result<GlobMatcher> {
+    let glob = if pattern.contains('/') {
+        pattern.trim_start_matches('/').to_string()
+    } else {
+        format!("**/{}", pattern)
+    };
+
+    Ok(GlobBuilder::new(&glob)
+        .literal_separator(true)
+        .build()
+        .with_context(|| format!("Invalid rule pattern: {}", pattern))?
+        .compile_matcher())
+}
+
+/// Suggest the action of the first matching rule for each file
+pub fn rule_suggestions(rules: &[Rule], start_dir: &Path, dir_info: &DirectoryMap) -> SuggestionMap {
+    let mut suggestions = SuggestionMap::new();
+    let matchers: Vec<(&Rule, GlobMatcher)> = rules
+        .iter()
+        .filter_map(|rule| rule_matcher(&rule.pattern).ok().map(|m| (rule, m)))
+        .collect();
+    if matchers.is_empty() {
+        return suggestions;
+    }
+
+    for file in dir_info.values().flat_map(|info| info.files.iter()) {
+        let rel_path = file.path.strip_prefix(start_dir).unwrap_or(&file.path);
+        if let Some((rule, _)) = matchers.iter().find(|(_, m)| m.is_match(rel_path)) {
+            suggestions.insert(
+                file.path.clone(),
+                Suggestion {
+                    action: rule.action.clone(),
+                    reason: format!("config rule {}", rule.pattern),
+                },
+            );
+        }
+    }
+
+    propagate_to_directories(&mut suggestions, dir_info);
+    suggestions
+}
+
+/// Suggest the configured action for each file's class
+pub fn class_suggestions(
+    class_actions: &BTreeMap<String, String>,
+    start_dir: &Path,
+    dir_info: &DirectoryMap,
+) -> SuggestionMap {
+    let mut suggestions = SuggestionMap::new();
+    if class_actions.is_empty() {
+        return suggestions;
+    }
+
+    for file in dir_info.values().flat_map(|info| info.files.iter()).filter(|f| !f.binary) {
+        let class = file_class(file.path.strip_prefix(start_dir).unwrap_or(&file.path));
+        if let Some(action) = class_actions.get(class) {
+            suggestions.insert(
+                file.path.clone(),
+                Suggestion {
+                    action: action.clone(),
+                    reason: format!("{} file (config class_actions)", class),
+                },
+            );
+        }
+    }
+
+    propagate_to_directories(&mut suggestions, dir_info);
+    suggestions
+}
@@ -1,15 +1,257 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
+use globset::GlobSet;
 use log::info;
 use std::fs::{self, File, OpenOptions};
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use tempfile::NamedTempFile;
+use std::process::{Command, Stdio};
+use tempfile::TempDir;
+use std::collections::BTreeMap;
 use std::env;
 
+use crate::anchors::{context_part_paths, is_block_end, is_collection_end, uncommented};
 use crate::file_analysis::CLAUDE_TOKEN_LIMIT;
+use crate::formats::{Continuation, Continued, JSON_MANIFEST_FILE_NAME, OutputFormat, part_format};
+use crate::index::INDEX_FILE_NAME;
+use crate::metadata::GenerationMetadata;
+use crate::normalize::Normalization;
+use crate::tokenizer::count_tokens;
 
 /// Name of the directory to store context files in
 pub const CONTEXT_DIR_NAME: &str = ".claude-context";
+/// Pointer to the newest session in a context directory
+pub const LATEST_NAME: &str = "latest";
+/// Start of the first line of every context part, used to recognize parts
+/// whatever they are named
+pub const PART_HEADER_PREFIX: &str =
+    "The following content is a collection of files and directories (Part ";
+/// Start of the line ending the part of a file block that continues in the next part
+pub const CONTINUED_PREFIX: &str = "===== CONTINUED IN ";
+/// Start of the line opening the rest of a file block from the previous part
+pub const CONTINUATION_PREFIX: &str = "===== CONTINUATION OF ";
+/// Header line telling the reader how to cite files
+pub const CITATION_INSTRUCTION: &str =
+    "An ANCHOR line before each file gives its citation ID, such as [F3A9C1]; cite these IDs when referring to code.";
+/// Header line describing the START and END tags around files
+pub const MARKER_INSTRUCTION: &str = "Each file is clearly marked with a START and END tag.";
+/// Header line asking for 'Ready', unless a preamble replaces it
+pub const READY_INSTRUCTION: &str =
+    "After reading these files, you will respond with 'Ready' and await further instructions.";
+/// Subdirectory of the context directory holding the output of earlier runs
+pub const ARCHIVE_DIR_NAME: &str = "archive";
+
+/// Default file name pattern for context parts
+pub const DEFAULT_PART_TEMPLATE: &str = "context-{num}.{ext}";
+
+/// File extension of context parts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PartExtension {
+    #[default]
+    Txt,
+    Md,
+    Xml,
+}
+
+impl PartExtension {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PartExtension::Txt => "txt",
+            PartExtension::Md => "md",
+            PartExtension::Xml => "xml",
+        }
+    }
+}
+
+/// How context parts are named. Templates can use `{project}` (start
+/// directory name), `{date}` (YYYY-MM-DD), `{num}` (zero-padded part number)
+/// and `{ext}`.
+#[derive(Debug, Clone)]
+pub struct PartNaming {
+    template: String,
+    ext: PartExtension,
+    date: String,
+}
+
+impl PartNaming {
+    /// Validate a template; it must contain `{num}` so parts get distinct names
+    pub fn new(template: &str, ext: PartExtension) -> Result<Self> {
+        if !template.contains("{num}") {
+            bail!("Part name template must contain {{num}}: {}", template);
+        }
+        if template.contains('/') || template.contains('\\') {
+            bail!("Part name template must be a file name, not a path: {}", template);
+        }
+        Ok(Self {
+            template: template.to_string(),
+            ext,
+            // Fixed for the run so every part carries the same date
+            date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+        })
+    }
+
+    /// File name of a part for a source directory
+    pub fn file_name(&self, file_num: usize, base_dir: &Path) -> String {
+        let project = base_dir
+            .file_name()
+            .map_or_else(|| "project".to_string(), |n| n.to_string_lossy().to_string());
+        self.template
+            .replace("{project}", &project)
+            .replace("{date}", &self.date)
+            .replace("{num}", &format!("{:03}", file_num))
+            .replace("{ext}", self.ext.as_str())
+    }
+}
+
+impl Default for PartNaming {
+    fn default() -> Self {
+        Self::new(DEFAULT_PART_TEMPLATE, PartExtension::default())
+            .expect("default part template is valid")
+    }
+}
+
+/// Writes the pieces of a part in a format as they are appended. Pieces
+/// are built as text, with the markers of the text format, and a formatter
+/// lays each one out in turn.
+pub trait PartFormatter {
+    /// Opening of a part: its title, instructions and details
+    fn header(&self, text: &str) -> String;
+    /// Material before the first block, such as the project tree
+    fn overview(&self, text: &str) -> String;
+    /// A directory heading with the lines listed under it
+    fn directory(&self, path: &Path, body: &str) -> String;
+    /// A file block, anchor line first, and where its rest is when it's split
+    fn file(&self, text: &str, continued: Option<&Continued>) -> String;
+    /// The piece of a split block continuing one from the previous part
+    fn continuation(&self, piece: &Continuation) -> String;
+    /// End of a part: the end of the collection and what follows it
+    fn footer(&self, text: &str) -> String;
+    /// A finished part rewritten, for layouts that need all of it, such as
+    /// a table of contents
+    fn finish(&self, _content: &str) -> Option<String> {
+        None
+    }
+}
+
+/// Where context files are written and what goes in their headers
+#[derive(Debug, Clone, Default)]
+pub struct OutputSettings {
+    /// Directory for context files (a TempWorkspace's for temporary output)
+    pub dir: Option<PathBuf>,
+    /// Provenance stamped into each part's header
+    pub metadata: Option<GenerationMetadata>,
+    /// Layout the parts are written in
+    pub format: OutputFormat,
+    /// File names of the parts
+    pub naming: PartNaming,
+    /// Show newly generated summaries for approval or editing before writing them
+    pub edit_summaries: bool,
+    /// Attach signatures of std and dependency items to skeletons and API docs of Rust files
+    pub external_signatures: bool,
+    /// Fold function bodies of at least this many lines in fully read files
+    pub fold_bodies: Option<usize>,
+    /// Tokens of a split block repeated at the start of its next piece
+    pub chunk_overlap_tokens: usize,
+    /// Normalizations applied to the text of each block
+    pub normalize: Vec<Normalization>,
+    /// Read only the declarations with matching names from code files
+    pub symbols: Option<GlobSet>,
+    /// Text opening the first part, in place of the standard instruction
+    pub preamble: Option<String>,
+    /// Text closing the last part, in place of the standard instruction
+    pub epilogue: Option<String>,
+    /// Write file and directory paths as absolute paths instead of relative
+    /// to the project root
+    pub absolute_paths: bool,
+}
+
+/// A path as written in the parts: relative to the project root (`.` for
+/// the root itself), or absolute
+pub fn shown_path(path: &Path, base_dir: &Path, absolute: bool) -> PathBuf {
+    match path.strip_prefix(base_dir) {
+        _ if absolute => path.to_path_buf(),
+        Ok(rel_path) if rel_path.as_os_str().is_empty() => PathBuf::from("."),
+        Ok(rel_path) => rel_path.to_path_buf(),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// Where a run's context goes. Parts are always written as files while a run
+/// is in progress; other targets receive the finished parts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputTarget {
+    /// Keep the parts in the output directory
+    File,
+    /// Print the parts to standard output
+    Stdout,
+    /// Copy the parts to the system clipboard
+    Clipboard,
+}
+
+/// Clipboard commands tried in order
+const CLIPBOARD_COMMANDS: [(&str, &[&str]); 5] = [
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+/// Copy text to the clipboard with the first clipboard command that works
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    for (program, args) in CLIPBOARD_COMMANDS {
+        let Ok(mut child) = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(text.as_bytes())
+                .with_context(|| format!("Failed to write to {}", program))?;
+        }
+        if child.wait().is_ok_and(|status| status.success()) {
+            info!("Copied context to clipboard with {}", program);
+            return Ok(());
+        }
+    }
+
+    let names: Vec<&str> = CLIPBOARD_COMMANDS.iter().map(|(name, _)| *name).collect();
+    bail!("No clipboard command found (tried {})", names.join(", "))
+}
+
+/// Send finished context parts to each non-file target
+pub fn emit_parts(parts: &[PathBuf], targets: &[OutputTarget]) -> Result<()> {
+    if targets.iter().all(|t| *t == OutputTarget::File) {
+        return Ok(());
+    }
+
+    let mut text = String::new();
+    for part in parts {
+        text.push_str(
+            &fs::read_to_string(part)
+                .with_context(|| format!("Failed to read context file {}", part.display()))?,
+        );
+    }
+
+    for target in targets {
+        match target {
+            OutputTarget::File => {}
+            OutputTarget::Stdout => {
+                let mut stdout = std::io::stdout().lock();
+                stdout.write_all(text.as_bytes())?;
+                stdout.flush()?;
+            }
+            OutputTarget::Clipboard => copy_to_clipboard(&text)?,
+        }
+    }
+
+    Ok(())
+}
 
 /// Information about the current context file
 #[derive(Debug, Clone)]
@@ -19,41 +261,59 @@ pub struct ContextFile {
     pub current_tokens: usize,
 }
 
+/// Temporary directory owning the parts of a run that keeps no output
+/// directory. It is removed when dropped, also while unwinding from a panic,
+/// unless it is kept for debugging.
+#[derive(Debug)]
+pub struct TempWorkspace {
+    dir: TempDir,
+}
+
+impl TempWorkspace {
+    pub fn new(keep: bool) -> Result<Self> {
+        let mut dir = tempfile::Builder::new()
+            .prefix("llm-context-")
+            .tempdir()
+            .context("Failed to create temporary directory")?;
+        dir.disable_cleanup(keep);
+        Ok(Self { dir })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
 /// Creates a new context file with proper header
 pub fn create_context_file(
     file_num: usize,
     total_files: usize,
     base_dir: &Path,
-    output_dir: Option<&Path>,
+    output: &OutputSettings,
 ) -> Result<ContextFile> {
-    let file_path = if let Some(out_dir) = output_dir {
-        fs::create_dir_all(out_dir).context("Failed to create output directory")?;
-        out_dir.join(format!("context-{:03}.txt", file_num))
-    } else {
-        let temp_file = NamedTempFile::new().context("Failed to create temporary file")?;
-        let path = temp_file.path().to_path_buf();
-        // Keep the temp file in scope so it's not deleted
-        std::mem::forget(temp_file);
-        path
+    let Some(out_dir) = &output.dir else {
+        bail!("No directory for context files (use a TempWorkspace for temporary output)");
     };
+    fs::create_dir_all(out_dir).context("Failed to create output directory")?;
+    let file_path = out_dir.join(output.naming.file_name(file_num, base_dir));
 
     let mut file = File::create(&file_path).context("Failed to create context file")?;
 
-    writeln!(
-        file,
-        "The following content is a collection of files and directories (Part {} of {}).",
-        file_num, total_files
-    )?;
-    writeln!(
-        file,
-        "Each file is clearly marked with a START and END tag."
-    )?;
-    writeln!(
-        file,
-        "After reading these files, you will respond with 'Ready' and await further instructions."
-    )?;
-    writeln!(file, "===== DIRECTORY CONTENT=====")?;
-    writeln!(file, "Source directory: {}", base_dir.display())?;
+    let mut header = format!("{}{} of {}).\n", PART_HEADER_PREFIX, file_num, total_files);
+    header.push_str(&format!("{}\n", CITATION_INSTRUCTION));
+    header.push_str(&format!("{}\n", MARKER_INSTRUCTION));
+    // A preamble replaces the standard instruction, opening the first part
+    match (&output.preamble, file_num) {
+        (Some(preamble), 1) => header.push_str(&format!("{}\n", preamble.trim_end())),
+        (Some(_), _) => {}
+        (None, _) => header.push_str(&format!("{}\n", READY_INSTRUCTION)),
+    }
+    header.push_str("===== DIRECTORY CONTENT=====\n");
+    header.push_str(&format!("Source directory: {}\n", base_dir.display()));
+    if let Some(metadata) = &output.metadata {
+        header.push_str(&metadata.render());
+    }
+    write!(file, "{}", output.format.formatter().header(&header))?;
 
     info!("Created new context file at: {}", file_path.display());
 
@@ -69,7 +329,7 @@ pub fn get_or_rotate_file(
     current_file: &ContextFile,
     total_files: usize,
     base_dir: &Path,
-    output_dir: Option<&Path>,
+    output: &OutputSettings,
 ) -> Result<ContextFile> {
     // If we're under token limit, just return current file
     if current_file.current_tokens < CLAUDE_TOKEN_LIMIT {
@@ -77,15 +337,173 @@ pub fn get_or_rotate_file(
     }
 
     // Create a new file because we've hit token limit
+    rotate_file(current_file, total_files, base_dir, output)
+}
+
+/// Start the next part, whether or not the current one is full
+pub fn rotate_file(
+    current_file: &ContextFile,
+    total_files: usize,
+    base_dir: &Path,
+    output: &OutputSettings,
+) -> Result<ContextFile> {
     let file_num = current_file.file_num + 1;
     info!(
         "\nCreating new context file {} (token limit of {} reached)",
         file_num, CLAUDE_TOKEN_LIMIT
     );
 
-    let new_file = create_context_file(file_num, total_files, base_dir, output_dir)?;
+    create_context_file(file_num, total_files, base_dir, output)
+}
+
+/// File name of the part after `current`, for markers written before it exists
+pub fn next_part_name(current: &ContextFile, base_dir: &Path, output: &OutputSettings) -> String {
+    output.naming.file_name(current.file_num + 1, base_dir)
+}
+
+/// File name of a part, as named in continuation markers
+pub fn part_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Line ending the chunk of a block that continues in `next_part`
+pub fn continued_marker(next_part: &str, id: &str) -> String {
+    format!("{}{} AT MARKER {} =====", CONTINUED_PREFIX, next_part, id)
+}
+
+/// Line opening the chunk of a block continued from `previous_part`
+pub fn continuation_marker(rel_path: &Path, previous_part: &str, id: &str) -> String {
+    format!(
+        "{}{} FROM {} AT MARKER {} =====",
+        CONTINUATION_PREFIX,
+        rel_path.display(),
+        previous_part,
+        id
+    )
+}
+
+/// A piece of a block split across parts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub text: String,
+    /// Leading lines repeated from the end of the previous chunk
+    pub overlap_lines: usize,
+}
 
-    Ok(new_file)
+/// Split a block into chunks of whole lines: the first fits in `first_tokens`
+/// and the others in `tokens` each. Each chunk after the first repeats the
+/// last lines of the one before, up to `overlap_tokens`; the block's first
+/// line (its anchor) is never repeated. A chunk always adds at least one line.
+pub fn split_block(
+    block: &str,
+    first_tokens: usize,
+    tokens: usize,
+    overlap_tokens: usize,
+) -> Vec<Chunk> {
+    let lines: Vec<&str> = block.split_inclusive('\n').collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut overlap = 0;
+
+    let line_tokens: Vec<usize> = lines.iter().map(|l| count_tokens(l)).collect();
+    while start < lines.len() {
+        let budget = if chunks.is_empty() { first_tokens } else { tokens };
+        let mut used: usize = line_tokens[start - overlap..start].iter().sum();
+        let mut end = start;
+        while end < lines.len() && (end == start || used + line_tokens[end] <= budget) {
+            used += line_tokens[end];
+            end += 1;
+        }
+        chunks.push(Chunk {
+            text: lines[start - overlap..end].concat(),
+            overlap_lines: overlap,
+        });
+
+        // Lines of this chunk to repeat at the start of the next one
+        overlap = 0;
+        let mut overlap_used = 0;
+        while overlap < end - start && end - 1 - overlap > 0 {
+            let line_used = line_tokens[end - 1 - overlap];
+            if overlap_used + line_used > overlap_tokens {
+                break;
+            }
+            overlap_used += line_used;
+            overlap += 1;
+        }
+        start = end;
+    }
+    chunks
+}
+
+/// The part's continuation line with a prefix, if it has one. File contents
+/// may hold lines that look like markers, so only the frame counts: a
+/// CONTINUATION OF line comes before any block, and a CONTINUED IN line is
+/// the last marker before the end of the file collection (or of the part,
+/// before it is finalized). Markdown and XML parts keep their markers in
+/// comments, so only those count there.
+fn frame_continuation<'a>(content: &'a str, prefix: &str) -> Option<&'a str> {
+    let commented = matches!(part_format(content), OutputFormat::Markdown | OutputFormat::Xml);
+    let markers: Vec<&str> = content
+        .lines()
+        .filter(|l| !l.trim().is_empty() && (!commented || uncommented(l) != *l))
+        .map(uncommented)
+        .collect();
+    let line = if prefix == CONTINUATION_PREFIX {
+        markers.into_iter().find(|l| l.starts_with(prefix) || is_block_end(l))?
+    } else {
+        let end = markers.iter().position(|l| is_collection_end(l)).unwrap_or(markers.len());
+        *markers[..end].last()?
+    };
+    line.starts_with(prefix).then_some(line)
+}
+
+/// Part and marker of each continuation line in some parts, by marker
+fn continuation_lines(parts: &[PathBuf], prefix: &str) -> Result<BTreeMap<String, (String, String)>> {
+    let mut found = BTreeMap::new();
+    for part in parts {
+        let content = fs::read_to_string(part)
+            .with_context(|| format!("Failed to read context file {}", part.display()))?;
+        if let Some(line) = frame_continuation(&content, prefix) {
+            let Some((rest, id)) = line
+                .trim_end_matches(" =====")
+                .rsplit_once(" AT MARKER ")
+            else {
+                bail!("Malformed continuation marker in {}: {}", part.display(), line);
+            };
+            // The part named by the marker: the next part for CONTINUED IN,
+            // the previous one for CONTINUATION OF
+            let named = match rest.rsplit_once(" FROM ") {
+                Some((_, previous)) if prefix == CONTINUATION_PREFIX => previous,
+                _ => rest.trim_start_matches(prefix),
+            };
+            found.insert(id.to_string(), (part_name(part), named.to_string()));
+        }
+    }
+    Ok(found)
+}
+
+/// Check that every block split across parts has both of its markers, each
+/// naming the part that holds the other
+pub fn check_continuations(parts: &[PathBuf]) -> Result<()> {
+    let continued = continuation_lines(parts, CONTINUED_PREFIX)?;
+    let continuations = continuation_lines(parts, CONTINUATION_PREFIX)?;
+
+    for (id, (part, next)) in &continued {
+        match continuations.get(id) {
+            Some((found_in, previous)) if found_in == next && previous == part => {}
+            Some((found_in, _)) => bail!(
+                "Marker {} in {} continues in {}, but its continuation is in {}",
+                id, part, next, found_in
+            ),
+            None => bail!("Marker {} in {} has no continuation in {}", id, part, next),
+        }
+    }
+    if let Some((id, (part, _))) = continuations.iter().find(|(id, _)| !continued.contains_key(*id)) {
+        bail!("Continuation {} in {} has no matching CONTINUED IN marker", id, part);
+    }
+    Ok(())
 }
 
 /// Append content to a context file
@@ -100,6 +518,35 @@ pub fn append_to_file(path: &Path, content: &str) -> Result<()> {
     Ok(())
 }
 
+/// Point the last part of an earlier run at the first part appended to it
+pub fn link_appended_part(previous_last: &Path, next: &Path) -> Result<()> {
+    append_to_file(
+        previous_last,
+        &format!(
+            "\nIMPORTANT: more files were added later. Continue reading the next context file at: {}\n",
+            next.display()
+        ),
+    )
+}
+
+/// Rewrite the "Part N of M" header of each part for its place among all of
+/// them, such as once parts are appended to an earlier run's
+pub fn renumber_parts(parts: &[PathBuf]) -> Result<()> {
+    for (idx, part) in parts.iter().enumerate() {
+        let content = fs::read_to_string(part)
+            .with_context(|| format!("Failed to read context file {}", part.display()))?;
+        let Some((first_line, rest)) = content.split_once('\n') else {
+            continue;
+        };
+        let header = format!("{}{} of {}).", PART_HEADER_PREFIX, idx + 1, parts.len());
+        if first_line.starts_with(PART_HEADER_PREFIX) && first_line != header {
+            fs::write(part, format!("{}\n{}", header, rest))
+                .with_context(|| format!("Failed to write context file {}", part.display()))?;
+        }
+    }
+    Ok(())
+}
+
 /// Get the default directory for context files
 pub fn get_default_context_dir() -> Result<PathBuf> {
     let current_dir = env::current_dir().context("Failed to get current working directory")?;
@@ -113,33 +560,231 @@ pub fn get_default_context_dir() -> Result<PathBuf> {
     Ok(context_dir)
 }
 
-/// Finalize all context files
+/// Create a new directory under `parent` named by the current time
+fn timestamped_dir(parent: &Path) -> Result<PathBuf> {
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut dir = parent.join(&stamp);
+    let mut suffix = 2;
+    while dir.exists() {
+        dir = parent.join(format!("{}-{}", stamp, suffix));
+        suffix += 1;
+    }
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// Create the directory for a new session in a context directory
+pub fn create_session_dir(context_dir: &Path) -> Result<PathBuf> {
+    let dir = timestamped_dir(context_dir)?;
+    info!("Writing session to: {}", dir.display());
+    Ok(dir)
+}
+
+/// Session directories in a context directory, newest first
+pub fn session_dirs(context_dir: &Path) -> Result<Vec<PathBuf>> {
+    if !context_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut sessions: Vec<PathBuf> = fs::read_dir(context_dir)
+        .context("Failed to read context directory")?
+        .filter_map(Result::ok)
+        // Sessions are named by timestamp; `latest` is a symlink, not a directory
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|n| n.starts_with(|c: char| c.is_ascii_digit()))
+        })
+        .map(|entry| entry.path())
+        .collect();
+    sessions.sort();
+    sessions.reverse();
+    Ok(sessions)
+}
+
+/// Point `latest` in a context directory at a session: a relative symlink on
+/// Unix, elsewhere a file holding the session's directory name
+pub fn set_latest_session(context_dir: &Path, session: &Path) -> Result<()> {
+    let latest = context_dir.join(LATEST_NAME);
+    if latest.symlink_metadata().is_ok() {
+        fs::remove_file(&latest).context("Failed to replace latest session pointer")?;
+    }
+
+    let name = session.file_name().unwrap_or_default();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(name, &latest).context("Failed to link latest session")?;
+    #[cfg(not(unix))]
+    fs::write(&latest, name.to_string_lossy().as_bytes())
+        .context("Failed to write latest session marker")?;
+
+    Ok(())
+}
+
+/// The session `latest` points to, if there is one
+pub fn latest_session(context_dir: &Path) -> Option<PathBuf> {
+    let latest = context_dir.join(LATEST_NAME);
+    let metadata = latest.symlink_metadata().ok()?;
+    let session = if metadata.file_type().is_symlink() {
+        context_dir.join(fs::read_link(&latest).ok()?)
+    } else {
+        context_dir.join(fs::read_to_string(&latest).ok()?.trim())
+    };
+    session.is_dir().then_some(session)
+}
+
+/// Delete all but the `keep` newest sessions, returning how many were deleted
+pub fn prune_sessions(context_dir: &Path, keep: usize) -> Result<usize> {
+    let sessions = session_dirs(context_dir)?;
+    let stale = sessions.get(keep..).unwrap_or_default();
+    for session in stale {
+        fs::remove_dir_all(session)
+            .with_context(|| format!("Failed to delete session {}", session.display()))?;
+        info!("Deleted session: {}", session.display());
+    }
+    Ok(stale.len())
+}
+
+/// Files in a context directory written by the last run (parts and index)
+fn run_output_paths(context_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = context_part_paths(context_dir)?;
+    for name in [INDEX_FILE_NAME, JSON_MANIFEST_FILE_NAME] {
+        let path = context_dir.join(name);
+        if path.exists() {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+/// Archived runs in a context directory, newest first
+pub fn archived_runs(context_dir: &Path) -> Result<Vec<PathBuf>> {
+    let archive_dir = context_dir.join(ARCHIVE_DIR_NAME);
+    if !archive_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut runs: Vec<PathBuf> = fs::read_dir(&archive_dir)
+        .context("Failed to read archive directory")?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    // Runs are named by timestamp, so name order is age order
+    runs.sort();
+    runs.reverse();
+    Ok(runs)
+}
+
+/// Delete all but the `keep` newest archived runs, returning how many were deleted
+pub fn prune_archives(context_dir: &Path, keep: usize) -> Result<usize> {
+    let runs = archived_runs(context_dir)?;
+    let stale = runs.get(keep..).unwrap_or_default();
+    for run in stale {
+        fs::remove_dir_all(run)
+            .with_context(|| format!("Failed to delete archived run {}", run.display()))?;
+        info!("Deleted archived run: {}", run.display());
+    }
+    Ok(stale.len())
+}
+
+/// Clear the previous run's files out of a context directory before writing a
+/// new run, so no stale parts are left behind. With `keep` > 0 they are moved
+/// to a timestamped archive subdirectory, keeping the `keep` newest archives.
+pub fn prepare_output_dir(context_dir: &Path, keep: usize) -> Result<()> {
+    if !context_dir.is_dir() {
+        return Ok(());
+    }
+
+    let previous = run_output_paths(context_dir)?;
+    if keep > 0 && !previous.is_empty() {
+        let run_dir = timestamped_dir(&context_dir.join(ARCHIVE_DIR_NAME))?;
+
+        for path in &previous {
+            let name = path.file_name().unwrap_or_default();
+            fs::rename(path, run_dir.join(name))
+                .with_context(|| format!("Failed to archive {}", path.display()))?;
+        }
+        info!("Archived previous run to: {}", run_dir.display());
+    } else {
+        for path in &previous {
+            fs::remove_file(path)
+                .with_context(|| format!("Failed to delete {}", path.display()))?;
+        }
+    }
+
+    prune_archives(context_dir, keep)?;
+    Ok(())
+}
+
+/// Delete the latest flat run's files and all but the `keep` newest archived
+/// runs and sessions, returning how many files and runs were deleted
+pub fn clean_context_dir(context_dir: &Path, keep: usize) -> Result<(usize, usize)> {
+    let files = run_output_paths(context_dir)?;
+    for path in &files {
+        fs::remove_file(path).with_context(|| format!("Failed to delete {}", path.display()))?;
+    }
+
+    let runs = prune_archives(context_dir, keep)? + prune_sessions(context_dir, keep)?;
+    if archived_runs(context_dir)?.is_empty() {
+        // Only removed if nothing else was put there
+        let _ = fs::remove_dir(context_dir.join(ARCHIVE_DIR_NAME));
+    }
+
+    // Keep `latest` pointing at a session that still exists
+    match session_dirs(context_dir)?.first() {
+        Some(newest) => set_latest_session(context_dir, newest)?,
+        None => {
+            let latest = context_dir.join(LATEST_NAME);
+            if latest.symlink_metadata().is_ok() {
+                fs::remove_file(&latest).context("Failed to remove latest session pointer")?;
+            }
+        }
+    }
+
+    Ok((files.len(), runs))
+}
+
+/// Finalize all context files, closing the last with the epilogue in place
+/// of the standard instruction
 pub fn finalize_context_files(
     context_files: &[ContextFile],
     included_files_count: usize,
+    epilogue: Option<&str>,
+    formatter: &dyn PartFormatter,
 ) -> Result<()> {
+    let parts: Vec<PathBuf> = context_files.iter().map(|f| f.path.clone()).collect();
+    check_continuations(&parts)?;
+
     for (idx, file) in context_files.iter().enumerate() {
-        let mut output = OpenOptions::new()
-            .append(true)
-            .open(&file.path)
-            .context("Failed to open context file for finalizing")?;
-
-        writeln!(output, "\n\n===== END OF FILE COLLECTION =====\n")?;
-        writeln!(output, "Total files included: {}", included_files_count)?;
-        writeln!(output, "This is the complete source code for your review.")?;
-        
+        let mut footer = "\n\n===== END OF FILE COLLECTION =====\n\n".to_string();
+        footer.push_str(&format!("Total files included: {}\n", included_files_count));
+        footer.push_str("This is the complete source code for your review.\n");
+
         // Add pointer to the next file if this isn't the last file
         if idx < context_files.len() - 1 {
-            writeln!(output, "\nIMPORTANT: continue reading the next context file at: {}", context_files[idx + 1].path.display())?;
+            footer.push_str(&format!(
+                "\nIMPORTANT: continue reading the next context file at: {}\n",
+                context_files[idx + 1].path.display()
+            ));
         } else {
-        writeln!(
-            output,
-            "You have now read all the files. Respond only with 'Ready' and await further instructions."
-        )?;
-
+            match epilogue {
+                Some(epilogue) => footer.push_str(&format!("\n{}\n", epilogue.trim_end())),
+                None => footer.push_str(
+                    "You have now read all the files. Respond only with 'Ready' and await further instructions.\n",
+                ),
+            }
         }
+        append_to_file(&file.path, &formatter.footer(&footer))?;
 
-        
+        let content = fs::read_to_string(&file.path)
+            .with_context(|| format!("Failed to read context file {}", file.path.display()))?;
+        if let Some(finished) = formatter.finish(&content) {
+            fs::write(&file.path, finished)
+                .with_context(|| format!("Failed to write context file {}", file.path.display()))?;
+        }
     }
 
     Ok(())
@@ -0,0 +1,138 @@
+use anyhow::{Context, Result, bail};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::suggestions::{Suggestion, SuggestionMap};
+
+/// Line coverage of a single file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileCoverage {
+    /// Instrumented lines
+    pub lines_found: usize,
+    /// Lines executed at least once
+    pub lines_hit: usize,
+}
+
+/// Coverage by absolute file path
+pub type CoverageMap = HashMap<PathBuf, FileCoverage>;
+
+lazy_static! {
+    static ref COBERTURA_SOURCE: Regex = Regex::new(r"<source>\s*([^<]*?)\s*</source>").unwrap();
+    static ref COBERTURA_CLASS: Regex =
+        Regex::new(r#"(?s)<class\b[^>]*\bfilename="([^"]*)"[^>]*>(.*?)</class>"#).unwrap();
+    static ref COBERTURA_LINE: Regex =
+        Regex::new(r#"<line\b[^>]*\bnumber="(\d+)"[^>]*\bhits="(\d+)""#).unwrap();
+}
+
+/// Resolve a path from a coverage report against candidate roots
+fn resolve(path: &str, roots: &[PathBuf]) -> PathBuf {
+    let path = Path::new(path);
+    let resolved = roots
+        .iter()
+        .map(|root| root.join(path))
+        .find(|p| p.exists())
+        .unwrap_or_else(|| roots[0].join(path));
+    resolved.canonicalize().unwrap_or(resolved)
+}
+
+/// Parse an lcov tracefile (`SF:`, `DA:` and `end_of_record` lines)
+pub fn parse_lcov(content: &str, start_dir: &Path) -> CoverageMap {
+    let roots = [start_dir.to_path_buf()];
+    let mut coverage = CoverageMap::new();
+    let mut current: Option<(PathBuf, HashMap<usize, usize>)> = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(file) = line.strip_prefix("SF:") {
+            current = Some((resolve(file, &roots), HashMap::new()));
+        } else if let Some(data) = line.strip_prefix("DA:") {
+            let mut parts = data.split(',');
+            if let (Some((_, lines)), Some(number), Some(hits)) =
+                (current.as_mut(), parts.next(), parts.next())
+                && let (Ok(number), Ok(hits)) = (number.parse(), hits.parse::<usize>())
+            {
+                *lines.entry(number).or_insert(0) += hits;
+            }
+        } else if line == "end_of_record"
+            && let Some((path, lines)) = current.take()
+        {
+            let entry = coverage.entry(path).or_default();
+            entry.lines_found += lines.len();
+            entry.lines_hit += lines.values().filter(|h| **h > 0).count();
+        }
+    }
+
+    coverage
+}
+
+/// Parse a Cobertura XML report, resolving filenames against its `<source>` roots
+pub fn parse_cobertura(content: &str, start_dir: &Path) -> CoverageMap {
+    let mut roots: Vec<PathBuf> = COBERTURA_SOURCE
+        .captures_iter(content)
+        .map(|c| start_dir.join(&c[1]))
+        .collect();
+    roots.push(start_dir.to_path_buf());
+
+    // A file can appear in several classes, so merge line hits per file first
+    let mut files: HashMap<PathBuf, HashMap<usize, usize>> = HashMap::new();
+    for class in COBERTURA_CLASS.captures_iter(content) {
+        let lines = files.entry(resolve(&class[1], &roots)).or_default();
+        for line in COBERTURA_LINE.captures_iter(&class[2]) {
+            if let (Ok(number), Ok(hits)) = (line[1].parse(), line[2].parse::<usize>()) {
+                *lines.entry(number).or_insert(0) += hits;
+            }
+        }
+    }
+
+    files
+        .into_iter()
+        .map(|(path, lines)| {
+            (
+                path,
+                FileCoverage {
+                    lines_found: lines.len(),
+                    lines_hit: lines.values().filter(|h| **h > 0).count(),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Load an lcov or Cobertura coverage file, detecting the format from its content
+pub fn load_coverage(path: &Path, start_dir: &Path) -> Result<CoverageMap> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read coverage file {}", path.display()))?;
+
+    let coverage = if content.trim_start().starts_with('<') {
+        parse_cobertura(&content, start_dir)
+    } else if content.contains("SF:") {
+        parse_lcov(&content, start_dir)
+    } else {
+        bail!(
+            "Unrecognized coverage format in {} (expected lcov or Cobertura XML)",
+            path.display()
+        );
+    };
+
+    Ok(coverage)
+}
+
+/// Suggest reading every file that had lines executed
+pub fn coverage_suggestions(coverage: &CoverageMap) -> SuggestionMap {
+    coverage
+        .iter()
+        .filter(|(_, c)| c.lines_hit > 0)
+        .map(|(path, c)| {
+            (
+                path.clone(),
+                Suggestion {
+                    action: "read".to_string(),
+                    reason: format!("{} of {} lines ran", c.lines_hit, c.lines_found),
+                },
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,270 @@
+use anyhow::{Context, Result, anyhow, bail};
+use log::{info, warn};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+use crate::context_files::{OutputSettings, create_context_file, finalize_context_files, prepare_output_dir};
+use crate::file_analysis::{CLAUDE_TOKEN_LIMIT, DirectoryMap, analyze_directory, is_skipped};
+use crate::index::write_index;
+use crate::ledger::flush_ledger;
+use crate::manifest::{Manifest, apply_manifest, load_manifest};
+use crate::optout::remove_opted_out;
+use crate::processing::Action;
+use crate::run_summary::RunSummary;
+use crate::serve::{Response, query_param};
+use crate::summary_cache::{SummaryCache, load_summary_cache, save_summary_cache};
+
+/// Port the daemon listens on by default
+pub const DAEMON_PORT: u16 = 7879;
+/// Seconds between checks of the tree for changes
+pub const DEFAULT_POLL_SECS: u64 = 2;
+/// Where builds are written, under the start directory, unless a request names a directory
+pub const DAEMON_OUTPUT_DIR: &str = ".claude-context/daemon";
+/// Token budget of a build that names none
+const DEFAULT_BUILD_TOKENS: usize = 100000;
+
+/// Hash of the path, size and modification time of every file under the
+/// start directory; it changes whenever a file is added, removed or written
+pub fn tree_fingerprint(start_dir: &Path, excludes: &[String]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for entry in WalkDir::new(start_dir)
+        .follow_links(true)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !is_skipped(e.path(), start_dir, e.file_type().is_dir(), excludes))
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+    {
+        entry.path().hash(&mut hasher);
+        if let Ok(metadata) = entry.metadata() {
+            metadata.len().hash(&mut hasher);
+            let modified = metadata.modified().ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok());
+            modified.hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// What the daemon keeps between requests
+struct Warm {
+    dir_info: DirectoryMap,
+    fingerprint: u64,
+    summary_cache: SummaryCache,
+    /// Times the tree has been analyzed
+    analyses: usize,
+}
+
+/// A project analyzed once and kept up to date, answering build requests
+/// without analyzing the tree again
+pub struct Daemon {
+    start_dir: PathBuf,
+    excludes: Vec<String>,
+    opt_out_marker: String,
+    warm: Mutex<Warm>,
+}
+
+impl Daemon {
+    /// Analyze the start directory and load its summary cache
+    pub fn new(start_dir: PathBuf, excludes: Vec<String>, opt_out_marker: String) -> Result<Self> {
+        let fingerprint = tree_fingerprint(&start_dir, &excludes);
+        let mut dir_info = analyze_directory(&start_dir, &excludes)?;
+        remove_opted_out(&mut dir_info, &opt_out_marker);
+        let summary_cache = load_summary_cache(&start_dir)?;
+        Ok(Self {
+            start_dir,
+            excludes,
+            opt_out_marker,
+            warm: Mutex::new(Warm {
+                dir_info,
+                fingerprint,
+                summary_cache,
+                analyses: 1,
+            }),
+        })
+    }
+
+    fn warm(&self) -> Result<MutexGuard<'_, Warm>> {
+        self.warm.lock().map_err(|_| anyhow!("Daemon state is poisoned"))
+    }
+
+    /// A path from a request, relative to the start directory, with the part
+    /// of it that exists resolved through symlinks. Paths that climb out with
+    /// `..`, or lead out through a symlink, are refused.
+    fn inside_start_dir(&self, path: &str) -> Result<PathBuf> {
+        let outside = || anyhow!("{} is not in {}", path, self.start_dir.display());
+        if Path::new(path).components().any(|c| c == Component::ParentDir) {
+            return Err(outside());
+        }
+        let joined = self.start_dir.join(path);
+        let mut existing = joined.as_path();
+        while !existing.exists() {
+            existing = existing.parent().ok_or_else(outside)?;
+        }
+        let rest = joined.strip_prefix(existing).unwrap_or(Path::new(""));
+        let resolved = existing.canonicalize()?.join(rest);
+        if !resolved.starts_with(&self.start_dir) {
+            return Err(outside());
+        }
+        Ok(resolved)
+    }
+
+    /// Analyze the tree again if anything in it changed. Returns whether it did.
+    pub fn refresh(&self) -> Result<bool> {
+        let fingerprint = tree_fingerprint(&self.start_dir, &self.excludes);
+        if self.warm()?.fingerprint == fingerprint {
+            return Ok(false);
+        }
+        let mut dir_info = analyze_directory(&self.start_dir, &self.excludes)?;
+        remove_opted_out(&mut dir_info, &self.opt_out_marker);
+
+        let mut warm = self.warm()?;
+        warm.dir_info = dir_info;
+        warm.fingerprint = fingerprint;
+        warm.analyses += 1;
+        info!("Re-analyzed {} after changes", self.start_dir.display());
+        Ok(true)
+    }
+
+    /// Check the tree for changes every `every` on a background thread
+    pub fn watch(self: &Arc<Self>, every: Duration) {
+        let daemon = Arc::clone(self);
+        thread::spawn(move || {
+            loop {
+                thread::sleep(every);
+                if let Err(e) = daemon.refresh() {
+                    warn!("Failed to refresh analysis: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Build context parts for a manifest, reusing the warm analysis and
+    /// summary cache
+    pub fn build(&self, manifest: &Manifest, out_dir: &Path, max_tokens: usize) -> Result<RunSummary> {
+        let mut warm = self.warm()?;
+        fs::create_dir_all(out_dir).context("Failed to create output directory")?;
+        prepare_output_dir(out_dir, 0)?;
+
+        let output = OutputSettings {
+            dir: Some(out_dir.to_path_buf()),
+            ..Default::default()
+        };
+        let total_tokens: usize = warm.dir_info.values().map(|dir| dir.tokens).sum();
+        let estimated_files = total_tokens.div_ceil(CLAUDE_TOKEN_LIMIT).max(1);
+        let mut context_file = create_context_file(1, estimated_files, &self.start_dir, &output)?;
+        let result = apply_manifest(
+            manifest,
+            &warm.dir_info,
+            &mut context_file,
+            max_tokens,
+            estimated_files,
+            &self.start_dir,
+            &output,
+            Some(&warm.summary_cache),
+        )?;
+
+        for summary_info in result.file_summaries {
+            warm.summary_cache
+                .insert_summary(&summary_info.path, &summary_info.content_hash, summary_info.summary);
+        }
+        save_summary_cache(&self.start_dir, &warm.summary_cache)?;
+        flush_ledger(&self.start_dir)?;
+
+        finalize_context_files(&result.context_files, result.included_files.len(), None, output.format.formatter())?;
+        let parts: Vec<PathBuf> = result.context_files.iter().map(|f| f.path.clone()).collect();
+        write_index(out_dir, &parts)?;
+        Ok(RunSummary::new(
+            parts,
+            manifest,
+            result.included_files.len(),
+            result.total_tokens,
+            warm.summary_cache.run_stats(),
+            Vec::new(),
+        ))
+    }
+
+    /// The manifest a build request asks for: a saved manifest, or paths
+    /// relative to the start directory with one action
+    fn requested_manifest(&self, query: &str) -> Result<Manifest> {
+        if let Some(path) = query_param(query, "manifest") {
+            let manifest = load_manifest(&self.inside_start_dir(&path)?)?;
+            for entry in &manifest.entries {
+                let resolved = self.start_dir.join(&entry.path).canonicalize();
+                if resolved.is_ok_and(|p| !p.starts_with(&self.start_dir)) {
+                    bail!("Manifest entry {} is not in {}", entry.path.display(), self.start_dir.display());
+                }
+            }
+            return Ok(manifest);
+        }
+        let Some(paths) = query_param(query, "paths") else {
+            bail!("Give manifest=PATH or paths=A,B[&action=ACTION]");
+        };
+        let action = query_param(query, "action").unwrap_or_else(|| "read".to_string());
+        if Action::parse_str(&action).is_none() {
+            bail!("Invalid action '{}'", action);
+        }
+
+        let mut manifest = Manifest::new();
+        for path in paths.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let path = match self.start_dir.join(path).canonicalize() {
+                Ok(path) if path.starts_with(&self.start_dir) => path,
+                _ => bail!("{} is not in {}", path, self.start_dir.display()),
+            };
+            manifest.record(&path, &self.start_dir, &action);
+        }
+        Ok(manifest)
+    }
+
+    /// Answer a request: `/status`, or `/build` with `paths=A,B&action=read`
+    /// or `manifest=PATH`, and optionally `out=DIR` and `max_tokens=N`
+    pub fn respond(&self, target: &str) -> Response {
+        let (route, query) = target.split_once('?').unwrap_or((target, ""));
+        match route {
+            "/" | "/status" => match self.warm() {
+                Ok(warm) => {
+                    let files: usize = warm.dir_info.values().map(|d| d.files.len()).sum();
+                    let tokens: usize = warm.dir_info.values().map(|d| d.tokens).sum();
+                    let status = serde_json::json!({
+                        "start_dir": self.start_dir,
+                        "files": files,
+                        "tokens": tokens,
+                        "analyses": warm.analyses,
+                    });
+                    Response::ok(format!("{}\n", status))
+                }
+                Err(e) => Response::error(500, &format!("{:#}", e)),
+            },
+            "/build" => {
+                let out_dir = match query_param(query, "out").map(|dir| self.inside_start_dir(&dir)) {
+                    Some(Ok(dir)) => dir,
+                    Some(Err(e)) => return Response::error(400, &format!("{:#}", e)),
+                    None => self.start_dir.join(DAEMON_OUTPUT_DIR),
+                };
+                let max_tokens = match query_param(query, "max_tokens").map(|n| n.parse()) {
+                    Some(Ok(n)) => n,
+                    Some(Err(_)) => return Response::error(400, "Invalid max_tokens"),
+                    None => DEFAULT_BUILD_TOKENS,
+                };
+                let manifest = match self.requested_manifest(query) {
+                    Ok(manifest) => manifest,
+                    Err(e) => return Response::error(400, &format!("{:#}", e)),
+                };
+                // Answer from the current tree even between polls
+                if let Err(e) = self.refresh() {
+                    warn!("Failed to refresh analysis: {}", e);
+                }
+                match self.build(&manifest, &out_dir, max_tokens).and_then(|s| s.to_json_line()) {
+                    Ok(json) => Response::ok(format!("{}\n", json)),
+                    Err(e) => Response::error(500, &format!("{:#}", e)),
+                }
+            }
+            _ => Response::error(404, "Unknown route; use /status or /build?paths=...&action=..."),
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use crate::file_analysis::DirectoryMap;
+use crate::processing::generate_summary;
+
+/// Most used items listed for a dependency before the rest are counted
+pub const MAX_LISTED_ITEMS: usize = 20;
+
+lazy_static! {
+    static ref USE_DECL: Regex = Regex::new(r"(?s)\buse\s+(?:::)?(\w+)::([^;]+);").unwrap();
+    static ref LINE_COMMENT: Regex = Regex::new(r"//[^\n]*").unwrap();
+}
+
+/// A direct dependency from Cargo.toml
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// Name of the package on crates.io
+    pub package: String,
+    /// Name the code refers to it by, with dashes as underscores
+    pub ident: String,
+    /// Resolved versions from Cargo.lock, or the requirement from Cargo.toml
+    pub version: String,
+}
+
+/// Items of one dependency that the project uses
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyUse {
+    /// Paths used, starting with the crate's name
+    pub items: BTreeSet<String>,
+    /// Source files that use the dependency
+    pub files: usize,
+}
+
+/// Direct dependencies of the crate in `start_dir`, with versions from
+/// Cargo.lock when there is one
+pub fn direct_dependencies(start_dir: &Path) -> Result<Vec<Dependency>> {
+    let manifest_path = start_dir.join("Cargo.toml");
+    let manifest = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?
+        .parse::<toml::Table>()
+        .with_context(|| format!("Invalid {}", manifest_path.display()))?;
+    let locked = locked_versions(start_dir);
+
+    let mut dependencies = Vec::new();
+    let table = manifest.get("dependencies").and_then(|d| d.as_table());
+    for (name, spec) in table.into_iter().flatten() {
+        let package = spec
+            .get("package")
+            .and_then(|p| p.as_str())
+            .unwrap_or(name)
+            .to_string();
+        let requirement = match spec {
+            toml::Value::String(version) => Some(version.clone()),
+            _ => spec.get("version").and_then(|v| v.as_str()).map(str::to_string),
+        };
+        let version = match locked.get(&package) {
+            Some(versions) => versions.join(", "),
+            None => requirement.unwrap_or_else(|| "unknown version".to_string()),
+        };
+        dependencies.push(Dependency {
+            ident: name.replace('-', "_"),
+            package,
+            version,
+        });
+    }
+    Ok(dependencies)
+}
+
+/// Versions of each package in Cargo.lock
+fn locked_versions(start_dir: &Path) -> BTreeMap<String, Vec<String>> {
+    let mut versions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let Ok(lock) = fs::read_to_string(start_dir.join("Cargo.lock")) else {
+        return versions;
+    };
+    let Ok(lock) = lock.parse::<toml::Table>() else {
+        return versions;
+    };
+
+    let packages = lock.get("package").and_then(|p| p.as_array());
+    for package in packages.into_iter().flatten() {
+        if let (Some(name), Some(version)) = (
+            package.get("name").and_then(|n| n.as_str()),
+            package.get("version").and_then(|v| v.as_str()),
+        ) {
+            versions.entry(name.to_string()).or_default().push(version.to_string());
+        }
+    }
+    versions
+}
+
+/// Paths named by a use tree such as `{de::Visitor, Serialize as Ser, self}`
+pub fn expand_use_tree(prefix: &str, tree: &str, paths: &mut Vec<String>) {
+    let tree = tree.trim();
+    if let Some(inner) = tree.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+        // Split on top-level commas only
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in inner.char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                ',' if depth == 0 => {
+                    expand_use_tree(prefix, &inner[start..i], paths);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        expand_use_tree(prefix, &inner[start..], paths);
+        return;
+    }
+    if tree.is_empty() {
+        return;
+    }
+
+    match tree.split_once("::") {
+        Some((head, rest)) if !head.contains('{') => {
+            expand_use_tree(&format!("{}::{}", prefix, head.trim()), rest, paths)
+        }
+        _ => {
+            let name = tree.split(" as ").next().unwrap_or(tree).trim();
+            match name {
+                "self" => paths.push(prefix.to_string()),
+                "*" => paths.push(format!("{}::*", prefix)),
+                name => paths.push(format!("{}::{}", prefix, name)),
+            }
+        }
+    }
+}
+
+/// Paths of `ident` used by a Rust source file, from use declarations and
+/// fully qualified paths
+pub fn used_paths(ident: &str, source: &str) -> BTreeSet<String> {
+    let code = LINE_COMMENT.replace_all(source, "");
+    let mut paths = Vec::new();
+
+    for c in USE_DECL.captures_iter(&code) {
+        if &c[1] == ident {
+            expand_use_tree(ident, &c[2], &mut paths);
+        }
+    }
+
+    let qualified = Regex::new(&format!(r"\b{}((?:::\w+)+)", regex::escape(ident))).unwrap();
+    paths.extend(qualified.captures_iter(&code).map(|c| format!("{}{}", ident, &c[1])));
+
+    paths.into_iter().collect()
+}
+
+/// What the project's Rust sources use from each dependency
+pub fn dependency_uses(dependencies: &[Dependency], dir_info: &DirectoryMap) -> Vec<DependencyUse> {
+    let sources: Vec<String> = dir_info
+        .values()
+        .flat_map(|info| info.files.iter())
+        .filter(|f| f.ext == ".rs")
+        .filter_map(|f| fs::read_to_string(&f.path).ok())
+        .collect();
+
+    dependencies
+        .iter()
+        .map(|dep| {
+            let mut usage = DependencyUse::default();
+            for source in &sources {
+                let paths = used_paths(&dep.ident, source);
+                if !paths.is_empty() {
+                    usage.files += 1;
+                    usage.items.extend(paths);
+                }
+            }
+            usage
+        })
+        .collect()
+}
+
+/// The "Dependencies digest" section: each direct dependency with its
+/// version, the items used and a summary of them
+pub fn render_digest(dependencies: &[Dependency], uses: &[DependencyUse]) -> Result<String> {
+    let mut output = String::from("## Dependencies digest\n");
+    for (dep, usage) in dependencies.iter().zip(uses) {
+        output.push_str(&format!("\n### {} {}\n", dep.package, dep.version));
+        if usage.items.is_empty() {
+            output.push_str("Not referenced by path in the Rust sources (macros or features only).\n");
+            continue;
+        }
+
+        let items: Vec<&str> = usage.items.iter().map(String::as_str).collect();
+        let mut listed = items[..items.len().min(MAX_LISTED_ITEMS)]
+            .iter()
+            .map(|item| format!("`{}`", item))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if items.len() > MAX_LISTED_ITEMS {
+            listed.push_str(&format!(", +{} more", items.len() - MAX_LISTED_ITEMS));
+        }
+        output.push_str(&format!("Used in {} files: {}\n\n", usage.files, listed));
+
+        let prompt = format!(
+            "The project uses these items from the {} crate, version {}:\n{}\n",
+            dep.package,
+            dep.version,
+            items.join("\n")
+        );
+        // The items listed above still say what is used without a summary
+        match generate_summary(
+            "md",
+            &prompt,
+            Some("Describe in one paragraph what these APIs do and how they fit together."),
+        ) {
+            Ok(summary) => output.push_str(&summary),
+            Err(e) => output.push_str(&format!("(No summary: {:#})\n", e)),
+        }
+    }
+    Ok(output)
+}
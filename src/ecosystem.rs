@@ -0,0 +1,171 @@
+use std::fs;
+use std::path::Path;
+
+/// Which ecosystem-specific excludes to apply on top of the defaults
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExcludePreset {
+    /// Detect project types and exclude their build/cache directories
+    Auto,
+    /// Only use the built-in defaults and explicit --exclude patterns
+    None,
+}
+
+/// A project ecosystem detected from its marker files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ecosystem {
+    Rust,
+    Node,
+    Python,
+    Go,
+    Java,
+    Ruby,
+    Php,
+    Dotnet,
+}
+
+pub const ALL_ECOSYSTEMS: [Ecosystem; 8] = [
+    Ecosystem::Rust,
+    Ecosystem::Node,
+    Ecosystem::Python,
+    Ecosystem::Go,
+    Ecosystem::Java,
+    Ecosystem::Ruby,
+    Ecosystem::Php,
+    Ecosystem::Dotnet,
+];
+
+impl Ecosystem {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Ecosystem::Rust => "Rust",
+            Ecosystem::Node => "Node",
+            Ecosystem::Python => "Python",
+            Ecosystem::Go => "Go",
+            Ecosystem::Java => "Java",
+            Ecosystem::Ruby => "Ruby",
+            Ecosystem::Php => "PHP",
+            Ecosystem::Dotnet => ".NET",
+        }
+    }
+
+    /// File names whose presence identifies the ecosystem
+    fn marker_files(&self) -> &'static [&'static str] {
+        match self {
+            Ecosystem::Rust => &["Cargo.toml"],
+            Ecosystem::Node => &["package.json"],
+            Ecosystem::Python => &[
+                "pyproject.toml",
+                "setup.py",
+                "setup.cfg",
+                "requirements.txt",
+                "Pipfile",
+            ],
+            Ecosystem::Go => &["go.mod"],
+            Ecosystem::Java => &[
+                "pom.xml",
+                "build.gradle",
+                "build.gradle.kts",
+                "settings.gradle",
+            ],
+            Ecosystem::Ruby => &["Gemfile"],
+            Ecosystem::Php => &["composer.json"],
+            Ecosystem::Dotnet => &[],
+        }
+    }
+
+    /// File extensions whose presence identifies the ecosystem
+    fn marker_extensions(&self) -> &'static [&'static str] {
+        match self {
+            Ecosystem::Dotnet => &["sln", "csproj", "fsproj"],
+            _ => &[],
+        }
+    }
+
+    /// Exclude patterns for build output, caches and dependency directories.
+    /// Patterns ending in `/` match whole path components.
+    pub fn excludes(&self) -> &'static [&'static str] {
+        match self {
+            Ecosystem::Rust => &["target/"],
+            Ecosystem::Node => &[
+                "node_modules/",
+                "dist/",
+                "build/",
+                "coverage/",
+                ".next/",
+                ".nuxt/",
+                ".turbo/",
+                ".parcel-cache/",
+                ".yarn/",
+            ],
+            Ecosystem::Python => &[
+                ".venv/",
+                "venv/",
+                ".tox/",
+                ".nox/",
+                ".mypy_cache/",
+                ".pytest_cache/",
+                ".ruff_cache/",
+                "htmlcov/",
+                "build/",
+                "dist/",
+                ".egg-info",
+            ],
+            Ecosystem::Go => &["vendor/", "bin/"],
+            Ecosystem::Java => &[".gradle/", "build/", "target/", "out/"],
+            Ecosystem::Ruby => &["vendor/bundle/", ".bundle/", "coverage/"],
+            Ecosystem::Php => &["vendor/"],
+            Ecosystem::Dotnet => &["bin/", "obj/", "packages/"],
+        }
+    }
+
+    /// Check whether a directory contains this ecosystem's marker files
+    fn is_present_in(&self, dir: &Path) -> bool {
+        if self.marker_files().iter().any(|m| dir.join(m).is_file()) {
+            return true;
+        }
+
+        let extensions = self.marker_extensions();
+        !extensions.is_empty()
+            && fs::read_dir(dir).is_ok_and(|entries| {
+                entries.filter_map(Result::ok).any(|entry| {
+                    entry
+                        .path()
+                        .extension()
+                        .and_then(|e| e.to_str())
+                        .is_some_and(|e| extensions.contains(&e))
+                })
+            })
+    }
+}
+
+/// Detect the ecosystems used in a project from marker files in its root
+/// and immediate subdirectories (to catch polyglot monorepos)
+pub fn detect_ecosystems(directory: &Path) -> Vec<Ecosystem> {
+    let mut dirs = vec![directory.to_path_buf()];
+    if let Ok(entries) = fs::read_dir(directory) {
+        dirs.extend(
+            entries
+                .filter_map(Result::ok)
+                .map(|e| e.path())
+                .filter(|p| p.is_dir()),
+        );
+    }
+
+    ALL_ECOSYSTEMS
+        .into_iter()
+        .filter(|ecosystem| dirs.iter().any(|dir| ecosystem.is_present_in(dir)))
+        .collect()
+}
+
+/// Exclude patterns for a set of ecosystems, without duplicates
+pub fn ecosystem_excludes(ecosystems: &[Ecosystem]) -> Vec<String> {
+    let mut excludes: Vec<String> = Vec::new();
+    for ecosystem in ecosystems {
+        for pattern in ecosystem.excludes() {
+            if !excludes.iter().any(|e| e == pattern) {
+                excludes.push(pattern.to_string());
+            }
+        }
+    }
+    excludes
+}
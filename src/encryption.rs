@@ -0,0 +1,72 @@
+use anyhow::{Context, Result, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+
+/// Environment variable holding the base64 key that encrypts cached summaries
+pub const CACHE_KEY_VAR: &str = "LLM_CONTEXT_CACHE_KEY";
+/// First line of an encrypted cache file
+pub const ENCRYPTED_HEADER: &str = "llm-context-loader encrypted v1";
+
+const NONCE_LEN: usize = 24;
+
+/// Cipher for the key in CACHE_KEY_VAR, or None when it is not set
+pub fn cache_cipher() -> Result<Option<XChaCha20Poly1305>> {
+    match std::env::var(CACHE_KEY_VAR).ok().filter(|k| !k.is_empty()) {
+        Some(encoded) => parse_key(&encoded).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Cipher for a base64-encoded 32-byte key
+pub fn parse_key(encoded: &str) -> Result<XChaCha20Poly1305> {
+    let bytes = STANDARD
+        .decode(encoded.trim())
+        .with_context(|| format!("{} is not valid base64", CACHE_KEY_VAR))?;
+    if bytes.len() != 32 {
+        bail!("{} must hold 32 bytes, not {}", CACHE_KEY_VAR, bytes.len());
+    }
+    Ok(XChaCha20Poly1305::new(Key::from_slice(&bytes)))
+}
+
+/// A new random key, base64-encoded for CACHE_KEY_VAR
+pub fn generate_key() -> String {
+    STANDARD.encode(XChaCha20Poly1305::generate_key(&mut OsRng))
+}
+
+/// Whether file content was written by `encrypt`
+pub fn is_encrypted(content: &str) -> bool {
+    content.starts_with(ENCRYPTED_HEADER)
+}
+
+/// Encrypt text under a fresh nonce, as the header line and base64 of nonce and ciphertext
+pub fn encrypt(cipher: &XChaCha20Poly1305, plaintext: &str) -> Result<String> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt"))?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    Ok(format!("{}\n{}\n", ENCRYPTED_HEADER, STANDARD.encode(sealed)))
+}
+
+/// Decrypt content written by `encrypt`
+pub fn decrypt(cipher: &XChaCha20Poly1305, content: &str) -> Result<String> {
+    let Some(body) = content.strip_prefix(ENCRYPTED_HEADER) else {
+        bail!("Content is not encrypted");
+    };
+    let sealed = STANDARD
+        .decode(body.trim())
+        .context("Encrypted content is not valid base64")?;
+    if sealed.len() < NONCE_LEN {
+        bail!("Encrypted content is truncated");
+    }
+
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let plaintext = cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow::anyhow!("Failed to decrypt (wrong {}?)", CACHE_KEY_VAR))?;
+    String::from_utf8(plaintext).context("Decrypted content is not UTF-8")
+}
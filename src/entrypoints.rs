@@ -0,0 +1,244 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::file_analysis::{DirectoryMap, file_tokens};
+use crate::suggestions::{Suggestion, SuggestionMap};
+
+/// Conventional entry point files, relative to the start directory
+const CONVENTIONAL_ENTRY_POINTS: [&str; 14] = [
+    "src/main.rs",
+    "src/lib.rs",
+    "main.go",
+    "cmd/main.go",
+    "main.py",
+    "__main__.py",
+    "app.py",
+    "manage.py",
+    "index.js",
+    "index.ts",
+    "src/index.js",
+    "src/index.ts",
+    "src/main.ts",
+    "src/main.tsx",
+];
+
+/// Extensions tried when resolving extensionless JS/TS imports
+const JS_EXTENSIONS: [&str; 6] = ["js", "ts", "tsx", "jsx", "mjs", "cjs"];
+
+lazy_static! {
+    static ref RUST_MOD: Regex = Regex::new(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+(\w+)\s*;").unwrap();
+    static ref JS_IMPORT: Regex =
+        Regex::new(r#"(?:\bfrom\s+|\bimport\s+|\brequire\s*\(\s*)['"](\.{1,2}/[^'"]+)['"]"#).unwrap();
+    static ref PY_IMPORT: Regex =
+        Regex::new(r"(?m)^\s*(?:from\s+(\.*[\w.]*)\s+import|import\s+([\w.]+))").unwrap();
+}
+
+/// Entry points declared in Cargo.toml (`[[bin]]` paths) and package.json
+/// (`main`, `module`, `bin`), plus `src/bin/*.rs`
+fn declared_entry_points(start_dir: &Path) -> Vec<PathBuf> {
+    let mut entries = Vec::new();
+
+    if let Ok(content) = fs::read_to_string(start_dir.join("Cargo.toml"))
+        && let Ok(manifest) = content.parse::<toml::Table>()
+    {
+        let bins = manifest.get("bin").and_then(|b| b.as_array());
+        for bin in bins.into_iter().flatten() {
+            if let Some(path) = bin.get("path").and_then(|p| p.as_str()) {
+                entries.push(start_dir.join(path));
+            }
+        }
+    }
+
+    if let Ok(read_dir) = fs::read_dir(start_dir.join("src").join("bin")) {
+        let mut bins: Vec<PathBuf> = read_dir
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "rs"))
+            .collect();
+        bins.sort();
+        entries.extend(bins);
+    }
+
+    if let Ok(content) = fs::read_to_string(start_dir.join("package.json"))
+        && let Ok(package) = serde_json::from_str::<serde_json::Value>(&content)
+    {
+        for key in ["main", "module"] {
+            if let Some(path) = package.get(key).and_then(|v| v.as_str()) {
+                entries.push(start_dir.join(path));
+            }
+        }
+        match package.get("bin") {
+            Some(serde_json::Value::String(path)) => entries.push(start_dir.join(path)),
+            Some(serde_json::Value::Object(bins)) => {
+                entries.extend(bins.values().filter_map(|v| v.as_str()).map(|p| start_dir.join(p)))
+            }
+            _ => {}
+        }
+    }
+
+    entries
+}
+
+/// Entry points of the project that were analyzed (not excluded or binary)
+pub fn detect_entry_points(start_dir: &Path, dir_info: &DirectoryMap) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = CONVENTIONAL_ENTRY_POINTS
+        .iter()
+        .map(|p| start_dir.join(p))
+        .collect();
+    entries.extend(declared_entry_points(start_dir));
+
+    let mut result: Vec<PathBuf> = Vec::new();
+    for entry in entries {
+        // Normalize `./x` style paths from manifests
+        let entry = entry.canonicalize().unwrap_or(entry);
+        if file_tokens(dir_info, &entry).is_some() && !result.contains(&entry) {
+            result.push(entry);
+        }
+    }
+    result
+}
+
+/// Resolve a JS/TS relative import to an existing file
+fn resolve_js_import(base: &Path, spec: &str) -> Option<PathBuf> {
+    let target = base.join(spec);
+    if target.is_file() {
+        return Some(target);
+    }
+    JS_EXTENSIONS
+        .iter()
+        .map(|ext| target.with_extension(ext))
+        .chain(JS_EXTENSIONS.iter().map(|ext| target.join(format!("index.{}", ext))))
+        .find(|p| p.is_file())
+}
+
+/// Resolve a Python module path to a file relative to `base`
+fn resolve_py_module(base: &Path, module: &str) -> Option<PathBuf> {
+    let mut dir = base.to_path_buf();
+    let dots = module.chars().take_while(|c| *c == '.').count();
+    for _ in 1..dots {
+        dir = dir.parent()?.to_path_buf();
+    }
+
+    let rel: PathBuf = module[dots..].split('.').filter(|s| !s.is_empty()).collect();
+    if rel.as_os_str().is_empty() {
+        return None;
+    }
+    let target = dir.join(rel);
+    [target.with_extension("py"), target.join("__init__.py")]
+        .into_iter()
+        .find(|p| p.is_file())
+}
+
+/// Source file extensions whose imports can be resolved
+pub const IMPORT_EXTENSIONS: [&str; 8] = ["rs", "js", "jsx", "ts", "tsx", "mjs", "cjs", "py"];
+
+/// Local files imported directly by a source file (Rust `mod`, JS/TS relative
+/// imports, Python imports that resolve next to the file or from the project root)
+pub fn first_degree_imports(file: &Path, start_dir: &Path) -> Vec<PathBuf> {
+    let Ok(content) = fs::read_to_string(file) else {
+        return Vec::new();
+    };
+    let Some(dir) = file.parent() else {
+        return Vec::new();
+    };
+    let ext = file.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+    let imports: Vec<PathBuf> = match ext {
+        "rs" => {
+            // main.rs, lib.rs and mod.rs own their directory; other files own a
+            // directory named after themselves
+            let stem = file.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            let module_dir = if matches!(stem, "main" | "lib" | "mod") {
+                dir.to_path_buf()
+            } else {
+                dir.join(stem)
+            };
+            RUST_MOD
+                .captures_iter(&content)
+                .filter_map(|c| {
+                    let name = &c[1];
+                    [
+                        module_dir.join(format!("{}.rs", name)),
+                        module_dir.join(name).join("mod.rs"),
+                    ]
+                    .into_iter()
+                    .find(|p| p.is_file())
+                })
+                .collect()
+        }
+        "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" => JS_IMPORT
+            .captures_iter(&content)
+            .filter_map(|c| resolve_js_import(dir, &c[1]))
+            .collect(),
+        "py" => PY_IMPORT
+            .captures_iter(&content)
+            .filter_map(|c| {
+                let module = c.get(1).or_else(|| c.get(2))?.as_str();
+                resolve_py_module(dir, module).or_else(|| {
+                    // Absolute imports are relative to the project root
+                    (!module.starts_with('.'))
+                        .then(|| resolve_py_module(start_dir, module))
+                        .flatten()
+                })
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut result: Vec<PathBuf> = Vec::new();
+    for import in imports {
+        let import = import.canonicalize().unwrap_or(import);
+        if !result.contains(&import) {
+            result.push(import);
+        }
+    }
+    result
+}
+
+/// Entry points followed by their first-degree imports, without duplicates
+/// and limited to analyzed files
+pub fn entry_point_files(
+    start_dir: &Path,
+    dir_info: &DirectoryMap,
+) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let entries = detect_entry_points(start_dir, dir_info);
+
+    let mut imports: Vec<PathBuf> = Vec::new();
+    for entry in &entries {
+        for import in first_degree_imports(entry, start_dir) {
+            if file_tokens(dir_info, &import).is_some()
+                && !entries.contains(&import)
+                && !imports.contains(&import)
+            {
+                imports.push(import);
+            }
+        }
+    }
+
+    (entries, imports)
+}
+
+/// Suggest reading entry points and the modules they import
+pub fn entry_point_suggestions(entries: &[PathBuf], imports: &[PathBuf]) -> SuggestionMap {
+    let suggest = |paths: &[PathBuf], reason: &str| -> Vec<(PathBuf, Suggestion)> {
+        paths
+            .iter()
+            .map(|p| {
+                (
+                    p.clone(),
+                    Suggestion {
+                        action: "read".to_string(),
+                        reason: reason.to_string(),
+                    },
+                )
+            })
+            .collect()
+    };
+
+    suggest(entries, "entry point")
+        .into_iter()
+        .chain(suggest(imports, "imported by an entry point"))
+        .collect()
+}
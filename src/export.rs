@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use log::info;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::file_analysis::DirectoryMap;
+use crate::manifest::Manifest;
+
+/// Format of an exported selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// One file path per line, e.g. for aider's `--read` or custom loaders
+    List,
+    /// A shell script that `cat`s each file under a header
+    Script,
+}
+
+/// A file included in the context and the action that included it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectedFile {
+    pub path: PathBuf,
+    pub action: String,
+}
+
+/// Expand a manifest into the files it includes, in selection order.
+/// Directory actions apply to every text file below the directory, and a
+/// file keeps the first action that reached it.
+pub fn selected_files(
+    manifest: &Manifest,
+    start_dir: &Path,
+    dir_info: &DirectoryMap,
+) -> Vec<SelectedFile> {
+    let mut seen = HashSet::new();
+    let mut selected = Vec::new();
+
+    for entry in &manifest.entries {
+        if entry.action == "enter" {
+            continue;
+        }
+
+        // Collect files the same way processing walks a directory:
+        // its files first, then each subdirectory in turn
+        let path = start_dir.join(&entry.path);
+        let mut files = Vec::new();
+        if dir_info.contains_key(&path) {
+            let mut stack = vec![path];
+            while let Some(dir) = stack.pop() {
+                if let Some(info) = dir_info.get(&dir) {
+                    files.extend(info.files.iter().filter(|f| !f.binary).map(|f| f.path.clone()));
+                    stack.extend(info.subdirs.iter().rev().cloned());
+                }
+            }
+        } else if path.is_file() {
+            files.push(path);
+        }
+
+        for file in files {
+            if seen.insert(file.clone()) && entry.action != "exclude" {
+                selected.push(SelectedFile {
+                    path: file,
+                    action: entry.action.clone(),
+                });
+            }
+        }
+    }
+
+    selected
+}
+
+/// Quote a string for POSIX shells
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Render the selection in the given format
+pub fn render_export(files: &[SelectedFile], start_dir: &Path, format: ExportFormat) -> String {
+    let mut output = String::new();
+
+    match format {
+        ExportFormat::List => {
+            for file in files {
+                output.push_str(&format!("{}\n", file.path.display()));
+            }
+        }
+        ExportFormat::Script => {
+            output.push_str("#!/bin/sh\n");
+            output.push_str("# Selection exported by llm-context-loader\n");
+            for file in files {
+                let rel_path = file.path.strip_prefix(start_dir).unwrap_or(&file.path);
+                output.push_str(&format!(
+                    "\n# action: {}\nprintf '===== %s =====\\n' {}\ncat -- {}\n",
+                    file.action,
+                    shell_quote(&rel_path.display().to_string()),
+                    shell_quote(&file.path.display().to_string())
+                ));
+            }
+        }
+    }
+
+    output
+}
+
+/// Write the selection to a file (scripts are made executable)
+pub fn write_export(
+    path: &Path,
+    files: &[SelectedFile],
+    start_dir: &Path,
+    format: ExportFormat,
+) -> Result<()> {
+    fs::write(path, render_export(files, start_dir, format))
+        .context("Failed to write export file")?;
+
+    #[cfg(unix)]
+    if format == ExportFormat::Script {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .context("Failed to make export script executable")?;
+    }
+
+    info!("Exported {} files to {}", files.len(), path.display());
+
+    Ok(())
+}
@@ -7,6 +7,10 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 use walkdir::WalkDir;
 
+use crate::tokenizer::count_tokens;
+use crate::vcs::is_bare_repo;
+use crate::ui::{print_field, print_header, print_title, say};
+
 // Token estimation constants
 pub const TOKENS_PER_BYTE: f64 = 0.3;
 pub const CLAUDE_TOKEN_LIMIT: usize = 20000;
@@ -31,6 +35,25 @@ pub struct DirInfo {
 
 pub type DirectoryMap = HashMap<PathBuf, DirInfo>;
 
+/// Tokens in a text file, counted from its content; a file that can't be
+/// read falls back to an estimate from its size
+pub fn text_file_tokens(path: &Path, size: u64) -> usize {
+    match fs::read(path) {
+        Ok(bytes) => count_tokens(&String::from_utf8_lossy(&bytes)),
+        Err(_) => (size as f64 * TOKENS_PER_BYTE).ceil() as usize,
+    }
+}
+
+/// Tokens recorded for a file during analysis, if it was analyzed
+pub fn file_tokens(dir_info: &DirectoryMap, path: &Path) -> Option<usize> {
+    dir_info
+        .get(path.parent()?)?
+        .files
+        .iter()
+        .find(|f| f.path == path)
+        .map(|f| f.tokens)
+}
+
 /// Check if file is binary using the 'file' command
 pub fn is_binary(filepath: &Path) -> Result<bool> {
     let output = Command::new("file")
@@ -42,6 +65,33 @@ pub fn is_binary(filepath: &Path) -> Result<bool> {
     Ok(stdout.contains("charset=binary") || stdout.contains("application/octet-stream"))
 }
 
+/// Check if a path, relative to the directory being walked, matches an
+/// exclude pattern. Patterns ending in `/` match whole path components
+/// (`build/` skips `build` but not `build.rs`); anything else is a plain
+/// substring match
+pub fn matches_exclude(path: &Path, pattern: &str) -> bool {
+    let path_str = path.to_string_lossy();
+
+    match pattern.strip_suffix('/') {
+        Some(components) => {
+            format!("/{}/", path_str.trim_start_matches('/')).contains(&format!("/{}/", components))
+        }
+        None => path_str.contains(pattern),
+    }
+}
+
+/// Whether the walk from `root` skips a path: its path below the root
+/// matches an exclude pattern, or it is a bare repository, whose objects are
+/// history rather than source. The root itself is never skipped, whatever
+/// it or its ancestors are called.
+pub fn is_skipped(path: &Path, root: &Path, is_dir: bool, exclude_patterns: &[String]) -> bool {
+    let rel_path = path.strip_prefix(root).unwrap_or(path);
+    if rel_path.as_os_str().is_empty() {
+        return false;
+    }
+    exclude_patterns.iter().any(|p| matches_exclude(rel_path, p)) || (is_dir && is_bare_repo(path))
+}
+
 /// Recursively analyze directory structure
 pub fn analyze_directory(directory: &Path, exclude_patterns: &[String]) -> Result<DirectoryMap> {
     let mut result = DirectoryMap::new();
@@ -49,11 +99,7 @@ pub fn analyze_directory(directory: &Path, exclude_patterns: &[String]) -> Resul
     for entry in WalkDir::new(directory)
         .follow_links(true)
         .into_iter()
-        .filter_entry(|e| {
-            !exclude_patterns
-                .iter()
-                .any(|p| e.path().to_string_lossy().contains(p))
-        })
+        .filter_entry(|e| !is_skipped(e.path(), directory, e.file_type().is_dir(), exclude_patterns))
     {
         let entry = match entry {
             Ok(entry) => entry,
@@ -74,13 +120,10 @@ pub fn analyze_directory(directory: &Path, exclude_patterns: &[String]) -> Resul
                 for subdir_entry in entries.filter_map(Result::ok) {
                     let subdir_path = subdir_entry.path();
                     if subdir_path.is_dir()
-                        && !exclude_patterns
-                            .iter()
-                            .any(|p| subdir_path.to_string_lossy().contains(p))
+                        && !is_skipped(&subdir_path, directory, true, exclude_patterns)
+                        && let Some(dir_info) = result.get_mut(&dir_path)
                     {
-                        if let Some(dir_info) = result.get_mut(&dir_path) {
-                            dir_info.subdirs.push(subdir_path);
-                        }
+                        dir_info.subdirs.push(subdir_path);
                     }
                 }
             }
@@ -118,22 +161,23 @@ pub fn analyze_directory(directory: &Path, exclude_patterns: &[String]) -> Resul
     Ok(result)
 }
 
-fn process_file_info(filepath: &Path) -> Result<FileInfo> {
+/// Analyze one file: whether it is binary, its size and estimated tokens
+pub fn process_file_info(filepath: &Path) -> Result<FileInfo> {
     let binary = is_binary(filepath)?;
     let metadata = fs::metadata(filepath).context("Failed to get file metadata")?;
     let size = metadata.len();
-    let tokens = if binary {
-        0
-    } else {
-        (size as f64 * TOKENS_PER_BYTE).ceil() as usize
-    };
-
     let ext = filepath
         .extension()
         .and_then(|e| e.to_str())
         .unwrap_or("")
         .to_lowercase();
 
+    let tokens = if binary {
+        0
+    } else {
+        text_file_tokens(filepath, size)
+    };
+
     Ok(FileInfo {
         path: filepath.to_path_buf(),
         binary,
@@ -143,18 +187,80 @@ fn process_file_info(filepath: &Path) -> Result<FileInfo> {
     })
 }
 
-/// Display directory information
-pub fn show_dir_info(dir_path: &Path, info: &DirInfo) {
-    println!("\n{}", "=".repeat(60));
-    println!("DIR: {}", dir_path.display());
-    println!("{}", "=".repeat(60));
-    println!(
-        "Files: {} ({} text)",
-        info.total_files,
-        info.total_files - info.binary_files
+/// Totals for a directory and everything below it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TreeTotals {
+    pub files: usize,
+    pub binary_files: usize,
+    pub tokens: usize,
+    /// Subdirectories at any depth
+    pub dirs: usize,
+}
+
+/// Recursive totals for a directory; DirInfo itself only counts direct files
+pub fn tree_totals(dir_info: &DirectoryMap, dir: &Path) -> TreeTotals {
+    let mut totals = TreeTotals::default();
+    for (path, info) in dir_info.iter().filter(|(path, _)| path.starts_with(dir)) {
+        totals.files += info.total_files;
+        totals.binary_files += info.binary_files;
+        totals.tokens += info.tokens;
+        if path != dir {
+            totals.dirs += 1;
+        }
+    }
+    totals
+}
+
+/// The `n` immediate subdirectories with the most tokens below them
+pub fn largest_subtrees(dir_info: &DirectoryMap, dir: &Path, n: usize) -> Vec<(PathBuf, TreeTotals)> {
+    let Some(info) = dir_info.get(dir) else {
+        return Vec::new();
+    };
+
+    let mut subtrees: Vec<(PathBuf, TreeTotals)> = info
+        .subdirs
+        .iter()
+        .map(|sub| (sub.clone(), tree_totals(dir_info, sub)))
+        .collect();
+    subtrees.sort_by_key(|(path, totals)| (std::cmp::Reverse(totals.tokens), path.clone()));
+    subtrees.truncate(n);
+    subtrees
+}
+
+/// Display directory information, with totals for the whole tree below it
+pub fn show_dir_info(dir_path: &Path, dir_info: &DirectoryMap) {
+    let Some(info) = dir_info.get(dir_path) else {
+        return;
+    };
+    let totals = tree_totals(dir_info, dir_path);
+
+    print_header("DIR", dir_path, '=');
+    print_field(
+        "Files",
+        format!(
+            "{} ({} text), {} directly inside",
+            totals.files,
+            totals.files - totals.binary_files,
+            info.total_files
+        ),
     );
-    println!("Tokens: ~{}", info.tokens);
-    println!("Subdirs: {}", info.subdirs.len());
+    print_field(
+        "Tokens",
+        format!("~{}, ~{} directly inside", totals.tokens, info.tokens),
+    );
+    print_field(
+        "Subdirs",
+        format!("{} ({} at any depth)", info.subdirs.len(), totals.dirs),
+    );
+
+    let subtrees = largest_subtrees(dir_info, dir_path, 3);
+    if subtrees.iter().any(|(_, t)| t.tokens > 0) {
+        print_title("Largest subdirectories:");
+        for (path, sub) in &subtrees {
+            let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+            say(format!("  {}: ~{} tokens in {} files", name, sub.tokens, sub.files));
+        }
+    }
 
     // Count file extensions
     let mut exts: HashMap<String, usize> = HashMap::new();
@@ -164,12 +270,12 @@ pub fn show_dir_info(dir_path: &Path, info: &DirInfo) {
     }
 
     if !exts.is_empty() {
-        println!("\nExtensions:");
+        print_title("Extensions:");
         let mut ext_counts: Vec<(String, usize)> = exts.into_iter().collect();
-        ext_counts.sort_by(|a, b| b.1.cmp(&a.1));
+        ext_counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
 
         for (ext, count) in ext_counts.iter().take(5) {
-            println!("  {}: {}", ext, count);
+            say(format!("  {}: {}", ext, count));
         }
     }
 }
@@ -0,0 +1,956 @@
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::anchors::{Anchor, citation_id, format_anchor, is_block_end, parse_anchor};
+use crate::context_files::{
+    CITATION_INSTRUCTION, CONTINUATION_PREFIX, CONTINUED_PREFIX, MARKER_INSTRUCTION,
+    PartExtension, PartFormatter, READY_INSTRUCTION, continuation_marker, continued_marker, part_name,
+};
+use crate::processing::CODE_EXTENSIONS;
+use crate::tokenizer::count_tokens;
+
+/// Layout of the context parts. Blocks are built as text and each is
+/// written in the part's format as it is appended.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    /// Headings per directory and file, fenced code and a table of contents
+    Markdown,
+    /// Files in `<document>` tags, for long-context prompts
+    Xml,
+    /// Text parts plus a manifest of every file with its content, for tools
+    Json,
+}
+
+impl OutputFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Xml => "xml",
+            OutputFormat::Json => "json",
+        }
+    }
+
+    /// The formatter writing parts in this format
+    pub fn formatter(&self) -> &'static dyn PartFormatter {
+        match self {
+            OutputFormat::Text | OutputFormat::Json => &TextFormatter,
+            OutputFormat::Markdown => &MarkdownFormatter,
+            OutputFormat::Xml => &XmlFormatter,
+        }
+    }
+
+    /// Extension of parts in this format when --part-ext isn't given
+    pub fn part_extension(&self) -> PartExtension {
+        match self {
+            OutputFormat::Text | OutputFormat::Json => PartExtension::Txt,
+            OutputFormat::Markdown => PartExtension::Md,
+            OutputFormat::Xml => PartExtension::Xml,
+        }
+    }
+}
+
+/// Name of the manifest --format json writes beside the parts
+pub const JSON_MANIFEST_FILE_NAME: &str = "context.json";
+
+/// Start of the comment naming the format of a rendered part
+const FORMAT_MARKER_PREFIX: &str = "<!-- llm-context-loader format: ";
+
+const FILE_START_PREFIX: &str = "===== FILE START: ";
+const FILE_END_PREFIX: &str = "===== FILE END: ";
+const DIRECTORY_PREFIX: &str = "## DIRECTORY: ";
+const DIRECTORY_CONTENT_LINE: &str = "===== DIRECTORY CONTENT=====";
+const COLLECTION_END_PREFIX: &str = "===== END OF FILE COLLECTION";
+
+/// Titles opening the blocks of actions that don't include the file as is
+const BLOCK_TITLES: [(&str, BlockKind); 5] = [
+    ("# Summary of ", BlockKind::Summary),
+    ("# Sample of ", BlockKind::Sample),
+    ("# Skeleton of ", BlockKind::Skeleton),
+    ("# API docs of ", BlockKind::Docs),
+    ("# File: ", BlockKind::Stats),
+];
+
+/// How a file block presents its file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+    /// The file's text, whole or in line ranges, between START and END tags
+    Content,
+    Summary,
+    Sample,
+    Skeleton,
+    Docs,
+    Stats,
+}
+
+/// Where the rest of a split block is: the part and the marker pairing the pieces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Continued {
+    pub part: String,
+    pub marker: String,
+}
+
+/// A file block of a part
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileBlock {
+    pub anchor: Anchor,
+    /// Path as written in the block, absolute with --absolute-paths
+    pub shown_path: String,
+    pub kind: BlockKind,
+    /// Blob hash on the FILE START line
+    pub blob: Option<String>,
+    /// Other bracketed notes on the FILE START line, e.g. `lines 1-20 of 80`
+    pub notes: Vec<String>,
+    /// Extension on the fence around the file's content, for code files
+    pub fence: Option<String>,
+    pub content: String,
+    pub continued: Option<Continued>,
+}
+
+/// The piece of a split block continuing one from the previous part
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Continuation {
+    pub path: String,
+    pub previous_part: String,
+    pub marker: String,
+    /// Note on the lines repeated from the previous piece
+    pub note: Option<String>,
+    /// Text of the piece as split from the block, ending with the FILE END
+    /// line in the last piece
+    pub content: String,
+    pub continued: Option<Continued>,
+}
+
+/// A directory, file or continuation in a part, in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartItem {
+    Directory { path: String, body: String },
+    File(FileBlock),
+    Continuation(Continuation),
+}
+
+/// A text part split into what the other formats render
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedPart {
+    /// First line, naming the part and how many there are
+    pub title: String,
+    /// Instructions, or the preamble, before the directory content
+    pub intro: Vec<String>,
+    /// `Key: value` lines after the directory content line: source
+    /// directory and generation metadata
+    pub details: Vec<(String, String)>,
+    /// Anything else before the first block, such as the project tree
+    pub overview: String,
+    pub items: Vec<PartItem>,
+    /// Lines after the end of the file collection
+    pub closing: Vec<String>,
+}
+
+/// Whether a line starts a directory, file or continuation
+fn starts_item(line: &str) -> bool {
+    is_block_end(line) || line.starts_with(CONTINUATION_PREFIX)
+}
+
+fn trim_blank_lines(lines: &mut Vec<&str>) {
+    while lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
+    while lines.first().is_some_and(|l| l.trim().is_empty()) {
+        lines.remove(0);
+    }
+}
+
+/// Take a trailing CONTINUED IN marker off a block's lines
+fn take_continued(lines: &mut Vec<&str>) -> Option<Continued> {
+    let rest = lines.last()?.strip_prefix(CONTINUED_PREFIX)?;
+    let (part, marker) = rest.trim_end_matches(" =====").rsplit_once(" AT MARKER ")?;
+    let continued = Continued {
+        part: part.to_string(),
+        marker: marker.to_string(),
+    };
+    lines.pop();
+    trim_blank_lines(lines);
+    Some(continued)
+}
+
+/// Take the FILE END line and the closing fence of a fenced block off its lines
+fn take_file_end(lines: &mut Vec<&str>, fenced: bool) {
+    if lines.last().is_some_and(|l| l.starts_with(FILE_END_PREFIX)) {
+        lines.pop();
+        if fenced && lines.last() == Some(&"```") {
+            lines.pop();
+        }
+    }
+}
+
+/// Split the lines of a file block after its anchor into its title or
+/// START line, notes and content
+fn parse_file_block(anchor: Anchor, mut lines: Vec<&str>) -> FileBlock {
+    trim_blank_lines(&mut lines);
+    let mut block = FileBlock {
+        shown_path: anchor.path.display().to_string(),
+        anchor,
+        kind: BlockKind::Content,
+        blob: None,
+        notes: Vec::new(),
+        fence: None,
+        content: String::new(),
+        continued: None,
+    };
+
+    let first = lines.first().copied().unwrap_or_default();
+    if let Some(start) = first.strip_prefix(FILE_START_PREFIX) {
+        lines.remove(0);
+        let start = start.trim_end_matches(" =====");
+        let (path, notes) = start.split_once(" [").unwrap_or((start, ""));
+        block.shown_path = path.to_string();
+        for note in notes.trim_end_matches(']').split("] [").filter(|n| !n.is_empty()) {
+            match note.strip_prefix("blob ") {
+                Some(blob) => block.blob = Some(blob.to_string()),
+                None => block.notes.push(note.to_string()),
+            }
+        }
+        if let Some(ext) = lines.first().and_then(|l| l.strip_prefix("```"))
+            && !ext.is_empty()
+        {
+            block.fence = Some(ext.to_string());
+            lines.remove(0);
+        }
+        take_file_end(&mut lines, block.fence.is_some());
+        trim_blank_lines(&mut lines);
+    } else if let Some((title, kind)) = BLOCK_TITLES.iter().find(|(title, _)| first.starts_with(title)) {
+        block.shown_path = first[title.len()..].to_string();
+        block.kind = *kind;
+        lines.remove(0);
+    }
+    block.content = lines.join("\n");
+    block
+}
+
+/// A file block from its text, anchor line first
+fn parse_block(text: &str, continued: Option<&Continued>) -> Option<FileBlock> {
+    let mut lines = text.lines();
+    let anchor = parse_anchor(lines.next()?)?;
+    let mut block = parse_file_block(anchor, lines.collect());
+    block.continued = continued.cloned();
+    Some(block)
+}
+
+fn parse_continuation(line: &str, mut lines: Vec<&str>) -> Option<Continuation> {
+    let rest = line.strip_prefix(CONTINUATION_PREFIX)?.trim_end_matches(" =====");
+    let (rest, marker) = rest.rsplit_once(" AT MARKER ")?;
+    let (path, previous_part) = rest.rsplit_once(" FROM ")?;
+
+    trim_blank_lines(&mut lines);
+    let continued = take_continued(&mut lines);
+    let note = match lines.first() {
+        Some(first) if first.starts_with("(The first ") => Some(lines.remove(0).to_string()),
+        _ => None,
+    };
+
+    Some(Continuation {
+        path: path.to_string(),
+        previous_part: previous_part.to_string(),
+        marker: marker.to_string(),
+        note,
+        content: lines.join("\n"),
+        continued,
+    })
+}
+
+/// The file's lines in a continuation, without the FILE END line and
+/// closing fence that end the last piece
+pub fn continuation_content(piece: &Continuation) -> String {
+    let mut lines: Vec<&str> = piece.content.lines().collect();
+    // Only code files are fenced; the opening fence was in the first piece
+    let ext = extension(&piece.path);
+    take_file_end(&mut lines, CODE_EXTENSIONS.contains(&format!(".{}", ext).as_str()));
+    trim_blank_lines(&mut lines);
+    lines.join("\n")
+}
+
+/// Index of the line after the content of the item starting at `start`.
+/// File contents may hold lines that look like markers, so a block with a
+/// FILE START line runs to the FILE END line for its path, or when its rest
+/// is in the next part, to the part's CONTINUED IN marker.
+fn content_end(lines: &[&str], start: usize) -> usize {
+    let path = match lines.get(start + 1).and_then(|l| l.strip_prefix(FILE_START_PREFIX)) {
+        Some(rest) if parse_anchor(lines[start]).is_some() => rest.trim_end_matches(" =====").split(" [").next(),
+        _ => lines[start]
+            .strip_prefix(CONTINUATION_PREFIX)
+            .and_then(|rest| rest.rsplit_once(" FROM "))
+            .map(|(path, _)| path),
+    };
+    let Some(path) = path else {
+        return start + 1;
+    };
+    let end_line = format!("{}{} =====", FILE_END_PREFIX, path);
+    lines[start + 1..]
+        .iter()
+        .position(|l| *l == end_line)
+        .map(|at| start + 1 + at)
+        .or_else(|| lines.iter().rposition(|l| l.starts_with(CONTINUED_PREFIX)).filter(|at| *at > start))
+        .map_or(start + 1, |at| at + 1)
+}
+
+/// Index of the line ending the item starting at `start`: the next marker
+/// outside its content. Markdown and XML parts keep their markers in
+/// comments, so only those count there.
+pub fn block_end(lines: &[&str], start: usize, format: OutputFormat) -> usize {
+    let (from, commented) = match format {
+        OutputFormat::Text | OutputFormat::Json => (content_end(lines, start), false),
+        OutputFormat::Markdown | OutputFormat::Xml => (start + 1, true),
+    };
+    lines[from..]
+        .iter()
+        .position(|l| is_block_end(l) && (!commented || l.starts_with("<!-- ")))
+        .map_or(lines.len(), |at| from + at)
+}
+
+/// Fill in a part's title, instructions and details from its header lines
+fn parse_header(lines: &[&str], part: &mut ParsedPart) {
+    part.title = lines.first().map(|l| l.to_string()).unwrap_or_default();
+    let header = lines.get(1..).unwrap_or_default();
+    let (intro, rest) = match header.iter().position(|l| *l == DIRECTORY_CONTENT_LINE) {
+        Some(at) => (&header[..at], &header[at + 1..]),
+        None => (header, &[][..]),
+    };
+    part.intro = intro.iter().map(|l| l.to_string()).collect();
+    let details = rest.iter().take_while(|l| l.contains(": ")).count();
+    part.details = rest[..details]
+        .iter()
+        .filter_map(|l| l.split_once(": "))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    part.overview = rest[details..].join("\n").trim().to_string();
+}
+
+/// Split a text part into its header, items and closing lines
+pub fn parse_part(content: &str) -> ParsedPart {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut part = ParsedPart::default();
+
+    let header_end = lines.iter().position(|l| starts_item(l)).unwrap_or(lines.len());
+    parse_header(&lines[..header_end], &mut part);
+
+    let mut start = header_end;
+    while start < lines.len() {
+        let from = content_end(&lines, start);
+        let end = lines[from..]
+            .iter()
+            .position(|l| starts_item(l))
+            .map_or(lines.len(), |at| from + at);
+        let line = lines[start];
+        let mut body = lines[start + 1..end].to_vec();
+        if line.starts_with(COLLECTION_END_PREFIX) {
+            let mut closing = lines[start + 1..].to_vec();
+            trim_blank_lines(&mut closing);
+            part.closing = closing.iter().map(|l| l.to_string()).collect();
+            break;
+        } else if let Some(path) = line.strip_prefix(DIRECTORY_PREFIX) {
+            part.items.push(PartItem::Directory {
+                path: path.to_string(),
+                body: body.join("\n").trim().to_string(),
+            });
+        } else if let Some(anchor) = parse_anchor(line) {
+            trim_blank_lines(&mut body);
+            let continued = take_continued(&mut body);
+            let mut block = parse_file_block(anchor, body);
+            block.continued = continued;
+            part.items.push(PartItem::File(block));
+        } else if let Some(continuation) = parse_continuation(line, body) {
+            part.items.push(PartItem::Continuation(continuation));
+        }
+        start = end;
+    }
+    part
+}
+
+/// The lines closing a part, after the end of the file collection
+fn closing_lines(footer: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = footer
+        .lines()
+        .skip_while(|l| !l.starts_with(COLLECTION_END_PREFIX))
+        .skip(1)
+        .collect();
+    trim_blank_lines(&mut lines);
+    lines
+}
+
+/// Markdown language tag for a file extension, so fences highlight right
+pub fn language_tag(ext: &str) -> String {
+    let ext = ext.to_lowercase();
+    let tag = match ext.as_str() {
+        "" | "txt" => "text",
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" | "mts" | "cts" => "typescript",
+        "rb" => "ruby",
+        "sh" | "bash" | "zsh" => "bash",
+        "yml" | "yaml" => "yaml",
+        "md" | "markdown" => "markdown",
+        "h" => "c",
+        "cc" | "cxx" | "hpp" | "hh" | "hxx" => "cpp",
+        "cs" => "csharp",
+        "kt" | "kts" => "kotlin",
+        "ps1" => "powershell",
+        "htm" => "html",
+        "tf" => "hcl",
+        _ => return ext,
+    };
+    tag.to_string()
+}
+
+/// A fence longer than any run of backticks opening a line of the content
+fn fence_for(content: &str) -> String {
+    let longest = content
+        .lines()
+        .map(|l| l.trim_start().chars().take_while(|c| *c == '`').count())
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn fenced(content: &str, tag: &str) -> String {
+    let fence = fence_for(content);
+    format!("{}{}\n{}\n{}\n", fence, tag, content, fence)
+}
+
+/// Give fences already in a block (skeletons, API docs) language tags in
+/// place of extensions
+fn retag_fences(content: &str) -> String {
+    content
+        .lines()
+        .map(|line| match line.strip_prefix("```") {
+            Some(ext) if !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric()) => {
+                format!("```{}", language_tag(ext))
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn extension(path: &str) -> &str {
+    Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("")
+}
+
+fn file_id(anchor: &Anchor) -> String {
+    anchor.citation.clone().unwrap_or_else(|| citation_id(&anchor.path))
+}
+
+/// Link target of a directory's heading
+fn directory_id(path: &str) -> String {
+    format!("dir-{}", citation_id(Path::new(path)))
+}
+
+/// A marker line kept as a comment, so parts in any format share the lines
+/// updating, merging and checking look for
+fn comment(line: &str) -> String {
+    format!("<!-- {} -->\n", line)
+}
+
+/// The marker comment ending a piece continued in the next part
+fn continued_comment(continued: Option<&Continued>) -> String {
+    continued.map_or_else(String::new, |c| comment(&continued_marker(&c.part, &c.marker)))
+}
+
+/// Parts as text, the layout every other format starts from
+pub struct TextFormatter;
+
+impl PartFormatter for TextFormatter {
+    fn header(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn overview(&self, text: &str) -> String {
+        format!("\n\n{}\n", text)
+    }
+
+    fn directory(&self, path: &Path, body: &str) -> String {
+        format!("\n\n{}{}\n{}", DIRECTORY_PREFIX, path.display(), body)
+    }
+
+    fn file(&self, text: &str, continued: Option<&Continued>) -> String {
+        let mut out = format!("\n\n{}", text);
+        if let Some(c) = continued {
+            out.push_str(&format!("\n{}\n", continued_marker(&c.part, &c.marker)));
+        }
+        out
+    }
+
+    fn continuation(&self, piece: &Continuation) -> String {
+        let mut out = format!(
+            "\n\n{}\n",
+            continuation_marker(Path::new(&piece.path), &piece.previous_part, &piece.marker)
+        );
+        if let Some(note) = &piece.note {
+            out.push_str(&format!("{}\n", note));
+        }
+        out.push_str(&piece.content);
+        if let Some(c) = &piece.continued {
+            out.push_str(&format!("\n{}\n", continued_marker(&c.part, &c.marker)));
+        }
+        out
+    }
+
+    fn footer(&self, text: &str) -> String {
+        text.to_string()
+    }
+}
+
+/// Comments around the table of contents in a markdown header, which is
+/// filled in once the part is finished and again whenever it is rewritten
+const CONTENTS_START: &str = "<!-- llm-context-loader contents -->";
+const CONTENTS_END: &str = "<!-- end of contents -->";
+
+/// Markdown with a heading per directory and file and a table of contents.
+/// The title stays the first line, so the part is still recognized, and
+/// markers are kept as comments for tools reading the part back.
+pub struct MarkdownFormatter;
+
+impl PartFormatter for MarkdownFormatter {
+    fn header(&self, text: &str) -> String {
+        let mut part = ParsedPart::default();
+        parse_header(&text.lines().collect::<Vec<_>>(), &mut part);
+        let mut out = format!("{}\n{}{} -->\n\n", part.title, FORMAT_MARKER_PREFIX, OutputFormat::Markdown.as_str());
+
+        // Standard instructions are paragraphs of their own; a preamble is kept as written
+        for line in &part.intro {
+            match line.as_str() {
+                CITATION_INSTRUCTION => {
+                    out.push_str("Each file's heading gives its citation ID, such as [F3A9C1]; cite these IDs when referring to code.\n\n")
+                }
+                MARKER_INSTRUCTION => out.push_str("Each file is a section of its own, with its content in a fenced code block.\n\n"),
+                READY_INSTRUCTION => out.push_str(&format!("{}\n\n", line)),
+                _ => out.push_str(&format!("{}\n", line)),
+            }
+        }
+        if !out.ends_with("\n\n") {
+            out.push('\n');
+        }
+        for (key, value) in &part.details {
+            out.push_str(&format!("- {}: {}\n", key, value));
+        }
+        out.push_str(&format!("\n{}\n{}\n", CONTENTS_START, CONTENTS_END));
+        out
+    }
+
+    fn overview(&self, text: &str) -> String {
+        format!("\n{}\n", text)
+    }
+
+    fn directory(&self, path: &Path, body: &str) -> String {
+        let path = path.display().to_string();
+        let mut out = format!(
+            "\n{}<a id=\"{}\"></a>\n\n## {}/\n",
+            comment(&format!("{}{}", DIRECTORY_PREFIX, path)),
+            directory_id(&path),
+            path.trim_end_matches('/')
+        );
+        if !body.trim().is_empty() {
+            out.push('\n');
+            for line in body.lines().filter(|l| !l.trim().is_empty()) {
+                match line.strip_prefix("  ") {
+                    Some(nested) => out.push_str(&format!("  - {}\n", nested)),
+                    None => out.push_str(&format!("- {}\n", line)),
+                }
+            }
+        }
+        out
+    }
+
+    fn file(&self, text: &str, continued: Option<&Continued>) -> String {
+        let Some(block) = parse_block(text, continued) else {
+            return TextFormatter.file(text, continued);
+        };
+        let id = file_id(&block.anchor);
+        let anchor = format_anchor(&block.anchor.path, &block.anchor.hash, &block.anchor.action);
+        let mut out = format!(
+            "\n{}<a id=\"{}\"></a>\n\n### {} [{}]\n\n",
+            comment(&anchor),
+            id,
+            block.shown_path,
+            id
+        );
+
+        let mut info = vec![block.anchor.action.spec()];
+        info.extend(block.blob.iter().map(|b| format!("blob {}", b)));
+        info.extend(block.notes.iter().cloned());
+        out.push_str(&format!("*{}*\n\n", info.join(" · ")));
+
+        let tag = language_tag(extension(&block.shown_path));
+        match block.kind {
+            BlockKind::Content => out.push_str(&fenced(&block.content, &tag)),
+            BlockKind::Stats => {
+                for line in block.content.lines().filter(|l| !l.trim().is_empty()) {
+                    out.push_str(&format!("- {}\n", line.trim()));
+                }
+            }
+            BlockKind::Sample if !block.content.contains("```") => out.push_str(&fenced(&block.content, &tag)),
+            BlockKind::Summary | BlockKind::Sample | BlockKind::Skeleton | BlockKind::Docs => {
+                out.push_str(&format!("{}\n", retag_fences(&block.content)))
+            }
+        }
+        out.push_str(&markdown_continued(continued));
+        out
+    }
+
+    fn continuation(&self, piece: &Continuation) -> String {
+        let marker = continuation_marker(Path::new(&piece.path), &piece.previous_part, &piece.marker);
+        let mut out = format!(
+            "\n{}<a id=\"cont-{}\"></a>\n\n### {} (continued)\n\n*Continued from {} (marker {}).",
+            comment(&marker),
+            piece.marker,
+            piece.path,
+            piece.previous_part,
+            piece.marker
+        );
+        if let Some(note) = &piece.note {
+            out.push_str(&format!(" {}", note));
+        }
+        out.push_str("*\n\n");
+        out.push_str(&fenced(&continuation_content(piece), &language_tag(extension(&piece.path))));
+        out.push_str(&markdown_continued(piece.continued.as_ref()));
+        out
+    }
+
+    fn footer(&self, text: &str) -> String {
+        let mut out = format!(
+            "\n{}\n---\n\n**End of file collection.**\n\n",
+            comment(&format!("{} =====", COLLECTION_END_PREFIX))
+        );
+        for line in closing_lines(text).iter().filter(|l| !l.trim().is_empty()) {
+            out.push_str(&format!("{}\n\n", line));
+        }
+        out
+    }
+
+    /// List the part's directories, files and continuations under its
+    /// header, found by their marker comments
+    fn finish(&self, content: &str) -> Option<String> {
+        let mut contents = String::from("## Contents\n");
+        // Entries under the directory listed last are nested below it
+        let mut directory: Option<&str> = None;
+        let indent = |directory: Option<&str>, path: &str| match directory {
+            Some(dir) if dir == "." || path.starts_with(&format!("{}/", dir.trim_end_matches('/'))) => "  ",
+            _ => "",
+        };
+        for line in content.lines() {
+            let Some(marker) = line.strip_prefix("<!-- ").and_then(|l| l.strip_suffix(" -->")) else {
+                continue;
+            };
+            if let Some(path) = marker.strip_prefix(DIRECTORY_PREFIX) {
+                contents.push_str(&format!("\n- [{}/](#{})", path.trim_end_matches('/'), directory_id(path)));
+                directory = Some(path);
+            } else if let Some(anchor) = parse_anchor(marker) {
+                let path = anchor.path.display().to_string();
+                contents.push_str(&format!(
+                    "\n{}- [{}](#{}) ({})",
+                    indent(directory, &path),
+                    path,
+                    file_id(&anchor),
+                    anchor.action.spec()
+                ));
+            } else if let Some(piece) = parse_continuation(marker, Vec::new()) {
+                contents.push_str(&format!(
+                    "\n{}- [{} (continued)](#cont-{})",
+                    indent(directory, &piece.path),
+                    piece.path,
+                    piece.marker
+                ));
+            }
+        }
+        let start = content.find(CONTENTS_START)?;
+        let end = start + content[start..].find(CONTENTS_END)? + CONTENTS_END.len();
+        Some(format!(
+            "{}{}\n{}\n{}{}",
+            &content[..start],
+            CONTENTS_START,
+            contents,
+            CONTENTS_END,
+            &content[end..]
+        ))
+    }
+}
+
+/// The note ending a markdown piece continued in the next part
+fn markdown_continued(continued: Option<&Continued>) -> String {
+    match continued {
+        Some(c) => format!(
+            "\n{}*Continued in {} (marker {}).*\n",
+            continued_comment(Some(c)),
+            c.part,
+            c.marker
+        ),
+        None => String::new(),
+    }
+}
+
+/// Text with `&`, `<` and `>` escaped, for tags holding short values
+fn xml_text(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// An attribute value, quotes escaped as well
+fn xml_attr(text: &str) -> String {
+    xml_text(text).replace('"', "&quot;")
+}
+
+fn xml_continued(continued: Option<&Continued>) -> String {
+    match continued {
+        Some(c) => format!(" continued_in=\"{}\" marker=\"{}\"", xml_attr(&c.part), xml_attr(&c.marker)),
+        None => String::new(),
+    }
+}
+
+/// Each file in `<document>` tags with its `<source>` and
+/// `<document_contents>`, the layout Anthropic recommends for long
+/// documents. File contents go in as written, since the tags delimit them
+/// for a model rather than an XML parser. The title stays the first line so
+/// the part is still recognized, and markers are kept as comments.
+pub struct XmlFormatter;
+
+impl PartFormatter for XmlFormatter {
+    fn header(&self, text: &str) -> String {
+        let mut part = ParsedPart::default();
+        parse_header(&text.lines().collect::<Vec<_>>(), &mut part);
+        let mut out = format!("{}\n{}{} -->\n", part.title, FORMAT_MARKER_PREFIX, OutputFormat::Xml.as_str());
+
+        out.push_str("<instructions>\n");
+        for line in &part.intro {
+            match line.as_str() {
+                CITATION_INSTRUCTION => out.push_str(
+                    "Each document carries a citation ID, such as F3A9C1; cite these IDs when referring to code.\n",
+                ),
+                MARKER_INSTRUCTION => out.push_str(
+                    "Each file is a <document>, with its path in <source> and its text in <document_contents>.\n",
+                ),
+                _ => out.push_str(&format!("{}\n", line)),
+            }
+        }
+        out.push_str("</instructions>\n");
+
+        if !part.details.is_empty() {
+            out.push_str("<context_info>\n");
+            for (key, value) in &part.details {
+                out.push_str(&format!("{}: {}\n", key, value));
+            }
+            out.push_str("</context_info>\n");
+        }
+        out.push_str("<documents>\n");
+        out
+    }
+
+    fn overview(&self, text: &str) -> String {
+        format!("<overview>\n{}\n</overview>\n", text)
+    }
+
+    fn directory(&self, path: &Path, body: &str) -> String {
+        let path = path.display().to_string();
+        let mut out = format!(
+            "{}<directory path=\"{}\"",
+            comment(&format!("{}{}", DIRECTORY_PREFIX, path)),
+            xml_attr(&path)
+        );
+        match body.trim() {
+            "" => out.push_str(" />\n"),
+            body => out.push_str(&format!(">\n{}\n</directory>\n", body)),
+        }
+        out
+    }
+
+    fn file(&self, text: &str, continued: Option<&Continued>) -> String {
+        let Some(block) = parse_block(text, continued) else {
+            return TextFormatter.file(text, continued);
+        };
+        let anchor = format_anchor(&block.anchor.path, &block.anchor.hash, &block.anchor.action);
+        let mut out = format!(
+            "{}<document citation=\"{}\" action=\"{}\"",
+            comment(&anchor),
+            file_id(&block.anchor),
+            xml_attr(&block.anchor.action.spec())
+        );
+        if let Some(blob) = &block.blob {
+            out.push_str(&format!(" blob=\"{}\"", xml_attr(blob)));
+        }
+        if !block.notes.is_empty() {
+            out.push_str(&format!(" notes=\"{}\"", xml_attr(&block.notes.join("; "))));
+        }
+        out.push_str(&xml_continued(continued));
+        out.push_str(&format!(
+            ">\n<source>{}</source>\n<document_contents>\n{}\n</document_contents>\n</document>\n",
+            xml_text(&block.shown_path),
+            block.content
+        ));
+        out.push_str(&continued_comment(continued));
+        out
+    }
+
+    fn continuation(&self, piece: &Continuation) -> String {
+        let marker = continuation_marker(Path::new(&piece.path), &piece.previous_part, &piece.marker);
+        let mut out = format!(
+            "{}<document continued_from=\"{}\" marker=\"{}\"{}>\n<source>{}</source>\n",
+            comment(&marker),
+            xml_attr(&piece.previous_part),
+            xml_attr(&piece.marker),
+            xml_continued(piece.continued.as_ref()),
+            xml_text(&piece.path)
+        );
+        if let Some(note) = &piece.note {
+            out.push_str(&format!("<note>{}</note>\n", xml_text(note)));
+        }
+        out.push_str(&format!(
+            "<document_contents>\n{}\n</document_contents>\n</document>\n",
+            continuation_content(piece)
+        ));
+        out.push_str(&continued_comment(piece.continued.as_ref()));
+        out
+    }
+
+    fn footer(&self, text: &str) -> String {
+        let mut out = format!("{}</documents>\n", comment(&format!("{} =====", COLLECTION_END_PREFIX)));
+        let closing = closing_lines(text);
+        if !closing.is_empty() {
+            out.push_str(&format!("<closing>\n{}\n</closing>\n", closing.join("\n")));
+        }
+        out
+    }
+}
+
+/// A file in the JSON manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JsonEntry {
+    /// Path relative to the source directory
+    pub path: PathBuf,
+    pub citation: String,
+    pub action: String,
+    /// Estimated tokens of the content or summary
+    pub tokens: usize,
+    /// What the parts hold of the file, for every action but summarize
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// Part the file's block is in, or starts in when split
+    pub context_file: String,
+}
+
+/// Every file block in some parts, in order. A block split across parts
+/// is one entry, with the lines its pieces repeat dropped.
+pub fn json_entries(parts: &[PathBuf]) -> Result<Vec<JsonEntry>> {
+    let mut entries: Vec<JsonEntry> = Vec::new();
+    // Entry each CONTINUED IN marker belongs to
+    let mut split: HashMap<String, usize> = HashMap::new();
+    for part in parts {
+        let text = fs::read_to_string(part)
+            .with_context(|| format!("Failed to read context file {}", part.display()))?;
+        for item in parse_part(&text).items {
+            match item {
+                PartItem::Directory { .. } => {}
+                PartItem::File(block) => {
+                    if let Some(continued) = &block.continued {
+                        split.insert(continued.marker.clone(), entries.len());
+                    }
+                    let summarized = block.kind == BlockKind::Summary;
+                    entries.push(JsonEntry {
+                        citation: file_id(&block.anchor),
+                        action: block.anchor.action.spec(),
+                        path: block.anchor.path,
+                        tokens: 0,
+                        content: (!summarized).then(|| block.content.clone()),
+                        summary: summarized.then_some(block.content),
+                        context_file: part_name(part),
+                    });
+                }
+                PartItem::Continuation(piece) => {
+                    let Some(&idx) = split.get(&piece.marker) else {
+                        continue;
+                    };
+                    let repeated: usize = piece
+                        .note
+                        .as_deref()
+                        .and_then(|n| n.strip_prefix("(The first "))
+                        .and_then(|n| n.split(' ').next())
+                        .and_then(|n| n.parse().ok())
+                        .unwrap_or(0);
+                    let content = continuation_content(&piece);
+                    let rest: Vec<&str> = content.lines().skip(repeated).collect();
+                    let entry = &mut entries[idx];
+                    if let Some(text) = entry.content.as_mut().or(entry.summary.as_mut()) {
+                        text.push('\n');
+                        text.push_str(&rest.join("\n"));
+                    }
+                    if let Some(continued) = piece.continued {
+                        split.insert(continued.marker, idx);
+                    }
+                }
+            }
+        }
+    }
+    for entry in &mut entries {
+        entry.tokens = count_tokens(entry.content.as_deref().or(entry.summary.as_deref()).unwrap_or(""));
+    }
+    Ok(entries)
+}
+
+/// Write the JSON manifest of some parts beside them
+pub fn write_json_manifest(parts: &[PathBuf]) -> Result<PathBuf> {
+    let Some(dir) = parts.first().and_then(|p| p.parent()) else {
+        bail!("No context parts to list");
+    };
+    let path = dir.join(JSON_MANIFEST_FILE_NAME);
+    let json = serde_json::to_string_pretty(&json_entries(parts)?).context("Failed to serialize JSON manifest")?;
+    fs::write(&path, json + "\n").with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// The files to hand on once parts are finished: the parts, or for JSON
+/// the manifest written beside them
+pub fn render_parts(parts: &[PathBuf], format: OutputFormat) -> Result<Vec<PathBuf>> {
+    if format == OutputFormat::Json {
+        return Ok(vec![write_json_manifest(parts)?]);
+    }
+    Ok(parts.to_vec())
+}
+
+/// The format a part was written in, from the marker rendered parts carry
+pub fn part_format(content: &str) -> OutputFormat {
+    content
+        .lines()
+        .take(3)
+        .find_map(|l| l.strip_prefix(FORMAT_MARKER_PREFIX)?.strip_suffix(" -->"))
+        .and_then(|name| OutputFormat::from_str(name, true).ok())
+        .unwrap_or_default()
+}
+
+/// The format some parts were written in. Updating, merging and appending
+/// write new blocks in that format, so parts in different formats can't be
+/// combined.
+pub fn parts_format(parts: &[PathBuf]) -> Result<OutputFormat> {
+    let mut found: Option<(OutputFormat, &PathBuf)> = None;
+    for part in parts {
+        let content = fs::read_to_string(part)
+            .with_context(|| format!("Failed to read context file {}", part.display()))?;
+        let format = part_format(&content);
+        match found {
+            Some((first, first_part)) if first != format => bail!(
+                "{} was written with --format {} but {} with --format {}; rebuild the context in one format",
+                first_part.display(),
+                first.as_str(),
+                part.display(),
+                format.as_str()
+            ),
+            Some(_) => {}
+            None => found = Some((format, part)),
+        }
+    }
+    Ok(found.map(|(format, _)| format).unwrap_or_default())
+}
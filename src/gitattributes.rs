@@ -0,0 +1,161 @@
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use log::{info, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::file_analysis::DirectoryMap;
+use crate::suggestions::{Suggestion, SuggestionMap, propagate_to_directories};
+
+/// Default action for files marked `linguist-generated`
+pub const GENERATED_ACTION: &str = "exclude";
+/// Default action for files marked `linguist-vendored`
+pub const VENDORED_ACTION: &str = "stats";
+
+/// A single `.gitattributes` line that sets a linguist attribute
+#[derive(Debug)]
+pub struct LinguistRule {
+    /// Directory containing the .gitattributes file the rule came from
+    base: PathBuf,
+    matcher: GlobMatcher,
+    generated: Option<bool>,
+    vendored: Option<bool>,
+}
+
+/// Parse the value of a gitattributes attribute token for the given name
+fn parse_attribute(token: &str, name: &str) -> Option<bool> {
+    if token == name {
+        return Some(true);
+    }
+    if let Some(rest) = token.strip_prefix('-').or_else(|| token.strip_prefix('!'))
+        && rest == name
+    {
+        return Some(false);
+    }
+    match token.strip_prefix(name).and_then(|t| t.strip_prefix('=')) {
+        Some("true") | Some("1") => Some(true),
+        Some(_) => Some(false),
+        None => None,
+    }
+}
+
+/// Build a matcher for a gitattributes pattern relative to its file's directory
+fn build_matcher(pattern: &str) -> Result<GlobMatcher> {
+    // Patterns without a slash match at any depth, others are anchored
+    let glob = if pattern.contains('/') {
+        pattern.trim_start_matches('/').to_string()
+    } else {
+        format!("**/{}", pattern)
+    };
+
+    Ok(GlobBuilder::new(&glob)
+        .literal_separator(true)
+        .build()
+        .with_context(|| format!("Invalid .gitattributes pattern: {}", pattern))?
+        .compile_matcher())
+}
+
+/// Parse the linguist rules of a single .gitattributes file
+pub fn parse_gitattributes(base: &Path, content: &str) -> Vec<LinguistRule> {
+    let mut rules = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut tokens = line.split_whitespace();
+        let Some(pattern) = tokens.next() else {
+            continue;
+        };
+
+        let mut generated = None;
+        let mut vendored = None;
+        for token in tokens {
+            generated = parse_attribute(token, "linguist-generated").or(generated);
+            vendored = parse_attribute(token, "linguist-vendored").or(vendored);
+        }
+
+        if generated.is_none() && vendored.is_none() {
+            continue;
+        }
+
+        match build_matcher(pattern) {
+            Ok(matcher) => rules.push(LinguistRule {
+                base: base.to_path_buf(),
+                matcher,
+                generated,
+                vendored,
+            }),
+            Err(e) => warn!("{}", e),
+        }
+    }
+
+    rules
+}
+
+/// Load linguist rules from every .gitattributes file in the analyzed tree,
+/// shallowest first so that deeper files take precedence
+pub fn load_linguist_rules(dir_info: &DirectoryMap) -> Vec<LinguistRule> {
+    let mut dirs: Vec<&PathBuf> = dir_info.keys().collect();
+    dirs.sort_by_key(|d| d.components().count());
+
+    let mut rules = Vec::new();
+    for dir in dirs {
+        let attributes_path = dir.join(".gitattributes");
+        if let Ok(content) = fs::read_to_string(&attributes_path) {
+            info!("Reading linguist attributes from {}", attributes_path.display());
+            rules.extend(parse_gitattributes(dir, &content));
+        }
+    }
+    rules
+}
+
+/// Suggest default actions for files marked generated or vendored in .gitattributes
+pub fn linguist_suggestions(dir_info: &DirectoryMap) -> SuggestionMap {
+    let rules = load_linguist_rules(dir_info);
+    let mut suggestions = SuggestionMap::new();
+    if rules.is_empty() {
+        return suggestions;
+    }
+
+    for info in dir_info.values() {
+        for file in &info.files {
+            let mut generated = false;
+            let mut vendored = false;
+
+            // Later rules override earlier ones, as in git
+            for rule in &rules {
+                let Ok(rel_path) = file.path.strip_prefix(&rule.base) else {
+                    continue;
+                };
+                if rule.matcher.is_match(rel_path) {
+                    generated = rule.generated.unwrap_or(generated);
+                    vendored = rule.vendored.unwrap_or(vendored);
+                }
+            }
+
+            let suggestion = if generated {
+                Some(Suggestion {
+                    action: GENERATED_ACTION.to_string(),
+                    reason: "linguist-generated".to_string(),
+                })
+            } else if vendored {
+                Some(Suggestion {
+                    action: VENDORED_ACTION.to_string(),
+                    reason: "linguist-vendored".to_string(),
+                })
+            } else {
+                None
+            };
+
+            if let Some(suggestion) = suggestion {
+                suggestions.insert(file.path.clone(), suggestion);
+            }
+        }
+    }
+
+    propagate_to_directories(&mut suggestions, dir_info);
+    suggestions
+}
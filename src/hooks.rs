@@ -0,0 +1,76 @@
+use anyhow::{Context, Result, bail};
+use log::info;
+use std::io;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::ui::messages_to_stderr;
+
+/// Environment variables passed to hook commands
+pub const START_DIR_VAR: &str = "LLM_CONTEXT_START_DIR";
+pub const MANIFEST_VAR: &str = "LLM_CONTEXT_MANIFEST";
+pub const OUTPUT_DIR_VAR: &str = "LLM_CONTEXT_OUTPUT_DIR";
+/// Newline-separated paths of the parts written
+pub const PARTS_VAR: &str = "LLM_CONTEXT_PARTS";
+
+/// Where a run's files are, for the hooks around it
+#[derive(Debug, Clone, Default)]
+pub struct HookEnv {
+    pub start_dir: PathBuf,
+    pub manifest: PathBuf,
+    /// Known once parts are being written
+    pub output_dir: Option<PathBuf>,
+    pub parts: Vec<PathBuf>,
+}
+
+impl HookEnv {
+    /// The variables set for a hook command
+    pub fn vars(&self) -> Vec<(&'static str, String)> {
+        let mut vars = vec![
+            (START_DIR_VAR, self.start_dir.display().to_string()),
+            (MANIFEST_VAR, self.manifest.display().to_string()),
+        ];
+        if let Some(dir) = &self.output_dir {
+            vars.push((OUTPUT_DIR_VAR, dir.display().to_string()));
+        }
+        if !self.parts.is_empty() {
+            let parts: Vec<String> = self.parts.iter().map(|p| p.display().to_string()).collect();
+            vars.push((PARTS_VAR, parts.join("\n")));
+        }
+        vars
+    }
+}
+
+/// Run a hook command through the shell in the start directory, failing
+/// the run if it fails. Its output goes where messages do.
+pub fn run_hook(name: &str, command: &str, env: &HookEnv) -> Result<()> {
+    info!("Running {} hook: {}", name, command);
+    let mut hook = shell(command);
+    if messages_to_stderr() {
+        hook.stdout(io::stderr());
+    }
+    let status = hook
+        .current_dir(&env.start_dir)
+        .envs(env.vars())
+        .status()
+        .with_context(|| format!("Failed to run {} hook", name))?;
+    if !status.success() {
+        bail!("{} hook failed ({}): {}", name, status, command);
+    }
+    Ok(())
+}
+
+/// A command line run by the platform's shell
+fn shell(command: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut sh = Command::new("sh");
+        sh.arg("-c");
+        sh
+    };
+    shell.arg(command);
+    shell
+}
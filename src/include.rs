@@ -0,0 +1,61 @@
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::file_analysis::DirectoryMap;
+
+/// Build a matcher for --include patterns, relative to the start directory;
+/// patterns without a slash match at any depth, as in config rules
+pub fn include_matcher(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = if pattern.contains('/') {
+            pattern.trim_start_matches("./").trim_start_matches('/').to_string()
+        } else {
+            format!("**/{}", pattern)
+        };
+        builder.add(
+            GlobBuilder::new(&glob)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid include pattern: {}", pattern))?,
+        );
+    }
+    builder.build().context("Failed to build include patterns")
+}
+
+/// Keep only the files matching the include patterns, dropping directories
+/// left with nothing below them so they aren't offered. Excludes have already
+/// pruned the walk, so a file must be both included and not excluded.
+/// Returns the number of files dropped.
+pub fn restrict_to_includes(dir_info: &mut DirectoryMap, start_dir: &Path, includes: &GlobSet) -> usize {
+    let mut dropped = 0;
+    for info in dir_info.values_mut() {
+        let before = info.files.len();
+        info.files
+            .retain(|f| includes.is_match(f.path.strip_prefix(start_dir).unwrap_or(&f.path)));
+        dropped += before - info.files.len();
+        info.total_files = info.files.len();
+        info.binary_files = info.files.iter().filter(|f| f.binary).count();
+        info.tokens = info.files.iter().filter(|f| !f.binary).map(|f| f.tokens).sum();
+    }
+
+    // Deepest directories first, so a parent sees whether its children survived
+    let mut dirs: Vec<PathBuf> = dir_info.keys().cloned().collect();
+    dirs.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
+    let mut empty: HashSet<PathBuf> = HashSet::new();
+    for dir in dirs {
+        let Some(info) = dir_info.get_mut(&dir) else {
+            continue;
+        };
+        info.subdirs.retain(|sub| !empty.contains(sub));
+        if info.files.is_empty() && info.subdirs.is_empty() && dir != start_dir {
+            empty.insert(dir);
+        }
+    }
+    for dir in &empty {
+        dir_info.remove(dir);
+    }
+    dropped
+}
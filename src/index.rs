@@ -0,0 +1,133 @@
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::anchors::{parse_anchor, source_dir};
+
+/// Name of the index written next to the context files
+pub const INDEX_FILE_NAME: &str = "index.json";
+
+lazy_static! {
+    /// Marker left by `skeleton::fold_marker`
+    static ref FOLD_MARKER: Regex = Regex::new(r"… \d+ lines: (\d+)-(\d+) …").unwrap();
+}
+
+/// Machine-readable listing of what the context files contain
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ContextIndex {
+    /// Files in the order they appear in the context
+    pub files: Vec<IndexEntry>,
+}
+
+/// A file block in the context
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct IndexEntry {
+    /// Path relative to the source directory
+    pub path: PathBuf,
+    /// ID the block's anchor gives the file for citing it, e.g. `F3A9C1`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub citation: Option<String>,
+    /// Action that produced the block
+    pub action: String,
+    /// Short git blob hash of the content the block was built from
+    pub hash: String,
+    /// Context part containing the block
+    pub part: String,
+    /// Source directory the path is relative to, from the part's header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<PathBuf>,
+    /// Function bodies left out of the block, to be read from the file when needed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub folds: Vec<FoldedRegion>,
+}
+
+/// Lines of a file folded out of its block (1-based, inclusive)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FoldedRegion {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Build the index from the anchors in a set of context files
+pub fn build_index(context_files: &[PathBuf]) -> Result<ContextIndex> {
+    let mut files = Vec::new();
+
+    for part in context_files {
+        let content = fs::read_to_string(part)
+            .with_context(|| format!("Failed to read context file {}", part.display()))?;
+        let part_name = part
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let source = source_dir(&content);
+
+        let first = files.len();
+        for line in content.lines() {
+            if let Some(anchor) = parse_anchor(line) {
+                files.push(IndexEntry {
+                    path: anchor.path,
+                    citation: anchor.citation,
+                    action: anchor.action.spec(),
+                    hash: anchor.hash,
+                    part: part_name.clone(),
+                    source: source.clone(),
+                    folds: Vec::new(),
+                });
+            } else if files.len() > first
+                && let Some(c) = FOLD_MARKER.captures(line)
+                && let Some(entry) = files.last_mut()
+            {
+                entry.folds.push(FoldedRegion {
+                    start: c[1].parse()?,
+                    end: c[2].parse()?,
+                });
+            }
+        }
+    }
+
+    // IDs come from path hashes, so two files could in principle share one
+    let mut cited: HashMap<&str, &Path> = HashMap::new();
+    for entry in &files {
+        if let Some(citation) = &entry.citation
+            && let Some(other) = cited.insert(citation, &entry.path)
+            && other != entry.path
+        {
+            warn!(
+                "{} and {} share citation ID {}",
+                other.display(),
+                entry.path.display(),
+                citation
+            );
+        }
+    }
+
+    Ok(ContextIndex { files })
+}
+
+/// Load an index written by an earlier run
+pub fn load_index(path: &Path) -> Result<ContextIndex> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read index {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Invalid index {}", path.display()))
+}
+
+/// Write index.json for the context files into a directory
+pub fn write_index(output_dir: &Path, context_files: &[PathBuf]) -> Result<()> {
+    save_index(output_dir, &build_index(context_files)?)
+}
+
+/// Save an index as index.json in a directory
+pub fn save_index(output_dir: &Path, index: &ContextIndex) -> Result<()> {
+    let index_path = output_dir.join(INDEX_FILE_NAME);
+
+    let content = serde_json::to_string_pretty(index).context("Failed to serialize index")?;
+    fs::write(&index_path, content).context("Failed to write index file")?;
+
+    info!("Index saved to {}", index_path.display());
+
+    Ok(())
+}
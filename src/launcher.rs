@@ -0,0 +1,94 @@
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Name of the Claude CLI on PATH
+pub const CLAUDE_LAUNCHER: &str = "claude";
+
+/// Extensions tried on Windows when PATHEXT isn't set
+const DEFAULT_PATHEXT: &str = ".COM;.EXE;.BAT;.CMD";
+
+/// Find a program on PATH without shelling out to `which`/`where`. On
+/// Windows the PATHEXT extensions are tried, so `.exe` binaries and the
+/// `.cmd` shims npm installs are both found.
+pub fn find_on_path(name: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    let extensions = if cfg!(windows) {
+        windows_extensions(env::var_os("PATHEXT"))
+    } else {
+        Vec::new()
+    };
+    find_in(name, &path, &extensions)
+}
+
+/// Find a program in the directories of a PATH-style list, trying each
+/// extension after the bare name
+pub fn find_in(name: &str, path: &OsString, extensions: &[String]) -> Option<PathBuf> {
+    env::split_paths(path).filter(|dir| !dir.as_os_str().is_empty()).find_map(|dir| {
+        let bare = dir.join(name);
+        if is_executable(&bare) {
+            return Some(bare);
+        }
+        extensions
+            .iter()
+            .map(|ext| dir.join(format!("{}{}", name, ext)))
+            .find(|candidate| is_executable(candidate))
+    })
+}
+
+/// Extensions from PATHEXT, lowercased, falling back to the usual set
+fn windows_extensions(pathext: Option<OsString>) -> Vec<String> {
+    let pathext = pathext
+        .and_then(|p| p.into_string().ok())
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| DEFAULT_PATHEXT.to_string());
+    pathext
+        .split(';')
+        .filter(|ext| !ext.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// The message that starts a session on the context files
+pub fn kickoff_message(parts: &[String]) -> String {
+    format!(
+        "The context files are at {}. Read each file in its entirety, then say 'Ready'.",
+        parts.join(", ")
+    )
+}
+
+/// Command starting the launcher found on PATH with the kickoff message.
+/// Spawning the resolved path rather than the bare name lets `.cmd` shims
+/// run on Windows, where std then escapes the arguments for cmd.
+pub fn launch_command(launcher: &Path, message: &str) -> Command {
+    let mut command = Command::new(launcher);
+    command.args(["-d", "--verbose", message]);
+    command
+}
+
+/// The launch as a line to paste into the platform's shell
+pub fn manual_command(message: &str) -> String {
+    format!("{} -d --verbose {}", CLAUDE_LAUNCHER, shell_quote(message, cfg!(windows)))
+}
+
+/// Quote an argument for a POSIX shell, or for cmd/PowerShell on Windows
+pub fn shell_quote(arg: &str, windows: bool) -> String {
+    if windows {
+        format!("\"{}\"", arg.replace('"', "\\\""))
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
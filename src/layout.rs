@@ -0,0 +1,91 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::context_files::{ContextFile, OutputSettings, append_to_file};
+use crate::entrypoints::first_degree_imports;
+use crate::file_analysis::DirectoryMap;
+use crate::manifest::{Manifest, ManifestEntry};
+use crate::priority::overview_rank;
+use crate::summary_cache::SummaryCache;
+use crate::tokenizer::count_tokens;
+use crate::tree::render_tree;
+
+/// How selections are laid out across the parts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Layout {
+    /// In the order they were selected
+    #[default]
+    Selection,
+    /// Overview first (tree, README, manifests, architecture notes), then
+    /// the rest with files next to the files they import
+    Ordered,
+}
+
+/// Rank of an overview file: top-level README, manifests and LICENSE, then
+/// architecture and design notes anywhere
+fn overview_position(rel_path: &Path) -> Option<usize> {
+    if rel_path.parent().is_some_and(|p| p.as_os_str().is_empty())
+        && let Some(rank) = overview_rank(rel_path)
+    {
+        return Some(rank);
+    }
+    let name = rel_path.file_name()?.to_str()?.to_uppercase();
+    (name.starts_with("ARCHITECTURE") || name.starts_with("DESIGN")).then_some(usize::MAX)
+}
+
+/// Reorder a manifest for the ordered layout. Overview files come first;
+/// every other selection follows in selection order, except that each is
+/// followed by the selected files it imports that aren't placed yet.
+/// Directory headers (enter) are dropped, since files no longer follow
+/// their directory.
+pub fn ordered_manifest(manifest: &Manifest, start_dir: &Path) -> Manifest {
+    let mut overview: Vec<(usize, &ManifestEntry)> = Vec::new();
+    let mut detail: Vec<&ManifestEntry> = Vec::new();
+    for entry in manifest.entries.iter().filter(|e| e.action != "enter") {
+        match overview_position(&entry.path) {
+            Some(rank) if entry.action != "exclude" => overview.push((rank, entry)),
+            _ => detail.push(entry),
+        }
+    }
+    overview.sort_by_key(|(rank, _)| *rank);
+
+    let by_path: HashMap<PathBuf, &ManifestEntry> =
+        detail.iter().map(|e| (start_dir.join(&e.path), *e)).collect();
+    let mut placed: HashSet<&Path> = HashSet::new();
+    let mut ordered = Manifest::new();
+    ordered.entries.extend(overview.into_iter().map(|(_, e)| e.clone()));
+
+    for entry in detail {
+        // Depth-first through imports, so each file is followed by what it uses
+        let mut stack = vec![entry];
+        while let Some(entry) = stack.pop() {
+            if !placed.insert(&entry.path) {
+                continue;
+            }
+            ordered.entries.push(entry.clone());
+            let mut imports: Vec<&ManifestEntry> = first_degree_imports(&start_dir.join(&entry.path), start_dir)
+                .iter()
+                .filter_map(|import| by_path.get(import).copied())
+                .filter(|e| !placed.contains(e.path.as_path()))
+                .collect();
+            imports.reverse();
+            stack.extend(imports);
+        }
+    }
+    ordered
+}
+
+/// Open a part with the project tree, as the first overview material
+pub fn write_tree_overview(
+    context_file: &mut ContextFile,
+    start_dir: &Path,
+    dir_info: &DirectoryMap,
+    output: &OutputSettings,
+) -> Result<()> {
+    let tree = render_tree(start_dir, dir_info, &HashMap::new(), &SummaryCache::new(), true);
+    let content = output.format.formatter().overview(&format!("## PROJECT TREE\n```\n{}```", tree));
+    append_to_file(&context_file.path, &content)?;
+    context_file.current_tokens += count_tokens(&content);
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use anyhow::{Context, Result, bail};
+use chrono::{Local, SecondsFormat};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// Ledger of summarizer calls, one JSON object per line, in the start directory
+pub const LEDGER_FILE_NAME: &str = ".claude-ledger.jsonl";
+
+/// Model the summarizer runs on
+pub const SUMMARY_MODEL: &str = "claude-haiku-4-5";
+
+/// Published prices in USD per million input and output tokens
+const PRICES: [(&str, f64, f64); 5] = [
+    ("claude-haiku-4-5", 1.0, 5.0),
+    ("claude-sonnet-4-5", 3.0, 15.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-opus-4-1", 15.0, 75.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+];
+
+/// One summarizer call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    /// When the call was made (RFC 3339, local time)
+    pub time: String,
+    /// When the run that made it started, shared by its calls
+    pub run: String,
+    pub model: String,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    /// None for models without a known price
+    pub cost_usd: Option<f64>,
+}
+
+/// Estimated cost in USD of a call to a model
+pub fn estimate_cost(model: &str, input_tokens: usize, output_tokens: usize) -> Option<f64> {
+    let (_, input, output) = PRICES.iter().find(|(name, _, _)| *name == model)?;
+    Some((input_tokens as f64 * input + output_tokens as f64 * output) / 1_000_000.0)
+}
+
+/// Calls made during this run, written out by `flush_ledger`
+static PENDING: Mutex<Vec<LedgerEntry>> = Mutex::new(Vec::new());
+static RUN_STARTED: OnceLock<String> = OnceLock::new();
+
+fn now() -> String {
+    Local::now().to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+/// Record a summarizer call for the ledger
+pub fn record_call(model: &str, input_tokens: usize, output_tokens: usize) {
+    let entry = LedgerEntry {
+        time: now(),
+        run: RUN_STARTED.get_or_init(now).clone(),
+        model: model.to_string(),
+        input_tokens,
+        output_tokens,
+        cost_usd: estimate_cost(model, input_tokens, output_tokens),
+    };
+    match PENDING.lock() {
+        Ok(mut pending) => pending.push(entry),
+        Err(_) => warn!("Ledger is poisoned; not recording a summarizer call"),
+    }
+}
+
+/// Append the calls recorded so far to a directory's ledger, returning how
+/// many were written
+pub fn flush_ledger(directory: &Path) -> Result<usize> {
+    let entries = match PENDING.lock() {
+        Ok(mut pending) => std::mem::take(&mut *pending),
+        Err(_) => bail!("Ledger is poisoned"),
+    };
+    if entries.is_empty() {
+        return Ok(0);
+    }
+
+    let path = directory.join(LEDGER_FILE_NAME);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    for entry in &entries {
+        writeln!(file, "{}", serde_json::to_string(entry)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(entries.len())
+}
+
+/// Read a directory's ledger; lines that don't parse are skipped
+pub fn load_ledger(directory: &Path) -> Result<Vec<LedgerEntry>> {
+    let path = directory.join(LEDGER_FILE_NAME);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                warn!("Skipping invalid ledger line: {}", e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// How a cost report groups calls
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CostGrouping {
+    #[default]
+    Day,
+    Run,
+    Model,
+}
+
+/// Totals of a group of calls
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CostTotals {
+    pub calls: usize,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    pub cost_usd: f64,
+    /// Calls to models without a known price, left out of the cost
+    pub unpriced_calls: usize,
+}
+
+/// Totals of the ledger's calls by day, run or model, in key order
+pub fn cost_report(entries: &[LedgerEntry], by: CostGrouping) -> BTreeMap<String, CostTotals> {
+    let mut report: BTreeMap<String, CostTotals> = BTreeMap::new();
+    for entry in entries {
+        let key = match by {
+            // The date part of the RFC 3339 time
+            CostGrouping::Day => entry.time.get(..10).unwrap_or(&entry.time).to_string(),
+            CostGrouping::Run => entry.run.clone(),
+            CostGrouping::Model => entry.model.clone(),
+        };
+        let totals = report.entry(key).or_default();
+        totals.calls += 1;
+        totals.input_tokens += entry.input_tokens;
+        totals.output_tokens += entry.output_tokens;
+        match entry.cost_usd {
+            Some(cost) => totals.cost_usd += cost,
+            None => totals.unpriced_calls += 1,
+        }
+    }
+    report
+}
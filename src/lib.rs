@@ -1,6 +1,58 @@
+pub mod analysis_cache;
+pub mod anchors;
+pub mod autoselect;
+pub mod budget;
+pub mod bundle;
 pub mod cache;
+pub mod cfg;
+pub mod changelog;
+pub mod config;
 pub mod context_files;
+pub mod coverage;
+pub mod daemon;
+pub mod deps;
+pub mod ecosystem;
+pub mod encryption;
+pub mod entrypoints;
+pub mod export;
 pub mod file_analysis;
+pub mod formats;
+pub mod gitattributes;
+pub mod hooks;
+pub mod include;
+pub mod index;
+pub mod launcher;
+pub mod layout;
+pub mod ledger;
+pub mod llm;
+pub mod lock;
+pub mod manifest;
+pub mod markup;
+pub mod merge;
+pub mod metadata;
+pub mod normalize;
+pub mod optout;
+pub mod outline;
+pub mod priority;
 pub mod logging;
 pub mod processing;
+pub mod profile;
+pub mod recency;
+pub mod references;
+pub mod review;
+pub mod run_summary;
+pub mod serve;
+pub mod signatures;
+pub mod skeleton;
+pub mod snapshots;
+pub mod structured;
+pub mod suggestions;
+pub mod summarizer;
 pub mod summary_cache;
+pub mod tasks;
+pub mod token_count;
+pub mod tokenizer;
+pub mod tree;
+pub mod ui;
+pub mod vcs;
+pub mod vendored;
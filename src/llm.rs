@@ -0,0 +1,408 @@
+use anyhow::{Context, Result, anyhow, bail};
+use log::warn;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
+
+use crate::config::SummarizerConfig;
+use crate::token_count::ANTHROPIC_VERSION;
+use crate::tokenizer;
+
+/// Anthropic messages endpoint
+pub const MESSAGES_URL: &str = "https://api.anthropic.com/v1/messages";
+/// OpenAI chat completions endpoint
+pub const OPENAI_URL: &str = "https://api.openai.com/v1/chat/completions";
+/// Environment variable holding the OpenAI API key
+pub const OPENAI_KEY_VAR: &str = "OPENAI_API_KEY";
+/// OpenAI model summaries are asked of when none is configured
+pub const DEFAULT_OPENAI_MODEL: &str = "gpt-4o-mini";
+/// Local model summaries are asked of when none is configured
+pub const DEFAULT_OLLAMA_MODEL: &str = "qwen2.5-coder";
+
+/// Context windows, in tokens, when the config doesn't give one
+pub const CLAUDE_CONTEXT: usize = 200_000;
+pub const OPENAI_CONTEXT: usize = 128_000;
+/// Ollama's own default is much smaller, so this is asked for explicitly
+pub const OLLAMA_CONTEXT: usize = 32_768;
+
+/// Longest summary asked for, in tokens
+pub const MAX_SUMMARY_TOKENS: usize = 1024;
+
+/// Retries after a failed call when the config doesn't say
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+/// Growth of the wait between retries when the config doesn't say
+pub const DEFAULT_BACKOFF_MULTIPLIER: f64 = 2.0;
+/// Wait before the first retry
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest wait to connect to a service
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest wait for a response once sent; long enough for a local model
+/// to finish a summary on a slow machine
+const READ_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// HTTP client for every API call, with timeouts so a stalled server fails
+/// the call (which may then be retried) instead of hanging the run
+pub fn http_agent() -> &'static ureq::Agent {
+    static AGENT: OnceLock<ureq::Agent> = OnceLock::new();
+    AGENT.get_or_init(|| {
+        ureq::AgentBuilder::new()
+            .timeout_connect(CONNECT_TIMEOUT)
+            .timeout_read(READ_TIMEOUT)
+            .timeout_write(READ_TIMEOUT)
+            .build()
+    })
+}
+
+/// A model that summaries can be generated with
+pub trait LlmProvider: Send + Sync {
+    /// Model name, as recorded in the ledger
+    fn model(&self) -> &str;
+    /// Completion of a summary prompt, retrying failures that may pass
+    fn generate_summary(&self, prompt: &str) -> Result<Completion>;
+    /// Tokens a text takes for this model
+    fn count_tokens(&self, text: &str) -> usize {
+        tokenizer::count_tokens(text)
+    }
+    /// Tokens the model accepts, prompt and summary together
+    fn max_context(&self) -> usize;
+}
+
+/// A summary with the tokens it took
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub text: String,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+}
+
+/// How often and how patiently a failed call is retried
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub backoff_multiplier: f64,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &SummarizerConfig) -> Self {
+        Self {
+            max_retries: config.max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+            initial_backoff: INITIAL_BACKOFF,
+            backoff_multiplier: config.backoff_multiplier.unwrap_or(DEFAULT_BACKOFF_MULTIPLIER),
+        }
+    }
+
+    /// Wait before retry number `retry`, counting from 0
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .mul_f64(self.backoff_multiplier.max(1.0).powi(retry as i32))
+    }
+
+    /// Run a call until it succeeds, fails for good or runs out of retries
+    pub fn run<T>(&self, mut call: impl FnMut() -> Result<T, CallError>) -> Result<T> {
+        let mut retry = 0;
+        loop {
+            match call() {
+                Ok(value) => return Ok(value),
+                Err(e) if e.retryable && retry < self.max_retries => {
+                    let wait = self.backoff(retry);
+                    warn!("API call failed ({}); retrying in {:.1}s", e.error, wait.as_secs_f64());
+                    thread::sleep(wait);
+                    retry += 1;
+                }
+                Err(e) => return Err(e.error),
+            }
+        }
+    }
+}
+
+/// A failed call, and whether trying again could help
+pub struct CallError {
+    error: anyhow::Error,
+    retryable: bool,
+}
+
+impl CallError {
+    pub fn fatal(error: anyhow::Error) -> Self {
+        Self { error, retryable: false }
+    }
+
+    fn transient(error: anyhow::Error) -> Self {
+        Self { error, retryable: true }
+    }
+}
+
+/// POST a JSON body, mapping failures to whether they may pass: rate
+/// limits, overload, server and network errors. A refused connection is
+/// only transient for remote services; a local server that isn't running
+/// won't start while we wait.
+pub fn post_json(
+    request: ureq::Request,
+    body: &serde_json::Value,
+    service: &str,
+    local: bool,
+) -> Result<String, CallError> {
+    let response = request
+        .set("content-type", "application/json")
+        .send_string(&body.to_string())
+        .map_err(|e| match e {
+            ureq::Error::Status(code, response) => CallError {
+                error: anyhow!("{} returned {}: {}", service, code, response.into_string().unwrap_or_default()),
+                retryable: code == 429 || code >= 500,
+            },
+            ureq::Error::Transport(t) if local && t.kind() == ureq::ErrorKind::ConnectionFailed => {
+                CallError::fatal(anyhow!("no {} server running ({})", service, t))
+            }
+            other => CallError::transient(anyhow!("{} request failed: {}", service, other)),
+        })?;
+    response.into_string().map_err(|e| CallError::transient(e.into()))
+}
+
+/// The Anthropic messages API
+pub struct AnthropicApi {
+    pub key: String,
+    pub model: String,
+    pub context: usize,
+    pub retry: RetryPolicy,
+}
+
+impl LlmProvider for AnthropicApi {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn generate_summary(&self, prompt: &str) -> Result<Completion> {
+        let body = serde_json::json!({
+            "model": self.model,
+            "max_tokens": MAX_SUMMARY_TOKENS,
+            "messages": [{"role": "user", "content": prompt}],
+        });
+        self.retry.run(|| {
+            let request = http_agent().post(MESSAGES_URL)
+                .set("x-api-key", &self.key)
+                .set("anthropic-version", ANTHROPIC_VERSION);
+            let response = post_json(request, &body, "messages API", false)?;
+            parse_messages_response(&response).map_err(CallError::fatal)
+        })
+    }
+
+    fn max_context(&self) -> usize {
+        self.context
+    }
+}
+
+/// Signs in the claude CLI's error output of a failure that may pass: rate
+/// limits, overload, server and network errors
+const CLI_TRANSIENT_ERRORS: [&str; 9] = [
+    "rate limit",
+    "rate_limit",
+    "overloaded",
+    "api error: 429",
+    "api error: 5",
+    "timed out",
+    "timeout",
+    "econnreset",
+    "connection",
+];
+
+/// Whether a failed claude CLI run may succeed when retried. Failures are
+/// taken to be for good (a missing login, a bad key or model, a usage
+/// error) unless the error output says otherwise.
+pub fn is_transient_cli_error(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    CLI_TRANSIENT_ERRORS.iter().any(|sign| stderr.contains(sign))
+}
+
+/// `claude -p`, which doesn't report usage, so tokens are estimated
+pub struct ClaudeCli {
+    pub path: PathBuf,
+    pub model: String,
+    pub context: usize,
+    pub retry: RetryPolicy,
+}
+
+impl ClaudeCli {
+    fn call(&self, prompt: &str) -> Result<Completion, CallError> {
+        let run = || -> Result<std::process::Output> {
+            let mut child = Command::new(&self.path)
+                .args(["-p", "--model", &self.model])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .with_context(|| format!("Failed to run {}", self.path.display()))?;
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(prompt.as_bytes())?;
+            }
+            Ok(child.wait_with_output()?)
+        };
+        let output = run().map_err(CallError::fatal)?;
+        if !output.status.success() {
+            // Some versions report errors on stdout
+            let message = format!(
+                "{}\n{}",
+                String::from_utf8_lossy(&output.stderr).trim(),
+                String::from_utf8_lossy(&output.stdout).trim()
+            );
+            let message = message.trim();
+            return Err(CallError {
+                error: anyhow!("claude exited with {}: {}", output.status, message),
+                retryable: is_transient_cli_error(message),
+            });
+        }
+        let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if text.is_empty() {
+            return Err(CallError::transient(anyhow!("claude returned an empty summary")));
+        }
+        Ok(Completion {
+            input_tokens: self.count_tokens(prompt),
+            output_tokens: self.count_tokens(&text),
+            text,
+        })
+    }
+}
+
+impl LlmProvider for ClaudeCli {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn generate_summary(&self, prompt: &str) -> Result<Completion> {
+        self.retry.run(|| self.call(prompt))
+    }
+
+    fn max_context(&self) -> usize {
+        self.context
+    }
+}
+
+/// The OpenAI chat completions API
+pub struct OpenAi {
+    pub key: String,
+    pub model: String,
+    pub context: usize,
+    pub retry: RetryPolicy,
+}
+
+impl LlmProvider for OpenAi {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn generate_summary(&self, prompt: &str) -> Result<Completion> {
+        let body = serde_json::json!({
+            "model": self.model,
+            "max_tokens": MAX_SUMMARY_TOKENS,
+            "messages": [{"role": "user", "content": prompt}],
+        });
+        self.retry.run(|| {
+            let request = http_agent().post(OPENAI_URL).set("authorization", &format!("Bearer {}", self.key));
+            let response = post_json(request, &body, "OpenAI", false)?;
+            parse_openai_response(&response).map_err(CallError::fatal)
+        })
+    }
+
+    fn max_context(&self) -> usize {
+        self.context
+    }
+}
+
+/// A local model served by Ollama
+pub struct Ollama {
+    /// Base URL of the server
+    pub url: String,
+    pub model: String,
+    pub context: usize,
+    pub retry: RetryPolicy,
+}
+
+impl LlmProvider for Ollama {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn generate_summary(&self, prompt: &str) -> Result<Completion> {
+        let body = serde_json::json!({
+            "model": self.model,
+            "prompt": prompt,
+            "stream": false,
+            "options": {"num_ctx": self.context},
+        });
+        self.retry.run(|| {
+            let request = http_agent().post(&format!("{}/api/generate", self.url));
+            let response = post_json(request, &body, "Ollama", true)?;
+            parse_ollama_response(&response).map_err(CallError::fatal)
+        })
+    }
+
+    fn max_context(&self) -> usize {
+        self.context
+    }
+}
+
+/// A count from a JSON object, 0 when missing
+fn count_field(value: Option<&serde_json::Value>, field: &str) -> usize {
+    value
+        .and_then(|v| v.get(field))
+        .and_then(|v| v.as_u64())
+        .map_or(0, |v| v as usize)
+}
+
+/// The text and usage of a messages API response
+pub fn parse_messages_response(body: &str) -> Result<Completion> {
+    let value: serde_json::Value = serde_json::from_str(body).context("Invalid messages response")?;
+    let text: String = value
+        .get("content")
+        .and_then(|c| c.as_array())
+        .map(|blocks| {
+            blocks
+                .iter()
+                .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+                .collect()
+        })
+        .unwrap_or_default();
+    if text.trim().is_empty() {
+        bail!("Messages response has no text: {}", body);
+    }
+    Ok(Completion {
+        text: text.trim().to_string(),
+        input_tokens: count_field(value.get("usage"), "input_tokens"),
+        output_tokens: count_field(value.get("usage"), "output_tokens"),
+    })
+}
+
+/// The text and usage of an OpenAI chat completions response
+pub fn parse_openai_response(body: &str) -> Result<Completion> {
+    let value: serde_json::Value = serde_json::from_str(body).context("Invalid OpenAI response")?;
+    let text = value
+        .pointer("/choices/0/message/content")
+        .and_then(|c| c.as_str())
+        .unwrap_or_default()
+        .trim();
+    if text.is_empty() {
+        bail!("OpenAI response has no text: {}", body);
+    }
+    Ok(Completion {
+        text: text.to_string(),
+        input_tokens: count_field(value.get("usage"), "prompt_tokens"),
+        output_tokens: count_field(value.get("usage"), "completion_tokens"),
+    })
+}
+
+/// The text and usage of an Ollama generate response
+pub fn parse_ollama_response(body: &str) -> Result<Completion> {
+    let value: serde_json::Value = serde_json::from_str(body).context("Invalid Ollama response")?;
+    let text = value.get("response").and_then(|r| r.as_str()).unwrap_or_default().trim();
+    if text.is_empty() {
+        bail!("Ollama response has no text: {}", body);
+    }
+    Ok(Completion {
+        text: text.to_string(),
+        input_tokens: count_field(Some(&value), "prompt_eval_count"),
+        output_tokens: count_field(Some(&value), "eval_count"),
+    })
+}
@@ -0,0 +1,65 @@
+use anyhow::{Context, Result, bail};
+use log::{info, warn};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Lock file created in the directory a run writes to
+pub const LOCK_FILE_NAME: &str = ".claude-lock";
+
+/// Held for the duration of a run; removes the lock file when dropped
+#[derive(Debug)]
+pub struct RunLock {
+    path: PathBuf,
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Failed to remove lock file {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Whether the process that wrote a lock is known to have exited. Only
+/// detectable where /proc is available; elsewhere locks are never stale.
+fn is_stale(pid: &str) -> bool {
+    let proc_dir = Path::new("/proc");
+    proc_dir.is_dir() && pid.parse::<u32>().is_ok() && !proc_dir.join(pid).exists()
+}
+
+/// Take the lock for a directory so concurrent runs don't interleave writes to
+/// its cache and context files. A lock left by a process that has exited is
+/// replaced; otherwise this fails unless `force` is set.
+pub fn acquire_lock(directory: &Path, force: bool) -> Result<RunLock> {
+    let path = directory.join(LOCK_FILE_NAME);
+
+    loop {
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                write!(file, "{}", std::process::id()).context("Failed to write lock file")?;
+                return Ok(RunLock { path });
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                let owner = fs::read_to_string(&path).unwrap_or_default();
+                let owner = owner.trim();
+
+                if force || is_stale(owner) {
+                    info!("Replacing lock file {} held by process {}", path.display(), owner);
+                    fs::remove_file(&path).context("Failed to remove existing lock file")?;
+                    continue;
+                }
+
+                bail!(
+                    "Another run (process {}) is using {}. Wait for it to finish, or pass --force if it is no longer running (lock file: {})",
+                    owner,
+                    directory.display(),
+                    path.display()
+                );
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to create lock file {}", path.display()));
+            }
+        }
+    }
+}
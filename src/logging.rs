@@ -21,6 +21,11 @@ pub fn debug() {
     CoreLogger::init_with_filter(Debug);
 }
 
+/// Debug logging on stderr, for runs whose stdout is output
+pub fn debug_to_stderr() {
+    CoreLogger::init_with_target(Debug, Target::Stderr);
+}
+
 pub fn info() {
     CoreLogger::init_with_filter(log::LevelFilter::Info);
 }
@@ -59,6 +64,9 @@ impl CoreLogger {
         CoreLogger::init_with_filter(Debug);
     }
     pub fn init_with_filter(level: LevelFilter) {
+        CoreLogger::init_with_target(level, Target::Stdout);
+    }
+    pub fn init_with_target(level: LevelFilter, target: Target) {
         START.call_once(|| {
             // Filtering here doesn't improve performance while filtering in the process.toml file does.
             let mut builder = env_logger::builder();
@@ -102,7 +110,7 @@ impl CoreLogger {
                         writeln!(buf, "{}", record)
                     }
                 })
-                .target(Target::Stdout);
+                .target(target);
 
             for module in THIRD_PARTY_MODULES_TO_FILTER {
                 builder.filter_module(module, LevelFilter::Warn);
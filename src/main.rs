@@ -1,89 +1,1047 @@
+mod analysis_cache;
+mod anchors;
+mod autoselect;
+mod budget;
+mod bundle;
 mod cache;
+mod cfg;
+mod changelog;
+mod config;
 mod context_files;
+mod coverage;
+mod daemon;
+mod deps;
+mod ecosystem;
+mod encryption;
+mod entrypoints;
+mod export;
 mod file_analysis;
+mod formats;
+mod gitattributes;
+mod hooks;
+mod include;
+mod index;
+mod launcher;
+mod layout;
+mod ledger;
+mod llm;
+mod lock;
+mod manifest;
+mod markup;
+mod merge;
+mod metadata;
+mod normalize;
+mod optout;
+mod outline;
+mod priority;
 mod processing;
+mod profile;
+mod recency;
+mod references;
+mod review;
+mod run_summary;
+mod serve;
+mod signatures;
+mod skeleton;
+mod snapshots;
+mod structured;
+mod suggestions;
+mod summarizer;
 mod summary_cache;
-
-use anyhow::{Context, Result};
-use clap::Parser;
+mod tasks;
+mod token_count;
+mod tokenizer;
+mod tree;
+mod ui;
+mod vcs;
+mod vendored;
+
+use anyhow::{Context, Result, bail};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
 use llm_context_loader::logging;
 use log::{error, info, warn};
-use std::collections::{HashMap, HashSet};
-use std::io::{self, Write};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
-
-use crate::cache::{get_action_for_path, load_cache, save_cache, should_prompt_for_directory};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::analysis_cache::{analyze_directory_cached, load_analysis_cache, save_analysis_cache};
+use crate::anchors::{action_breakdown, context_part_paths, source_dir, update_context_dir};
+use crate::autoselect::{auto_select, largest_files};
+use crate::budget::{BudgetPlan, DEFAULT_LADDER, Ladder, ReadImpact, node_costs};
+use crate::bundle::{
+    BUNDLE_EXTENSION, BundleListing, bundle_contents, default_unpack_dir, pack, read_bundle, unpack,
+};
+use crate::cache::{
+    CacheMap, cached_enters, get_action_for_path, load_cache, save_cache, should_prompt_for_directory,
+};
+use crate::changelog::changelog_suggestions;
+use crate::config::{
+    Config, NON_INTERACTIVE_CLASS_ACTIONS, SummarizerConfig, class_suggestions, load_project_config,
+    load_user_config, rule_matcher, rule_suggestions,
+};
 use crate::context_files::{
-    ContextFile, append_to_file, create_context_file, finalize_context_files,
-    get_default_context_dir, get_or_rotate_file,
+    ContextFile, DEFAULT_PART_TEMPLATE, OutputSettings, OutputTarget, PartExtension, PartNaming, TempWorkspace,
+    append_to_file,
+    clean_context_dir, create_context_file, create_session_dir, emit_parts, finalize_context_files,
+    get_default_context_dir, get_or_rotate_file, latest_session, link_appended_part,
+    prepare_output_dir,
+    prune_sessions, renumber_parts, set_latest_session, shown_path,
+};
+use crate::coverage::{coverage_suggestions, load_coverage};
+use crate::daemon::{DAEMON_PORT, DEFAULT_POLL_SECS, Daemon};
+use crate::deps::{dependency_uses, direct_dependencies, render_digest};
+use crate::ecosystem::{ExcludePreset, detect_ecosystems, ecosystem_excludes};
+use crate::encryption::generate_key;
+use crate::file_analysis::{
+    CLAUDE_TOKEN_LIMIT, DirectoryMap, analyze_directory, is_binary, show_dir_info, tree_totals,
+};
+use crate::entrypoints::{entry_point_files, entry_point_suggestions};
+use crate::export::{ExportFormat, selected_files, write_export};
+use crate::formats::{OutputFormat, parts_format, render_parts};
+use crate::gitattributes::linguist_suggestions;
+use crate::hooks::{HookEnv, run_hook};
+use crate::include::{include_matcher, restrict_to_includes};
+use crate::index::{INDEX_FILE_NAME, build_index, load_index, write_index};
+use crate::layout::{Layout, ordered_manifest, write_tree_overview};
+use crate::launcher::{CLAUDE_LAUNCHER, find_on_path, kickoff_message, launch_command, manual_command};
+use crate::ledger::{CostGrouping, LEDGER_FILE_NAME, cost_report, flush_ledger, load_ledger};
+use crate::lock::acquire_lock;
+use crate::merge::merge_bundles;
+use crate::manifest::{
+    EntryOptions, Manifest, apply_manifest, default_manifest_path, load_manifest, save_manifest,
+};
+use crate::markup::markup_suggestions;
+use crate::priority::{priority_files, priority_suggestions};
+use crate::metadata::GenerationMetadata;
+use crate::normalize::Normalization;
+use crate::optout::{DEFAULT_OPT_OUT_MARKER, remove_opted_out};
+use crate::processing::{
+    Action, DEFAULT_SUMMARY_JOBS, apply_cached_actions, ordered_cache_paths, parse_line_ranges, process_node,
+    set_cfg_features, set_summary_prompt, summarize_files, summary_targets,
+};
+use crate::profile::{print_profile, profile_directory};
+use crate::recency::{RecencyMap, collect_recency, dirty_files, recency_suggestions, sort_by_recency};
+use crate::references::{unreferenced_files, unreferenced_suggestions};
+use crate::review::review_manifest;
+use crate::run_summary::RunSummary;
+use crate::serve::{DEFAULT_PORT, bind, listen, serve};
+use crate::skeleton::symbol_matcher;
+use crate::snapshots::{changed_reads, load_snapshots, record_reads, save_snapshots};
+use crate::suggestions::{Suggestion, SuggestionMap, get_suggestion_for_path, merge_suggestions};
+use crate::summarizer::{SummarizerKind, configure_summarizer, summarizer, summarizer_requirement};
+use crate::summary_cache::{
+    CacheStats, SummaryCache, hash_file_content, load_summary_cache, read_summary_export, save_summary_cache,
+    write_summary_export,
+};
+use crate::tasks::{fill_preamble, task_preset};
+use crate::token_count::{
+    API_KEY_VAR, DEFAULT_COUNT_MODEL, TokenCounter, apply_exact_counts, load_token_cache,
+    save_token_cache,
+};
+use crate::tree::render_tree;
+use crate::ui::{
+    CHOICE_PROMPT, PromptTimeout, SCRIPTED_ANSWERS_HELP, Style, action_for_choice, choice_for_action,
+    is_scripted, open_in_viewer, paint, parse_timeout, print_cache_summary, print_field, print_header,
+    print_help, print_options, print_title, print_warning, prompt, prompt_timeout, say, script_line,
+    set_messages_to_stderr, set_non_interactive, view_text,
 };
-use crate::file_analysis::{CLAUDE_TOKEN_LIMIT, analyze_directory, is_binary, show_dir_info};
-use crate::processing::{Action, apply_cached_actions, process_node};
-use crate::summary_cache::{SummaryCache, load_summary_cache, save_summary_cache};
+use crate::vcs::{nested_repo_suggestions, vcs_excludes};
+use crate::vendored::vendored_suggestions;
 
 #[derive(Parser)]
 #[command(
     name = "llm-context-loader",
     about = "Process directory structure for LLM loading",
-    version
+    version,
+    args_conflicts_with_subcommands = true,
+    after_help = SCRIPTED_ANSWERS_HELP
 )]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
     /// Starting directory (default: current directory)
     #[arg(default_value_t = String::from("."))]
     start_dir: String,
 
-    /// Patterns to exclude
+    /// Patterns to exclude (a trailing `/` matches whole path components, e.g. `dist/`)
     #[arg(short, long, action = clap::ArgAction::Append)]
     exclude: Vec<String>,
 
-    /// Maximum tokens to process
+    /// Glob patterns restricting the analysis to matching files, relative to
+    /// the start directory (e.g. `src/**/*.rs`; patterns without a `/` match at
+    /// any depth). Excludes still apply to included files.
+    #[arg(short, long, action = clap::ArgAction::Append)]
+    include: Vec<String>,
+
+    /// Ecosystem-specific exclude preset (`none` disables project type detection)
+    #[arg(long, value_enum, default_value_t = ExcludePreset::Auto)]
+    preset: ExcludePreset,
+
+    /// Maximum tokens to process (default: LLM_CONTEXT_MAX_TOKENS, then
+    /// max_tokens from llm-context.toml or the user config, then 100000)
     #[arg(short, long, default_value_t = 100000)]
     max_tokens: usize,
 
+    /// Largest file, in tokens, that a cached read applies to; bigger files
+    /// are summarized instead
+    #[arg(long, default_value_t = CLAUDE_TOKEN_LIMIT)]
+    max_file_tokens: usize,
+
     /// Ignore existing cache file
     #[arg(long)]
     no_cache: bool,
 
-    /// Directory to store output files (default: .claude-context in current directory)
+    /// Directory to store output files (default: output_dir from llm-context.toml
+    /// or the user config, then .claude-context in current directory)
     #[arg(short, long)]
     output_dir: Option<PathBuf>,
 
+    /// Where the context goes, comma-separated: file, stdout, clipboard. Without
+    /// `file`, parts are written to temporary files unless --output-dir is given
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "file")]
+    output: Vec<OutputTarget>,
+
     /// Create context files in a subdirectory of current working directory
     #[arg(long, default_value_t = true)]
     local_context: bool,
+
+    /// Keep the temporary directory used when the parts aren't written to an
+    /// output directory, for debugging
+    #[arg(long)]
+    keep_temp: bool,
+
+    /// Where to save the session's selection manifest (default: .claude-manifest.json in the start directory)
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+
+    /// Rebuild the context non-interactively from a saved selection manifest
+    #[arg(long)]
+    from_manifest: Option<PathBuf>,
+
+    /// Also write the included files to this path for use by other tools
+    #[arg(long)]
+    export_list: Option<PathBuf>,
+
+    /// Count tokens exactly with Anthropic's count_tokens API (needs ANTHROPIC_API_KEY)
+    #[arg(long)]
+    exact_tokens: bool,
+
+    /// Preset from the user config's [models] table supplying --max-tokens and
+    /// --token-model when they aren't given
+    #[arg(long)]
+    model: Option<String>,
+
+    /// Task preset (review, onboarding, bugfix, or one from the config's
+    /// [tasks] table) supplying the budget split, default actions by file
+    /// class and the preamble when they aren't given
+    #[arg(long, value_name = "NAME")]
+    task: Option<String>,
+
+    /// Model whose tokenizer --exact-tokens uses
+    #[arg(long, default_value_t = String::from(DEFAULT_COUNT_MODEL))]
+    token_model: String,
+
+    /// Split --max-tokens into per-category ceilings, e.g. source=70,docs=20,tests+config=10
+    /// (categories: source, docs, tests, config, vendored)
+    #[arg(long)]
+    budget: Option<String>,
+
+    /// Actions to step down through for nodes that don't fit the remaining
+    /// --max-tokens (truncate is the same as sample)
+    #[arg(long, default_value_t = String::from(DEFAULT_LADDER))]
+    ladder: String,
+
+    /// Read directories with fewer tokens than this (recursively) without
+    /// prompting, unless an action is cached or suggested for them
+    #[arg(long)]
+    auto_read_below: Option<usize>,
+
+    /// Ask before a read that takes more than this percent of what is left
+    /// of --max-tokens, showing its share and the parts it adds (0 never asks)
+    #[arg(long, value_name = "PERCENT", default_value_t = 25)]
+    confirm_reads_over: usize,
+
+    /// Keep directory order instead of offering recently changed files first
+    #[arg(long)]
+    no_recency: bool,
+
+    /// Select files automatically within --max-tokens instead of prompting
+    #[arg(long)]
+    auto_select: bool,
+
+    /// List text files largest first and choose actions for the top ones, then
+    /// auto-select the rest
+    #[arg(long, conflicts_with_all = ["from_manifest", "auto_select"])]
+    largest_first: bool,
+
+    /// lcov or Cobertura coverage file; files that ran are suggested and, with
+    /// --auto-select, are the only non-priority files considered
+    #[arg(long)]
+    coverage: Option<PathBuf>,
+
+    /// File name pattern for context parts ({project}, {date}, {num} and {ext} are filled in)
+    #[arg(long, default_value_t = String::from(DEFAULT_PART_TEMPLATE))]
+    part_name: String,
+
+    /// File extension of context parts [default: the --format's own, e.g. md for markdown]
+    #[arg(long, value_enum)]
+    part_ext: Option<PartExtension>,
+
+    /// Layout of the context parts: markdown gives each directory and file a
+    /// heading, fences code with its language and opens with a linked table
+    /// of contents; xml wraps each file in <document> tags for long-context
+    /// prompts; json keeps text parts and writes context.json beside them,
+    /// listing each file's action, tokens and content, which is emitted in
+    /// their place
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, conflicts_with = "append")]
+    format: OutputFormat,
+
+    /// Take over the lock held by another run in the same directory
+    #[arg(long)]
+    force: bool,
+
+    /// Add newly selected files as extra parts to the run that wrote this
+    /// index.json, skipping files it already contains
+    #[arg(long, conflicts_with_all = ["from_manifest", "auto_select", "session"])]
+    append: Option<PathBuf>,
+
+    /// Show each newly generated summary before it is written, to accept,
+    /// regenerate with an extra instruction, or edit it in $EDITOR
+    #[arg(long)]
+    edit_summaries: bool,
+
+    /// Where new summaries come from (default: backend from the config, then
+    /// claude, which uses the API when ANTHROPIC_API_KEY is set, else the
+    /// claude CLI)
+    #[arg(long, value_enum)]
+    summarizer: Option<SummarizerKind>,
+
+    /// Model new summaries are asked of (default: model from the config,
+    /// then claude-haiku-4-5, gpt-4o-mini with OpenAI or qwen2.5-coder with
+    /// Ollama)
+    #[arg(long)]
+    summarizer_model: Option<String>,
+
+    /// Attach the signatures of std and dependency items used by Rust files
+    /// included as skeletons or API docs, looked up in rust-src and cargo's
+    /// downloaded sources
+    #[arg(long)]
+    external_signatures: bool,
+
+    /// Fold function bodies of at least N lines in fully read files into a
+    /// marker naming the folded lines; index.json lists the folded regions
+    #[arg(long, value_name = "N")]
+    fold_bodies: Option<usize>,
+
+    /// Tokens at the end of each piece of a file split across parts that are
+    /// repeated at the start of the next piece, so nothing is lost at the seam
+    #[arg(long, default_value_t = 0)]
+    chunk_overlap_tokens: usize,
+
+    /// Normalize the text of the parts, comma-separated: line-endings (CRLF to
+    /// LF), final-newline (end each file with a newline), utf8 (replace
+    /// invalid UTF-8 rather than skip the file's content)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    normalize: Vec<Normalization>,
+
+    /// Read only the declarations whose names match these comma-separated
+    /// patterns (such as "Parser*,parse_*") from code files, with their doc
+    /// comments and attributes; code files with no matches are left out
+    #[arg(long, value_delimiter = ',')]
+    symbols: Vec<String>,
+
+    /// Cargo features the crate is built with (comma-separated, taken
+    /// literally, so list the defaults too): Rust skeletons drop items whose
+    /// #[cfg] gates these features turn off and mark the rest
+    #[arg(long, value_delimiter = ',')]
+    features: Option<Vec<String>>,
+
+    /// Text that leaves out any file containing it in its first lines, so
+    /// owners can opt files out of every bundle (default "llm-context: ignore";
+    /// an empty value disables it)
+    #[arg(long, value_name = "TEXT")]
+    opt_out_marker: Option<String>,
+
+    /// Finish by printing the outcome (parts, file and token totals, cache
+    /// hits, errors) as a single line of JSON instead of offering to start
+    /// Claude
+    #[arg(long)]
+    json_summary: bool,
+
+    /// Order of the content in the parts: as selected, or ordered (the tree
+    /// and overview files in part 1, then files next to the files they import)
+    #[arg(long, value_enum, default_value_t = Layout::Selection, conflicts_with = "append")]
+    layout: Layout,
+
+    /// Write file and directory paths in the parts as absolute paths instead
+    /// of relative to the start directory
+    #[arg(long)]
+    absolute_paths: bool,
+
+    /// Stop waiting for a choice after this long (e.g. 15s, 2m) and apply the
+    /// cached or suggested action, or --timeout-default when there is none
+    #[arg(long, value_name = "DURATION", value_parser = parse_timeout)]
+    prompt_timeout: Option<Duration>,
+
+    /// Action applied when a choice with no other default times out, or goes
+    /// unanswered with --non-interactive
+    #[arg(long, value_name = "ACTION", default_value = "summarize")]
+    timeout_default: String,
+
+    /// Never prompt or read stdin, for scripts and CI: the cache is used,
+    /// cached and suggested actions are accepted, directories are entered and
+    /// files without a default take their class's action (code is read, the
+    /// rest reduced to stats, unless the config's class_actions say otherwise)
+    #[arg(long, visible_alias = "yes")]
+    non_interactive: bool,
+
+    /// Text opening the first part (project conventions, the task) in place
+    /// of the instruction to answer 'Ready'
+    #[arg(long, value_name = "TEXT", conflicts_with = "preamble_file")]
+    preamble: Option<String>,
+
+    /// File whose content opens the first part, as with --preamble
+    #[arg(long, value_name = "FILE")]
+    preamble_file: Option<PathBuf>,
+
+    /// Text closing the last part (answer format, what to do next) in place
+    /// of the instruction to answer 'Ready'
+    #[arg(long, value_name = "TEXT", conflicts_with = "epilogue_file")]
+    epilogue: Option<String>,
+
+    /// File whose content closes the last part, as with --epilogue
+    #[arg(long, value_name = "FILE")]
+    epilogue_file: Option<PathBuf>,
+
+    /// Show the planned selections before finalizing and allow changing or
+    /// dropping them; the parts are rebuilt if anything changed
+    #[arg(long, conflicts_with = "append")]
+    review: bool,
+
+    /// Write each run to a timestamped subdirectory of the output directory and
+    /// point `latest` at it
+    #[arg(long)]
+    session: bool,
+
+    /// Previous runs to keep: sessions with --session (default: all), otherwise
+    /// runs moved to the output directory's archive/ (default: 0)
+    #[arg(long)]
+    keep: Option<usize>,
+
+    /// Format of the --export-list output
+    #[arg(long, value_enum, default_value_t = ExportFormat::List)]
+    export_format: ExportFormat,
 }
 
-fn main() -> Result<()> {
-    // Initialize logger
-    logging::debug();
+#[derive(Subcommand)]
+enum Commands {
+    /// Refresh the blocks of files that changed since existing context files were written
+    Update {
+        /// Directory containing the context files (default: .claude-context in current directory)
+        context_dir: Option<PathBuf>,
+
+        /// Take over the lock held by another run on the same context files
+        #[arg(long)]
+        force: bool,
+    },
+    /// Delete context files and archived runs from a context directory
+    Clean {
+        /// Directory containing the context files (default: .claude-context in current directory)
+        context_dir: Option<PathBuf>,
+
+        /// Archived runs to keep, newest first
+        #[arg(long, default_value_t = 0)]
+        keep: usize,
+
+        /// Take over the lock held by another run on the same context files
+        #[arg(long)]
+        force: bool,
+    },
+    /// Combine context bundles (directories, session roots or index.json files)
+    /// into one set of parts, dropping files that appear more than once
+    Merge {
+        /// Bundles to merge, in order
+        #[arg(required = true, num_args = 2..)]
+        bundles: Vec<PathBuf>,
+
+        /// Directory to write the merged parts to
+        #[arg(short, long)]
+        output_dir: PathBuf,
+
+        /// File name pattern for the merged parts
+        #[arg(long, default_value_t = String::from(DEFAULT_PART_TEMPLATE))]
+        part_name: String,
+
+        /// File extension of the merged parts
+        #[arg(long, value_enum, default_value_t = PartExtension::Txt)]
+        part_ext: PartExtension,
+
+        /// Take over the lock held by another run on the output directory
+        #[arg(long)]
+        force: bool,
+    },
+    /// Pack a run's parts, index.json and manifest into one .ctx.tar.zst file
+    /// with integrity hashes, to attach to a ticket or move to another machine
+    Pack {
+        /// Directory containing the context files (default: .claude-context in current directory)
+        context_dir: Option<PathBuf>,
+
+        /// Manifest to include (default: the one in the directory the parts
+        /// were built from, if it exists)
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+
+        /// Bundle to write (default: <project>.ctx.tar.zst in the current directory)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Check a bundle's integrity hashes and write its files to a directory
+    Unpack {
+        /// Bundle to unpack
+        bundle: PathBuf,
+
+        /// Directory to write to (default: the bundle's name without .ctx.tar.zst)
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+
+        /// Replace files that already exist in the directory
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check a bundle and list what it holds without unpacking it
+    Inspect {
+        /// Bundle to inspect
+        bundle: PathBuf,
+    },
+    /// Time each phase of a run over a directory and report the slowest files
+    Profile {
+        /// Directory to profile (default: current directory)
+        #[arg(default_value_t = String::from("."))]
+        start_dir: String,
+
+        /// Patterns to exclude, in addition to the defaults
+        #[arg(short, long, action = clap::ArgAction::Append)]
+        exclude: Vec<String>,
+
+        /// Ecosystem-specific exclude preset (`none` disables project type detection)
+        #[arg(long, value_enum, default_value_t = ExcludePreset::Auto)]
+        preset: ExcludePreset,
+
+        /// Number of slowest files to list
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+
+        /// Summarize files missing from the summary cache, timing live summarizer calls
+        #[arg(long)]
+        summarize: bool,
+    },
+    /// Print the project as a tree, marking cached actions and summaries
+    Tree {
+        /// Directory to show (default: current directory)
+        #[arg(default_value_t = String::from("."))]
+        start_dir: String,
+
+        /// Patterns to exclude, in addition to the defaults
+        #[arg(short, long, action = clap::ArgAction::Append)]
+        exclude: Vec<String>,
+
+        /// Ecosystem-specific exclude preset (`none` disables project type detection)
+        #[arg(long, value_enum, default_value_t = ExcludePreset::Auto)]
+        preset: ExcludePreset,
+
+        /// Annotate each node with its token total (directories include everything below them)
+        #[arg(long)]
+        tokens: bool,
+
+        /// Write the tree to a file instead of printing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Write a digest of the crate's direct dependencies and the APIs it uses from them
+    DepsDocs {
+        /// Crate directory (default: current directory)
+        #[arg(default_value_t = String::from("."))]
+        start_dir: String,
+
+        /// Patterns to exclude, in addition to the defaults
+        #[arg(short, long, action = clap::ArgAction::Append)]
+        exclude: Vec<String>,
+
+        /// Write the digest to a file instead of printing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Serve the current content of indexed files over HTTP on localhost, so
+    /// summarized, outlined or folded files can be expanded during a conversation
+    Serve {
+        /// Directory containing the context files (default: .claude-context in current directory)
+        context_dir: Option<PathBuf>,
+
+        /// Port to listen on
+        #[arg(short, long, default_value_t = DEFAULT_PORT)]
+        port: u16,
+    },
+    /// Keep a project analyzed in memory, re-analyzing when files change, and
+    /// build contexts on request over HTTP on localhost:
+    /// GET /build?paths=src/a.rs,docs&action=read (or manifest=PATH),
+    /// optionally with out=DIR and max_tokens=N; GET /status
+    Daemon {
+        /// Directory to analyze
+        #[arg(default_value = ".")]
+        start_dir: String,
+
+        /// Patterns to exclude
+        #[arg(short, long, action = clap::ArgAction::Append)]
+        exclude: Vec<String>,
+
+        /// Ecosystem-specific exclude preset
+        #[arg(long, value_enum, default_value_t = ExcludePreset::Auto)]
+        preset: ExcludePreset,
+
+        /// Port to listen on
+        #[arg(short, long, default_value_t = DAEMON_PORT)]
+        port: u16,
+
+        /// Seconds between checks for changed files
+        #[arg(long, default_value_t = DEFAULT_POLL_SECS)]
+        poll: u64,
+    },
+    /// Share generated summaries with other machines
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    /// See what summarization has cost, from the ledger of summarizer calls
+    Cost {
+        #[command(subcommand)]
+        command: CostCommands,
+    },
+    /// Generate summaries of a project's files into its summary cache without
+    /// building a context, so later runs find them cached (e.g. as a nightly job)
+    SummarizeAll {
+        /// Project directory (default: current directory)
+        #[arg(default_value_t = String::from("."))]
+        start_dir: String,
+
+        /// Files to summarize, as patterns like those of config rules (`*.rs`,
+        /// `src/**`); repeatable (default: every text file)
+        #[arg(long, action = clap::ArgAction::Append)]
+        filter: Vec<String>,
+
+        /// Patterns to exclude, in addition to the defaults
+        #[arg(short, long, action = clap::ArgAction::Append)]
+        exclude: Vec<String>,
+
+        /// Regenerate summaries that are already current
+        #[arg(long)]
+        regenerate: bool,
+
+        /// Summaries generated at once
+        #[arg(short, long, default_value_t = DEFAULT_SUMMARY_JOBS)]
+        jobs: usize,
+
+        /// Text that leaves out any file containing it in its first lines
+        /// (default: the config's, else "llm-context: ignore")
+        #[arg(long, value_name = "TEXT")]
+        opt_out_marker: Option<String>,
+
+        /// Take over the lock held by another run on the same project
+        #[arg(long)]
+        force: bool,
+    },
+    /// Read a project's cached summaries without building a context
+    Summaries {
+        #[command(subcommand)]
+        command: SummariesCommands,
+    },
+}
 
+#[derive(Subcommand)]
+enum SummariesCommands {
+    /// Print the cached summary of a file
+    Show {
+        /// File to show
+        path: PathBuf,
+
+        /// Project directory (default: current directory)
+        #[arg(short, long, default_value_t = String::from("."))]
+        dir: String,
+    },
+    /// List the cached summaries containing a term (ignoring case), with the
+    /// lines that match
+    Search {
+        /// Text to look for in summaries and file paths
+        term: String,
+
+        /// Project directory (default: current directory)
+        #[arg(short, long, default_value_t = String::from("."))]
+        dir: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CostCommands {
+    /// Total the tokens and estimated cost of summarizer calls
+    Report {
+        /// Project directory (default: current directory)
+        #[arg(short, long, default_value_t = String::from("."))]
+        dir: String,
+
+        /// Group the calls by day, run or model
+        #[arg(long, value_enum, default_value_t = CostGrouping::Day)]
+        by: CostGrouping,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Write the project's current summaries to a file, keyed by file content
+    Export {
+        /// File to write
+        file: PathBuf,
+
+        /// Project directory (default: current directory)
+        #[arg(short, long, default_value_t = String::from("."))]
+        dir: String,
+    },
+    /// Add summaries from an export for files with the same content
+    Import {
+        /// Export file to read
+        file: PathBuf,
+
+        /// Project directory (default: current directory)
+        #[arg(short, long, default_value_t = String::from("."))]
+        dir: String,
+    },
+    /// Print a new key for encrypting the summary cache (set it in LLM_CONTEXT_CACHE_KEY)
+    Keygen,
+    /// Show how often cached summaries saved generating new ones
+    Stats {
+        /// Project directory (default: current directory)
+        #[arg(short, long, default_value_t = String::from("."))]
+        dir: String,
+    },
+}
+
+fn main() -> Result<()> {
     // Parse command line arguments
-    let args = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let args = Cli::from_arg_matches(&matches)?;
+
+    // Initialize logger; when the context or the JSON summary goes to stdout,
+    // logs, menus and prompts go to stderr so they don't end up in it
+    if args.command.is_none() && (args.json_summary || args.output.contains(&OutputTarget::Stdout)) {
+        set_messages_to_stderr();
+        logging::debug_to_stderr();
+    } else {
+        logging::debug();
+    }
+
+    // A failed run still ends with its summary when one was asked for
+    let json_summary = args.json_summary;
+    let start_dir = PathBuf::from(&args.start_dir);
+    let result = run(args, &matches);
+
+    // Summarizer calls are logged even when the run failed part way
+    if let Err(e) = start_dir.canonicalize().map_err(anyhow::Error::from).and_then(|dir| flush_ledger(&dir)) {
+        warn!("Failed to write the summarizer ledger: {:#}", e);
+    }
+    if json_summary && let Err(e) = &result {
+        println!("{}", RunSummary::failed(e).to_json_line()?);
+    }
+    result
+}
 
+/// Run the subcommand or the interactive selection
+fn run(mut args: Cli, matches: &ArgMatches) -> Result<()> {
+    // User-wide excludes, rules, presets and credentials
+    let mut config = load_user_config()?;
+
+    match args.command {
+        Some(Commands::Update { context_dir, force }) => {
+            let context_dir = match context_dir {
+                Some(dir) => dir,
+                None => get_default_context_dir()?,
+            };
+            let _lock = acquire_lock(&context_dir, force)?;
+            // With sessions, the newest one is the one to update
+            let context_dir = latest_session(&context_dir).unwrap_or(context_dir);
+            return run_update(&context_dir, config);
+        }
+        Some(Commands::Clean {
+            context_dir,
+            keep,
+            force,
+        }) => {
+            let context_dir = match context_dir {
+                Some(dir) => dir,
+                None => get_default_context_dir()?,
+            };
+            let _lock = acquire_lock(&context_dir, force)?;
+            return run_clean(&context_dir, keep);
+        }
+        Some(Commands::Merge {
+            bundles,
+            output_dir,
+            part_name,
+            part_ext,
+            force,
+        }) => {
+            fs::create_dir_all(&output_dir).context("Failed to create output directory")?;
+            let _lock = acquire_lock(&output_dir, force)?;
+            let naming = PartNaming::new(&part_name, part_ext)?;
+            let summary = merge_bundles(&bundles, &output_dir, &naming)?;
+
+            print_title("Merge summary:");
+            print_field("Parts", summary.parts);
+            print_field("Files", summary.files);
+            print_field("Duplicates", summary.duplicates);
+            return Ok(());
+        }
+        Some(Commands::Profile {
+            start_dir,
+            exclude,
+            preset,
+            top,
+            summarize,
+        }) => return run_profile(&start_dir, config, exclude, preset, top, summarize),
+        Some(Commands::Tree {
+            start_dir,
+            exclude,
+            preset,
+            tokens,
+            output,
+        }) => return run_tree(&start_dir, &config, exclude, preset, tokens, output.as_deref()),
+        Some(Commands::DepsDocs {
+            start_dir,
+            exclude,
+            output,
+        }) => return run_deps_docs(&start_dir, config, exclude, output.as_deref()),
+        Some(Commands::Serve { context_dir, port }) => {
+            let context_dir = match context_dir {
+                Some(dir) => dir,
+                None => get_default_context_dir()?,
+            };
+            let context_dir = latest_session(&context_dir).unwrap_or(context_dir);
+            return run_serve(&context_dir, port);
+        }
+        Some(Commands::Daemon {
+            start_dir,
+            exclude,
+            preset,
+            port,
+            poll,
+        }) => return run_daemon(&start_dir, config, exclude, preset, port, poll),
+        Some(Commands::Pack {
+            context_dir,
+            manifest,
+            output,
+        }) => {
+            let context_dir = match context_dir {
+                Some(dir) => dir,
+                None => get_default_context_dir()?,
+            };
+            let context_dir = latest_session(&context_dir).unwrap_or(context_dir);
+            return run_pack(&context_dir, manifest, output);
+        }
+        Some(Commands::Unpack {
+            bundle,
+            output_dir,
+            force,
+        }) => {
+            let out_dir = output_dir.unwrap_or_else(|| default_unpack_dir(&bundle));
+            let listing = unpack(&bundle, &out_dir, force)?;
+            println!("Unpacked {} files to {}", listing.files.len(), out_dir.display());
+            return Ok(());
+        }
+        Some(Commands::Inspect { bundle }) => {
+            let (listing, _) = read_bundle(&bundle)?;
+            print_bundle_listing(&bundle, &listing);
+            return Ok(());
+        }
+        Some(Commands::Cache { command }) => return run_cache(command, &config),
+        Some(Commands::Cost {
+            command: CostCommands::Report { dir, by },
+        }) => return run_cost_report(&dir, by),
+        Some(Commands::SummarizeAll {
+            start_dir,
+            filter,
+            exclude,
+            regenerate,
+            jobs,
+            opt_out_marker,
+            force,
+        }) => {
+            let start_dir = PathBuf::from(start_dir)
+                .canonicalize()
+                .context("Failed to resolve start directory")?;
+            let _lock = acquire_lock(&start_dir, force)?;
+            return run_summarize_all(&start_dir, config, &filter, exclude, regenerate, jobs, opt_out_marker);
+        }
+        Some(Commands::Summaries { command }) => return run_summaries(command),
+        None => {}
+    }
+
+    if args.json_summary && args.output.contains(&OutputTarget::Stdout) {
+        bail!("--json-summary takes stdout for itself; send the context to a file or the clipboard");
+    }
     let start_dir = PathBuf::from(&args.start_dir)
         .canonicalize()
         .context("Failed to resolve start directory")?;
 
-    let mut excludes = vec![
-        ".git".to_string(),
-        "node_modules".to_string(),
-        "__pycache__".to_string(),
-        ".env".to_string(),
-        "venv".to_string(),
-        "target".to_string(),
-        ".claude-context".to_string(),
-        ".claude-summaries".to_string(),
-        ".claude_include".to_string(),
-        ".claude".to_string(),
-    ];
-    excludes.extend(args.exclude);
+    // Keep concurrent runs from interleaving writes to the cache files
+    let _start_lock = acquire_lock(&start_dir, args.force)?;
+
+    // Settings come from, highest first: the command line, the environment,
+    // the project's llm-context.toml, the user config, then the defaults
+    config = project_config(config, &start_dir)?;
+    if let Some(max_tokens) = config.max_tokens
+        && matches.value_source("max_tokens") != Some(ValueSource::CommandLine)
+    {
+        args.max_tokens = max_tokens;
+    }
+    if args.output_dir.is_none() {
+        args.output_dir = config.output_dir.clone();
+    }
+    if let Some(kind) = args.summarizer {
+        config.summarizer.backend = Some(kind);
+    }
+    if let Some(model) = &args.summarizer_model {
+        config.summarizer.model = Some(model.clone());
+    }
+    configure_summaries(&config.summarizer);
+    if let Some(features) = &args.features {
+        set_cfg_features(features);
+    }
+
+    // A task preset fills in the settings not given on the command line; a
+    // model preset's token limit takes precedence over the task's
+    if let Some(name) = &args.task {
+        let task = task_preset(&config, name)?;
+        if let Some(max_tokens) = task.max_tokens
+            && matches.value_source("max_tokens") != Some(ValueSource::CommandLine)
+        {
+            args.max_tokens = max_tokens;
+        }
+        if args.budget.is_none() {
+            args.budget = task.budget;
+        }
+        if task.auto_select == Some(true)
+            && !args.largest_first
+            && args.from_manifest.is_none()
+            && args.append.is_none()
+        {
+            args.auto_select = true;
+        }
+        if args.preamble.is_none() && args.preamble_file.is_none() {
+            args.preamble = task.preamble.map(|template| fill_preamble(&template, &start_dir));
+        }
+        config.class_actions.extend(task.class_actions);
+    }
+
+    // Files nothing else decides for get a default by class when no one is
+    // there to choose
+    if args.non_interactive {
+        set_non_interactive();
+        for (class, action) in NON_INTERACTIVE_CLASS_ACTIONS {
+            config
+                .class_actions
+                .entry(class.to_string())
+                .or_insert_with(|| action.to_string());
+        }
+    }
+
+    // A model preset fills in the settings not given on the command line
+    if let Some(name) = &args.model {
+        let preset = config.model(name)?;
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        if let Some(max_tokens) = preset.max_tokens
+            && !from_cli("max_tokens")
+        {
+            args.max_tokens = max_tokens;
+        }
+        if let Some(token_model) = &preset.token_model
+            && !from_cli("token_model")
+        {
+            args.token_model = token_model.clone();
+        }
+    }
+
+    let excludes = build_excludes(&start_dir, args.preset, &config, args.exclude.clone());
+
+    // Hooks see the paths of the run; the output ones once parts exist
+    let manifest_path = args
+        .manifest
+        .clone()
+        .unwrap_or_else(|| default_manifest_path(&start_dir));
+    let mut hook_env = HookEnv {
+        start_dir: start_dir.clone(),
+        manifest: manifest_path.clone(),
+        ..Default::default()
+    };
+    if let Some(command) = &config.hooks.pre_build {
+        run_hook("pre_build", command, &hook_env)?;
+    }
 
     info!("Analyzing directory: {}", start_dir.display());
     info!("Excluding: {}", excludes.join(", "));
 
-    // Analyze directory structure
-    let dir_info = analyze_directory(&start_dir, &excludes)?;
+    // Analyze directory structure, reusing the last analysis of unchanged directories
+    let mut dir_info = if args.no_cache {
+        analyze_directory(&start_dir, &excludes)?
+    } else {
+        let mut analysis_cache = load_analysis_cache(&start_dir)?;
+        let dir_info = analyze_directory_cached(&start_dir, &excludes, &mut analysis_cache)?;
+        save_analysis_cache(&start_dir, &analysis_cache)?;
+        dir_info
+    };
+
+    // Only the included files are offered, after excludes have pruned the walk
+    if !args.include.is_empty() {
+        let includes = include_matcher(&args.include)?;
+        let dropped = restrict_to_includes(&mut dir_info, &start_dir, &includes);
+        info!("Including: {} ({} other files left out)", args.include.join(", "), dropped);
+    }
+
+    // Files whose owners opted them out are left out whoever runs the tool
+    let marker = args
+        .opt_out_marker
+        .clone()
+        .or_else(|| config.opt_out_marker.clone())
+        .unwrap_or_else(|| DEFAULT_OPT_OUT_MARKER.to_string());
+    let opted_out = remove_opted_out(&mut dir_info, &marker);
+    if !opted_out.is_empty() {
+        info!("Leaving out {} files marked '{}'", opted_out.len(), marker);
+        for path in &opted_out {
+            info!("Opted out: {}", path.display());
+        }
+    }
+
+    // Replace estimates with exact counts when requested
+    if args.exact_tokens {
+        let counter = TokenCounter::from_env(&args.token_model).or_else(|| {
+            config
+                .summarizer
+                .api_key
+                .as_ref()
+                .map(|key| TokenCounter::new(key, &args.token_model))
+        });
+        match counter {
+            Some(counter) => {
+                let mut token_cache = load_token_cache(&start_dir)?;
+                apply_exact_counts(&mut dir_info, &counter, &mut token_cache);
+                save_token_cache(&start_dir, &token_cache)?;
+            }
+            None => warn!(
+                "{} is not set and the config has no api_key; using estimated token counts",
+                API_KEY_VAR
+            ),
+        }
+    }
 
     // Estimate total tokens and files needed
     let total_tokens: usize = dir_info.values().map(|dir| dir.tokens).sum();
@@ -96,10 +1054,103 @@ fn main() -> Result<()> {
     info!("Estimated total tokens: {}", total_tokens);
     info!("Estimated context files needed: {}", estimated_files);
 
+    // Default generated/vendored paths from .gitattributes to exclude/stats
+    let mut suggestions = linguist_suggestions(&dir_info);
+    if !suggestions.is_empty() {
+        info!(
+            "Found {} linguist-generated/vendored paths in .gitattributes",
+            suggestions.len()
+        );
+    }
+
+    // Then the user's config rules
+    merge_suggestions(&mut suggestions, rule_suggestions(&config.rules, &start_dir, &dir_info));
+
+    // Third-party code defaults to stats (vendor directories) or summarize
+    // (another project's license header)
+    merge_suggestions(&mut suggestions, vendored_suggestions(&start_dir, &dir_info));
+
+    // Repositories nested in the project collapse to one node unless entered
+    merge_suggestions(&mut suggestions, nested_repo_suggestions(&start_dir, &dir_info));
+
+    // Changelogs default to their recent releases
+    merge_suggestions(&mut suggestions, changelog_suggestions(&dir_info));
+
+    // SVG and XML default to an outline of their tags
+    merge_suggestions(&mut suggestions, markup_suggestions(&dir_info));
+
+    // Files that ran in the given coverage report default to read
+    let coverage = match &args.coverage {
+        Some(path) => {
+            let coverage = load_coverage(path, &start_dir)?;
+            info!("Loaded coverage for {} files", coverage.len());
+            merge_suggestions(&mut suggestions, coverage_suggestions(&coverage));
+            Some(coverage)
+        }
+        None => None,
+    };
+
+    // Offer README, manifests and LICENSE first, defaulting them to read
+    let mut priority = priority_files(&start_dir, &dir_info);
+    merge_suggestions(&mut suggestions, priority_suggestions(&priority));
+
+    // Then entry points and the modules they import, so the core of the project comes next
+    let (entry_points, imports) = entry_point_files(&start_dir, &dir_info);
+    merge_suggestions(
+        &mut suggestions,
+        entry_point_suggestions(&entry_points, &imports),
+    );
+    for path in entry_points.into_iter().chain(imports) {
+        if !priority.contains(&path) {
+            priority.push(path);
+        }
+    }
+
+    // Files nothing imports are likely dead or standalone, so default them to summarize
+    let unreferenced = unreferenced_files(&start_dir, &dir_info);
+    if !unreferenced.is_empty() {
+        info!("Found {} unreferenced source files", unreferenced.len());
+    }
+    merge_suggestions(&mut suggestions, unreferenced_suggestions(&unreferenced));
+
+    // Offer recently changed files first, and default uncommitted ones to read
+    let recency = if args.no_recency {
+        RecencyMap::new()
+    } else {
+        let dirty = dirty_files(&start_dir);
+        merge_suggestions(&mut suggestions, recency_suggestions(&dir_info, &dirty));
+        collect_recency(&start_dir, &dir_info, &dirty)
+    };
+
+    // Configured defaults by file class, for files nothing more specific covers
+    merge_suggestions(
+        &mut suggestions,
+        class_suggestions(&config.class_actions, &start_dir, &dir_info),
+    );
+
+    // Files already in the context being appended to, and where it lives
+    let (appended_dir, appended_files) = match &args.append {
+        Some(index_path) => {
+            let index = load_index(index_path)?;
+            let files: HashSet<PathBuf> =
+                index.files.iter().map(|f| start_dir.join(&f.path)).collect();
+            let dir = index_path
+                .canonicalize()
+                .context("Failed to resolve index path")?
+                .parent()
+                .map(Path::to_path_buf);
+            info!("Appending to a context of {} files", files.len());
+            (dir, files)
+        }
+        None => (None, HashSet::new()),
+    };
+
     // Determine the output directory
-    let output_dir = if args.output_dir.is_some() {
+    let output_dir = if appended_dir.is_some() {
+        appended_dir.clone()
+    } else if args.output_dir.is_some() {
         args.output_dir.clone()
-    } else if args.local_context {
+    } else if args.local_context && args.output.contains(&OutputTarget::File) {
         Some(get_default_context_dir()?)
     } else {
         None
@@ -113,20 +1164,243 @@ fn main() -> Result<()> {
         )
     );
 
-    // Create the first output file
-    let mut context_file =
-        create_context_file(1, estimated_files, &start_dir, output_dir.as_deref())?;
+    // ...and to the context directory, when it is elsewhere
+    let _output_lock = match &output_dir {
+        Some(dir) if dir.canonicalize().ok().as_ref() != Some(&start_dir) => {
+            fs::create_dir_all(dir).context("Failed to create output directory")?;
+            Some(acquire_lock(dir, args.force)?)
+        }
+        _ => None,
+    };
+
+    // Start a new session, or clear out (or archive) the previous run so no
+    // stale parts remain
+    let session_root = if args.session { output_dir.clone() } else { None };
+    let output_dir = match (&session_root, &output_dir) {
+        (Some(root), _) => Some(create_session_dir(root)?),
+        (None, Some(dir)) if appended_dir.is_some() => Some(dir.clone()),
+        (None, Some(dir)) => {
+            prepare_output_dir(dir, args.keep.unwrap_or(0))?;
+            Some(dir.clone())
+        }
+        (None, None) => None,
+    };
+
+    // Parts that only go to stdout or the clipboard are written to a
+    // temporary directory, removed when the run ends
+    let temp_workspace = match &output_dir {
+        Some(_) => None,
+        None => {
+            let workspace = TempWorkspace::new(args.keep_temp)?;
+            if args.keep_temp {
+                say(format!("Keeping temporary parts in {}", workspace.path().display()));
+            }
+            Some(workspace)
+        }
+    };
+    // Temporary parts are gone once the run ends, so their paths aren't worth listing
+    let temporary_parts = temp_workspace.is_some() && !args.keep_temp;
+    let output_dir = output_dir.or_else(|| temp_workspace.as_ref().map(|w| w.path().to_path_buf()));
+
+    let mut output = OutputSettings {
+        dir: output_dir,
+        format: args.format,
+        metadata: Some(GenerationMetadata::collect(
+            &start_dir,
+            std::env::args().collect(),
+        )),
+        naming: PartNaming::new(&args.part_name, args.part_ext.unwrap_or(args.format.part_extension()))?,
+        edit_summaries: args.edit_summaries,
+        external_signatures: args.external_signatures,
+        fold_bodies: args.fold_bodies,
+        chunk_overlap_tokens: args.chunk_overlap_tokens,
+        normalize: args.normalize.clone(),
+        symbols: match args.symbols.is_empty() {
+            true => None,
+            false => Some(symbol_matcher(&args.symbols)?),
+        },
+        absolute_paths: args.absolute_paths,
+        preamble: text_or_file(&args.preamble, &args.preamble_file)?,
+        epilogue: text_or_file(&args.epilogue, &args.epilogue_file)?,
+    };
+    // Stored with each selection so a replay writes files the same way
+    let entry_options = EntryOptions {
+        fold_bodies: args.fold_bodies,
+        symbols: args.symbols.clone(),
+        external_signatures: args.external_signatures,
+        normalize: args.normalize.clone(),
+    };
+
+    // Create the first output file, numbered after any parts being appended to
+    let previous_parts = match &appended_dir {
+        Some(dir) => context_part_paths(dir)?,
+        None => Vec::new(),
+    };
+    // Appended parts are written in the format of the ones before them
+    if !previous_parts.is_empty() {
+        output.format = parts_format(&previous_parts)?;
+    }
+    let mut context_file = create_context_file(
+        previous_parts.len() + 1,
+        previous_parts.len() + estimated_files,
+        &start_dir,
+        &output,
+    )?;
+    if let Some(last) = previous_parts.last() {
+        link_appended_part(last, &context_file.path)?;
+    }
 
     // Keep track of all used context files
     let mut all_context_files = vec![context_file.clone()];
 
+    // Category ceilings within --max-tokens
+    let mut plan = match &args.budget {
+        Some(spec) => BudgetPlan::parse(spec, args.max_tokens, &start_dir)?,
+        None => BudgetPlan::default(),
+    };
+
+    let ladder = Ladder::parse(&args.ladder)?;
+
+    let timeout = match (args.prompt_timeout, args.non_interactive) {
+        (None, false) => None,
+        (after, non_interactive) => {
+            if choice_for_action(&args.timeout_default).and_then(action_for_choice).is_none() {
+                bail!("Invalid --timeout-default '{}'", args.timeout_default);
+            }
+            Some(PromptTimeout {
+                after: after.unwrap_or_default(),
+                action: args.timeout_default.clone(),
+                enter_dirs: non_interactive,
+            })
+        }
+    };
+
+    // Replay a saved manifest, or build one automatically, instead of prompting
+    let preset_manifest = if let Some(path) = &args.from_manifest {
+        Some(load_manifest(path)?)
+    } else if args.auto_select || args.largest_first {
+        if args.largest_first {
+            select_largest_first(&start_dir, &dir_info, &mut suggestions, &mut priority)?;
+        }
+        let mut manifest = auto_select(
+            &start_dir,
+            &dir_info,
+            &suggestions,
+            &priority,
+            &recency,
+            coverage.as_ref(),
+            args.max_tokens,
+            &plan,
+            &ladder,
+        );
+        manifest.fill_options(&entry_options);
+        save_manifest(&manifest_path, &manifest)?;
+        Some(manifest)
+    } else {
+        None
+    };
+
+    if let Some(manifest) = preset_manifest {
+        let mut summary_cache = load_summary_cache(&start_dir)?;
+        let manifest = match args.layout {
+            Layout::Ordered => {
+                write_tree_overview(&mut context_file, &start_dir, &dir_info, &output)?;
+                ordered_manifest(&manifest, &start_dir)
+            }
+            Layout::Selection => manifest,
+        };
+
+        let result = apply_manifest(
+            &manifest,
+            &dir_info,
+            &mut context_file,
+            args.max_tokens,
+            estimated_files,
+            &start_dir,
+            &output,
+            Some(&summary_cache),
+        )?;
+        all_context_files.extend(result.context_files.into_iter().skip(1)); // Skip first since it's already in the list
+        for summary_info in result.file_summaries {
+            summary_cache.insert_summary(&summary_info.path, &summary_info.content_hash, summary_info.summary);
+        }
+        // Also keeps the lookup counts
+        save_summary_cache(&start_dir, &summary_cache)?;
+
+        finalize_context_files(
+            &all_context_files,
+            result.included_files.len(),
+            output.epilogue.as_deref(),
+            output.format.formatter(),
+        )?;
+        let parts = all_parts(&previous_parts, &all_context_files);
+        renumber_parts(&parts)?;
+        if let Some(dir) = &output.dir {
+            write_index(dir, &context_part_paths(dir)?)?;
+        }
+        let rendered = render_parts(&parts, output.format)?;
+        emit_parts(&rendered, &args.output)?;
+        if let Some(command) = &config.hooks.post_build {
+            hook_env.output_dir = output.dir.clone();
+            hook_env.parts = parts.clone();
+            run_hook("post_build", command, &hook_env)?;
+        }
+        if let Some(export_path) = &args.export_list {
+            let files = selected_files(&manifest, &start_dir, &dir_info);
+            write_export(export_path, &files, &start_dir, args.export_format)?;
+        }
+        if let (Some(root), Some(dir)) = (&session_root, &output.dir) {
+            finish_session(root, dir, args.keep)?;
+        }
+        print_run_summary(
+            result.processed.len(),
+            result.included_files.len(),
+            result.total_tokens,
+            &parts,
+            summary_cache.run_stats(),
+        );
+        let mut charged = HashSet::new();
+        for entry in &manifest.entries {
+            if entry.action == "exclude" || entry.action == "enter" {
+                continue;
+            }
+            let node = start_dir.join(&entry.path);
+            for (path, cost) in node_costs(&node, &dir_info, &entry.action, &charged) {
+                plan.record(&path, cost);
+                charged.insert(path);
+            }
+        }
+        plan.print_utilization();
+        if args.json_summary {
+            let summary = RunSummary::new(
+                parts,
+                &manifest,
+                result.included_files.len(),
+                result.total_tokens,
+                summary_cache.run_stats(),
+                Vec::new(),
+            );
+            println!("{}", summary.to_json_line()?);
+            return Ok(());
+        }
+        show_parts(&parts, temporary_parts);
+        return Ok(());
+    }
+
+    // An appended run extends the earlier run's manifest
+    let mut manifest = if args.append.is_some() && manifest_path.exists() {
+        load_manifest(&manifest_path)?
+    } else {
+        Manifest::new()
+    };
+
     // Initialize file action cache
     let mut cache = if args.no_cache {
         HashMap::new()
     } else {
         load_cache(&start_dir)?
     };
-    let use_cache = !cache.is_empty() && !args.no_cache;
+    let use_cache = !cache.is_empty() && !args.no_cache && args.append.is_none();
 
     // Initialize summary cache
     let mut summary_cache = if args.no_cache {
@@ -140,55 +1414,73 @@ fn main() -> Result<()> {
         warn!("Failed to clean up summary cache: {}", e);
     }
 
+    let run_summary;
     if use_cache {
         info!("Found existing cache with {} entries", cache.len());
 
         // Display cache summary
-        let mut action_paths: HashMap<String, Vec<PathBuf>> = HashMap::new();
-        for (path, action) in &cache {
-            action_paths
-                .entry(action.clone())
-                .or_default()
-                .push(path.clone());
-        }
-
-        println!("\nCache summary:");
-        for (action, paths) in action_paths.iter() {
-            println!("\n  {}: {} items", action, paths.len());
-            for path in paths.iter().take(5) {
-                // Only show first 5 for brevity
-                println!(
-                    "    - {}",
-                    path.strip_prefix(&start_dir).unwrap_or(path).display()
-                );
-            }
-            if paths.len() > 5 {
-                println!("    - ... and {} more", paths.len() - 5);
-            }
-        }
+        print_cache_summary(&cache, &start_dir);
 
         // Ask if user wants to use the cache
-        print!("\nUse existing cache file? [Y/n]: ");
-        io::stdout().flush()?;
-
-        let mut response = String::new();
-        io::stdin().read_line(&mut response)?;
-        let use_cache = response.trim().to_lowercase() != "n";
+        let use_cache = prompt("\nUse existing cache file? [Y/n]: ")?.to_lowercase() != "n";
 
         if use_cache {
+            // Files read last time may have been rewritten since
+            confirm_changed_reads(&mut cache, &dir_info, &start_dir, timeout.as_ref())?;
+
             // Apply cached actions first if needed
             info!("Applying actions from cache...");
-            let result = apply_cached_actions(
+            let (result, downgrades) = apply_cached_actions(
                 &dir_info,
                 &mut context_file,
                 args.max_tokens,
+                args.max_file_tokens,
                 &cache,
                 estimated_files,
                 &start_dir,
-                output_dir.as_deref(),
+                &output,
                 Some(&summary_cache),
             )?;
 
+            // Record the cached selections in the order they were applied,
+            // with the action actually used for oversized reads. Entered
+            // directories are recorded as their headers are written
+            for path in ordered_cache_paths(&cache) {
+                let action = &cache[path];
+                if !path.exists() || action == "enter" || Action::parse_str(action).is_none() {
+                    continue;
+                }
+                match downgrades.iter().find(|d| d.path == **path) {
+                    Some(downgrade) => manifest.record_with_options(
+                        path,
+                        &start_dir,
+                        downgrade.applied.as_str(),
+                        [
+                            ("requested".to_string(), action.clone()),
+                            ("reason".to_string(), "over per-file cap".to_string()),
+                        ]
+                        .into(),
+                    ),
+                    None => manifest.record(path, &start_dir, action),
+                }
+            }
+
+            // Oversized reads were summarized instead
+            if !downgrades.is_empty() {
+                print_title("Downgraded oversized files:");
+            }
+            for downgrade in &downgrades {
+                print_field(
+                    &downgrade.path.strip_prefix(&start_dir).unwrap_or(&downgrade.path).display().to_string(),
+                    format!(
+                        "~{} tokens, {} instead of {}",
+                        downgrade.tokens,
+                        downgrade.applied.as_str(),
+                        downgrade.requested.as_str()
+                    ),
+                );
+            }
+
             let total_tokens = result.total_tokens;
             let processed = result.processed;
             let included_files = result.included_files;
@@ -203,174 +1495,936 @@ fn main() -> Result<()> {
                 );
             }
 
-            process_interactive_loop(
-                start_dir,
-                dir_info,
+            run_summary = process_interactive_loop(
+                start_dir.clone(),
+                &dir_info,
                 &mut context_file,
                 args.max_tokens,
                 &mut cache,
                 &mut summary_cache,
+                &suggestions,
+                &priority,
+                &recency,
+                &mut plan,
+                &ladder,
+                timeout.as_ref(),
+                args.layout,
+                args.auto_read_below,
+                (args.confirm_reads_over > 0).then_some(args.confirm_reads_over),
+                args.review,
+                &mut manifest,
                 use_cache,
                 total_tokens,
                 processed,
                 included_files,
                 estimated_files,
-                output_dir.as_deref(),
+                &previous_parts,
+                &output,
                 &mut all_context_files,
             )?;
         } else {
-            process_interactive_loop(
-                start_dir,
-                dir_info,
+            run_summary = process_interactive_loop(
+                start_dir.clone(),
+                &dir_info,
                 &mut context_file,
                 args.max_tokens,
                 &mut cache,
                 &mut summary_cache,
+                &suggestions,
+                &priority,
+                &recency,
+                &mut plan,
+                &ladder,
+                timeout.as_ref(),
+                args.layout,
+                args.auto_read_below,
+                (args.confirm_reads_over > 0).then_some(args.confirm_reads_over),
+                args.review,
+                &mut manifest,
                 false,
                 0,
                 HashSet::new(),
                 HashSet::new(),
                 estimated_files,
-                output_dir.as_deref(),
+                &previous_parts,
+                &output,
                 &mut all_context_files,
             )?;
         }
-    } else {
-        process_interactive_loop(
-            start_dir,
-            dir_info,
-            &mut context_file,
-            args.max_tokens,
-            &mut cache,
-            &mut summary_cache,
-            false,
-            0,
-            HashSet::new(),
-            HashSet::new(),
-            estimated_files,
-            output_dir.as_deref(),
-            &mut all_context_files,
-        )?;
+    } else {
+        run_summary = process_interactive_loop(
+            start_dir.clone(),
+            &dir_info,
+            &mut context_file,
+            args.max_tokens,
+            &mut cache,
+            &mut summary_cache,
+            &suggestions,
+            &priority,
+            &recency,
+            &mut plan,
+            &ladder,
+            timeout.as_ref(),
+            args.layout,
+            args.auto_read_below,
+            (args.confirm_reads_over > 0).then_some(args.confirm_reads_over),
+            args.review,
+            &mut manifest,
+            false,
+            0,
+            appended_files.clone(),
+            appended_files,
+            estimated_files,
+            &previous_parts,
+            &output,
+            &mut all_context_files,
+        )?;
+    }
+
+    manifest.fill_options(&entry_options);
+    save_manifest(&manifest_path, &manifest)?;
+    if let Some(export_path) = &args.export_list {
+        let files = selected_files(&manifest, &start_dir, &dir_info);
+        write_export(export_path, &files, &start_dir, args.export_format)?;
+    }
+    if let (Some(root), Some(dir)) = (&session_root, &output.dir) {
+        finish_session(root, dir, args.keep)?;
+    }
+    let parts = run_summary.parts.clone();
+    let rendered = render_parts(&parts, output.format)?;
+    emit_parts(&rendered, &args.output)?;
+    if let Some(command) = &config.hooks.post_build {
+        hook_env.output_dir = output.dir.clone();
+        hook_env.parts = parts.clone();
+        run_hook("post_build", command, &hook_env)?;
+    }
+    if args.json_summary {
+        println!("{}", run_summary.to_json_line()?);
+        return Ok(());
+    }
+
+    // Finalize all context files
+    // This is also done in the interactive loop to handle ctrl+c, but we do it again here to make sure
+
+    let context_files_paths: Vec<_> = parts.iter().map(|p| p.display().to_string()).collect();
+    let message = kickoff_message(&context_files_paths);
+    let show_manual = |intro: &str| {
+        if !temporary_parts {
+            say(format!("\n{} {}", intro, manual_command(&message)));
+        }
+    };
+
+    // Ask to start Claude with instructions to read the context files (defaulting to Yes),
+    // unless its session would write into the context on stdout
+    let launcher = find_on_path(CLAUDE_LAUNCHER);
+    let can_launch = !args.non_interactive && !args.output.contains(&OutputTarget::Stdout);
+    match &launcher {
+        Some(launcher) if !all_context_files.is_empty() && can_launch => {
+            let response = prompt("\nStart Claude with context files? [Y/n]: ")?;
+            if response.to_lowercase() == "n" {
+                show_parts(&parts, temporary_parts);
+                show_manual("Start Claude manually with:");
+                return Ok(());
+            }
+
+            info!("Starting Claude with all context files...");
+            match launch_command(launcher, &message).status() {
+                Ok(status) if status.success() => info!("Claude session completed"),
+                Ok(status) => error!("Claude exited with status: {}", status),
+                Err(e) => {
+                    error!("Error starting Claude ({}): {}", launcher.display(), e);
+                    show_parts(&parts, temporary_parts);
+                    show_manual("You can try starting manually with:");
+                }
+            }
+        }
+        Some(_) => {
+            show_parts(&parts, temporary_parts);
+            show_manual("Start Claude manually with:");
+        }
+        None => {
+            show_parts(&parts, temporary_parts);
+            if !temporary_parts {
+                print_warning("\nClaude CLI not found. You can view the context files directly.");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// List the parts written; temporary ones are removed when the run ends,
+/// so there is only a note on keeping them
+fn show_parts(parts: &[PathBuf], temporary: bool) {
+    if temporary {
+        print_warning(
+            "\nThe parts were written to a temporary directory that is removed on exit; \
+             pass --output-dir or --keep-temp to keep them.",
+        );
+        return;
+    }
+    print_title("Context files are available at:");
+    for part in parts {
+        say(format!("  {}", part.display()));
+    }
+}
+
+/// Parts of an earlier run being appended to, then this run's
+fn all_parts(previous_parts: &[PathBuf], context_files: &[ContextFile]) -> Vec<PathBuf> {
+    previous_parts
+        .iter()
+        .cloned()
+        .chain(context_files.iter().map(|f| f.path.clone()))
+        .collect()
+}
+
+/// Built-in excludes, the ecosystem preset's patterns and any extra patterns
+fn build_excludes(
+    start_dir: &Path,
+    preset: ExcludePreset,
+    config: &Config,
+    extra: Vec<String>,
+) -> Vec<String> {
+    let mut excludes = vec![
+        ".git".to_string(),
+        "node_modules".to_string(),
+        "__pycache__".to_string(),
+        ".env".to_string(),
+        "venv".to_string(),
+        "target".to_string(),
+        ".claude-context".to_string(),
+        ".claude-summaries".to_string(),
+        ".claude_include".to_string(),
+        ".claude-manifest".to_string(),
+        ".claude-token-counts".to_string(),
+        ".claude-snapshots".to_string(),
+        ".claude-analysis".to_string(),
+        ".claude-ledger.jsonl".to_string(),
+        ".claude-lock".to_string(),
+        ".claude".to_string(),
+    ];
+    // Other version control metadata, wherever it is
+    for pattern in vcs_excludes() {
+        if !excludes.contains(&pattern) {
+            excludes.push(pattern);
+        }
+    }
+    if preset == ExcludePreset::Auto {
+        let ecosystems = detect_ecosystems(start_dir);
+        if !ecosystems.is_empty() {
+            let names: Vec<&str> = ecosystems.iter().map(|e| e.name()).collect();
+            info!("Detected project types: {}", names.join(", "));
+        }
+        for pattern in ecosystem_excludes(&ecosystems) {
+            if !excludes.contains(&pattern) {
+                excludes.push(pattern);
+            }
+        }
+    }
+    for pattern in &config.exclude {
+        if !excludes.contains(pattern) {
+            excludes.push(pattern.clone());
+        }
+    }
+    excludes.extend(extra);
+    excludes
+}
+
+/// Point `latest` at a finished session and drop sessions beyond the `keep`
+/// previous ones
+fn finish_session(root: &Path, session: &Path, keep: Option<usize>) -> Result<()> {
+    set_latest_session(root, session)?;
+    if let Some(keep) = keep {
+        prune_sessions(root, keep + 1)?;
+    }
+    Ok(())
+}
+
+/// The user config with the project's and the environment's settings over it
+fn project_config(config: Config, start_dir: &Path) -> Result<Config> {
+    let config = match load_project_config(start_dir)? {
+        Some(project) => config.merge(project),
+        None => config,
+    };
+    config.with_env()
+}
+
+/// Set up the prompt and provider summaries are generated with; commands
+/// that may render a summary call this before rendering
+fn configure_summaries(config: &SummarizerConfig) {
+    if let Some(prompt) = &config.prompt {
+        set_summary_prompt(prompt);
+    }
+    configure_summarizer(config);
+}
+
+/// Patch changed file blocks in existing context files
+fn run_update(context_dir: &Path, config: Config) -> Result<()> {
+    info!("Updating context files in: {}", context_dir.display());
+    // Summaries of changed files use the settings of the project the parts came from
+    let project = context_part_paths(context_dir)?
+        .first()
+        .and_then(|part| fs::read_to_string(part).ok())
+        .and_then(|content| source_dir(&content));
+    let config = match project {
+        Some(start_dir) => project_config(config, &start_dir)?,
+        None => config.with_env()?,
+    };
+    configure_summaries(&config.summarizer);
+    let results = update_context_dir(context_dir)?;
+    write_index(context_dir, &context_part_paths(context_dir)?)?;
+
+    print_title("Update summary:");
+    for (file, count) in &results {
+        print_field(
+            &file.file_name().unwrap_or_default().to_string_lossy(),
+            format!("{} blocks updated", count),
+        );
+    }
+    let total: usize = results.iter().map(|(_, count)| count).sum();
+    print_field("Total", format!("{} blocks updated", total));
+
+    Ok(())
+}
+
+/// Serve the files listed in a context directory's index
+fn run_serve(context_dir: &Path, port: u16) -> Result<()> {
+    let index_path = context_dir.join(INDEX_FILE_NAME);
+    let index = if index_path.is_file() {
+        load_index(&index_path)?
+    } else {
+        build_index(&context_part_paths(context_dir)?)?
+    };
+    serve(&index, std::env::current_dir()?, port)
+}
+
+/// Analyze a project once and answer build requests until interrupted
+fn run_daemon(
+    start_dir: &str,
+    config: Config,
+    exclude: Vec<String>,
+    preset: ExcludePreset,
+    port: u16,
+    poll: u64,
+) -> Result<()> {
+    let start_dir = PathBuf::from(start_dir)
+        .canonicalize()
+        .context("Failed to resolve start directory")?;
+    let config = project_config(config, &start_dir)?;
+    configure_summaries(&config.summarizer);
+    let excludes = build_excludes(&start_dir, preset, &config, exclude);
+    let marker = config.opt_out_marker.clone().unwrap_or_else(|| DEFAULT_OPT_OUT_MARKER.to_string());
+
+    let listener = bind(port)?;
+    let daemon = Arc::new(Daemon::new(start_dir.clone(), excludes, marker)?);
+    daemon.watch(Duration::from_secs(poll.max(1)));
+
+    println!("Watching {} on http://127.0.0.1:{}/", start_dir.display(), port);
+    println!("  GET /status                                   files and tokens analyzed");
+    println!("  GET /build?paths=A,B[&action=ACTION]          build parts, answering with a run summary");
+    println!("  GET /build?manifest=PATH[&out=DIR&max_tokens=N]");
+    listen(listener, |target| daemon.respond(target))
+}
+
+/// Remove generated context files, keeping the newest archived runs
+fn run_clean(context_dir: &Path, keep: usize) -> Result<()> {
+    let (files, runs) = clean_context_dir(context_dir, keep)?;
+
+    print_title("Clean summary:");
+    print_field("Files deleted", files);
+    print_field("Archives deleted", runs);
+
+    Ok(())
+}
+
+/// Time a run over a directory and print where the time went
+fn run_profile(
+    start_dir: &str,
+    config: Config,
+    exclude: Vec<String>,
+    preset: ExcludePreset,
+    top: usize,
+    summarize: bool,
+) -> Result<()> {
+    let start_dir = PathBuf::from(start_dir)
+        .canonicalize()
+        .context("Failed to resolve start directory")?;
+    let config = project_config(config, &start_dir)?;
+    if summarize {
+        configure_summaries(&config.summarizer);
+    }
+    let excludes = build_excludes(&start_dir, preset, &config, exclude);
+
+    info!("Profiling: {}", start_dir.display());
+    let report = profile_directory(&start_dir, &excludes, summarize)?;
+    print_profile(&report, &start_dir, top);
+
+    Ok(())
+}
+
+/// Offer text files largest first so the biggest consumers can be decided up
+/// front. Chosen files move to the front of `priority` with their action as
+/// the suggestion, leaving the rest to auto-selection. Enter leaves a file to
+/// auto-selection and `q` stops listing.
+fn select_largest_first(
+    start_dir: &Path,
+    dir_info: &DirectoryMap,
+    suggestions: &mut SuggestionMap,
+    priority: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let files = largest_files(dir_info);
+    let mut chosen = Vec::new();
+
+    print_title("Largest files first:");
+    for (idx, (path, tokens)) in files.iter().enumerate() {
+        let rel_path = path.strip_prefix(start_dir).unwrap_or(path);
+        let suggested = get_suggestion_for_path(path, suggestions)
+            .map(|s| format!(" (suggested: {})", s.action))
+            .unwrap_or_default();
+        say(format!(
+            "\n{:>4}. {} {}{}",
+            idx + 1,
+            rel_path.display(),
+            paint(format!("~{} tokens", tokens), Style::Number),
+            paint(suggested, Style::Muted)
+        ));
+
+        let action = loop {
+            let choice = prompt(
+                "Action [1 read, 2 exclude, 4 summarize, 5 stats, 6 sample, Enter to leave, q to finish]: ",
+            )?;
+            if choice.is_empty() || choice == "q" {
+                break choice;
+            }
+            match action_for_choice(&choice) {
+                Some(action) => break action.to_string(),
+                None => print_warning("Invalid choice."),
+            }
+        };
+        if action == "q" {
+            break;
+        }
+        if action.is_empty() {
+            continue;
+        }
+
+        suggestions.insert(
+            path.clone(),
+            Suggestion {
+                action,
+                reason: "chosen in largest-first view".to_string(),
+            },
+        );
+        chosen.push(path.clone());
     }
 
-    // Finalize all context files
-    // This is also done in the interactive loop to handle ctrl+c, but we do it again here to make sure
+    info!("Chose actions for {} files; auto-selecting the rest", chosen.len());
+    priority.retain(|p| !chosen.contains(p));
+    chosen.append(priority);
+    *priority = chosen;
+    Ok(())
+}
 
-    // Check if claude command is available
-    let claude_available = Command::new("which")
-        .arg("claude")
-        .status()
-        .map(|status| status.success())
-        .unwrap_or(false);
+/// Analyze a directory and print or write its annotated tree
+fn run_tree(
+    start_dir: &str,
+    config: &Config,
+    exclude: Vec<String>,
+    preset: ExcludePreset,
+    tokens: bool,
+    output: Option<&Path>,
+) -> Result<()> {
+    let start_dir = PathBuf::from(start_dir)
+        .canonicalize()
+        .context("Failed to resolve start directory")?;
+    let excludes = build_excludes(&start_dir, preset, config, exclude);
+
+    let mut dir_info = analyze_directory(&start_dir, &excludes)?;
+    remove_opted_out(
+        &mut dir_info,
+        config.opt_out_marker.as_deref().unwrap_or(DEFAULT_OPT_OUT_MARKER),
+    );
+    let cache = load_cache(&start_dir)?;
+    let summaries = load_summary_cache(&start_dir)?;
+    let tree = render_tree(&start_dir, &dir_info, &cache, &summaries, tokens);
+
+    match output {
+        Some(path) => {
+            fs::write(path, &tree).context("Failed to write tree")?;
+            info!("Tree written to {}", path.display());
+        }
+        None => print!("{}", tree),
+    }
+    Ok(())
+}
 
-    // Ask to start Claude with instructions to read the context files (defaulting to Yes)
-    if claude_available && !all_context_files.is_empty() {
-        print!("\nStart Claude with context files? [Y/n]: ");
-        io::stdout().flush()?;
+/// Write the dependencies digest of a crate
+fn run_deps_docs(start_dir: &str, config: Config, exclude: Vec<String>, output: Option<&Path>) -> Result<()> {
+    let start_dir = PathBuf::from(start_dir)
+        .canonicalize()
+        .context("Failed to resolve start directory")?;
+    let config = project_config(config, &start_dir)?;
+    configure_summaries(&config.summarizer);
+    let excludes = build_excludes(&start_dir, ExcludePreset::Auto, &config, exclude);
 
-        let mut response = String::new();
-        io::stdin().read_line(&mut response)?;
+    let dependencies = direct_dependencies(&start_dir)?;
+    let dir_info = analyze_directory(&start_dir, &excludes)?;
+    let uses = dependency_uses(&dependencies, &dir_info);
+    let digest = render_digest(&dependencies, &uses)?;
 
-        if response.trim().to_lowercase() != "n" {
-            // Create a list of all context file paths
-            let context_files_paths: Vec<_> = all_context_files
-                .iter()
-                .map(|f| f.path.display().to_string())
-                .collect();
+    match output {
+        Some(path) => {
+            fs::write(path, &digest).context("Failed to write dependencies digest")?;
+            info!("Dependencies digest written to {}", path.display());
+        }
+        None => print!("{}", digest),
+    }
+    Ok(())
+}
 
-            info!("Starting Claude with all context files...");
+/// Export or import the summary cache of a project
+fn run_cache(command: CacheCommands, config: &Config) -> Result<()> {
+    let (file, dir, import) = match command {
+        CacheCommands::Export { file, dir } => (file, dir, false),
+        CacheCommands::Import { file, dir } => (file, dir, true),
+        CacheCommands::Keygen => {
+            println!("{}", generate_key());
+            return Ok(());
+        }
+        CacheCommands::Stats { dir } => {
+            let start_dir = PathBuf::from(dir)
+                .canonicalize()
+                .context("Failed to resolve project directory")?;
+            print_summary_cache_stats(&load_summary_cache(&start_dir)?);
+            return Ok(());
+        }
+    };
+    let start_dir = PathBuf::from(dir)
+        .canonicalize()
+        .context("Failed to resolve project directory")?;
+    let excludes = build_excludes(&start_dir, ExcludePreset::Auto, config, Vec::new());
+    let dir_info = analyze_directory(&start_dir, &excludes)?;
+    let files: Vec<PathBuf> = dir_info
+        .values()
+        .flat_map(|info| info.files.iter())
+        .filter(|f| !f.binary)
+        .map(|f| f.path.clone())
+        .collect();
+    let mut summaries = load_summary_cache(&start_dir)?;
+
+    if import {
+        let export = read_summary_export(&file)?;
+        let imported = summaries.import(&export, &files);
+        save_summary_cache(&start_dir, &summaries)?;
+        println!(
+            "Imported {} of {} summaries from {}",
+            imported,
+            export.summaries.len(),
+            file.display()
+        );
+    } else {
+        let export = summaries.export(&files, &start_dir);
+        write_summary_export(&file, &export)?;
+        println!("Exported {} summaries to {}", export.summaries.len(), file.display());
+    }
+    Ok(())
+}
 
-            // Start Claude with instructions to read all context files
-            let message = format!(
-                "The context files are at {}. Read the first each file in its entirety, then say 'Ready'.",
-                context_files_paths.join(", ")
-            );
+/// Generate summaries of the project's text files matching the filters into
+/// its summary cache, skipping current ones unless regenerating
+fn run_summarize_all(
+    start_dir: &Path,
+    config: Config,
+    filters: &[String],
+    exclude: Vec<String>,
+    regenerate: bool,
+    jobs: usize,
+    opt_out_marker: Option<String>,
+) -> Result<()> {
+    let config = project_config(config, start_dir)?;
+    configure_summaries(&config.summarizer);
+    let Some(provider) = summarizer() else {
+        bail!("No summarizer available; {}, or choose another backend", summarizer_requirement());
+    };
+    let matchers = filters
+        .iter()
+        .map(|filter| rule_matcher(filter))
+        .collect::<Result<Vec<_>>>()?;
+
+    let excludes = build_excludes(start_dir, ExcludePreset::Auto, &config, exclude);
+    let mut dir_info = analyze_directory(start_dir, &excludes)?;
+    let marker = opt_out_marker
+        .or_else(|| config.opt_out_marker.clone())
+        .unwrap_or_else(|| DEFAULT_OPT_OUT_MARKER.to_string());
+    let mut summaries = load_summary_cache(start_dir)?;
+    let mut files = summary_targets(start_dir, &mut dir_info, &matchers, &marker);
+    let matched = files.len();
+    if !regenerate {
+        files.retain(|path| {
+            fs::read_to_string(path)
+                .map_or(true, |content| summaries.get_summary(path, &hash_file_content(path, &content)).is_none())
+        });
+    }
 
-            match Command::new("claude")
-                .args(["-d", "--verbose", &message])
-                .status()
-            {
-                Ok(status) => {
-                    if status.success() {
-                        info!("Claude session completed");
-                    } else {
-                        error!("Claude exited with status: {}", status);
-                    }
+    println!(
+        "Summarizing {} of {} matching files with {}",
+        files.len(),
+        matched,
+        provider.model()
+    );
+    let (mut generated, mut failed) = (0, 0);
+    summarize_files(&files, jobs, |path, result| {
+        let shown = path.strip_prefix(start_dir).unwrap_or(path);
+        match result {
+            Ok(info) => {
+                summaries.insert_summary(&info.path, &info.content_hash, info.summary);
+                generated += 1;
+                println!("[{}/{}] {}", generated + failed, files.len(), shown.display());
+            }
+            Err(e) => {
+                failed += 1;
+                warn!("Failed to summarize {}: {:#}", shown.display(), e);
+            }
+        }
+    });
+    // Save what was generated even when some files failed
+    save_summary_cache(start_dir, &summaries)?;
+    flush_ledger(start_dir)?;
+
+    print_title("Summarize-all summary:");
+    print_field("Generated", generated);
+    print_field("Already current", matched - files.len());
+    print_field("Failed", failed);
+    if failed > 0 {
+        bail!("{} of {} summaries failed", failed, files.len());
+    }
+    Ok(())
+}
+
+/// Print a cached summary, or the cached summaries matching a term
+fn run_summaries(command: SummariesCommands) -> Result<()> {
+    let (SummariesCommands::Show { dir, .. } | SummariesCommands::Search { dir, .. }) = &command;
+    let start_dir = PathBuf::from(dir)
+        .canonicalize()
+        .context("Failed to resolve project directory")?;
+    let summaries = load_summary_cache(&start_dir)?;
+
+    match command {
+        SummariesCommands::Show { path, .. } => {
+            let Some(entry) = summaries.entry(&path) else {
+                bail!("No cached summary for {}", path.display());
+            };
+            println!("{}", entry.summary.trim_end());
+            let current = fs::read_to_string(&path)
+                .is_ok_and(|content| hash_file_content(&path, &content) == entry.content_hash);
+            if !current {
+                println!("(Stale: {} changed since it was summarized)", path.display());
+            }
+        }
+        SummariesCommands::Search { term, .. } => {
+            let found = summaries.search(&term, &start_dir);
+            if found.is_empty() {
+                println!("No cached summaries match '{}'", term);
+            }
+            let lowered = term.to_lowercase();
+            for (path, entry) in found {
+                let shown = path.strip_prefix(&start_dir).unwrap_or(&path);
+                let mut lines: Vec<&str> = entry
+                    .summary
+                    .lines()
+                    .filter(|line| line.to_lowercase().contains(&lowered))
+                    .collect();
+                // A match on the path alone shows the summary's first line
+                if lines.is_empty() {
+                    lines.extend(entry.summary.lines().find(|line| !line.trim().is_empty()));
                 }
-                Err(e) => {
-                    error!("Error starting Claude: {}", e);
-                    println!("\nContext files are available at:");
-                    for file in &all_context_files {
-                        println!("  {}", file.path.display());
-                    }
-                    println!(
-                        "\nYou can try starting manually with: claude -d --verbose \"The context files are at {}. Read the first file in its entirety, then say 'Ready'.\"",
-                        context_files_paths.join(", ")
-                    );
+                for line in lines {
+                    println!("{}: {}", shown.display(), line.trim());
                 }
             }
+        }
+    }
+    Ok(())
+}
+
+/// Pack the parts of a context directory, its index and a manifest into a bundle
+fn run_pack(context_dir: &Path, manifest: Option<PathBuf>, output: Option<PathBuf>) -> Result<()> {
+    let parts = context_part_paths(context_dir)?;
+    let source = match parts.first() {
+        Some(part) => source_dir(&fs::read_to_string(part)?),
+        None => None,
+    };
+    let manifest = manifest.or_else(|| {
+        source
+            .as_deref()
+            .map(default_manifest_path)
+            .filter(|path| path.exists())
+    });
+    let out = output.unwrap_or_else(|| {
+        let project = source
+            .as_deref()
+            .and_then(|dir| dir.file_name())
+            .map_or_else(|| "context".to_string(), |n| n.to_string_lossy().to_string());
+        PathBuf::from(format!("{}{}", project, BUNDLE_EXTENSION))
+    });
+
+    let listing = pack(&bundle_contents(context_dir, manifest.as_deref())?, &out)?;
+    println!("Packed {} files into {}", listing.files.len(), out.display());
+    Ok(())
+}
+
+/// Print what a bundle holds
+fn print_bundle_listing(bundle: &Path, listing: &BundleListing) {
+    print_title(&format!("Bundle {} (integrity verified):", bundle.display()));
+    print_field("Created", &listing.created);
+    if let Some(dir) = &listing.source_dir {
+        print_field("Source", dir.display());
+    }
+    for file in &listing.files {
+        print_field(&file.name, format!("{} bytes, sha1 {}", file.size, file.sha1));
+    }
+}
+
+/// Print the summarizer calls in a project's ledger grouped by day, run or model
+fn run_cost_report(dir: &str, by: CostGrouping) -> Result<()> {
+    let start_dir = PathBuf::from(dir)
+        .canonicalize()
+        .context("Failed to resolve project directory")?;
+    let entries = load_ledger(&start_dir)?;
+    if entries.is_empty() {
+        println!("No summarizer calls recorded in {}", start_dir.join(LEDGER_FILE_NAME).display());
+        return Ok(());
+    }
+
+    let report = cost_report(&entries, by);
+    print_title("Summarizer cost:");
+    let mut total = 0.0;
+    for (key, totals) in &report {
+        let unpriced = if totals.unpriced_calls > 0 {
+            format!(" ({} unpriced)", totals.unpriced_calls)
         } else {
-            // Create a list of all context file paths
-            let context_files_paths: Vec<_> = all_context_files
-                .iter()
-                .map(|f| f.path.display().to_string())
-                .collect();
-
-            println!("\nContext files are available at:");
-            for file in &all_context_files {
-                println!("  {}", file.path.display());
-            }
-            println!(
-                "\nStart Claude manually with: claude -d --verbose \"The context files are at {}. Read the each file in its entirety, then say 'Ready'.\"",
-                context_files_paths.join(", ")
-            );
+            String::new()
+        };
+        print_field(
+            key,
+            format!(
+                "{} calls, {} in / {} out tokens, ${:.4}{}",
+                totals.calls, totals.input_tokens, totals.output_tokens, totals.cost_usd, unpriced
+            ),
+        );
+        total += totals.cost_usd;
+    }
+    print_field("Total", format!("{} calls, ${:.4}", entries.len(), total));
+    Ok(())
+}
+
+/// Print the size and cumulative hit rate of a summary cache
+fn print_summary_cache_stats(summaries: &SummaryCache) {
+    print_title("Summary cache:");
+    print_field("Summaries", summaries.summary_count());
+    print_field("Never used", summaries.unused());
+    let totals = summaries.total_stats();
+    print_field("Lookups", totals.lookups());
+    print_field("Hits", totals.hits);
+    print_field("Misses", totals.misses);
+    if let Some(rate) = totals.hit_rate() {
+        print_field("Hit rate", format!("{}%", rate));
+    }
+}
+
+/// Inline text, or the content of a file
+fn text_or_file(text: &Option<String>, file: &Option<PathBuf>) -> Result<Option<String>> {
+    match (text, file) {
+        (Some(text), _) => Ok(Some(text.clone())),
+        (None, Some(path)) => fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))
+            .map(Some),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Show how the files behind each cached read changed since they were read,
+/// and drop the cached reads the user no longer wants applied as they are
+fn confirm_changed_reads(
+    cache: &mut CacheMap,
+    dir_info: &DirectoryMap,
+    start_dir: &Path,
+    timeout: Option<&PromptTimeout>,
+) -> Result<()> {
+    let snapshots = load_snapshots(start_dir)?;
+    for (path, files) in changed_reads(cache, &snapshots, dir_info) {
+        print_header("CHANGED SINCE LAST READ", &path, '-');
+        for (file, change) in &files {
+            let shown = file.strip_prefix(start_dir).unwrap_or(file);
+            print_field(&shown.display().to_string(), change.describe());
         }
-    } else {
-        // Create a list of all context file paths
-        let context_files_paths: Vec<_> = all_context_files
+        // An unanswered question keeps the read
+        let response = prompt_timeout("Keep cached action 'read'? [Y/n]: ", timeout.map(|t| t.after))?
+            .unwrap_or_default();
+        if response.to_lowercase() == "n" {
+            cache.remove(&path);
+        }
+    }
+    Ok(())
+}
+
+/// Capitalize the first letter of a word
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Print the totals for a run and list the context files created
+fn print_run_summary(
+    processed: usize,
+    included_files: usize,
+    total_tokens: usize,
+    parts: &[PathBuf],
+    summary_lookups: CacheStats,
+) {
+    print_title("Run summary:");
+    print_field("Processed nodes", processed);
+    print_field("Files included", included_files);
+    print_field("Estimated tokens", total_tokens);
+    if let Some(rate) = summary_lookups.hit_rate() {
+        print_field(
+            "Cached summaries",
+            format!(
+                "{} hits, {} misses ({}% hit rate)",
+                summary_lookups.hits, summary_lookups.misses, rate
+            ),
+        );
+    }
+    say(format!(
+        "Created {} context files (limited to ~{} tokens each):",
+        paint(parts.len(), Style::Number),
+        paint(CLAUDE_TOKEN_LIMIT, Style::Number)
+    ));
+
+    let mut totals: BTreeMap<&str, usize> = BTreeMap::new();
+    for (i, part) in parts.iter().enumerate() {
+        say(format!("  {}. {}", i + 1, part.display()));
+
+        // Where each part's tokens went
+        let Ok(content) = fs::read_to_string(part) else {
+            continue;
+        };
+        let breakdown = action_breakdown(&content);
+        let line: Vec<String> = breakdown
             .iter()
-            .map(|f| f.path.display().to_string())
+            .map(|(action, tokens)| format!("{} ~{}", action, tokens))
             .collect();
-
-        println!("\nContext files are available at:");
-        for file in &all_context_files {
-            println!("  {}", file.path.display());
+        say(format!("     {}", paint(line.join(", "), Style::Muted)));
+        for (action, tokens) in breakdown {
+            *totals.entry(action).or_default() += tokens;
         }
+    }
 
-        if claude_available {
-            println!(
-                "\nStart Claude manually with: claude -d --verbose \"The context files are at {}. Read the first file in its entirety, then say 'Ready'.\"",
-                context_files_paths.join(", ")
-            );
-        } else {
-            println!("\nClaude CLI not found. You can view the context files directly.");
+    if !totals.is_empty() {
+        print_title("Tokens by action:");
+        let all: usize = totals.values().sum();
+        for (action, tokens) in &totals {
+            let percent = (tokens * 100).checked_div(all).unwrap_or(0);
+            print_field(&capitalize(action), format!("~{} ({}%)", tokens, percent));
         }
     }
+}
+
+/// Queue the unprocessed children of a directory. The queue pops from the
+/// end, so the most recently changed children are pushed last; without
+/// recency data subdirectories keep coming before files.
+fn push_children(
+    to_process: &mut Vec<PathBuf>,
+    dir: &Path,
+    dir_info: &DirectoryMap,
+    processed: &HashSet<PathBuf>,
+    recency: &RecencyMap,
+) {
+    let Some(info) = dir_info.get(dir) else {
+        return;
+    };
+
+    // Children in the order they would be popped without recency data
+    let mut children: Vec<PathBuf> = info
+        .files
+        .iter()
+        .map(|f| &f.path)
+        .chain(info.subdirs.iter())
+        .filter(|p| !processed.contains(*p))
+        .rev()
+        .cloned()
+        .collect();
+    sort_by_recency(&mut children, recency);
+    to_process.extend(children.into_iter().rev());
+}
+
+/// Add the header for an entered directory, starting a new part when it
+/// wouldn't fit
+fn write_directory_header(
+    dir: &Path,
+    start_dir: &Path,
+    context_file: &mut ContextFile,
+    total_files: usize,
+    output: &OutputSettings,
+    all_context_files: &mut Vec<ContextFile>,
+) -> Result<()> {
+    if context_file.current_tokens + 200 > CLAUDE_TOKEN_LIMIT {
+        // Rough estimate
+        *context_file = get_or_rotate_file(context_file, total_files, start_dir, output)?;
+        all_context_files.push(context_file.clone());
+    }
 
+    let rel_path = shown_path(dir, start_dir, output.absolute_paths);
+    let content = output.format.formatter().directory(&rel_path, "");
+    append_to_file(&context_file.path, &content)?;
+    context_file.current_tokens += 200; // Rough estimate
     Ok(())
 }
 
 #[allow(clippy::too_many_arguments)]
 fn process_interactive_loop(
     start_dir: PathBuf,
-    dir_info: HashMap<PathBuf, file_analysis::DirInfo>,
+    dir_info: &DirectoryMap,
     context_file: &mut ContextFile,
     max_tokens: usize,
     cache: &mut HashMap<PathBuf, String>,
     summary_cache: &mut SummaryCache,
+    suggestions: &SuggestionMap,
+    priority: &[PathBuf],
+    recency: &RecencyMap,
+    plan: &mut BudgetPlan,
+    ladder: &Ladder,
+    timeout: Option<&PromptTimeout>,
+    layout: Layout,
+    auto_read_below: Option<usize>,
+    confirm_reads_over: Option<usize>,
+    review: bool,
+    manifest: &mut Manifest,
     use_cache: bool,
     initial_tokens: usize,
     initial_processed: HashSet<PathBuf>,
     initial_included_files: HashSet<PathBuf>,
     total_files: usize,
-    output_dir: Option<&Path>,
+    previous_parts: &[PathBuf],
+    output: &OutputSettings,
     all_context_files: &mut Vec<ContextFile>,
-) -> Result<()> {
-    // Interactive processing setup
+) -> Result<RunSummary> {
+    // Interactive processing setup; the stack pops from the end, so priority
+    // files are pushed in reverse to be offered first
     let mut to_process = vec![start_dir.clone()];
+    to_process.extend(priority.iter().rev().cloned());
     let mut processed = initial_processed;
     let mut included_files = initial_included_files;
     let mut total_tokens = initial_tokens;
+    // Nodes whose default action didn't fit the budget; they are asked again
+    // without offering that default
+    let mut over_budget: HashSet<PathBuf> = HashSet::new();
+    // Directories below a depth-limited enter, with the levels still entered
+    // automatically; at 0 they can only be decided as a whole
+    let mut enter_depths: HashMap<PathBuf, usize> = HashMap::new();
 
     // Interactive processing loop
     let result: Result<()> = (|| {
@@ -396,159 +2450,279 @@ fn process_interactive_loop(
 
             // For directories with cached "enter" action, apply it automatically without prompting
             if !is_file && use_cache && cached_action.as_deref() == Some("enter") {
-                println!(
+                say(format!(
                     "\nAutomatically entering directory (from cache): {}",
                     current.display()
-                );
+                ));
 
-                // Add a header for the directory - check token limit first
-                if context_file.current_tokens + 200 > CLAUDE_TOKEN_LIMIT {
-                    // Rough estimate
-                    *context_file =
-                        get_or_rotate_file(context_file, total_files, &start_dir, output_dir)?;
-                    all_context_files.push(context_file.clone());
-                }
-
-                let rel_path = current.strip_prefix(&start_dir).unwrap_or(&current);
-                let content = format!("\n\n## DIRECTORY: {}\n", rel_path.display());
-                append_to_file(&context_file.path, &content)?;
-                context_file.current_tokens += 200; // Rough estimate
+                write_directory_header(
+                    &current,
+                    &start_dir,
+                    context_file,
+                    total_files,
+                    output,
+                    all_context_files,
+                )?;
 
                 // Mark directory as processed but add all its child nodes to the queue
                 processed.insert(current.clone());
+                manifest.record(&current, &start_dir, "enter");
 
-                // Add all files in this directory to the processing queue
-                if let Some(dir_info) = dir_info.get(&current) {
-                    for file in &dir_info.files {
-                        if !processed.contains(&file.path) {
-                            to_process.push(file.path.clone());
-                        }
-                    }
+                // Add all child nodes of this directory to the processing queue
+                push_children(&mut to_process, &current, dir_info, &processed, recency);
 
-                    // Add all subdirectories to the processing queue
-                    for subdir in &dir_info.subdirs {
-                        if !processed.contains(subdir) {
-                            to_process.push(subdir.clone());
-                        }
-                    }
-                }
+                continue;
+            }
 
+            // Keep entering below a depth-limited enter until the limit is reached
+            if let Some(&depth) = enter_depths.get(&current)
+                && depth > 0
+            {
+                say(format!(
+                    "\nAutomatically entering directory ({} more levels): {}",
+                    depth,
+                    current.display()
+                ));
+                write_directory_header(
+                    &current,
+                    &start_dir,
+                    context_file,
+                    total_files,
+                    output,
+                    all_context_files,
+                )?;
+                processed.insert(current.clone());
+                cache.insert(current.clone(), "enter".to_string());
+                manifest.record(&current, &start_dir, "enter");
+                push_children(&mut to_process, &current, dir_info, &processed, recency);
+                for sub in &dir_info[&current].subdirs {
+                    enter_depths.insert(sub.clone(), depth - 1);
+                }
                 continue;
             }
+            // Files, and directories past an enter's depth limit, can't be entered
+            let leaf = is_file || enter_depths.get(&current) == Some(&0);
 
             // For other directories, check if we need to prompt or can use cached actions
-            if !is_file && use_cache && !should_prompt_for_directory(&current, &dir_info, cache) {
-                println!(
+            if !is_file && use_cache && !should_prompt_for_directory(&current, dir_info, cache) {
+                say(format!(
                     "\nUsing cached actions for directory: {}",
                     current.display()
-                );
+                ));
                 processed.insert(current.clone()); // Mark as processed and skip prompting
+
+                // Directories entered last time get their headers, as they
+                // would have when reached
+                if cached_action.is_none() {
+                    for dir in cached_enters(&current, dir_info, cache) {
+                        write_directory_header(
+                            &dir,
+                            &start_dir,
+                            context_file,
+                            total_files,
+                            output,
+                            all_context_files,
+                        )?;
+                        processed.insert(dir.clone());
+                        manifest.record(&dir, &start_dir, "enter");
+                    }
+                }
                 continue;
             }
 
+            // Small directories are read whole without asking, unless something
+            // else is cached or suggested for them
+            let auto_read = !is_file
+                && cached_action.is_none()
+                && get_suggestion_for_path(&current, suggestions).is_none()
+                && !over_budget.contains(&current)
+                && auto_read_below
+                    .is_some_and(|limit| tree_totals(dir_info, &current).tokens < limit);
+
             // Show information about the current node
-            if is_file {
+            if auto_read {
+                say(format!(
+                    "\nReading small directory (~{} tokens): {}",
+                    tree_totals(dir_info, &current).tokens,
+                    current.display()
+                ));
+            } else if is_file {
                 if is_binary(&current)? {
-                    println!("\n{}", "-".repeat(60));
-                    println!("BINARY FILE: {}", current.display());
-                    println!("{}", "-".repeat(60));
-                    println!("Binary files are not processed.");
+                    print_header("BINARY FILE", &current, '-');
+                    say(paint("Binary files are not processed.", Style::Muted));
                     processed.insert(current.clone());
                     continue;
                 }
 
                 let metadata = std::fs::metadata(&current)?;
                 let size = metadata.len();
-                let tokens = (size as f64 * file_analysis::TOKENS_PER_BYTE).ceil() as usize;
                 let ext = current
                     .extension()
                     .and_then(|e| e.to_str())
                     .unwrap_or("")
                     .to_lowercase();
+                let tokens = file_analysis::file_tokens(dir_info, &current)
+                    .unwrap_or_else(|| file_analysis::text_file_tokens(&current, size));
 
-                println!("\n{}", "-".repeat(60));
-                println!("FILE: {}", current.display());
-                println!("{}", "-".repeat(60));
-                println!("Size: {} bytes", size);
-                println!("Estimated tokens: {}", tokens);
-                println!("Extension: .{}", ext);
-            } else if let Some(info) = dir_info.get(&current) {
-                show_dir_info(&current, info);
+                print_header("FILE", &current, '-');
+                print_field("Size", format!("{} bytes", size));
+                print_field("Estimated tokens", tokens);
+                print_field("Extension", format!(".{}", ext));
+            } else {
+                show_dir_info(&current, dir_info);
             }
 
             // Display appropriate options based on node type
-            println!("\nOptions:");
-            println!("  1. Read (include full content)");
-            println!("  2. Exclude (skip this node)");
-            if !is_file {
-                println!("  3. Enter (process each child node separately)");
-            }
-            println!("  4. Summarize (create summary)");
-            println!("  5. Stats only (just include statistics)");
-            println!("  q. Quit");
-
-            // Show cached action if it exists
-            let choice = if let Some(cached_action) = cached_action {
-                println!("\nCached action: {}", cached_action);
-                print!("Use cached action '{}'? [Y/n]: ", cached_action);
-                io::stdout().flush()?;
-
-                let mut response = String::new();
-                io::stdin().read_line(&mut response)?;
-                let auto_apply = response.trim().to_lowercase() != "n";
-
-                if auto_apply {
+            if !auto_read {
+                print_options(leaf);
+            }
+
+            // Fall back to a suggested default (e.g. from .gitattributes) if nothing is cached
+            let default_action = match cached_action {
+                _ if over_budget.contains(&current) => None,
+                Some(action) => Some((action, "cached", String::new())),
+                None => get_suggestion_for_path(&current, suggestions)
+                    .map(|s| (s.action.clone(), "suggested", format!(" ({})", s.reason))),
+            };
+
+            // Use the cached or suggested action if accepted, otherwise ask for a choice
+            let mut choice = auto_read.then(|| "1".to_string());
+            if let Some((cached_action, source, reason)) = default_action.filter(|_| !auto_read) {
+                say(format!(
+                    "\n{} action: {}{}",
+                    capitalize(source),
+                    paint(&cached_action, Style::Header),
+                    paint(reason, Style::Muted)
+                ));
+                // An unanswered question takes its default, yes
+                let response = prompt_timeout(
+                    &format!("Use {} action '{}'? [Y/n]: ", source, cached_action),
+                    timeout.map(|t| t.after),
+                )?
+                .unwrap_or_default();
+
+                if response.to_lowercase() != "n" {
                     // For files, don't allow 'enter' choice
                     if is_file && cached_action == "enter" {
-                        println!("Invalid cached action: Files don't have child nodes to enter.");
-                        print!("\nEnter choice [1-5, q]: ");
-                        io::stdout().flush()?;
-                        let mut choice = String::new();
-                        io::stdin().read_line(&mut choice)?;
-                        choice.trim().to_string()
+                        print_warning("Invalid cached action: Files don't have child nodes to enter.");
+                    } else if leaf && cached_action == "enter" {
+                        print_warning("Past the enter depth limit: choose an action for the whole directory.");
                     } else {
-                        match cached_action.as_str() {
-                            "read" => "1".to_string(),
-                            "exclude" => "2".to_string(),
-                            "enter" => "3".to_string(),
-                            "summarize" => "4".to_string(),
-                            "stats" => "5".to_string(),
-                            _ => {
-                                print!("\nEnter choice [1-5, q]: ");
-                                io::stdout().flush()?;
-                                let mut choice = String::new();
-                                io::stdin().read_line(&mut choice)?;
-                                choice.trim().to_string()
-                            }
-                        }
+                        choice = choice_for_action(&cached_action).map(str::to_string);
                     }
-                } else {
-                    print!("\nEnter choice [1-5, q]: ");
-                    io::stdout().flush()?;
-                    let mut choice = String::new();
-                    io::stdin().read_line(&mut choice)?;
-                    choice.trim().to_string()
                 }
-            } else {
-                print!("\nEnter choice [1-5, q]: ");
-                io::stdout().flush()?;
-                let mut choice = String::new();
-                io::stdin().read_line(&mut choice)?;
-                choice.trim().to_string()
+            }
+            let choice = match choice {
+                Some(choice) => choice,
+                None => match (prompt_timeout(CHOICE_PROMPT, timeout.map(|t| t.after))?, timeout) {
+                    (Some(choice), _) => choice,
+                    (None, Some(timeout)) => {
+                        let action = if timeout.enter_dirs && !is_file && !leaf {
+                            "enter"
+                        } else {
+                            timeout.action.as_str()
+                        };
+                        say(paint(format!("No answer: {}", action), Style::Muted));
+                        choice_for_action(action).unwrap_or_default().to_string()
+                    }
+                    // Only a prompt with a timeout goes unanswered
+                    (None, None) => String::new(),
+                },
             };
+            // Action names work as well as their keys
+            let choice = choice_for_action(&choice).map_or(choice, str::to_string);
 
             if choice == "q" {
                 break;
             }
 
+            // Step down the ladder when the choice doesn't fit what is left of
+            // --max-tokens
+            let included_action = match choice.as_str() {
+                "1" => Some("read"),
+                "4" => Some("summarize"),
+                "5" => Some("stats"),
+                "6" => Some("sample"),
+                "8" => Some("skeleton"),
+                "9" => Some("docs"),
+                _ => None,
+            };
+            let cost_of = |action: &str| -> usize {
+                node_costs(&current, dir_info, action, &included_files)
+                    .iter()
+                    .map(|(_, cost)| cost)
+                    .sum()
+            };
+            let mut downgraded_from = None;
+            let (choice, included_action) = match included_action {
+                Some(requested) => {
+                    let action = ladder.downgrade(requested, |action| {
+                        total_tokens + cost_of(action) <= max_tokens
+                    });
+                    if action != requested {
+                        print_warning(&format!(
+                            "Over the {}-token budget ({} used): {} instead of {}",
+                            max_tokens, total_tokens, action, requested
+                        ));
+                        downgraded_from = Some(requested);
+                    }
+                    let choice = choice_for_action(action).map_or(choice.clone(), str::to_string);
+                    (choice, Some(action).filter(|a| *a != "exclude"))
+                }
+                None => (choice, None),
+            };
+
+            // Reads that take a large share of what is left are confirmed first
+            if included_action == Some("read")
+                && !auto_read
+                && let Some(threshold) = confirm_reads_over
+            {
+                let impact = ReadImpact::new(
+                    cost_of("read"),
+                    total_tokens,
+                    max_tokens,
+                    context_file.current_tokens,
+                    CLAUDE_TOKEN_LIMIT,
+                );
+                if impact.percent_of_remaining > threshold {
+                    print_warning(&format!("Reading {}: {}.", current.display(), impact.describe()));
+                    // An unanswered question goes ahead with the read
+                    let response = prompt_timeout("Read it anyway? [Y/n]: ", timeout.map(|t| t.after))?
+                        .unwrap_or_default();
+                    if response.to_lowercase() == "n" {
+                        over_budget.insert(current.clone());
+                        to_process.push(current.clone());
+                        continue;
+                    }
+                }
+            }
+
+            // Hold choices that include content to the category ceilings
+            let costs = included_action
+                .map(|action| node_costs(&current, dir_info, action, &included_files))
+                .unwrap_or_default();
+            if let Some(bucket) = plan.overflow(&costs) {
+                print_warning(&format!(
+                    "Over the {} budget ({} of {} tokens used); choose a cheaper action",
+                    bucket.name, bucket.used, bucket.limit
+                ));
+                over_budget.insert(current.clone());
+                to_process.push(current.clone());
+                continue;
+            }
+            for (path, cost) in &costs {
+                plan.record(path, *cost);
+            }
+
             match choice.as_str() {
                 "1" => {
                     // Read
                     // Update cache
                     cache.insert(current.clone(), "read".to_string());
+                    manifest.record(&current, &start_dir, "read");
                     let result = process_node(
                         &current,
-                        &dir_info,
+                        dir_info,
                         context_file,
                         max_tokens,
                         total_tokens,
@@ -557,7 +2731,7 @@ fn process_interactive_loop(
                         Action::Read,
                         total_files,
                         &start_dir,
-                        output_dir,
+                        output,
                         Some(summary_cache),
                     )?;
                     total_tokens = result.total_tokens;
@@ -585,9 +2759,10 @@ fn process_interactive_loop(
                     // Exclude
                     // Update cache
                     cache.insert(current.clone(), "exclude".to_string());
+                    manifest.record(&current, &start_dir, "exclude");
                     let result = process_node(
                         &current,
-                        &dir_info,
+                        dir_info,
                         context_file,
                         max_tokens,
                         total_tokens,
@@ -596,7 +2771,7 @@ fn process_interactive_loop(
                         Action::Exclude,
                         total_files,
                         &start_dir,
-                        output_dir,
+                        output,
                         Some(summary_cache),
                     )?;
                     total_tokens = result.total_tokens;
@@ -612,59 +2787,82 @@ fn process_interactive_loop(
                         );
                     }
                 }
+                "3" | "7" if leaf && !is_file => {
+                    print_warning("Past the enter depth limit: choose an action for the whole directory.");
+                    to_process.push(current.clone());
+                }
                 "3" => {
                     // Enter
                     if is_file {
-                        println!("Invalid option: Files don't have child nodes to enter.");
+                        print_warning("Invalid option: Files don't have child nodes to enter.");
                         // Don't mark as processed so it will be prompted again
                         to_process.insert(0, current.clone());
                     } else {
-                        // Check if adding directory header would exceed token limit
-                        if context_file.current_tokens + 200 > CLAUDE_TOKEN_LIMIT {
-                            // Rough estimate
-                            *context_file = get_or_rotate_file(
-                                context_file,
-                                total_files,
-                                &start_dir,
-                                output_dir,
-                            )?;
-                            all_context_files.push(context_file.clone());
-                        }
-
-                        // Add a header for the directory
-                        let rel_path = current.strip_prefix(&start_dir).unwrap_or(&current);
-                        let content = format!("\n\n## DIRECTORY: {}\n", rel_path.display());
-                        append_to_file(&context_file.path, &content)?;
-                        context_file.current_tokens += 200; // Rough estimate
+                        write_directory_header(
+                            &current,
+                            &start_dir,
+                            context_file,
+                            total_files,
+                            output,
+                            all_context_files,
+                        )?;
 
                         // Mark directory as processed but add all its child nodes to the queue
                         processed.insert(current.clone());
                         cache.insert(current.clone(), "enter".to_string()); // Record that we entered this directory
+                        manifest.record(&current, &start_dir, "enter");
 
-                        // Add all files in this directory to the processing queue
-                        if let Some(dir_info) = dir_info.get(&current) {
-                            for file in &dir_info.files {
-                                if !processed.contains(&file.path) {
-                                    to_process.push(file.path.clone());
-                                }
-                            }
-
-                            // Add all subdirectories to the processing queue
-                            for subdir in &dir_info.subdirs {
-                                if !processed.contains(subdir) {
-                                    to_process.push(subdir.clone());
-                                }
+                        // Add all child nodes of this directory to the processing queue
+                        push_children(&mut to_process, &current, dir_info, &processed, recency);
+                    }
+                }
+                "7" => {
+                    // Enter to a depth: subdirectories above it are entered
+                    // without asking, the ones at it are decided as a whole
+                    let depth = if is_file {
+                        None
+                    } else {
+                        prompt("Levels to enter [1 = this level only]: ")?
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|d| *d > 0)
+                    };
+                    match depth {
+                        Some(depth) => {
+                            write_directory_header(
+                                &current,
+                                &start_dir,
+                                context_file,
+                                total_files,
+                                output,
+                                all_context_files,
+                            )?;
+                            processed.insert(current.clone());
+                            cache.insert(current.clone(), "enter".to_string());
+                            manifest.record(&current, &start_dir, "enter");
+                            push_children(&mut to_process, &current, dir_info, &processed, recency);
+                            for sub in &dir_info[&current].subdirs {
+                                enter_depths.insert(sub.clone(), depth - 1);
                             }
                         }
+                        None if is_file => {
+                            print_warning("Invalid option: Files don't have child nodes to enter.");
+                            to_process.insert(0, current.clone());
+                        }
+                        None => {
+                            print_warning("Invalid depth: enter a number of levels of at least 1.");
+                            to_process.push(current.clone());
+                        }
                     }
                 }
                 "4" => {
                     // Summarize
                     // Update cache
                     cache.insert(current.clone(), "summarize".to_string());
+                    manifest.record(&current, &start_dir, "summarize");
                     let result = process_node(
                         &current,
-                        &dir_info,
+                        dir_info,
                         context_file,
                         max_tokens,
                         total_tokens,
@@ -673,7 +2871,7 @@ fn process_interactive_loop(
                         Action::Summarize,
                         total_files,
                         &start_dir,
-                        output_dir,
+                        output,
                         Some(summary_cache),
                     )?;
                     total_tokens = result.total_tokens;
@@ -701,9 +2899,10 @@ fn process_interactive_loop(
                     // Stats
                     // Update cache
                     cache.insert(current.clone(), "stats".to_string());
+                    manifest.record(&current, &start_dir, "stats");
                     let result = process_node(
                         &current,
-                        &dir_info,
+                        dir_info,
                         context_file,
                         max_tokens,
                         total_tokens,
@@ -712,7 +2911,7 @@ fn process_interactive_loop(
                         Action::Stats,
                         total_files,
                         &start_dir,
-                        output_dir,
+                        output,
                         Some(summary_cache),
                     )?;
                     total_tokens = result.total_tokens;
@@ -736,72 +2935,190 @@ fn process_interactive_loop(
                         }
                     }
                 }
+                "6" | "8" | "9" | "l" => {
+                    // Sample, skeleton, API docs or chosen line ranges
+                    let action = match choice.as_str() {
+                        "6" => Action::Sample,
+                        "8" => Action::Skeleton,
+                        "9" => Action::Docs,
+                        _ if !is_file => {
+                            print_warning("Line ranges apply to files only");
+                            to_process.insert(0, current.clone());
+                            continue;
+                        }
+                        _ => match parse_line_ranges(&prompt("Line ranges (e.g. 120-240,400-450): ")?) {
+                            Some(ranges) => Action::Lines(ranges),
+                            None => {
+                                print_warning("Invalid line ranges; use start-end pairs separated by commas");
+                                to_process.insert(0, current.clone());
+                                continue;
+                            }
+                        },
+                    };
+                    // Update cache
+                    cache.insert(current.clone(), action.spec());
+                    manifest.record(&current, &start_dir, &action.spec());
+                    let result = process_node(
+                        &current,
+                        dir_info,
+                        context_file,
+                        max_tokens,
+                        total_tokens,
+                        &included_files,
+                        &processed,
+                        action,
+                        total_files,
+                        &start_dir,
+                        output,
+                        Some(summary_cache),
+                    )?;
+                    total_tokens = result.total_tokens;
+                    processed = result.processed;
+                    included_files = result.included_files;
+
+                    // Add any new context files to our tracking list
+                    for file in result.context_files.into_iter().skip(1) {
+                        // Skip first as it's the updated original
+                        if !all_context_files.iter().any(|f| f.path == file.path) {
+                            all_context_files.push(file);
+                        }
+                    }
+                }
+                "o" => {
+                    // Inspect the node, then prompt for it again
+                    let opened = if is_file {
+                        open_in_viewer(&current)
+                    } else {
+                        view_text(&render_tree(&current, dir_info, cache, summary_cache, true))
+                    };
+                    if let Err(e) = opened {
+                        print_warning(&format!("Could not open {}: {}", current.display(), e));
+                    }
+                    to_process.push(current.clone());
+                }
+                "?" | "h" => {
+                    // Help; prompt for the same node again
+                    print_help();
+                    to_process.push(current.clone());
+                }
+                // A script that gets out of step with the prompts would
+                // answer the wrong questions from here on
+                _ if is_scripted() => {
+                    bail!("Invalid answer '{}' on line {} of the answer script", choice, script_line());
+                }
                 _ => {
-                    println!("Invalid choice");
+                    print_warning("Invalid choice (? for help)");
                     to_process.insert(0, current.clone());
                 }
             }
+
+            // Log downgrades in the manifest for review
+            if let Some(requested) = downgraded_from {
+                manifest.annotate(&current, &start_dir, "requested", requested);
+                manifest.annotate(&current, &start_dir, "reason", "over budget");
+            }
         }
 
         Ok(())
     })();
 
+    // Last-minute changes, or the ordered layout, rebuild the parts from the manifest
+    if result.is_ok() {
+        let parts: Vec<PathBuf> = all_context_files.iter().map(|f| f.path.clone()).collect();
+        let reviewed = review && review_manifest(manifest, &start_dir, dir_info, &build_index(&parts)?)?;
+        if reviewed || layout == Layout::Ordered {
+            info!("Rebuilding context files from the selections");
+            for part in &parts {
+                fs::remove_file(part)
+                    .with_context(|| format!("Failed to remove context file {}", part.display()))?;
+            }
+            // Numbered after any parts being appended to
+            *context_file = create_context_file(previous_parts.len() + 1, total_files, &start_dir, output)?;
+            if layout == Layout::Ordered {
+                write_tree_overview(context_file, &start_dir, dir_info, output)?;
+            }
+            let laid_out = match layout {
+                Layout::Ordered => ordered_manifest(manifest, &start_dir),
+                Layout::Selection => manifest.clone(),
+            };
+            let rebuilt = apply_manifest(
+                &laid_out,
+                dir_info,
+                context_file,
+                max_tokens,
+                total_files,
+                &start_dir,
+                output,
+                Some(summary_cache),
+            )?;
+            total_tokens = rebuilt.total_tokens;
+            processed = rebuilt.processed;
+            included_files = rebuilt.included_files;
+            *all_context_files = rebuilt.context_files;
+            for summary_info in rebuilt.file_summaries {
+                summary_cache.insert_summary(
+                    &summary_info.path,
+                    &summary_info.content_hash,
+                    summary_info.summary,
+                );
+            }
+            for entry in &manifest.entries {
+                cache.insert(start_dir.join(&entry.path), entry.action.clone());
+            }
+        }
+    }
+
     // Finalize all context files - we do this regardless of whether the loop completed normally or was interrupted
-    finalize_context_files(all_context_files, included_files.len())?;
+    finalize_context_files(
+        all_context_files,
+        included_files.len(),
+        output.epilogue.as_deref(),
+        output.format.formatter(),
+    )?;
+    let parts = all_parts(previous_parts, all_context_files);
+    renumber_parts(&parts)?;
+    if let Some(dir) = &output.dir {
+        // Includes the parts of an earlier run when appending
+        write_index(dir, &context_part_paths(dir)?)?;
+    }
 
     // Save the cache files
     save_cache(&start_dir, cache)?;
+    let mut snapshots = load_snapshots(&start_dir)?;
+    record_reads(&mut snapshots, manifest, &start_dir, dir_info);
+    save_snapshots(&start_dir, &snapshots)?;
 
     // We need to do this outside the closure to avoid ownership issues
     save_summary_cache(&start_dir, summary_cache)?;
 
     // Display information
-    println!(
-        "\nProcessed {} nodes, {} files included.",
+    print_run_summary(
         processed.len(),
-        included_files.len()
-    );
-    println!("Estimated tokens: {}", total_tokens);
-    println!(
-        "Created {} context files (limited to ~{} tokens each):",
-        all_context_files.len(),
-        CLAUDE_TOKEN_LIMIT
+        included_files.len(),
+        total_tokens,
+        &parts,
+        summary_cache.run_stats(),
     );
 
-    for (i, file) in all_context_files.iter().enumerate() {
-        println!("  {}. {}", i + 1, file.path.display());
-    }
+    plan.print_utilization();
 
     // Display summary of cached actions
-    let mut action_paths: HashMap<String, Vec<PathBuf>> = HashMap::new();
-    for (path, action) in cache {
-        action_paths
-            .entry(action.clone())
-            .or_default()
-            .push(path.clone());
-    }
-
-    println!("\nCache summary:");
-    for (action, paths) in action_paths.iter() {
-        println!("\n  {}: {} items", action, paths.len());
-        for path in paths.iter().take(5) {
-            // Only show first 5 for brevity
-            println!(
-                "    - {}",
-                path.strip_prefix(&start_dir).unwrap_or(path).display()
-            );
-        }
-        if paths.len() > 5 {
-            println!("    - ... and {} more", paths.len() - 5);
-        }
-    }
+    print_cache_summary(cache, &start_dir);
 
     // Handle the result from the processing loop
-    match result {
-        Ok(_) => Ok(()),
+    let errors = match result {
+        Ok(_) => Vec::new(),
         Err(e) => {
-            println!("\nInterrupted: {}", e);
-            Ok(())
+            print_warning(&format!("\nInterrupted: {}", e));
+            vec![e.to_string()]
         }
-    }
+    };
+    Ok(RunSummary::new(
+        parts,
+        manifest,
+        included_files.len(),
+        total_tokens,
+        summary_cache.run_stats(),
+        errors,
+    ))
 }
@@ -0,0 +1,301 @@
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use log::{info, warn};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::context_files::{ContextFile, OutputSettings, append_to_file, get_or_rotate_file, shown_path};
+use crate::file_analysis::{CLAUDE_TOKEN_LIMIT, DirectoryMap};
+use crate::normalize::Normalization;
+use crate::processing::{Action, FileSummaryInfo, NodeProcessingResult, process_node};
+use crate::skeleton::symbol_matcher;
+use crate::summary_cache::SummaryCache;
+
+/// Current manifest format version
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Default manifest file name, stored in the start directory
+pub const MANIFEST_FILE_NAME: &str = ".claude-manifest.json";
+
+/// An explicit, shareable record of every selection made in a session
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Manifest {
+    /// Format version, checked when loading
+    pub version: u32,
+    /// Selections in the order they were made
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// A single selection in a manifest
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ManifestEntry {
+    /// Path relative to the start directory
+    pub path: PathBuf,
+    /// Action name, as stored in the cache (e.g. "read")
+    pub action: String,
+    /// Per-file options for the action
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub options: BTreeMap<String, String>,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Manifest {
+    /// Create an empty manifest
+    pub fn new() -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Record a selection, storing the path relative to the start directory
+    pub fn record(&mut self, path: &Path, start_dir: &Path, action: &str) {
+        self.record_with_options(path, start_dir, action, BTreeMap::new());
+    }
+
+    /// Record a selection along with options describing it
+    pub fn record_with_options(
+        &mut self,
+        path: &Path,
+        start_dir: &Path,
+        action: &str,
+        options: BTreeMap<String, String>,
+    ) {
+        let rel_path = path.strip_prefix(start_dir).unwrap_or(path);
+        self.entries.push(ManifestEntry {
+            path: rel_path.to_path_buf(),
+            action: action.to_string(),
+            options,
+        });
+    }
+
+    /// Store the options that apply to each entry's action, keeping any
+    /// option an entry already has
+    pub fn fill_options(&mut self, options: &EntryOptions) {
+        for entry in &mut self.entries {
+            for (key, value) in options.for_action(&entry.action) {
+                entry.options.entry(key).or_insert(value);
+            }
+        }
+    }
+
+    /// Set an option on the most recent selection of a path
+    pub fn annotate(&mut self, path: &Path, start_dir: &Path, key: &str, value: &str) {
+        let rel_path = path.strip_prefix(start_dir).unwrap_or(path);
+        if let Some(entry) = self.entries.iter_mut().rev().find(|e| e.path == rel_path) {
+            entry.options.insert(key.to_string(), value.to_string());
+        }
+    }
+}
+
+/// Output options of a run that change how a file's content is written,
+/// stored on each entry they apply to so a replay writes it the same way
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntryOptions {
+    /// Fold function bodies of at least this many lines in read files
+    pub fold_bodies: Option<usize>,
+    /// Read only the matching declarations from code files
+    pub symbols: Vec<String>,
+    /// Attach external signatures to Rust skeletons and API docs
+    pub external_signatures: bool,
+    /// Normalizations applied to the text of each block
+    pub normalize: Vec<Normalization>,
+}
+
+impl EntryOptions {
+    /// The options that apply to an action, as stored in an entry
+    pub fn for_action(&self, action: &str) -> BTreeMap<String, String> {
+        let mut options = BTreeMap::new();
+        if matches!(action, "enter" | "exclude") {
+            return options;
+        }
+        if action == "read" {
+            if let Some(min_lines) = self.fold_bodies {
+                options.insert("fold_bodies".to_string(), min_lines.to_string());
+            }
+            if !self.symbols.is_empty() {
+                options.insert("symbols".to_string(), self.symbols.join(","));
+            }
+        }
+        if self.external_signatures && matches!(action, "skeleton" | "docs") {
+            options.insert("external_signatures".to_string(), "true".to_string());
+        }
+        let normalize: Vec<String> = self
+            .normalize
+            .iter()
+            .filter_map(|n| n.to_possible_value().map(|v| v.get_name().to_string()))
+            .collect();
+        if !normalize.is_empty() {
+            options.insert("normalize".to_string(), normalize.join(","));
+        }
+        options
+    }
+}
+
+/// Output settings for replaying an entry: the run's, with the entry's
+/// options in place of the matching settings
+fn entry_output(output: &OutputSettings, options: &BTreeMap<String, String>) -> Result<OutputSettings> {
+    let mut output = output.clone();
+    for (key, value) in options {
+        match key.as_str() {
+            "fold_bodies" => {
+                let min_lines = value
+                    .parse()
+                    .with_context(|| format!("Invalid fold_bodies '{}'", value))?;
+                output.fold_bodies = Some(min_lines);
+            }
+            "symbols" => {
+                let patterns: Vec<String> = value.split(',').map(str::to_string).collect();
+                output.symbols = Some(symbol_matcher(&patterns)?);
+            }
+            "external_signatures" => output.external_signatures = value == "true",
+            "normalize" => {
+                output.normalize = value
+                    .split(',')
+                    .map(|name| Normalization::from_str(name, true).map_err(anyhow::Error::msg))
+                    .collect::<Result<_>>()?
+            }
+            // Notes such as why a read was downgraded
+            _ => {}
+        }
+    }
+    Ok(output)
+}
+
+/// Path of the manifest in a directory
+pub fn default_manifest_path(directory: &Path) -> PathBuf {
+    directory.join(MANIFEST_FILE_NAME)
+}
+
+/// Load a manifest, rejecting versions newer than this build understands
+pub fn load_manifest(path: &Path) -> Result<Manifest> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read manifest {}", path.display()))?;
+    let manifest: Manifest = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid manifest {}", path.display()))?;
+
+    if manifest.version > MANIFEST_VERSION {
+        bail!(
+            "Manifest {} has version {}, but only version {} is supported",
+            path.display(),
+            manifest.version,
+            MANIFEST_VERSION
+        );
+    }
+
+    info!(
+        "Loaded manifest with {} entries from {}",
+        manifest.entries.len(),
+        path.display()
+    );
+    Ok(manifest)
+}
+
+/// Save a manifest as pretty-printed JSON
+pub fn save_manifest(path: &Path, manifest: &Manifest) -> Result<()> {
+    let content =
+        serde_json::to_string_pretty(manifest).context("Failed to serialize manifest")?;
+    fs::write(path, content).context("Failed to write manifest file")?;
+
+    info!("Manifest saved to {}", path.display());
+
+    Ok(())
+}
+
+/// Rebuild the context from a manifest by replaying its entries in order
+#[allow(clippy::too_many_arguments)]
+pub fn apply_manifest(
+    manifest: &Manifest,
+    dir_info: &DirectoryMap,
+    context_file: &mut ContextFile,
+    max_tokens: usize,
+    total_files: usize,
+    base_dir: &Path,
+    output: &OutputSettings,
+    summary_cache: Option<&SummaryCache>,
+) -> Result<NodeProcessingResult> {
+    let mut processed = HashSet::new();
+    let mut included_files = HashSet::new();
+    let mut total_tokens = 0;
+    let mut all_context_files = vec![context_file.clone()];
+    let mut file_summaries: Vec<FileSummaryInfo> = Vec::new();
+
+    for entry in &manifest.entries {
+        let path = base_dir.join(&entry.path);
+        if !path.exists() {
+            warn!(
+                "Skipping manifest entry that no longer exists: {}",
+                entry.path.display()
+            );
+            continue;
+        }
+
+        let Some(action) = Action::parse_str(&entry.action) else {
+            warn!(
+                "Skipping manifest entry with unknown action '{}': {}",
+                entry.action,
+                entry.path.display()
+            );
+            continue;
+        };
+
+        if action == Action::Enter {
+            // Entering only adds the directory header; children have their own entries
+            if context_file.current_tokens + 200 > CLAUDE_TOKEN_LIMIT {
+                // Rough estimate
+                *context_file = get_or_rotate_file(context_file, total_files, base_dir, output)?;
+                all_context_files.push(context_file.clone());
+            }
+
+            let content = output
+                .format
+                .formatter()
+                .directory(&shown_path(&path, base_dir, output.absolute_paths), "");
+            append_to_file(&context_file.path, &content)?;
+            context_file.current_tokens += 200; // Rough estimate
+            processed.insert(path);
+            continue;
+        }
+
+        let entry_output = entry_output(output, &entry.options)
+            .with_context(|| format!("Invalid options for {}", entry.path.display()))?;
+        let result = process_node(
+            &path,
+            dir_info,
+            context_file,
+            max_tokens,
+            total_tokens,
+            &included_files,
+            &processed,
+            action,
+            total_files,
+            base_dir,
+            &entry_output,
+            summary_cache,
+        )?;
+
+        total_tokens = result.total_tokens;
+        processed = result.processed;
+        included_files = result.included_files;
+        all_context_files.extend(result.context_files.into_iter().skip(1)); // Skip first as it's already in the list
+        file_summaries.extend(result.file_summaries);
+    }
+
+    // Deduplicate context files
+    let mut seen_paths = HashSet::new();
+    all_context_files.retain(|file| seen_paths.insert(file.path.clone()));
+
+    Ok(NodeProcessingResult {
+        total_tokens,
+        processed,
+        included_files,
+        context_files: all_context_files,
+        file_summaries,
+    })
+}
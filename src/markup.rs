@@ -0,0 +1,186 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::file_analysis::DirectoryMap;
+use crate::suggestions::{Suggestion, SuggestionMap};
+
+/// Extensions of SVG and XML files, which are outlined rather than included
+pub const MARKUP_EXTENSIONS: [&str; 7] = ["svg", "xml", "xsd", "xsl", "xslt", "plist", "wsdl"];
+/// Action suggested for markup files
+pub const MARKUP_ACTION: &str = "sample";
+
+/// Deepest level of the tag outline
+const MAX_OUTLINE_DEPTH: usize = 4;
+/// Most lines in the tag outline
+const MAX_OUTLINE_LINES: usize = 40;
+/// Most element names listed with their counts
+const MAX_COUNTED_NAMES: usize = 8;
+/// Most root attributes shown
+const MAX_ROOT_ATTRIBUTES: usize = 6;
+/// Longest root attribute value shown before truncation
+const MAX_ATTRIBUTE_LEN: usize = 40;
+
+lazy_static! {
+    static ref IGNORED: Regex = Regex::new(r"(?s)<!--.*?-->|<!\[CDATA\[.*?\]\]>|<\?.*?\?>|<![^>]*>").unwrap();
+    static ref TAG: Regex =
+        Regex::new(r#"<(/)?([A-Za-z_][\w:.-]*)((?:[^>"']|"[^"]*"|'[^']*')*?)(/)?>"#).unwrap();
+    static ref ATTRIBUTE: Regex = Regex::new(r#"([\w:.-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+}
+
+/// Structure of an SVG or XML document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MarkupSummary {
+    /// Name of the root element
+    pub root: String,
+    /// Attributes of the root element (namespace declarations left out)
+    pub attributes: Vec<(String, String)>,
+    /// Occurrences of each element name
+    pub counts: BTreeMap<String, usize>,
+    /// Outline lines: depth, element name and consecutive siblings it stands for
+    pub outline: Vec<(usize, String, usize)>,
+}
+
+/// Whether a file is SVG or XML, by extension
+pub fn is_markup(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| MARKUP_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Parse the element structure of a document. None when it has no elements.
+pub fn summarize_markup(content: &str) -> Option<MarkupSummary> {
+    let content = IGNORED.replace_all(content, "");
+    let mut summary = MarkupSummary::default();
+    let mut stack: Vec<String> = Vec::new();
+    // Outline line of the previous sibling at each depth
+    let mut last_at: Vec<Option<usize>> = Vec::new();
+    // Depth below which a collapsed sibling's children are hidden
+    let mut hidden_below: Option<usize> = None;
+
+    for c in TAG.captures_iter(&content) {
+        let name = c[2].to_string();
+        if c.get(1).is_some() {
+            // Close up to the matching element, tolerating unclosed ones
+            if let Some(pos) = stack.iter().rposition(|open| *open == name) {
+                stack.truncate(pos);
+            }
+            if hidden_below.is_some_and(|depth| stack.len() <= depth) {
+                hidden_below = None;
+            }
+            continue;
+        }
+
+        let depth = stack.len();
+        if summary.root.is_empty() {
+            summary.root = name.clone();
+            summary.attributes = ATTRIBUTE
+                .captures_iter(&c[3])
+                .filter(|a| &a[1] != "xmlns" && !a[1].starts_with("xmlns:"))
+                .map(|a| {
+                    let value = a.get(2).or(a.get(3)).map_or("", |v| v.as_str());
+                    (a[1].to_string(), value.to_string())
+                })
+                .collect();
+        }
+        *summary.counts.entry(name.clone()).or_default() += 1;
+        let self_closing = c.get(4).is_some();
+
+        if depth < MAX_OUTLINE_DEPTH && hidden_below.is_none() {
+            last_at.resize(depth + 1, None);
+            match last_at[depth] {
+                Some(i) if summary.outline[i].1 == name => {
+                    summary.outline[i].2 += 1;
+                    if !self_closing {
+                        hidden_below = Some(depth);
+                    }
+                }
+                _ => {
+                    summary.outline.push((depth, name.clone(), 1));
+                    last_at[depth] = Some(summary.outline.len() - 1);
+                }
+            }
+        }
+
+        if !self_closing {
+            stack.push(name);
+        }
+    }
+
+    (!summary.root.is_empty()).then_some(summary)
+}
+
+impl MarkupSummary {
+    /// Total number of elements
+    pub fn element_count(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    /// Root element with its attributes (width, height and viewBox for an
+    /// SVG), and element counts
+    pub fn render_header(&self) -> String {
+        let mut root = format!("Root: <{}", self.root);
+        for (name, value) in self.attributes.iter().take(MAX_ROOT_ATTRIBUTES) {
+            let value = match value.char_indices().nth(MAX_ATTRIBUTE_LEN) {
+                Some((end, _)) => format!("{}...", &value[..end]),
+                None => value.clone(),
+            };
+            root.push_str(&format!(" {}=\"{}\"", name, value));
+        }
+        if self.attributes.len() > MAX_ROOT_ATTRIBUTES {
+            root.push_str(" ...");
+        }
+        root.push('>');
+
+        let mut counts: Vec<(&String, &usize)> = self.counts.iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let mut listed: Vec<String> = counts
+            .iter()
+            .take(MAX_COUNTED_NAMES)
+            .map(|(name, count)| format!("{} {}", name, count))
+            .collect();
+        if counts.len() > MAX_COUNTED_NAMES {
+            listed.push(format!("+{} more names", counts.len() - MAX_COUNTED_NAMES));
+        }
+
+        format!("{}\nElements: {} ({})\n", root, self.element_count(), listed.join(", "))
+    }
+
+    /// Header followed by an outline of the tags, with runs of sibling
+    /// elements of the same name collapsed
+    pub fn render(&self) -> String {
+        let mut output = self.render_header();
+        output.push_str("Outline:\n");
+        for (depth, name, count) in self.outline.iter().take(MAX_OUTLINE_LINES) {
+            output.push_str(&"  ".repeat(*depth));
+            output.push_str(name);
+            if *count > 1 {
+                output.push_str(&format!(" (x{})", count));
+            }
+            output.push('\n');
+        }
+        if self.outline.len() > MAX_OUTLINE_LINES {
+            output.push_str(&format!("... {} more outline lines\n", self.outline.len() - MAX_OUTLINE_LINES));
+        }
+        output
+    }
+}
+
+/// Suggest sampling SVG and XML files, which become an outline instead of markup
+pub fn markup_suggestions(dir_info: &DirectoryMap) -> SuggestionMap {
+    dir_info
+        .values()
+        .flat_map(|info| info.files.iter())
+        .filter(|f| !f.binary && is_markup(&f.path))
+        .map(|f| {
+            (
+                f.path.clone(),
+                Suggestion {
+                    action: MARKUP_ACTION.to_string(),
+                    reason: "SVG/XML markup (outline of tags)".to_string(),
+                },
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,163 @@
+use anyhow::{Context, Result, bail};
+use log::info;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::anchors::{context_part_paths, is_block_end, is_collection_end, parse_anchor, source_dir};
+use crate::context_files::{
+    ContextFile, PART_HEADER_PREFIX, PartNaming, finalize_context_files, latest_session,
+    prepare_output_dir,
+};
+use crate::formats::{OutputFormat, block_end, parts_format};
+use crate::index::{ContextIndex, INDEX_FILE_NAME, IndexEntry, build_index, save_index};
+
+/// Totals of a merge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergeSummary {
+    pub parts: usize,
+    pub files: usize,
+    pub duplicates: usize,
+}
+
+/// A context part split into its header and blocks, without its footer
+struct SplitPart {
+    header: String,
+    blocks: Vec<String>,
+}
+
+/// Split a context part at its anchors and directory headers, dropping the
+/// footer added when the run was finalized
+fn split_part(content: &str, format: OutputFormat) -> SplitPart {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let trimmed: Vec<&str> = lines.iter().map(|l| l.trim_end_matches(['\n', '\r'])).collect();
+    let first = trimmed.iter().position(|l| is_block_end(l)).unwrap_or(lines.len());
+    let mut split = SplitPart {
+        header: lines[..first].concat(),
+        blocks: Vec::new(),
+    };
+
+    let mut start = first;
+    while start < lines.len() && !is_collection_end(trimmed[start]) {
+        let end = block_end(&trimmed, start, format);
+        split.blocks.push(lines[start..end].concat());
+        start = end;
+    }
+    split
+}
+
+/// Resolve a bundle argument (a context directory, a session root with a
+/// `latest` pointer, or an index.json) to the directory holding its parts
+fn bundle_dir(bundle: &Path) -> PathBuf {
+    if bundle.file_name().is_some_and(|n| n == INDEX_FILE_NAME) {
+        return bundle.parent().unwrap_or(bundle).to_path_buf();
+    }
+    latest_session(bundle).unwrap_or_else(|| bundle.to_path_buf())
+}
+
+/// Combine the parts of several bundles into one set in `output_dir`. Parts
+/// are renumbered in bundle order and keep their own headers, so each still
+/// records its source directory. A file block whose content and action match
+/// one already merged is dropped, and the index lists it against the part
+/// holding the kept copy.
+pub fn merge_bundles(
+    bundles: &[PathBuf],
+    output_dir: &Path,
+    naming: &PartNaming,
+) -> Result<MergeSummary> {
+    let mut parts = Vec::new();
+    for bundle in bundles {
+        let dir = bundle_dir(bundle);
+        let bundle_parts = context_part_paths(&dir)?;
+        if bundle_parts.is_empty() {
+            bail!("No context parts found in {}", dir.display());
+        }
+        if dir.canonicalize().ok() == output_dir.canonicalize().ok() {
+            bail!("Output directory {} is one of the bundles", output_dir.display());
+        }
+        parts.extend(bundle_parts);
+    }
+    // Merged parts keep their blocks as written, so they must share a format
+    let format = parts_format(&parts)?;
+
+    fs::create_dir_all(output_dir).context("Failed to create output directory")?;
+    prepare_output_dir(output_dir, 0)?;
+
+    let total = parts.len();
+    let mut seen: HashMap<(String, String), String> = HashMap::new();
+    let mut duplicates = Vec::new();
+    let mut merged = Vec::new();
+
+    for (idx, part) in parts.iter().enumerate() {
+        let file_num = idx + 1;
+        let content = fs::read_to_string(part)
+            .with_context(|| format!("Failed to read context file {}", part.display()))?;
+        let source = source_dir(&content);
+        let split = split_part(&content, format);
+
+        let base_dir = source.clone().unwrap_or_else(|| part.to_path_buf());
+        let path = output_dir.join(naming.file_name(file_num, &base_dir));
+        let part_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        // Renumber the part, keeping the rest of its header
+        let rest = split.header.split_once('\n').map_or("", |(_, rest)| rest);
+        let mut output = format!("{}{} of {}).\n{}", PART_HEADER_PREFIX, file_num, total, rest);
+        output.truncate(output.trim_end_matches('\n').len());
+        output.push('\n');
+
+        for block in &split.blocks {
+            let first_line = block.lines().next().unwrap_or("");
+            if let Some(anchor) = parse_anchor(first_line) {
+                let key = (anchor.hash.clone(), anchor.action.spec());
+                if let Some(kept_in) = seen.get(&key) {
+                    duplicates.push(IndexEntry {
+                        path: anchor.path,
+                        citation: anchor.citation,
+                        action: key.1,
+                        hash: anchor.hash,
+                        part: kept_in.clone(),
+                        source: source.clone(),
+                        folds: Vec::new(),
+                    });
+                    continue;
+                }
+                seen.insert(key, part_name.clone());
+            }
+            output.push_str("\n\n");
+            output.push_str(block.trim_end_matches('\n'));
+            output.push('\n');
+        }
+
+        fs::write(&path, output)
+            .with_context(|| format!("Failed to write context file {}", path.display()))?;
+        merged.push(ContextFile {
+            path,
+            file_num,
+            current_tokens: 0,
+        });
+    }
+
+    let files = seen.len() + duplicates.len();
+    finalize_context_files(&merged, files, None, format.formatter())?;
+
+    let paths: Vec<PathBuf> = merged.iter().map(|f| f.path.clone()).collect();
+    let mut index: ContextIndex = build_index(&paths)?;
+    let removed = duplicates.len();
+    index.files.extend(duplicates);
+    save_index(output_dir, &index)?;
+
+    info!(
+        "Merged {} bundles into {} parts ({} duplicate files dropped)",
+        bundles.len(),
+        total,
+        removed
+    );
+    Ok(MergeSummary {
+        parts: total,
+        files,
+        duplicates: removed,
+    })
+}
@@ -0,0 +1,116 @@
+use chrono::{SecondsFormat, Utc};
+use std::path::Path;
+use std::process::Command;
+
+/// Characters of the commit hash shown in the header
+const SHORT_COMMIT_LEN: usize = 12;
+
+/// Provenance stamped into each context part so bundles can be audited and reproduced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenerationMetadata {
+    /// Name and version of the generator
+    pub generator: String,
+    /// UTC timestamp of the run (RFC 3339)
+    pub timestamp: String,
+    /// HEAD commit of the source repository, if it is one
+    pub git_commit: Option<String>,
+    /// Whether the working tree had uncommitted changes
+    pub git_dirty: bool,
+    /// URL of the `origin` remote (or the first remote), without credentials
+    pub git_remote: Option<String>,
+    /// Checked-out branch; None when HEAD is detached
+    pub git_branch: Option<String>,
+    /// Command line arguments the run was started with
+    pub arguments: Vec<String>,
+}
+
+impl GenerationMetadata {
+    /// Collect metadata for a run over `start_dir`
+    pub fn collect(start_dir: &Path, arguments: Vec<String>) -> Self {
+        let git_commit = git_output(start_dir, &["rev-parse", "HEAD"]);
+        let git_dirty = git_commit.is_some()
+            && git_output(start_dir, &["status", "--porcelain"]).is_some_and(|s| !s.is_empty());
+        let (git_remote, git_branch) = match git_commit {
+            Some(_) => (
+                git_remote(start_dir),
+                git_output(start_dir, &["symbolic-ref", "--short", "-q", "HEAD"]),
+            ),
+            None => (None, None),
+        };
+
+        Self {
+            generator: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            git_commit,
+            git_dirty,
+            git_remote,
+            git_branch,
+            arguments,
+        }
+    }
+
+    /// Render the header lines for a context part
+    pub fn render(&self) -> String {
+        let mut output = format!("Generated by: {}\n", self.generator);
+        output.push_str(&format!("Generated at: {}\n", self.timestamp));
+        if let Some(commit) = &self.git_commit {
+            if let Some(remote) = &self.git_remote {
+                output.push_str(&format!("Git remote: {}\n", remote));
+            }
+            output.push_str(&format!(
+                "Git branch: {}\n",
+                self.git_branch.as_deref().unwrap_or("(detached HEAD)")
+            ));
+            let dirty = if self.git_dirty {
+                " (with uncommitted changes)"
+            } else {
+                ""
+            };
+            output.push_str(&format!(
+                "Git commit: {}{}\n",
+                &commit[..commit.len().min(SHORT_COMMIT_LEN)],
+                dirty
+            ));
+        }
+        output.push_str(&format!("Arguments: {}\n", self.arguments.join(" ")));
+        output
+    }
+}
+
+/// Run a git command in a directory, returning its output (without trailing
+/// whitespace) on success
+pub fn git_output(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+}
+
+/// URL of the `origin` remote, or of the first remote when there is no origin
+fn git_remote(dir: &Path) -> Option<String> {
+    let name = match git_output(dir, &["remote"]) {
+        Some(remotes) if remotes.lines().any(|r| r == "origin") => "origin".to_string(),
+        Some(remotes) => remotes.lines().next()?.to_string(),
+        None => return None,
+    };
+    git_output(dir, &["remote", "get-url", &name]).map(|url| strip_credentials(&url))
+}
+
+/// Remove a user name and password from a URL, so tokens in remotes aren't shared
+pub fn strip_credentials(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let host_end = rest.find('/').unwrap_or(rest.len());
+    match rest[..host_end].rfind('@') {
+        Some(at) => format!("{}://{}", scheme, &rest[at + 1..]),
+        None => url.to_string(),
+    }
+}
@@ -0,0 +1,191 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+/// Extensions of documentation files, whose cache keys use normalized content
+pub const DOC_EXTENSIONS: [&str; 8] = ["md", "markdown", "rst", "txt", "adoc", "html", "htm", "xhtml"];
+/// Placeholder for version strings removed during normalization
+pub const VERSION_PLACEHOLDER: &str = "<version>";
+
+lazy_static! {
+    static ref DROPPED_ELEMENT: Regex =
+        Regex::new(r"(?is)<(script|style|noscript|template)\b[^>]*>.*?</\s*(script|style|noscript|template)\s*>").unwrap();
+    static ref HTML_COMMENT: Regex = Regex::new(r"(?s)<!--.*?-->").unwrap();
+    static ref BLOCK_TAG: Regex = Regex::new(
+        r"(?i)</?(p|div|br|hr|li|ul|ol|h[1-6]|tr|table|pre|section|article|header|footer|nav|blockquote|dt|dd)\b[^>]*>"
+    )
+    .unwrap();
+    static ref TAG: Regex = Regex::new(r"(?s)<[^>]*>").unwrap();
+    static ref ENTITY: Regex = Regex::new(r"&(#\d+|#x[0-9a-fA-F]+|[a-zA-Z]+);").unwrap();
+    static ref VERSION: Regex = Regex::new(r"\bv?\d+\.\d+\.\d+(?:-[0-9A-Za-z.]+)?(?:\+[0-9A-Za-z.]+)?\b").unwrap();
+    static ref SPACES: Regex = Regex::new(r"[ \t\u{a0}]+").unwrap();
+    static ref PRE: Regex = Regex::new(r"(?is)<pre\b[^>]*>(.*?)</pre\s*>").unwrap();
+    static ref HEADING: Regex = Regex::new(r"(?is)<h([1-6])\b[^>]*>(.*?)</h[1-6]\s*>").unwrap();
+    static ref LINK: Regex =
+        Regex::new(r#"(?is)<a\b[^>]*?\bhref\s*=\s*["']([^"']*)["'][^>]*>(.*?)</a\s*>"#).unwrap();
+    static ref STRONG: Regex = Regex::new(r"(?is)<(?:strong|b)\b[^>]*>(.*?)</(?:strong|b)\s*>").unwrap();
+    static ref EMPHASIS: Regex = Regex::new(r"(?is)<(?:em|i)\b[^>]*>(.*?)</(?:em|i)\s*>").unwrap();
+    static ref CODE: Regex = Regex::new(r"(?is)<code\b[^>]*>(.*?)</code\s*>").unwrap();
+    static ref LIST_ITEM: Regex = Regex::new(r"(?i)<li\b[^>]*>").unwrap();
+    static ref LIST_ITEM_END: Regex = Regex::new(r"(?i)</li\s*>").unwrap();
+    static ref PRE_PLACEHOLDER: Regex = Regex::new(r"\x00PRE(\d+)\x00").unwrap();
+}
+
+/// Normalization of the text written to context parts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Normalization {
+    /// Convert CRLF line endings to LF
+    LineEndings,
+    /// End every file's content with a newline, so the next marker starts its own line
+    FinalNewline,
+    /// Replace invalid UTF-8 with U+FFFD instead of failing to read the file
+    Utf8,
+}
+
+/// Read a file as text, replacing invalid UTF-8 when `lossy`
+pub fn read_text(path: &Path, lossy: bool) -> std::io::Result<String> {
+    if lossy {
+        fs::read(path).map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+/// Apply the line ending and final newline normalizations to text
+pub fn normalize_text(text: String, normalize: &[Normalization]) -> String {
+    let mut text = if normalize.contains(&Normalization::LineEndings) {
+        text.replace("\r\n", "\n")
+    } else {
+        text
+    };
+    if normalize.contains(&Normalization::FinalNewline) && !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text
+}
+
+/// Whether a file is documentation, by extension
+pub fn is_doc_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| DOC_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Whether a file is HTML, by extension
+pub fn is_html_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| matches!(ext.to_lowercase().as_str(), "html" | "htm" | "xhtml"))
+}
+
+/// Decode one HTML entity; unknown ones are kept as written
+fn decode_entity(entity: &str) -> String {
+    let code = match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => entity.strip_prefix('#').and_then(|num| {
+            match num.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => num.parse().ok(),
+            }
+            .and_then(char::from_u32)
+        }),
+    };
+    code.map_or_else(|| format!("&{};", entity), String::from)
+}
+
+/// Text of an HTML document: scripts, styles and comments are dropped, block
+/// elements become line breaks and entities are decoded
+pub fn html_to_text(html: &str) -> String {
+    let text = DROPPED_ELEMENT.replace_all(html, "");
+    let text = HTML_COMMENT.replace_all(&text, "");
+    let text = BLOCK_TAG.replace_all(&text, "\n");
+    let text = TAG.replace_all(&text, "");
+    ENTITY
+        .replace_all(&text, |c: &regex::Captures| decode_entity(&c[1]))
+        .into_owned()
+}
+
+/// Markdown for an HTML document: headings, links, emphasis, code, lists and
+/// preformatted blocks are kept as markdown, scripts and styles are dropped
+/// and everything else becomes plain text
+pub fn html_to_markdown(html: &str) -> String {
+    let text = DROPPED_ELEMENT.replace_all(html, "");
+    let text = HTML_COMMENT.replace_all(&text, "");
+
+    // Preformatted blocks keep their whitespace, so they are set aside until
+    // the rest has been collapsed
+    let mut blocks = Vec::new();
+    let text = PRE.replace_all(&text, |c: &regex::Captures| {
+        let code = TAG.replace_all(&c[1], "");
+        let code = ENTITY.replace_all(&code, |e: &regex::Captures| decode_entity(&e[1]));
+        blocks.push(format!("```\n{}\n```", code.trim_matches('\n')));
+        format!("\n\x00PRE{}\x00\n", blocks.len() - 1)
+    });
+
+    let text = HEADING.replace_all(&text, |c: &regex::Captures| {
+        let level: usize = c[1].parse().unwrap_or(1);
+        format!("\n\n{} {}\n\n", "#".repeat(level), c[2].trim())
+    });
+    let text = LINK.replace_all(&text, "[$2]($1)");
+    let text = STRONG.replace_all(&text, "**$1**");
+    let text = EMPHASIS.replace_all(&text, "*$1*");
+    let text = CODE.replace_all(&text, "`$1`");
+    let text = LIST_ITEM_END.replace_all(&text, "");
+    let text = LIST_ITEM.replace_all(&text, "\n- ");
+    let text = BLOCK_TAG.replace_all(&text, "\n");
+    let text = TAG.replace_all(&text, "");
+    let text = ENTITY.replace_all(&text, |c: &regex::Captures| decode_entity(&c[1]));
+
+    let markdown = collapse_whitespace(&text);
+    PRE_PLACEHOLDER
+        .replace_all(&markdown, |c: &regex::Captures| {
+            c[1].parse::<usize>()
+                .ok()
+                .and_then(|i| blocks.get(i).cloned())
+                .unwrap_or_default()
+        })
+        .into_owned()
+}
+
+/// Trim lines, collapse runs of spaces and of blank lines
+pub fn collapse_whitespace(text: &str) -> String {
+    let mut output = String::new();
+    let mut blank = false;
+    for line in text.lines() {
+        let line = SPACES.replace_all(line.trim(), " ");
+        if line.is_empty() {
+            blank = !output.is_empty();
+            continue;
+        }
+        if blank {
+            output.push('\n');
+            blank = false;
+        }
+        output.push_str(&line);
+        output.push('\n');
+    }
+    output
+}
+
+/// Replace semantic version strings, so copies of one document for
+/// different releases compare equal
+pub fn strip_versions(text: &str) -> String {
+    VERSION.replace_all(text, VERSION_PLACEHOLDER).into_owned()
+}
+
+/// Documentation content reduced to what it says: HTML becomes text, and
+/// versions and formatting whitespace are removed
+pub fn normalize_doc(path: &Path, content: &str) -> String {
+    let text = if is_html_file(path) {
+        html_to_text(content)
+    } else {
+        content.to_string()
+    };
+    collapse_whitespace(&strip_versions(&text))
+}
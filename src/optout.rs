@@ -0,0 +1,45 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::file_analysis::DirectoryMap;
+
+/// Text that opts a file out of every context bundle, in a comment or frontmatter
+pub const DEFAULT_OPT_OUT_MARKER: &str = "llm-context: ignore";
+
+/// Lines read from the top of a file when looking for the marker
+const MARKER_LINES: usize = 30;
+
+/// Whether the top of a file contains the opt-out marker
+pub fn has_opt_out_marker(path: &Path, marker: &str) -> bool {
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+    BufReader::new(file)
+        .lines()
+        .take(MARKER_LINES)
+        .map_while(Result::ok)
+        .any(|line| line.contains(marker))
+}
+
+/// Remove text files carrying the opt-out marker from the analysis, returning
+/// their paths. An empty marker disables opting out.
+pub fn remove_opted_out(dir_info: &mut DirectoryMap, marker: &str) -> Vec<PathBuf> {
+    let mut removed = Vec::new();
+    if marker.is_empty() {
+        return removed;
+    }
+    for info in dir_info.values_mut() {
+        let (opted_out, kept) = std::mem::take(&mut info.files)
+            .into_iter()
+            .partition(|f| !f.binary && has_opt_out_marker(&f.path, marker));
+        info.files = kept;
+        for file in opted_out {
+            info.total_files -= 1;
+            info.tokens -= file.tokens;
+            removed.push(file.path);
+        }
+    }
+    removed.sort();
+    removed
+}
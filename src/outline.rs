@@ -0,0 +1,58 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// Most items listed on a stats card before the rest are counted
+pub const MAX_CARD_ITEMS: usize = 12;
+
+lazy_static! {
+    static ref RUST_ITEM: Regex = Regex::new(
+        r#"(?m)^pub\s+(?:(?:async|const|unsafe|extern\s+"[^"]*")\s+)*(fn|struct|enum|trait|mod|const|static|type|union)\s+(\w+)"#
+    )
+    .unwrap();
+    static ref PY_ITEM: Regex = Regex::new(r"(?m)^(?:async\s+)?(def|class)\s+([A-Za-z]\w*)").unwrap();
+    static ref JS_ITEM: Regex = Regex::new(
+        r"(?m)^export\s+(?:default\s+)?(?:declare\s+)?(?:abstract\s+)?(?:async\s+)?(function\*?|class|const|let|var|interface|type|enum)\s+(\w+)"
+    )
+    .unwrap();
+    static ref GO_ITEM: Regex =
+        Regex::new(r"(?m)^(func|type)\s+(?:\([^)]*\)\s*)?([A-Z]\w*)").unwrap();
+    static ref JVM_ITEM: Regex = Regex::new(
+        r"(?m)^\s*public\s+(?:(?:static|final|abstract|sealed|partial)\s+)*(class|interface|enum|record|struct)\s+(\w+)"
+    )
+    .unwrap();
+}
+
+/// Top-level public items of a source file as `kind name`, in file order.
+/// Returns nothing for languages without a pattern.
+pub fn public_items(ext: &str, content: &str) -> Vec<String> {
+    let pattern: &Regex = match ext.trim_start_matches('.') {
+        "rs" => &RUST_ITEM,
+        "py" => &PY_ITEM,
+        "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" => &JS_ITEM,
+        "go" => &GO_ITEM,
+        "java" | "kt" | "cs" | "scala" => &JVM_ITEM,
+        _ => return Vec::new(),
+    };
+
+    pattern
+        .captures_iter(content)
+        .map(|c| format!("{} {}", c[1].trim_end_matches('*'), &c[2]))
+        .collect()
+}
+
+/// One-line listing of items for a stats card, capped at MAX_CARD_ITEMS
+pub fn item_card_line(items: &[String]) -> Option<String> {
+    if items.is_empty() {
+        return None;
+    }
+
+    let mut line = format!(
+        "Public items ({}): {}",
+        items.len(),
+        items[..items.len().min(MAX_CARD_ITEMS)].join(", ")
+    );
+    if items.len() > MAX_CARD_ITEMS {
+        line.push_str(&format!(", +{} more", items.len() - MAX_CARD_ITEMS));
+    }
+    Some(line)
+}
@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+
+use crate::file_analysis::DirectoryMap;
+use crate::suggestions::{Suggestion, SuggestionMap};
+
+/// Top-level project manifests, in the order they are offered
+pub const MANIFEST_FILES: [&str; 12] = [
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "setup.py",
+    "go.mod",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "Gemfile",
+    "composer.json",
+    "CMakeLists.txt",
+    "Makefile",
+];
+
+/// Rank of a top-level overview file (README, manifests, LICENSE);
+/// lower ranks are offered first, `None` means not an overview file
+pub fn overview_rank(path: &Path) -> Option<usize> {
+    let name = path.file_name()?.to_str()?;
+    let upper = name.to_uppercase();
+
+    if upper.starts_with("README") {
+        Some(0)
+    } else if let Some(idx) = MANIFEST_FILES.iter().position(|m| *m == name) {
+        Some(1 + idx)
+    } else if upper.starts_with("LICENSE") || upper.starts_with("LICENCE") || upper == "COPYING" {
+        Some(1 + MANIFEST_FILES.len())
+    } else {
+        None
+    }
+}
+
+/// Overview files in the start directory, highest priority first
+pub fn priority_files(start_dir: &Path, dir_info: &DirectoryMap) -> Vec<PathBuf> {
+    let Some(info) = dir_info.get(start_dir) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<(usize, PathBuf)> = info
+        .files
+        .iter()
+        .filter(|f| !f.binary)
+        .filter_map(|f| overview_rank(&f.path).map(|rank| (rank, f.path.clone())))
+        .collect();
+    files.sort();
+
+    files.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Suggest reading the overview files, since they orient the LLM best per token
+pub fn priority_suggestions(files: &[PathBuf]) -> SuggestionMap {
+    files
+        .iter()
+        .map(|path| {
+            (
+                path.clone(),
+                Suggestion {
+                    action: "read".to_string(),
+                    reason: "project overview".to_string(),
+                },
+            )
+        })
+        .collect()
+}
@@ -1,11 +1,13 @@
-use anyhow::{Context, Result};
-use log::info;
+use anyhow::{Context, Result, bail};
+use globset::GlobMatcher;
+use log::{info, warn};
 use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::io::Write;
 use std::path::{Path, PathBuf};
-use tempfile::NamedTempFile;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{OnceLock, mpsc};
+use std::thread;
 
 /// Summary information for a file
 pub struct FileSummaryInfo {
@@ -31,9 +33,28 @@ pub struct NodeProcessingResult {
     pub file_summaries: Vec<FileSummaryInfo>,
 }
 
-use crate::context_files::{ContextFile, append_to_file, get_or_rotate_file};
-use crate::file_analysis::{CLAUDE_TOKEN_LIMIT, DirectoryMap, is_binary};
-use crate::summary_cache::{SummaryCache, hash_content};
+use crate::anchors::{file_hash, format_anchor};
+use crate::context_files::{
+    ContextFile, OutputSettings, append_to_file, get_or_rotate_file, next_part_name, part_name,
+    rotate_file, shown_path, split_block,
+};
+use crate::file_analysis::{
+    CLAUDE_TOKEN_LIMIT, DirectoryMap, file_tokens, is_binary, text_file_tokens, tree_totals,
+};
+use crate::formats::{Continuation, Continued};
+use crate::ledger::record_call;
+use crate::llm::{LlmProvider, MAX_SUMMARY_TOKENS};
+use crate::markup::{is_markup, summarize_markup};
+use crate::normalize::{Normalization, html_to_markdown, is_html_file, normalize_text, read_text};
+use crate::optout::remove_opted_out;
+use crate::outline::{item_card_line, public_items};
+use crate::signatures::external_signatures;
+use crate::skeleton::{annotate_cfg_gates, fold_bodies, rust_docs, skeleton, symbol_ranges};
+use crate::structured::{is_structured_file, sample_content, summarize_structured};
+use crate::summarizer::{summarizer, summarizer_requirement};
+use crate::summary_cache::{SummaryCache, hash_file_content};
+use crate::tokenizer::count_tokens;
+use crate::ui::review_summary;
 
 // Common code file extensions
 pub const CODE_EXTENSIONS: [&str; 9] = [
@@ -47,6 +68,13 @@ pub enum Action {
     Enter,
     Summarize,
     Stats,
+    Sample,
+    /// Signatures and doc comments with function bodies removed
+    Skeleton,
+    /// Only doc comments and the items they document (Rust)
+    Docs,
+    /// Chosen line ranges of a file (1-based, inclusive), the rest elided
+    Lines(Vec<(usize, usize)>),
 }
 
 impl Action {
@@ -57,9 +85,77 @@ impl Action {
             "enter" => Some(Action::Enter),
             "summarize" => Some(Action::Summarize),
             "stats" => Some(Action::Stats),
-            _ => None,
+            "sample" => Some(Action::Sample),
+            "skeleton" => Some(Action::Skeleton),
+            "docs" => Some(Action::Docs),
+            _ => s
+                .strip_prefix("lines:")
+                .and_then(parse_line_ranges)
+                .map(Action::Lines),
         }
     }
+
+    /// Action name, as stored in the cache
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Action::Read => "read",
+            Action::Exclude => "exclude",
+            Action::Enter => "enter",
+            Action::Summarize => "summarize",
+            Action::Stats => "stats",
+            Action::Sample => "sample",
+            Action::Skeleton => "skeleton",
+            Action::Docs => "docs",
+            Action::Lines(_) => "lines",
+        }
+    }
+
+    /// Action as stored in the cache, manifest and anchors, with any
+    /// arguments (`lines:120-240,400-450`)
+    pub fn spec(&self) -> String {
+        match self {
+            Action::Lines(ranges) => format!("lines:{}", format_line_ranges(ranges)),
+            action => action.as_str().to_string(),
+        }
+    }
+}
+
+/// Parse line ranges such as `120-240,400-450` (a single line may be given
+/// as `57`); ranges are sorted and overlapping ones merged
+pub fn parse_line_ranges(spec: &str) -> Option<Vec<(usize, usize)>> {
+    let mut ranges = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (start, end) = part.split_once('-').unwrap_or((part, part));
+        let (start, end): (usize, usize) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+        if start == 0 || start > end {
+            return None;
+        }
+        ranges.push((start, end));
+    }
+    let merged = merge_line_ranges(ranges);
+    (!merged.is_empty()).then_some(merged)
+}
+
+/// Sort line ranges and merge those that overlap or touch
+fn merge_line_ranges(mut ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    ranges.sort();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Line ranges in the form `parse_line_ranges` reads
+pub fn format_line_ranges(ranges: &[(usize, usize)]) -> String {
+    ranges
+        .iter()
+        .map(|(start, end)| format!("{}-{}", start, end))
+        .collect::<Vec<_>>()
+        .join(",")
 }
 
 use std::str::FromStr;
@@ -72,6 +168,21 @@ impl FromStr for Action {
     }
 }
 
+/// Package name and description from a directory's Cargo.toml
+fn crate_package(dir_path: &Path) -> Option<(String, Option<String>)> {
+    let manifest = fs::read_to_string(dir_path.join("Cargo.toml"))
+        .ok()?
+        .parse::<toml::Table>()
+        .ok()?;
+    let package = manifest.get("package")?.as_table()?;
+    let name = package.get("name")?.as_str()?.to_string();
+    let description = package
+        .get("description")
+        .and_then(|d| d.as_str())
+        .map(str::to_string);
+    Some((name, description))
+}
+
 /// Process directory contents based on action type
 pub fn process_directory_content(
     dir_path: &Path,
@@ -80,7 +191,7 @@ pub fn process_directory_content(
     action: &Action,
     total_files: usize,
     base_dir: &Path,
-    output_dir: Option<&Path>,
+    output: &OutputSettings,
 ) -> Result<Vec<ContextFile>> {
     // Check if adding directory header would exceed token limit
     let header_tokens = 200; // Rough estimate
@@ -89,44 +200,64 @@ pub fn process_directory_content(
     let mut used_files = vec![current_file.clone()];
 
     if current_file.current_tokens + header_tokens > CLAUDE_TOKEN_LIMIT {
-        current_file = get_or_rotate_file(&current_file, total_files, base_dir, output_dir)?;
+        current_file = get_or_rotate_file(&current_file, total_files, base_dir, output)?;
         used_files.push(current_file.clone());
     }
 
-    let rel_path = dir_path
-        .strip_prefix(std::env::current_dir()?)
-        .unwrap_or(dir_path);
-    let mut content = format!("\n\n## DIRECTORY: {}\n", rel_path.display());
+    let rel_path = shown_path(dir_path, base_dir, output.absolute_paths);
+    let mut content = String::new();
 
-    if action == &Action::Stats {
-        if let Some(info) = dir_info.get(dir_path) {
+    // API docs of a crate open with the crate's name and description
+    if action == &Action::Docs
+        && let Some((name, description)) = crate_package(dir_path)
+    {
+        content.push_str(&format!("Crate: {}\n", name));
+        if let Some(description) = description {
+            content.push_str(&format!("{}\n", description));
+        }
+    }
+
+    if action == &Action::Stats
+        && let Some(info) = dir_info.get(dir_path)
+    {
+        content.push_str(&format!(
+            "Files: {} ({} text)\n",
+            info.total_files,
+            info.total_files - info.binary_files
+        ));
+        content.push_str(&format!("Tokens: ~{}\n", info.tokens));
+
+        // Totals for the whole tree when it has subdirectories
+        let totals = tree_totals(dir_info, dir_path);
+        if totals.dirs > 0 {
             content.push_str(&format!(
-                "Files: {} ({} text)\n",
-                info.total_files,
-                info.total_files - info.binary_files
+                "Files in tree: {} ({} text) across {} subdirectories\nTokens in tree: ~{}\n",
+                totals.files,
+                totals.files - totals.binary_files,
+                totals.dirs,
+                totals.tokens
             ));
-            content.push_str(&format!("Tokens: ~{}\n", info.tokens));
+        }
 
-            // Add extension stats
-            let mut exts: HashMap<String, usize> = HashMap::new();
-            for file in &info.files {
-                let entry = exts.entry(file.ext.clone()).or_insert(0);
-                *entry += 1;
-            }
+        // Add extension stats
+        let mut exts: HashMap<String, usize> = HashMap::new();
+        for file in &info.files {
+            let entry = exts.entry(file.ext.clone()).or_insert(0);
+            *entry += 1;
+        }
 
-            if !exts.is_empty() {
-                content.push_str("\nExtensions:\n");
-                let mut ext_counts: Vec<(String, usize)> = exts.into_iter().collect();
-                ext_counts.sort_by(|a, b| b.1.cmp(&a.1));
+        if !exts.is_empty() {
+            content.push_str("\nExtensions:\n");
+            let mut ext_counts: Vec<(String, usize)> = exts.into_iter().collect();
+            ext_counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
 
-                for (ext, count) in ext_counts.iter().take(5) {
-                    content.push_str(&format!("  {}: {}\n", ext, count));
-                }
+            for (ext, count) in ext_counts.iter().take(5) {
+                content.push_str(&format!("  {}: {}\n", ext, count));
             }
         }
     }
 
-    append_to_file(&current_file.path, &content)?;
+    append_to_file(&current_file.path, &output.format.formatter().directory(&rel_path, &content))?;
     current_file.current_tokens += header_tokens;
 
     // Update the original context file with the latest state
@@ -135,6 +266,460 @@ pub fn process_directory_content(
     Ok(used_files)
 }
 
+/// Note on the FILE START line of a Read block whose long bodies were folded
+fn fold_note(min_lines: usize) -> String {
+    format!("[bodies over {} lines folded]", min_lines)
+}
+
+/// The fold threshold noted in a Read block, if its bodies were folded. The
+/// note is on the FILE START line, or the line of notes under a markdown
+/// heading or in an XML document's attributes
+pub fn folded_lines(block: &str) -> Option<usize> {
+    let note = block.lines().take(6).find_map(|l| l.split("bodies over ").nth(1))?;
+    note.split(' ').next()?.parse().ok()
+}
+
+/// Full content of a file between FILE START and FILE END markers, with
+/// function bodies of at least `fold` lines folded when the language allows
+fn read_block(
+    path: &Path,
+    rel_path: &Path,
+    ext: &str,
+    fold: Option<usize>,
+    normalize: &[Normalization],
+) -> String {
+    // Markup is mostly noise to a model: HTML is included as markdown
+    if is_html_file(path) {
+        return html_block(path, rel_path, normalize);
+    }
+
+    let file_content = read_text(path, normalize.contains(&Normalization::Utf8))
+        .map(|content| normalize_text(content, normalize));
+    let folded = match (&file_content, fold) {
+        (Ok(file_content), Some(min_lines)) => fold_bodies(ext, file_content, min_lines).map(|f| (f, min_lines)),
+        _ => None,
+    };
+
+    let mut content = format!("===== FILE START: {} [blob {}]", rel_path.display(), file_hash(path));
+    if let Some((_, min_lines)) = &folded {
+        content.push_str(&format!(" {}", fold_note(*min_lines)));
+    }
+    content.push_str(" =====\n");
+
+    if CODE_EXTENSIONS.contains(&format!(".{}", ext).as_str()) {
+        content.push_str(&format!("```{}\n", ext));
+    }
+
+    match (file_content, folded) {
+        (_, Some((folded, _))) => content.push_str(&folded),
+        (Ok(file_content), None) => content.push_str(&file_content),
+        (Err(e), _) => content.push_str(&format!("Error reading file: {}\n", e)),
+    }
+
+    if CODE_EXTENSIONS.contains(&format!(".{}", ext).as_str()) {
+        // Content normalized to end with a newline doesn't need another
+        if normalize.contains(&Normalization::FinalNewline) && content.ends_with('\n') {
+            content.push_str("```\n");
+        } else {
+            content.push_str("\n```\n");
+        }
+    }
+
+    content.push_str(&format!("===== FILE END: {} =====\n", rel_path.display()));
+    content
+}
+
+/// An HTML file converted to markdown between FILE START and FILE END
+/// markers, noting the size of the original
+fn html_block(path: &Path, rel_path: &Path, normalize: &[Normalization]) -> String {
+    let mut content = format!("===== FILE START: {} [blob {}]", rel_path.display(), file_hash(path));
+    match read_text(path, normalize.contains(&Normalization::Utf8)) {
+        Ok(html) => {
+            content.push_str(&format!(" [converted from HTML, {} bytes] =====\n", html.len()));
+            content.push_str(&html_to_markdown(&html));
+        }
+        Err(e) => content.push_str(&format!(" =====\nError reading file: {}\n", e)),
+    }
+    content.push_str(&format!("===== FILE END: {} =====\n", rel_path.display()));
+    content
+}
+
+/// Line standing in for lines left out of a block
+fn elision_marker(start: usize, end: usize) -> String {
+    if start == end {
+        format!("… line {} elided …\n", start)
+    } else {
+        format!("… lines {}-{} elided …\n", start, end)
+    }
+}
+
+/// The chosen line ranges of a file between FILE START and FILE END
+/// markers, with a marker for each elided stretch
+fn lines_block(path: &Path, rel_path: &Path, ext: &str, ranges: &[(usize, usize)]) -> String {
+    let file_content = match fs::read_to_string(path) {
+        Ok(file_content) => file_content,
+        Err(e) => return format!("Error reading file: {}\n", e),
+    };
+    let lines: Vec<&str> = file_content.lines().collect();
+    let total = lines.len();
+    let ranges: Vec<(usize, usize)> = ranges
+        .iter()
+        .filter(|(start, _)| *start <= total)
+        .map(|(start, end)| (*start, (*end).min(total)))
+        .collect();
+
+    let mut content = format!(
+        "===== FILE START: {} [blob {}] [lines {} of {}] =====\n",
+        rel_path.display(),
+        file_hash(path),
+        format_line_ranges(&ranges),
+        total
+    );
+    let code = CODE_EXTENSIONS.contains(&format!(".{}", ext).as_str());
+    if code {
+        content.push_str(&format!("```{}\n", ext));
+    }
+
+    let mut next = 1;
+    for (start, end) in &ranges {
+        if *start > next {
+            content.push_str(&elision_marker(next, start - 1));
+        }
+        for line in &lines[start - 1..*end] {
+            content.push_str(line);
+            content.push('\n');
+        }
+        next = end + 1;
+    }
+    if next <= total {
+        content.push_str(&elision_marker(next, total));
+    }
+
+    if code {
+        content.push_str("```\n");
+    }
+    content.push_str(&format!("===== FILE END: {} =====\n", rel_path.display()));
+    content
+}
+
+/// Render a Read block, folding function bodies of at least `fold` lines
+/// and normalizing the file's text
+pub fn render_read_block(
+    path: &Path,
+    rel_path: &Path,
+    fold: Option<usize>,
+    normalize: &[Normalization],
+) -> String {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    read_block(path, rel_path, &ext, fold, normalize)
+}
+
+/// Render the body of a file's context block for an action (without its anchor)
+pub fn render_file_block(
+    path: &Path,
+    rel_path: &Path,
+    action: &Action,
+    summary_cache: Option<&SummaryCache>,
+) -> Result<String> {
+    let metadata = fs::metadata(path).context("Failed to get file metadata")?;
+    let size = metadata.len();
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let tokens = text_file_tokens(path, size);
+
+    let content = match action {
+        Action::Read => read_block(path, rel_path, &ext, None, &[]),
+        Action::Summarize => {
+            let mut content = format!("# Summary of {}\n", rel_path.display());
+            match fs::read_to_string(path) {
+                Ok(file_content) => {
+                    content.push_str(&summarize_file(
+                        path,
+                        rel_path,
+                        &ext,
+                        &file_content,
+                        summary_cache,
+                    )?);
+                }
+                Err(e) => {
+                    content.push_str(&format!("Error reading file: {}\n", e));
+                }
+            }
+            content
+        }
+        Action::Sample => {
+            let sample = match fs::read_to_string(path) {
+                Ok(file_content) => sample_content(path, &file_content),
+                Err(e) => format!("Error reading file: {}\n", e),
+            };
+            format!("# Sample of {}\n{}", rel_path.display(), sample)
+        }
+        Action::Skeleton => {
+            let mut content = format!("# Skeleton of {}\n", rel_path.display());
+            match fs::read_to_string(path) {
+                Ok(file_content) => match skeleton(&ext, &file_content) {
+                    Some(outline) if ext == "rs" => content.push_str(&format!(
+                        "```{}\n{}```\n",
+                        ext,
+                        annotate_cfg_gates(&outline, CFG_FEATURES.get())
+                    )),
+                    Some(outline) => content.push_str(&format!("```{}\n{}```\n", ext, outline)),
+                    None => content.push_str(&format!(
+                        "(No skeleton for .{} files; showing a sample)\n{}",
+                        ext,
+                        sample_content(path, &file_content)
+                    )),
+                },
+                Err(e) => content.push_str(&format!("Error reading file: {}\n", e)),
+            }
+            content
+        }
+        Action::Docs => {
+            let mut content = format!("# API docs of {}\n", rel_path.display());
+            match fs::read_to_string(path) {
+                Ok(file_content) if ext == "rs" => {
+                    content.push_str(&format!("```rust\n{}```\n", rust_docs(&file_content)))
+                }
+                Ok(file_content) => match skeleton(&ext, &file_content) {
+                    Some(outline) => content.push_str(&format!(
+                        "(No doc extraction for .{} files; showing a skeleton)\n```{}\n{}```\n",
+                        ext, ext, outline
+                    )),
+                    None => content.push_str(&format!(
+                        "(No doc extraction for .{} files; showing a sample)\n{}",
+                        ext,
+                        sample_content(path, &file_content)
+                    )),
+                },
+                Err(e) => content.push_str(&format!("Error reading file: {}\n", e)),
+            }
+            content
+        }
+        Action::Stats => {
+            let mut content = format!(
+                "# File: {}\nSize: {} bytes\nEstimated tokens: {}\nExtension: .{}\n",
+                rel_path.display(),
+                size,
+                tokens,
+                ext
+            );
+            // List what the file defines, for languages we can outline
+            if let Ok(file_content) = fs::read_to_string(path)
+                && let Some(line) = item_card_line(&public_items(&ext, &file_content))
+            {
+                content.push_str(&line);
+                content.push('\n');
+            }
+            // Markup gets its root element and element counts
+            if is_markup(path)
+                && let Ok(file_content) = fs::read_to_string(path)
+                && let Some(summary) = summarize_markup(&file_content)
+            {
+                content.push_str(&summary.render_header());
+            }
+            content
+        }
+        Action::Lines(ranges) => lines_block(path, rel_path, &ext, ranges),
+        Action::Exclude | Action::Enter => String::new(),
+    };
+
+    Ok(content)
+}
+
+/// Features Rust skeletons resolve `#[cfg(feature = ...)]` gates against
+static CFG_FEATURES: OnceLock<BTreeSet<String>> = OnceLock::new();
+
+/// Drop items gated off by these features from Rust skeletons
+pub fn set_cfg_features(features: &[String]) {
+    if CFG_FEATURES.set(features.iter().cloned().collect()).is_err() {
+        warn!("The cfg features were already set");
+    }
+}
+
+/// Instruction summaries are asked for with, unless the config gives one
+pub const DEFAULT_SUMMARY_PROMPT: &str = "Summarize this file concisely:";
+
+/// The configured summary instruction for this run
+static SUMMARY_PROMPT: OnceLock<String> = OnceLock::new();
+
+/// Ask for summaries with this instruction instead of the default
+pub fn set_summary_prompt(prompt: &str) {
+    if SUMMARY_PROMPT.set(prompt.trim_end().to_string()).is_err() {
+        warn!("The summary prompt was already set");
+    }
+}
+
+/// Generate a new summary for a file's content, with an optional extra
+/// instruction for the summarizer
+pub fn generate_summary(ext: &str, file_content: &str, tweak: Option<&str>) -> Result<String> {
+    let Some(provider) = summarizer() else {
+        bail!("no summarizer available ({})", summarizer_requirement());
+    };
+    let instruction = SUMMARY_PROMPT.get().map_or(DEFAULT_SUMMARY_PROMPT, String::as_str);
+    let mut prompt = format!("{}\n", instruction);
+    if let Some(tweak) = tweak {
+        prompt.push_str(&format!("{}\n", tweak));
+    }
+    prompt.push_str("\n\n");
+
+    let content = fit_to_context(provider, &prompt, file_content);
+    if CODE_EXTENSIONS.contains(&format!(".{}", ext).as_str()) {
+        prompt.push_str(&format!("```{}\n{}\n```\n", ext, content));
+    } else {
+        prompt.push_str(&content);
+    }
+
+    let completion = provider.generate_summary(&prompt)?;
+    record_call(provider.model(), completion.input_tokens, completion.output_tokens);
+    Ok(format!("{}\n", completion.text))
+}
+
+/// A file's content, cut at a line boundary when it wouldn't fit in the
+/// provider's context window after the instruction, leaving room for the
+/// summary
+fn fit_to_context(provider: &dyn LlmProvider, instruction: &str, file_content: &str) -> String {
+    let budget = provider
+        .max_context()
+        .saturating_sub(MAX_SUMMARY_TOKENS + provider.count_tokens(instruction));
+    if provider.count_tokens(file_content) <= budget {
+        return file_content.to_string();
+    }
+    let mut used = 0;
+    let kept: Vec<&str> = file_content
+        .lines()
+        .take_while(|line| {
+            used += provider.count_tokens(line) + 1;
+            used <= budget
+        })
+        .collect();
+    warn!(
+        "Summarizing only the first {} lines of a file too long for {}",
+        kept.len(),
+        provider.model()
+    );
+    format!("{}\n(The rest of the file was cut to fit the model's context.)", kept.join("\n"))
+}
+
+/// What is written for a file whose summary couldn't be generated; it isn't
+/// cached, so the next run tries again
+fn summary_fallback(error: &anyhow::Error) -> String {
+    format!("(No summary: {:#})\n", error)
+}
+
+/// Produce the summary text for a file's content
+fn summarize_file(
+    path: &Path,
+    rel_path: &Path,
+    ext: &str,
+    file_content: &str,
+    summary_cache: Option<&SummaryCache>,
+) -> Result<String> {
+    // Calculate content hash
+    let content_hash = hash_file_content(path, file_content);
+
+    // Structured config files get a schema-style key tree instead of prose
+    let structured_summary = if is_structured_file(path) {
+        match summarize_structured(path, file_content) {
+            Ok(tree) => Some(tree),
+            Err(e) => {
+                warn!("Failed to parse {}: {}", rel_path.display(), e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // Check if we have a cached summary
+    let summary = if let Some(tree) = structured_summary {
+        info!("Rendering key tree for: {}", rel_path.display());
+        format!("Structure (keys, types and counts):\n{}", tree)
+    } else if let Some(cache) = summary_cache {
+        if let Some(cached_summary) = cache.lookup_summary(path, &content_hash) {
+            info!("Using cached summary for: {}", rel_path.display());
+            format!("{}\n(Cached summary)\n", cached_summary)
+        } else {
+            // No cached summary, generate a new one
+            generate_summary(ext, file_content, None)
+                .unwrap_or_else(|e| summary_fallback(&e))
+        }
+    } else {
+        // No cache available, generate a summary without caching
+        generate_summary(ext, file_content, None).unwrap_or_else(|e| summary_fallback(&e))
+    };
+
+    Ok(summary)
+}
+
+/// Summaries generated at once by `summarize-all` unless told otherwise
+pub const DEFAULT_SUMMARY_JOBS: usize = 4;
+
+/// Files `summarize-all` would summarize: text files that aren't opted out
+/// with `marker` (those are dropped from `dir_info`) and that match one of
+/// `filters`, relative to `start_dir`, when there are any. Sorted.
+pub fn summary_targets(
+    start_dir: &Path,
+    dir_info: &mut DirectoryMap,
+    filters: &[GlobMatcher],
+    marker: &str,
+) -> Vec<PathBuf> {
+    remove_opted_out(dir_info, marker);
+    let mut files: Vec<PathBuf> = dir_info
+        .values()
+        .flat_map(|info| info.files.iter())
+        .filter(|f| !f.binary && !is_structured_file(&f.path))
+        .map(|f| f.path.clone())
+        .filter(|path| {
+            let rel_path = path.strip_prefix(start_dir).unwrap_or(path);
+            filters.is_empty() || filters.iter().any(|m| m.is_match(rel_path))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Generate summaries of files on `jobs` threads, passing each result to
+/// `done` (on the calling thread) as it finishes. Files are read as text and
+/// summarized without a cache lookup.
+pub fn summarize_files(files: &[PathBuf], jobs: usize, mut done: impl FnMut(&Path, Result<FileSummaryInfo>)) {
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, files.len().max(1)) {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || {
+                while let Some(path) = files.get(next.fetch_add(1, AtomicOrdering::Relaxed)) {
+                    let result = fs::read_to_string(path)
+                        .with_context(|| format!("Failed to read {}", path.display()))
+                        .and_then(|content| {
+                            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+                            Ok(FileSummaryInfo {
+                                path: path.clone(),
+                                content_hash: hash_file_content(path, &content),
+                                summary: generate_summary(ext, &content, None)?.trim_end().to_string(),
+                            })
+                        });
+                    if sender.send((path, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+        for (path, result) in receiver {
+            done(path, result);
+        }
+    });
+}
+
 /// Process a single file based on the action
 #[allow(clippy::too_many_arguments)]
 fn process_file(
@@ -146,171 +731,192 @@ fn process_file(
     processed: &HashSet<PathBuf>,
     total_files: usize,
     base_dir: &Path,
-    output_dir: Option<&Path>,
+    output: &OutputSettings,
     summary_cache: Option<&SummaryCache>,
+    known_tokens: Option<usize>,
 ) -> Result<NodeProcessingResult> {
     let mut total_tokens = total_tokens;
     let mut included_files = included_files.clone();
     let mut processed = processed.clone();
     let mut context_files = vec![context_file.clone()];
 
-    let rel_path = path.strip_prefix(std::env::current_dir()?).unwrap_or(path);
+    let rel_path = &shown_path(path, base_dir, output.absolute_paths);
     let metadata = fs::metadata(path).context("Failed to get file metadata")?;
     let size = metadata.len();
-    let tokens = (size as f64 * crate::file_analysis::TOKENS_PER_BYTE).ceil() as usize;
-    let ext = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("")
-        .to_lowercase();
-
-    match action {
-        Action::Read => {
-            // Check if adding this file would exceed token limit
-            if context_file.current_tokens + tokens > CLAUDE_TOKEN_LIMIT {
-                *context_file =
-                    get_or_rotate_file(context_file, total_files, base_dir, output_dir)?;
-                context_files.push(context_file.clone());
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    // Prefer the analyzed (possibly exact) count over a fresh estimate
+    let tokens = known_tokens.unwrap_or_else(|| text_file_tokens(path, size));
+
+    // With --symbols, a code file is read as the lines of its matching
+    // declarations, and left out when nothing in it matches
+    let selected = match &output.symbols {
+        Some(symbols) if *action == Action::Read => fs::read_to_string(path)
+            .ok()
+            .and_then(|content| symbol_ranges(ext, &content, symbols))
+            .map(merge_line_ranges),
+        _ => None,
+    };
+    if selected.as_ref().is_some_and(Vec::is_empty) {
+        info!("No matching symbols in: {}", rel_path.display());
+        processed.insert(path.to_path_buf());
+        return Ok(NodeProcessingResult {
+            total_tokens,
+            processed,
+            included_files,
+            context_files,
+            file_summaries: Vec::new(),
+        });
+    }
+    let selected = selected.map(Action::Lines);
+    let action = selected.as_ref().unwrap_or(action);
+
+    let block_tokens = match action {
+        Action::Read => tokens,
+        Action::Summarize => tokens / 4, // Rough estimate
+        Action::Stats => 100,            // Rough estimate
+        _ => 0,
+    };
+
+    // A summary missing from the cache is generated here, so the text written
+    // is the text cached; with --edit-summaries it is checked and edited first
+    let mut new_summary = None;
+    let mut fallback = None;
+    if *action == Action::Summarize
+        && !is_structured_file(path)
+        && let Ok(file_content) = fs::read_to_string(path)
+        && summary_cache.is_none_or(|c| c.get_summary(path, &hash_file_content(path, &file_content)).is_none())
+    {
+        if let Some(cache) = summary_cache {
+            cache.record_lookup(false);
+        }
+        match generate_summary(ext, &file_content, None) {
+            Ok(summary) if output.edit_summaries => {
+                new_summary = Some(review_summary(rel_path, summary, |tweak| {
+                    generate_summary(ext, &file_content, Some(tweak))
+                })?);
             }
-
-            info!("Reading: {}", rel_path.display());
-
-            let mut content = format!("\n\n===== FILE START: {} =====\n", rel_path.display());
-
-            if CODE_EXTENSIONS.contains(&format!(".{}", ext).as_str()) {
-                content.push_str(&format!("```{}\n", ext));
+            Ok(summary) => new_summary = Some(summary),
+            Err(e) => {
+                warn!("Failed to summarize {}: {:#}", rel_path.display(), e);
+                fallback = Some(summary_fallback(&e));
             }
+        }
+    }
 
-            match fs::read_to_string(path) {
-                Ok(file_content) => {
-                    content.push_str(&file_content);
-                }
-                Err(e) => {
-                    content.push_str(&format!("Error reading file: {}\n", e));
+    match action {
+        Action::Read
+        | Action::Summarize
+        | Action::Sample
+        | Action::Skeleton
+        | Action::Docs
+        | Action::Lines(_)
+        | Action::Stats => {
+            let mut body = match new_summary.as_ref().or(fallback.as_ref()) {
+                Some(summary) => format!("# Summary of {}\n{}", rel_path.display(), summary),
+                None if *action == Action::Read => {
+                    render_read_block(path, rel_path, output.fold_bodies, &output.normalize)
                 }
+                None => render_file_block(path, rel_path, action, summary_cache)?,
+            };
+            if output.normalize.contains(&Normalization::LineEndings) {
+                body = body.replace("\r\n", "\n");
             }
-
-            if CODE_EXTENSIONS.contains(&format!(".{}", ext).as_str()) {
-                content.push_str("\n```\n");
+            if output.external_signatures
+                && ext == "rs"
+                && matches!(action, Action::Skeleton | Action::Docs)
+                && let Ok(file_content) = fs::read_to_string(path)
+                && let Some(signatures) = external_signatures(&file_content)
+            {
+                body.push_str(&signatures);
             }
+            // Folded and converted files are shorter than their estimate, and
+            // summaries are only guessed at before they are written
+            let rewritten = *action == Action::Read && (output.fold_bodies.is_some() || is_html_file(path));
+            let block_tokens = if rewritten
+                || matches!(
+                    action,
+                    Action::Summarize | Action::Sample | Action::Skeleton | Action::Docs | Action::Lines(_)
+                )
+            {
+                count_tokens(&body)
+            } else {
+                block_tokens
+            };
 
-            content.push_str(&format!("===== FILE END: {} =====\n", rel_path.display()));
-
-            append_to_file(&context_file.path, &content)?;
-
-            // Update tracking variables
-            total_tokens += tokens;
-            context_file.current_tokens += tokens;
-            included_files.insert(path.to_path_buf());
-            processed.insert(path.to_path_buf());
-        }
-        Action::Summarize => {
-            // Check if adding summary would exceed token limit
-            let summary_tokens = tokens / 4; // Rough estimate
-
-            if context_file.current_tokens + summary_tokens > CLAUDE_TOKEN_LIMIT {
-                *context_file =
-                    get_or_rotate_file(context_file, total_files, base_dir, output_dir)?;
+            // Start the next part when this block would take the current one
+            // past its budget; an empty part takes it whatever its size
+            if context_file.current_tokens > 0
+                && context_file.current_tokens + block_tokens > CLAUDE_TOKEN_LIMIT
+            {
+                *context_file = rotate_file(context_file, total_files, base_dir, output)?;
                 context_files.push(context_file.clone());
             }
 
-            info!("Summarizing: {}", rel_path.display());
-
-            let content = format!("\n\n# Summary of {}\n", rel_path.display());
-            append_to_file(&context_file.path, &content)?;
+            match action {
+                Action::Read => info!("Reading: {}", rel_path.display()),
+                Action::Summarize => info!("Summarizing: {}", rel_path.display()),
+                Action::Sample => info!("Sampling: {}", rel_path.display()),
+                Action::Skeleton => info!("Outlining: {}", rel_path.display()),
+                Action::Docs => info!("Extracting docs: {}", rel_path.display()),
+                Action::Lines(_) => info!("Reading lines of: {}", rel_path.display()),
+                _ => info!("Stats for: {}", rel_path.display()),
+            }
 
-            // Get file content
-            let file_content = match fs::read_to_string(path) {
-                Ok(content) => content,
-                Err(e) => {
-                    let error_msg = format!("Error reading file: {}\n", e);
-                    append_to_file(&context_file.path, &error_msg)?;
-                    return Ok(NodeProcessingResult {
-                        total_tokens,
-                        processed,
-                        included_files,
-                        context_files,
-                        file_summaries: Vec::new(),
+            // The anchor lets `update` find and refresh this block later
+            let block_path = path.strip_prefix(base_dir).unwrap_or(path);
+            let anchor = format_anchor(block_path, &file_hash(path), action);
+            let block = format!("{}\n{}", anchor, body);
+            let formatter = output.format.formatter();
+            if block_tokens > CLAUDE_TOKEN_LIMIT {
+                // Too big for any part: split it, with markers pairing up the pieces
+                let chunks = split_block(
+                    &block,
+                    CLAUDE_TOKEN_LIMIT.saturating_sub(context_file.current_tokens),
+                    CLAUDE_TOKEN_LIMIT,
+                    output.chunk_overlap_tokens,
+                );
+                let hash = file_hash(path);
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let previous = context_file.clone();
+                    if i > 0 {
+                        *context_file = rotate_file(&previous, total_files, base_dir, output)?;
+                        context_files.push(context_file.clone());
+                    }
+                    // The rest of the block goes in the next part
+                    let continued = (i + 1 < chunks.len()).then(|| Continued {
+                        part: next_part_name(context_file, base_dir, output),
+                        marker: format!("{}-{}", hash, i + 1),
                     });
-                }
-            };
-            
-            // Calculate content hash
-            let content_hash = hash_content(&file_content);
-            
-            // Check if we have a cached summary
-            let summary = if let Some(cache) = summary_cache {
-                if let Some(cached_summary) = cache.get_summary(path, &content_hash) {
-                    info!("Using cached summary for: {}", rel_path.display());
-                    format!("{}\n(Cached summary)\n", cached_summary)
-                } else {
-                    // No cached summary, generate a new one
-                    let mut temp_file = NamedTempFile::new()?;
-                    writeln!(temp_file, "Summarize this file concisely:\n\n")?;
-                    
-                    if CODE_EXTENSIONS.contains(&format!(".{}", ext).as_str()) {
-                        writeln!(temp_file, "```{}\n{}\n```\n", ext, file_content)?;
+                    let text = if i == 0 {
+                        formatter.file(&chunk.text, continued.as_ref())
                     } else {
-                        write!(temp_file, "{}", file_content)?;
-                    }
-                    
-                    temp_file.flush()?;
-                    
-                    // Run Claude if available (in real implementation)
-                    // For now, use a placeholder
-                    let new_summary = "Summary would be generated by claude if available.\n".to_string();
-                    
-                    // The summary will be stored in the cache later
-                    
-                    new_summary
+                        formatter.continuation(&Continuation {
+                            path: block_path.display().to_string(),
+                            previous_part: part_name(&previous.path),
+                            marker: format!("{}-{}", hash, i),
+                            note: (chunk.overlap_lines > 0).then(|| {
+                                format!(
+                                    "(The first {} lines repeat the end of the previous part.)",
+                                    chunk.overlap_lines
+                                )
+                            }),
+                            content: chunk.text.clone(),
+                            continued,
+                        })
+                    };
+                    append_to_file(&context_file.path, &text)?;
+                    context_file.current_tokens += count_tokens(&chunk.text);
                 }
             } else {
-                // No cache available, generate a summary without caching
-                let mut temp_file = NamedTempFile::new()?;
-                writeln!(temp_file, "Summarize this file concisely:\n\n")?;
-                
-                if CODE_EXTENSIONS.contains(&format!(".{}", ext).as_str()) {
-                    writeln!(temp_file, "```{}\n{}\n```\n", ext, file_content)?;
-                } else {
-                    write!(temp_file, "{}", file_content)?;
-                }
-                
-                temp_file.flush()?;
-                
-                "Summary would be generated by claude if available.\n".to_string()
-            };
-            
-            // Add the summary to the context file
-            append_to_file(&context_file.path, &summary)?;
+                append_to_file(&context_file.path, &formatter.file(&block, None))?;
+                context_file.current_tokens += block_tokens;
+            }
 
             // Update tracking variables
-            total_tokens += summary_tokens;
-            context_file.current_tokens += summary_tokens;
-            included_files.insert(path.to_path_buf());
-            processed.insert(path.to_path_buf());
-        }
-        Action::Stats => {
-            // Check if adding stats would exceed token limit
-            let stats_tokens = 100; // Rough estimate
-
-            if context_file.current_tokens + stats_tokens > CLAUDE_TOKEN_LIMIT {
-                *context_file =
-                    get_or_rotate_file(context_file, total_files, base_dir, output_dir)?;
-                context_files.push(context_file.clone());
+            if *action != Action::Stats {
+                total_tokens += block_tokens;
+                included_files.insert(path.to_path_buf());
             }
-
-            info!("Stats for: {}", rel_path.display());
-
-            let content = format!(
-                "\n\n# File: {}\nSize: {} bytes\nEstimated tokens: {}\nExtension: .{}\n",
-                rel_path.display(),
-                size,
-                tokens,
-                ext
-            );
-
-            append_to_file(&context_file.path, &content)?;
-            context_file.current_tokens += stats_tokens;
             processed.insert(path.to_path_buf());
         }
         _ => {}
@@ -319,32 +925,18 @@ fn process_file(
     // Create vector to store any new summaries created
     let mut file_summaries = Vec::new();
     
-    // If we created a new summary, add it to the file_summaries
-    if let Action::Summarize = action {
-        if let Ok(file_content) = fs::read_to_string(path) {
-            let content_hash = hash_content(&file_content);
-            
-            // Only add if it wasn't already in the cache
-            if let Some(cache) = summary_cache {
-                if cache.get_summary(path, &content_hash).is_none() {
-                    // We generated a new summary that wasn't in the cache
-                    file_summaries.push(FileSummaryInfo {
-                        path: path.to_path_buf(),
-                        content_hash,
-                        summary: "Summary would be generated by claude if available.".to_string(),
-                    });
-                }
-            } else {
-                // No cache provided, always add the summary 
-                file_summaries.push(FileSummaryInfo {
-                    path: path.to_path_buf(),
-                    content_hash,
-                    summary: "Summary would be generated by claude if available.".to_string(),
-                });
-            }
-        }
+    // A newly generated summary goes in the cache as written, including any
+    // edits (structured files are rendered deterministically and never cached)
+    if let Some(summary) = new_summary
+        && let Ok(file_content) = fs::read_to_string(path)
+    {
+        file_summaries.push(FileSummaryInfo {
+            path: path.to_path_buf(),
+            content_hash: hash_file_content(path, &file_content),
+            summary: summary.trim_end().to_string(),
+        });
     }
-    
+
     Ok(NodeProcessingResult {
         total_tokens,
         processed,
@@ -367,7 +959,7 @@ pub fn process_node(
     action: Action,
     total_files: usize,
     base_dir: &Path,
-    output_dir: Option<&Path>,
+    output: &OutputSettings,
     summary_cache: Option<&SummaryCache>,
 ) -> Result<NodeProcessingResult> {
     let mut total_tokens = total_tokens;
@@ -402,7 +994,13 @@ pub fn process_node(
 
         // Process file based on action
         match action {
-            Action::Read | Action::Summarize | Action::Stats => {
+            Action::Read
+            | Action::Summarize
+            | Action::Stats
+            | Action::Sample
+            | Action::Skeleton
+            | Action::Docs
+            | Action::Lines(_) => {
                 let result = process_file(
                     path,
                     context_file,
@@ -412,8 +1010,9 @@ pub fn process_node(
                     &processed,
                     total_files,
                     base_dir,
-                    output_dir,
+                    output,
                     summary_cache,
+                    file_tokens(dir_info, path),
                 )?;
                 total_tokens = result.total_tokens;
                 included_files = result.included_files;
@@ -422,12 +1021,7 @@ pub fn process_node(
                 file_summaries.extend(result.file_summaries);
             }
             Action::Exclude => {
-                info!(
-                    "Excluding: {}",
-                    path.strip_prefix(std::env::current_dir()?)
-                        .unwrap_or(path)
-                        .display()
-                );
+                info!("Excluding: {}", shown_path(path, base_dir, output.absolute_paths).display());
                 processed.insert(path.to_path_buf());
             }
             _ => {}
@@ -457,7 +1051,12 @@ pub fn process_node(
 
         // Process directory based on action
         match action {
-            Action::Read | Action::Summarize | Action::Stats => {
+            Action::Read
+            | Action::Summarize
+            | Action::Stats
+            | Action::Sample
+            | Action::Skeleton
+            | Action::Docs => {
                 info!("Processing directory: {}", path.display());
 
                 // Add directory header
@@ -468,7 +1067,7 @@ pub fn process_node(
                     &action,
                     total_files,
                     base_dir,
-                    output_dir,
+                    output,
                 )?;
 
                 all_context_files.extend(used_files.into_iter().skip(1)); // Skip first as it's already in the list
@@ -488,7 +1087,7 @@ pub fn process_node(
                                 action.clone(),
                                 total_files,
                                 base_dir,
-                                output_dir,
+                                output,
                                 summary_cache,
                             )?;
                             total_tokens = result.total_tokens;
@@ -513,7 +1112,7 @@ pub fn process_node(
                                 action.clone(),
                                 total_files,
                                 base_dir,
-                                output_dir,
+                                output,
                                 summary_cache,
                             )?;
                             total_tokens = result.total_tokens;
@@ -558,14 +1157,6 @@ pub fn process_node(
             unique_context_files.push(file);
         }
     }
-    
-    // Collect all file summaries from child nodes
-    let file_summaries = Vec::new();
-    
-    if path.is_file() && matches!(action, Action::Summarize) {
-        // This has already been handled in the file processing
-        // The file_summaries will be populated by the file processing code
-    }
 
     Ok(NodeProcessingResult {
         total_tokens,
@@ -576,37 +1167,56 @@ pub fn process_node(
     })
 }
 
-/// Apply actions from the cache to matching files
+/// Cached paths in the order their actions are applied
+pub fn ordered_cache_paths(cache: &HashMap<PathBuf, String>) -> Vec<&PathBuf> {
+    // Sort cached paths by directories first (helps processing in hierarchical order)
+    let mut paths: Vec<_> = cache.keys().collect();
+    paths.sort_by(|a, b| {
+        if a.is_file() && b.is_dir() {
+            Ordering::Greater
+        } else if a.is_dir() && b.is_file() {
+            Ordering::Less
+        } else {
+            a.cmp(b)
+        }
+    });
+    paths
+}
+
+/// Action applied instead of a cached read of a file over the per-file cap
+pub const OVERSIZED_READ_ACTION: Action = Action::Summarize;
+
+/// A cached action replaced because the file outgrew the per-file cap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Downgrade {
+    pub path: PathBuf,
+    pub tokens: usize,
+    pub requested: Action,
+    pub applied: Action,
+}
+
+/// Apply actions from the cache to matching files. Cached reads of files
+/// over `file_token_cap` tokens are summarized instead and reported.
 #[allow(clippy::too_many_arguments)]
 pub fn apply_cached_actions(
     dir_info: &DirectoryMap,
     context_file: &mut ContextFile,
     _max_tokens: usize,
+    file_token_cap: usize,
     cache: &HashMap<PathBuf, String>,
     total_files: usize,
     base_dir: &Path,
-    output_dir: Option<&Path>,
+    output: &OutputSettings,
     summary_cache: Option<&SummaryCache>,
-) -> Result<NodeProcessingResult> {
+) -> Result<(NodeProcessingResult, Vec<Downgrade>)> {
     let mut processed = HashSet::new();
     let mut included_files = HashSet::new();
     let mut total_tokens = 0;
     let mut all_context_files = vec![context_file.clone()];
     let mut file_summaries = Vec::new();
+    let mut downgrades = Vec::new();
 
-    // Sort cached paths by directories first (helps processing in hierarchical order)
-    let mut paths: Vec<_> = cache.keys().collect();
-    paths.sort_by(|a, b| {
-        if a.is_file() && b.is_dir() {
-            Ordering::Greater
-        } else if a.is_dir() && b.is_file() {
-            Ordering::Less
-        } else {
-            a.cmp(b)
-        }
-    });
-
-    for path in paths {
+    for path in ordered_cache_paths(cache) {
         if !path.exists() {
             info!(
                 "Skipping cached path that no longer exists: {}",
@@ -615,34 +1225,58 @@ pub fn apply_cached_actions(
             continue;
         }
 
-        if let Some(action_str) = cache.get(path) {
-            if let Some(action) = Action::parse_str(action_str) {
-                info!(
-                    "Applying cached action '{}' to {}",
-                    action_str,
-                    path.display()
-                );
-                let result = process_node(
-                    path,
-                    dir_info,
-                    context_file,
-                    _max_tokens,
-                    total_tokens,
-                    &included_files,
-                    &processed,
-                    action,
-                    total_files,
-                    base_dir,
-                    output_dir,
-                    summary_cache,
-                )?;
-
-                total_tokens = result.total_tokens;
-                processed = result.processed;
-                included_files = result.included_files;
-                all_context_files.extend(result.context_files.into_iter().skip(1)); // Skip first as it's already in the list
-                file_summaries.extend(result.file_summaries);
+        if let Some(action_str) = cache.get(path)
+            && let Some(mut action) = Action::parse_str(action_str)
+        {
+            // The file may have grown a lot since it was chosen for reading
+            if action == Action::Read && path.is_file() {
+                let tokens = file_tokens(dir_info, path).unwrap_or_else(|| {
+                    let size = fs::metadata(path).map_or(0, |m| m.len());
+                    text_file_tokens(path, size)
+                });
+                if tokens > file_token_cap {
+                    warn!(
+                        "{} has ~{} tokens, over the per-file cap of {}: {} instead of read",
+                        path.display(),
+                        tokens,
+                        file_token_cap,
+                        OVERSIZED_READ_ACTION.as_str()
+                    );
+                    downgrades.push(Downgrade {
+                        path: path.clone(),
+                        tokens,
+                        requested: action,
+                        applied: OVERSIZED_READ_ACTION,
+                    });
+                    action = OVERSIZED_READ_ACTION;
+                }
             }
+
+            info!(
+                "Applying cached action '{}' to {}",
+                action.as_str(),
+                path.display()
+            );
+            let result = process_node(
+                path,
+                dir_info,
+                context_file,
+                _max_tokens,
+                total_tokens,
+                &included_files,
+                &processed,
+                action,
+                total_files,
+                base_dir,
+                output,
+                summary_cache,
+            )?;
+
+            total_tokens = result.total_tokens;
+            processed = result.processed;
+            included_files = result.included_files;
+            all_context_files.extend(result.context_files.into_iter().skip(1)); // Skip first as it's already in the list
+            file_summaries.extend(result.file_summaries);
         }
     }
 
@@ -655,15 +1289,15 @@ pub fn apply_cached_actions(
             unique_context_files.push(file);
         }
     }
-    
-    // Collect file summaries from all child processes
-    let file_summaries = Vec::new();
 
-    Ok(NodeProcessingResult {
-        total_tokens,
-        processed, 
-        included_files,
-        context_files: unique_context_files,
-        file_summaries,
-    })
+    Ok((
+        NodeProcessingResult {
+            total_tokens,
+            processed,
+            included_files,
+            context_files: unique_context_files,
+            file_summaries,
+        },
+        downgrades,
+    ))
 }
@@ -0,0 +1,267 @@
+use anyhow::Result;
+use log::{error, warn};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use walkdir::WalkDir;
+
+use crate::context_files::{OutputSettings, create_context_file};
+use crate::file_analysis::{DirectoryMap, FileInfo, is_binary, is_skipped, text_file_tokens};
+use crate::processing::{Action, generate_summary, process_node};
+use crate::summary_cache::{hash_file_content, load_summary_cache};
+use crate::ui::{Style, paint, print_field, print_title};
+
+/// Stages of a run that are timed separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    Walk,
+    BinaryDetection,
+    TokenEstimation,
+    Summarization,
+    Emission,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 5] = [
+        Phase::Walk,
+        Phase::BinaryDetection,
+        Phase::TokenEstimation,
+        Phase::Summarization,
+        Phase::Emission,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Walk => "Walk",
+            Phase::BinaryDetection => "Binary detection",
+            Phase::TokenEstimation => "Token estimation",
+            Phase::Summarization => "Summarization",
+            Phase::Emission => "Emission",
+        }
+    }
+}
+
+/// Time spent on a single file, by phase
+#[derive(Debug, Clone)]
+pub struct FileTiming {
+    pub path: PathBuf,
+    pub phases: HashMap<Phase, Duration>,
+}
+
+impl FileTiming {
+    pub fn total(&self) -> Duration {
+        self.phases.values().sum()
+    }
+
+    /// The phase this file spent the most time in
+    pub fn slowest_phase(&self) -> Option<Phase> {
+        Phase::ALL
+            .into_iter()
+            .filter(|p| self.phases.contains_key(p))
+            .max_by_key(|p| self.phases[p])
+    }
+}
+
+/// Timings of a profiled run
+#[derive(Debug, Default)]
+pub struct ProfileReport {
+    pub phases: HashMap<Phase, Duration>,
+    pub files: Vec<FileTiming>,
+    pub binary_files: usize,
+}
+
+impl ProfileReport {
+    /// Total time across all phases
+    pub fn total(&self) -> Duration {
+        self.phases.values().sum()
+    }
+
+    /// The `n` files that took the longest overall, slowest first
+    pub fn slowest_files(&self, n: usize) -> Vec<&FileTiming> {
+        let mut files: Vec<&FileTiming> = self.files.iter().collect();
+        files.sort_by_key(|f| std::cmp::Reverse(f.total()));
+        files.truncate(n);
+        files
+    }
+}
+
+/// Run `f`, adding its duration to the phase total and the file's timing
+fn timed<T>(
+    report: &mut ProfileReport,
+    timing: &mut FileTiming,
+    phase: Phase,
+    f: impl FnOnce() -> T,
+) -> T {
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    *report.phases.entry(phase).or_default() += elapsed;
+    *timing.phases.entry(phase).or_default() += elapsed;
+    result
+}
+
+/// Time each phase of a run over a directory without prompting: walk the
+/// tree, detect binaries and count tokens as analysis does, then read every
+/// text file into parts as if all of them had been selected. The parts are
+/// written to a temporary directory. Summarization times the summary cache
+/// lookups; with `summarize`, misses are also summarized through the
+/// configured summarizer, which makes live calls. New summaries aren't cached.
+pub fn profile_directory(start_dir: &Path, exclude_patterns: &[String], summarize: bool) -> Result<ProfileReport> {
+    let mut report = ProfileReport::default();
+    let summary_cache = load_summary_cache(start_dir)?;
+
+    let start = Instant::now();
+    let paths: Vec<PathBuf> = WalkDir::new(start_dir)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|e| !is_skipped(e.path(), start_dir, e.file_type().is_dir(), exclude_patterns))
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                error!("Error accessing entry: {}", e);
+                None
+            }
+        })
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect();
+    report.phases.insert(Phase::Walk, start.elapsed());
+
+    let mut dir_info = DirectoryMap::new();
+    let mut text_files = Vec::new();
+    for path in paths {
+        let mut timing = FileTiming {
+            path: path.clone(),
+            phases: HashMap::new(),
+        };
+
+        let binary = timed(&mut report, &mut timing, Phase::BinaryDetection, || {
+            is_binary(&path)
+        });
+        if binary.unwrap_or(true) {
+            report.binary_files += 1;
+            report.files.push(timing);
+            continue;
+        }
+
+        let file_info = timed(&mut report, &mut timing, Phase::TokenEstimation, || {
+            let size = fs::metadata(&path)?.len();
+            let ext = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            let tokens = text_file_tokens(&path, size);
+            Ok::<_, std::io::Error>(FileInfo {
+                path: path.clone(),
+                binary: false,
+                tokens,
+                size,
+                ext: format!(".{}", ext),
+            })
+        })?;
+
+        let failed = timed(&mut report, &mut timing, Phase::Summarization, || {
+            let content = fs::read_to_string(&path).ok()?;
+            let hash = hash_file_content(&path, &content);
+            let hit = summary_cache.get_summary(&path, &hash).is_some();
+            summary_cache.record_lookup(hit);
+            if hit || !summarize {
+                return None;
+            }
+            generate_summary(file_info.ext.trim_start_matches('.'), &content, None).err()
+        });
+        if let Some(e) = failed {
+            warn!("Failed to summarize {}: {}", path.display(), e);
+        }
+        if let Some(parent) = path.parent() {
+            let info = dir_info.entry(parent.to_path_buf()).or_default();
+            info.total_files += 1;
+            info.tokens += file_info.tokens;
+            info.files.push(file_info);
+        }
+
+        text_files.push(report.files.len());
+        report.files.push(timing);
+    }
+
+    // Emission goes through the same writer as a run, parts and all
+    let parts_dir = TempDir::new()?;
+    let output = OutputSettings {
+        dir: Some(parts_dir.path().to_path_buf()),
+        ..Default::default()
+    };
+    let mut context_file = create_context_file(1, 1, start_dir, &output)?;
+    let mut total_tokens = 0;
+    let mut included_files = HashSet::new();
+    let mut processed = HashSet::new();
+    for index in text_files {
+        let path = report.files[index].path.clone();
+        let mut timing = FileTiming {
+            path: path.clone(),
+            phases: HashMap::new(),
+        };
+        let result = timed(&mut report, &mut timing, Phase::Emission, || {
+            process_node(
+                &path,
+                &dir_info,
+                &mut context_file,
+                usize::MAX,
+                total_tokens,
+                &included_files,
+                &processed,
+                Action::Read,
+                1,
+                start_dir,
+                &output,
+                None,
+            )
+        })?;
+        total_tokens = result.total_tokens;
+        included_files = result.included_files;
+        processed = result.processed;
+        report.files[index].phases.extend(timing.phases);
+    }
+
+    Ok(report)
+}
+
+/// Format a duration in milliseconds
+fn millis(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Print per-phase timings and the slowest files
+pub fn print_profile(report: &ProfileReport, start_dir: &Path, top: usize) {
+    let total = report.total();
+
+    print_title("Phase timings:");
+    for phase in Phase::ALL {
+        let elapsed = report.phases.get(&phase).copied().unwrap_or_default();
+        let share = if total.is_zero() {
+            0.0
+        } else {
+            elapsed.as_secs_f64() / total.as_secs_f64() * 100.0
+        };
+        print_field(phase.name(), format!("{} ({:.0}%)", millis(elapsed), share));
+    }
+    print_field("Total", millis(total));
+    print_field(
+        "Files",
+        format!("{} ({} binary)", report.files.len(), report.binary_files),
+    );
+
+    print_title(&format!("Slowest {} files:", top));
+    for file in report.slowest_files(top) {
+        let rel = file.path.strip_prefix(start_dir).unwrap_or(&file.path);
+        let phase = file.slowest_phase().map_or("", |p| p.name());
+        println!(
+            "  {}  {} {}",
+            paint(millis(file.total()), Style::Number),
+            rel.display(),
+            paint(format!("(mostly {})", phase.to_lowercase()), Style::Muted)
+        );
+    }
+}
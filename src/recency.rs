@@ -0,0 +1,150 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::file_analysis::DirectoryMap;
+use crate::metadata::git_output;
+use crate::suggestions::{Suggestion, SuggestionMap};
+
+/// How many commits of history are scanned for last-change times
+const HISTORY_DEPTH: &str = "1000";
+
+/// Last-change time (unix seconds) of files and directories
+pub type RecencyMap = HashMap<PathBuf, u64>;
+
+/// Modification time of a file in unix seconds
+fn mtime(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+/// Latest commit time of each file in recent history, relative to the repo root
+fn git_commit_times(start_dir: &Path) -> HashMap<PathBuf, u64> {
+    let mut times = HashMap::new();
+    let Some(log) = git_output(
+        start_dir,
+        &["log", "-n", HISTORY_DEPTH, "--format=%x00%ct", "--name-only"],
+    ) else {
+        return times;
+    };
+
+    let mut current = 0;
+    for line in log.lines() {
+        if let Some(timestamp) = line.strip_prefix('\0') {
+            current = timestamp.trim().parse().unwrap_or(0);
+        } else if !line.is_empty() {
+            // Newest commits come first, so keep the first time seen
+            times.entry(PathBuf::from(line)).or_insert(current);
+        }
+    }
+    times
+}
+
+/// Files with uncommitted changes, relative to the repo root
+fn git_dirty_files(start_dir: &Path) -> HashSet<PathBuf> {
+    let Some(status) = git_output(start_dir, &["status", "--porcelain"]) else {
+        return HashSet::new();
+    };
+
+    status
+        .lines()
+        .filter_map(|line| line.get(3..))
+        .map(|path| match path.split_once(" -> ") {
+            Some((_, renamed)) => renamed,
+            None => path,
+        })
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Root of the git repository containing a directory, canonicalized to
+/// match analyzed paths
+fn git_root(start_dir: &Path) -> Option<PathBuf> {
+    let root = PathBuf::from(git_output(start_dir, &["rev-parse", "--show-toplevel"])?);
+    Some(root.canonicalize().unwrap_or(root))
+}
+
+/// Files in the working tree with uncommitted changes
+pub fn dirty_files(start_dir: &Path) -> HashSet<PathBuf> {
+    let Some(root) = git_root(start_dir) else {
+        return HashSet::new();
+    };
+
+    git_dirty_files(start_dir)
+        .into_iter()
+        .map(|path| root.join(path))
+        .collect()
+}
+
+/// When each analyzed file last changed: its mtime if it has uncommitted
+/// changes or is untracked, otherwise its last commit time. Directories get
+/// the most recent time of anything below them. `dirty` is the files with
+/// uncommitted changes, from `dirty_files`.
+pub fn collect_recency(start_dir: &Path, dir_info: &DirectoryMap, dirty: &HashSet<PathBuf>) -> RecencyMap {
+    let root = git_root(start_dir);
+    let commit_times = match &root {
+        Some(_) => git_commit_times(start_dir),
+        None => HashMap::new(),
+    };
+
+    let mut recency = RecencyMap::new();
+    for info in dir_info.values() {
+        for file in &info.files {
+            let committed = root
+                .as_ref()
+                .and_then(|r| file.path.strip_prefix(r).ok())
+                .and_then(|rel| commit_times.get(rel).copied());
+
+            let time = match committed {
+                Some(time) if !dirty.contains(&file.path) => Some(time),
+                _ => mtime(&file.path),
+            };
+            if let Some(time) = time {
+                recency.insert(file.path.clone(), time);
+            }
+        }
+    }
+
+    // Propagate to every ancestor directory within the analyzed tree
+    let file_times: Vec<(PathBuf, u64)> = recency.iter().map(|(p, t)| (p.clone(), *t)).collect();
+    for (path, time) in file_times {
+        for dir in path.ancestors().skip(1) {
+            if !dir_info.contains_key(dir) {
+                break;
+            }
+            let entry = recency.entry(dir.to_path_buf()).or_insert(0);
+            *entry = (*entry).max(time);
+        }
+    }
+
+    recency
+}
+
+/// Sort paths so the most recently changed come first (stable for ties)
+pub fn sort_by_recency(paths: &mut [PathBuf], recency: &RecencyMap) {
+    paths.sort_by_key(|p| std::cmp::Reverse(recency.get(p).copied().unwrap_or(0)));
+}
+
+/// Suggest reading files with uncommitted changes, since they are usually
+/// what is being worked on
+pub fn recency_suggestions(dir_info: &DirectoryMap, dirty: &HashSet<PathBuf>) -> SuggestionMap {
+    let analyzed: HashSet<&PathBuf> = dir_info
+        .values()
+        .flat_map(|info| info.files.iter().filter(|f| !f.binary).map(|f| &f.path))
+        .collect();
+
+    dirty
+        .iter()
+        .filter(|path| analyzed.contains(path))
+        .map(|path| {
+            (
+                path.clone(),
+                Suggestion {
+                    action: "read".to_string(),
+                    reason: "uncommitted changes".to_string(),
+                },
+            )
+        })
+        .collect()
+}
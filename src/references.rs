@@ -0,0 +1,142 @@
+use log::info;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::entrypoints::{IMPORT_EXTENSIONS, detect_entry_points, first_degree_imports};
+use crate::file_analysis::DirectoryMap;
+use crate::suggestions::{Suggestion, SuggestionMap};
+
+/// Directories whose Rust files are crates of their own: integration tests,
+/// examples and benchmarks
+const RUST_ROOT_DIRS: [&str; 3] = ["tests", "examples", "benches"];
+/// Directories of tests and scripts, which are run rather than imported
+const SCRIPT_DIRS: [&str; 5] = ["tests", "test", "__tests__", "scripts", "bin"];
+
+/// Language of a source extension, as the resolver groups them
+fn language(ext: &str) -> &'static str {
+    match ext {
+        "rs" => "Rust",
+        "py" => "Python",
+        "go" => "Go",
+        _ => "JavaScript",
+    }
+}
+
+/// Whether a source file is run or loaded by a tool rather than imported:
+/// Rust integration tests, examples, benches and build scripts; Python and
+/// JS tests, configs and scripts; Go tests and main packages
+fn is_standalone(rel_path: &Path, content: &str) -> bool {
+    let name = rel_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let ext = rel_path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let dirs: Vec<&str> = rel_path
+        .parent()
+        .into_iter()
+        .flat_map(|p| p.iter())
+        .filter_map(|c| c.to_str())
+        .collect();
+    let in_any = |names: &[&str]| dirs.iter().any(|d| names.contains(d));
+    match ext {
+        "rs" => name == "build.rs" || in_any(&RUST_ROOT_DIRS) || dirs.ends_with(&["src", "bin"]),
+        "py" => {
+            name.starts_with("test_")
+                || name.ends_with("_test.py")
+                || matches!(name, "conftest.py" | "setup.py" | "noxfile.py")
+                || in_any(&SCRIPT_DIRS)
+                || content.starts_with("#!")
+                || content.contains("__name__ == \"__main__\"")
+                || content.contains("__name__ == '__main__'")
+        }
+        "go" => name.ends_with("_test.go") || content.lines().any(|l| l.trim() == "package main"),
+        _ => {
+            name.contains(".test.")
+                || name.contains(".spec.")
+                || name.contains(".config.")
+                || in_any(&SCRIPT_DIRS)
+                || content.starts_with("#!")
+        }
+    }
+}
+
+/// Languages whose imports the resolver can't follow in this project, so
+/// an unreferenced file of theirs may well be imported: JS/TS with path
+/// aliases, Go without a go.mod, Rust with `#[path]` modules or `include!`
+fn unresolved_languages(start_dir: &Path, sources: &[(&PathBuf, String)]) -> HashSet<&'static str> {
+    let mut languages = HashSet::new();
+    let aliased = ["tsconfig.json", "jsconfig.json"].iter().any(|name| {
+        fs::read_to_string(start_dir.join(name))
+            .is_ok_and(|c| c.contains("\"paths\"") || c.contains("\"baseUrl\""))
+    }) || fs::read_to_string(start_dir.join("package.json")).is_ok_and(|c| c.contains("\"imports\""));
+    if aliased {
+        languages.insert("JavaScript");
+    }
+    if !start_dir.join("go.mod").is_file() {
+        languages.insert("Go");
+    }
+    let rust_paths = sources.iter().any(|(path, content)| {
+        path.extension().is_some_and(|e| e == "rs") && (content.contains("#[path") || content.contains("include!("))
+    });
+    if rust_paths {
+        languages.insert("Rust");
+    }
+    languages
+}
+
+/// Source files that no other analyzed file imports and that are not entry
+/// points, tests, examples or scripts, i.e. dead or standalone code.
+/// References come from the same import resolution used for entry points
+/// (Rust `mod`, JS/TS relative imports and Python imports), so only those
+/// languages are checked, and only where that resolution can see every import.
+pub fn unreferenced_files(start_dir: &Path, dir_info: &DirectoryMap) -> Vec<PathBuf> {
+    let sources: Vec<(&PathBuf, String)> = dir_info
+        .values()
+        .flat_map(|info| info.files.iter())
+        .filter(|f| !f.binary)
+        .filter(|f| IMPORT_EXTENSIONS.contains(&f.ext.trim_start_matches('.')))
+        .map(|f| (&f.path, fs::read_to_string(&f.path).unwrap_or_default()))
+        .collect();
+
+    let mut referenced: HashSet<PathBuf> = detect_entry_points(start_dir, dir_info)
+        .into_iter()
+        .collect();
+    for (source, _) in &sources {
+        referenced.extend(first_degree_imports(source, start_dir));
+    }
+    let unresolved = unresolved_languages(start_dir, &sources);
+    let present: HashSet<&str> = sources
+        .iter()
+        .map(|(path, _)| language(path.extension().and_then(|e| e.to_str()).unwrap_or("")))
+        .collect();
+    for language in unresolved.intersection(&present) {
+        info!("Not looking for unreferenced {} files; some of their imports can't be resolved", language);
+    }
+
+    let mut unreferenced: Vec<PathBuf> = sources
+        .into_iter()
+        .filter(|(path, _)| !referenced.contains(*path))
+        .filter(|(path, _)| {
+            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+            !unresolved.contains(language(ext))
+        })
+        .filter(|(path, content)| !is_standalone(path.strip_prefix(start_dir).unwrap_or(path), content))
+        .map(|(path, _)| path.clone())
+        .collect();
+    unreferenced.sort();
+    unreferenced
+}
+
+/// Suggest summarizing unreferenced files to reclaim budget
+pub fn unreferenced_suggestions(files: &[PathBuf]) -> SuggestionMap {
+    files
+        .iter()
+        .map(|path| {
+            (
+                path.clone(),
+                Suggestion {
+                    action: "summarize".to_string(),
+                    reason: "not referenced by other files".to_string(),
+                },
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,130 @@
+use anyhow::{Result, bail};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::budget::node_costs;
+use crate::file_analysis::DirectoryMap;
+use crate::index::ContextIndex;
+use crate::manifest::Manifest;
+use crate::ui::{Style, paint, print_title, print_warning, prompt, say};
+
+/// Actions an entry can be switched to from the review screen
+const REVIEW_ACTIONS: [&str; 6] = ["read", "skeleton", "docs", "summarize", "sample", "stats"];
+
+/// One included selection as shown on the review screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewRow {
+    /// Index of the entry in the manifest
+    pub entry: usize,
+    pub path: PathBuf,
+    pub action: String,
+    pub tokens: usize,
+    /// Part holding the entry's first file block
+    pub part: Option<String>,
+}
+
+/// The manifest's included selections with their cost and the part they landed in
+pub fn review_rows(
+    manifest: &Manifest,
+    start_dir: &Path,
+    dir_info: &DirectoryMap,
+    index: &ContextIndex,
+) -> Vec<ReviewRow> {
+    manifest
+        .entries
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| e.action != "enter" && e.action != "exclude")
+        .map(|(idx, e)| ReviewRow {
+            entry: idx,
+            path: e.path.clone(),
+            action: e.action.clone(),
+            tokens: node_costs(&start_dir.join(&e.path), dir_info, &e.action, &HashSet::new())
+                .iter()
+                .map(|(_, cost)| cost)
+                .sum(),
+            part: index
+                .files
+                .iter()
+                .find(|f| f.path.starts_with(&e.path))
+                .map(|f| f.part.clone()),
+        })
+        .collect()
+}
+
+/// Apply one review command to the manifest: `<n> <action>`, `<n> flip`
+/// (read and summarize swap) or `<n> drop`, where `n` numbers the rows
+pub fn apply_review_command(manifest: &mut Manifest, rows: &[ReviewRow], command: &str) -> Result<()> {
+    let Some((num, verb)) = command.split_once(char::is_whitespace) else {
+        bail!("Expected a row number and read, skeleton, docs, summarize, sample, stats, flip or drop");
+    };
+    let Some(row) = num.parse::<usize>().ok().and_then(|n| rows.get(n.wrapping_sub(1))) else {
+        bail!("No row {}", num);
+    };
+
+    let action = match verb.trim() {
+        "drop" => "exclude",
+        "flip" => match row.action.as_str() {
+            "read" => "summarize",
+            "summarize" => "read",
+            other => bail!("Only read and summarize can be flipped, not {}", other),
+        },
+        verb if REVIEW_ACTIONS.contains(&verb) => verb,
+        verb => bail!("Unknown review action '{}'", verb),
+    };
+
+    let entry = &mut manifest.entries[row.entry];
+    if entry.action != action {
+        entry.action = action.to_string();
+        // The choice is now the user's, not a downgrade
+        entry.options.remove("requested");
+        entry.options.remove("reason");
+    }
+    Ok(())
+}
+
+/// Print the review table
+pub fn print_review(rows: &[ReviewRow]) {
+    print_title("Planned context:");
+    for (i, row) in rows.iter().enumerate() {
+        say(format!(
+            "{:>4}. {} {} {} {}",
+            i + 1,
+            row.path.display(),
+            paint(&row.action, Style::Header),
+            paint(format!("~{} tokens", row.tokens), Style::Number),
+            paint(row.part.as_deref().unwrap_or("-"), Style::Muted)
+        ));
+    }
+    let total: usize = rows.iter().map(|r| r.tokens).sum();
+    say(format!("      {}", paint(format!("~{} tokens in total", total), Style::Number)));
+}
+
+/// Show the planned manifest and take changes until an empty line.
+/// Returns whether anything changed.
+pub fn review_manifest(
+    manifest: &mut Manifest,
+    start_dir: &Path,
+    dir_info: &DirectoryMap,
+    index: &ContextIndex,
+) -> Result<bool> {
+    let original = manifest.clone();
+    loop {
+        let rows = review_rows(manifest, start_dir, dir_info, index);
+        if rows.is_empty() {
+            break;
+        }
+        print_review(&rows);
+
+        let command = prompt(
+            "\nChange a row ('<n> read|skeleton|docs|summarize|sample|stats', '<n> flip', '<n> drop'), Enter to finish: ",
+        )?;
+        if command.is_empty() {
+            break;
+        }
+        if let Err(e) = apply_review_command(manifest, &rows, &command) {
+            print_warning(&e.to_string());
+        }
+    }
+    Ok(*manifest != original)
+}
@@ -0,0 +1,81 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::anchors::action_breakdown;
+use crate::manifest::Manifest;
+use crate::summary_cache::CacheStats;
+
+/// Outcome of a run, printed as one line of JSON for wrappers and agents
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RunSummary {
+    /// "ok", "interrupted" or "error"
+    pub status: String,
+    /// Context parts written, in order
+    pub parts: Vec<PathBuf>,
+    /// Files whose content (in any form) went into the parts
+    pub files_included: usize,
+    /// Selections by action, from the manifest
+    pub files_summarized: usize,
+    pub files_excluded: usize,
+    /// Estimated tokens of the included files
+    pub total_tokens: usize,
+    /// Estimated tokens of the parts by the action that produced them
+    pub tokens_by_action: BTreeMap<String, usize>,
+    /// Summary cache lookups during the run
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub errors: Vec<String>,
+}
+
+impl RunSummary {
+    /// Summarize a finished (or interrupted) run
+    pub fn new(
+        parts: Vec<PathBuf>,
+        manifest: &Manifest,
+        files_included: usize,
+        total_tokens: usize,
+        lookups: CacheStats,
+        errors: Vec<String>,
+    ) -> Self {
+        let selected = |action: &str| manifest.entries.iter().filter(|e| e.action == action).count();
+        let mut tokens_by_action = BTreeMap::new();
+        for part in &parts {
+            let Ok(content) = fs::read_to_string(part) else {
+                continue;
+            };
+            for (action, tokens) in action_breakdown(&content) {
+                *tokens_by_action.entry(action.to_string()).or_default() += tokens;
+            }
+        }
+
+        Self {
+            status: if errors.is_empty() { "ok" } else { "interrupted" }.to_string(),
+            parts,
+            files_included,
+            files_summarized: selected("summarize"),
+            files_excluded: selected("exclude"),
+            total_tokens,
+            tokens_by_action,
+            cache_hits: lookups.hits,
+            cache_misses: lookups.misses,
+            errors,
+        }
+    }
+
+    /// A run that failed before finishing
+    pub fn failed(error: &anyhow::Error) -> Self {
+        Self {
+            status: "error".to_string(),
+            errors: vec![format!("{:#}", error)],
+            ..Default::default()
+        }
+    }
+
+    /// The summary as a single line of JSON
+    pub fn to_json_line(&self) -> Result<String> {
+        serde_json::to_string(self).context("Failed to serialize run summary")
+    }
+}
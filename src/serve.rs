@@ -0,0 +1,187 @@
+use anyhow::{Context, Result, bail};
+use log::{info, warn};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+
+use crate::index::{ContextIndex, IndexEntry};
+
+/// Port the retrieval server listens on by default
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// A response from the retrieval server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    pub fn ok(body: String) -> Self {
+        Self { status: 200, body }
+    }
+
+    pub fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: format!("{}\n", message),
+        }
+    }
+}
+
+/// Decode %XX escapes and `+` in a query value
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Value of a query parameter in a request target
+pub fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| percent_decode(value))
+}
+
+/// Parse a `start-end` line range (1-based, inclusive)
+fn parse_lines(range: &str) -> Option<(usize, usize)> {
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+    (start >= 1 && start <= end).then_some((start, end))
+}
+
+/// Answer a request for a target such as `/file?path=src/main.rs&lines=12-95`.
+/// Only files listed in the index are served; `default_source` resolves
+/// entries without a source directory.
+pub fn respond(index: &ContextIndex, default_source: &Path, target: &str) -> Response {
+    let (route, query) = target.split_once('?').unwrap_or((target, ""));
+    match route {
+        "/" | "/index" => match serde_json::to_string_pretty(index) {
+            Ok(json) => Response::ok(json),
+            Err(e) => Response::error(500, &format!("Failed to serialize index: {}", e)),
+        },
+        "/file" => {
+            let Some(path) = query_param(query, "path") else {
+                return Response::error(400, "Missing path parameter");
+            };
+            let Some(entry) = index.files.iter().find(|e| e.path == Path::new(&path)) else {
+                return Response::error(404, &format!("{} is not in the index", path));
+            };
+            let lines = match query_param(query, "lines") {
+                Some(range) => match parse_lines(&range) {
+                    Some(lines) => Some(lines),
+                    None => return Response::error(400, &format!("Invalid line range {}", range)),
+                },
+                None => None,
+            };
+            match read_entry(entry, default_source, lines) {
+                Ok(content) => Response::ok(content),
+                Err(e) => Response::error(404, &format!("{:#}", e)),
+            }
+        }
+        _ => Response::error(404, "Unknown route; use /index or /file?path=...&lines=start-end"),
+    }
+}
+
+/// Current content of an indexed file, or of a range of its lines
+fn read_entry(entry: &IndexEntry, default_source: &Path, lines: Option<(usize, usize)>) -> Result<String> {
+    let source = entry.source.as_deref().unwrap_or(default_source);
+    let path = source.join(&entry.path);
+    let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let Some((start, end)) = lines else {
+        return Ok(content);
+    };
+    let selected: Vec<&str> = content.lines().skip(start - 1).take(end + 1 - start).collect();
+    if selected.is_empty() {
+        bail!("{} has no line {}", entry.path.display(), start);
+    }
+    Ok(format!("{}\n", selected.join("\n")))
+}
+
+/// Read one request and write its response
+fn handle_connection(stream: TcpStream, respond: &dyn Fn(&str) -> Response) -> Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Headers are not used, but are read so the client sees a clean close
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => respond(target),
+        _ => Response::error(405, "Only GET is supported"),
+    };
+    info!("{} {}", request_line.trim(), response.status);
+
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        response.body.len(),
+        response.body
+    )?;
+    Ok(())
+}
+
+/// Serve the files of an index on localhost until interrupted
+pub fn serve(index: &ContextIndex, default_source: PathBuf, port: u16) -> Result<()> {
+    let listener = bind(port)?;
+    println!("Serving {} files on http://127.0.0.1:{}/", index.files.len(), port);
+    println!("  GET /index                          the index as JSON");
+    println!("  GET /file?path=PATH[&lines=S-E]     current content of an indexed file");
+    listen(listener, |target| respond(index, &default_source, target))
+}
+
+/// Listen on a localhost port
+pub fn bind(port: u16) -> Result<TcpListener> {
+    TcpListener::bind(("127.0.0.1", port)).with_context(|| format!("Failed to listen on port {}", port))
+}
+
+/// Answer GET requests one at a time until interrupted
+pub fn listen(listener: TcpListener, respond: impl Fn(&str) -> Response) -> Result<()> {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = handle_connection(stream, &respond) {
+                    warn!("Failed to answer request: {}", e);
+                }
+            }
+            Err(e) => warn!("Failed to accept connection: {}", e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use lazy_static::lazy_static;
+use log::info;
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+use crate::deps::expand_use_tree;
+
+/// Most external signatures attached to one file
+pub const MAX_SIGNATURES: usize = 20;
+
+/// Crates of the standard library, found in the rust-src component
+const STD_CRATES: [&str; 3] = ["std", "core", "alloc"];
+/// Roots of use paths that refer to the current crate
+const LOCAL_ROOTS: [&str; 3] = ["crate", "self", "super"];
+
+lazy_static! {
+    static ref USE_DECL: Regex = Regex::new(r"(?s)\buse\s+(?:::)?(\w+)::([^;]+);").unwrap();
+    static ref LINE_COMMENT: Regex = Regex::new(r"//[^\n]*").unwrap();
+    static ref WHITESPACE: Regex = Regex::new(r"\s+").unwrap();
+    /// Signatures already looked up this run, by item path
+    static ref FOUND: Mutex<HashMap<String, Option<String>>> = Mutex::new(HashMap::new());
+    /// Source files of each crate, by crate name
+    static ref SOURCES: Mutex<HashMap<String, Vec<PathBuf>>> = Mutex::new(HashMap::new());
+}
+
+/// Items of other crates that a Rust file imports, and the associated
+/// functions it calls on them (`HashMap::new`), in file order
+pub fn external_items(source: &str) -> Vec<String> {
+    let code = LINE_COMMENT.replace_all(source, "");
+    let mut items = Vec::new();
+
+    for c in USE_DECL.captures_iter(&code) {
+        let root = &c[1];
+        if LOCAL_ROOTS.contains(&root) {
+            continue;
+        }
+        let mut paths = Vec::new();
+        expand_use_tree(root, &c[2], &mut paths);
+        items.extend(paths.into_iter().filter(|p| !p.ends_with("::*")));
+    }
+
+    // Associated functions of imported types
+    let mut calls = Vec::new();
+    for item in &items {
+        let Some(name) = item.rsplit("::").next() else {
+            continue;
+        };
+        if !name.starts_with(char::is_uppercase) {
+            continue;
+        }
+        let call = Regex::new(&format!(r"\b{}::([a-z_]\w*)\s*[(<:]", regex::escape(name))).unwrap();
+        calls.extend(call.captures_iter(&code).map(|m| format!("{}::{}", item, &m[1])));
+    }
+    items.extend(calls);
+
+    let mut seen = BTreeSet::new();
+    items.retain(|item| seen.insert(item.clone()));
+    items
+}
+
+/// Source directory of the standard library crates, when rust-src is installed
+fn std_source_dir(name: &str) -> Option<PathBuf> {
+    let output = Command::new("rustc").args(["--print", "sysroot"]).output().ok()?;
+    let sysroot = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let dir = Path::new(&sysroot).join("lib/rustlib/src/rust/library").join(name).join("src");
+    dir.is_dir().then_some(dir)
+}
+
+/// Source directory of a crate downloaded by cargo (the last version by name)
+fn registry_source_dir(name: &str) -> Option<PathBuf> {
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")))?;
+    let dashed = name.replace('_', "-");
+
+    let mut candidates: Vec<PathBuf> = fs::read_dir(cargo_home.join("registry").join("src"))
+        .ok()?
+        .filter_map(Result::ok)
+        .filter_map(|index| fs::read_dir(index.path()).ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name().and_then(|n| n.to_str()).is_some_and(|n| {
+                [name, dashed.as_str()].iter().any(|prefix| {
+                    n.strip_prefix(prefix)
+                        .and_then(|rest| rest.strip_prefix('-'))
+                        .is_some_and(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+                })
+            })
+        })
+        .collect();
+    candidates.sort();
+    candidates.pop().map(|dir| dir.join("src")).filter(|dir| dir.is_dir())
+}
+
+/// Rust files of a crate's sources, listed once per run
+fn crate_sources(name: &str) -> Vec<PathBuf> {
+    let mut sources = SOURCES.lock().unwrap();
+    sources
+        .entry(name.to_string())
+        .or_insert_with(|| {
+            let dir = if STD_CRATES.contains(&name) {
+                std_source_dir(name)
+            } else {
+                registry_source_dir(name)
+            };
+            match dir {
+                Some(dir) => walkdir::WalkDir::new(dir)
+                    .into_iter()
+                    .filter_map(Result::ok)
+                    .map(|e| e.into_path())
+                    .filter(|p| p.extension().is_some_and(|e| e == "rs"))
+                    .collect(),
+                None => {
+                    info!("No sources found for crate {}", name);
+                    Vec::new()
+                }
+            }
+        })
+        .clone()
+}
+
+/// Declaration of an item in some source files: for `Type::function`, the
+/// function in an impl of the type; otherwise a public type, trait, function
+/// or macro with the path's last name
+pub fn find_signature(files: &[PathBuf], item: &str) -> Option<String> {
+    let mut names = item.rsplit("::");
+    let name = regex::escape(names.next()?);
+    let owner = names.next().filter(|n| n.starts_with(char::is_uppercase));
+
+    let declaration = Regex::new(&format!(
+        r"(?s)(?:\bpub(?:\([^)]*\))?\s+(?:(?:const|async|unsafe|extern\s+\S+)\s+)*(?:fn|struct|enum|trait|type|union)\s+{name}\b[^{{;]*|\bmacro_rules!\s*{name}\b)"
+    ))
+    .unwrap();
+    let owner_impl = owner.map(|owner| {
+        Regex::new(&format!(r"\bimpl\b[^{{;]*\b{}\b[^{{;]*\{{", regex::escape(owner))).unwrap()
+    });
+
+    for file in files {
+        let Ok(content) = fs::read_to_string(file) else {
+            continue;
+        };
+        let start = match &owner_impl {
+            Some(pattern) => match pattern.find(&content) {
+                Some(m) => m.end(),
+                None => continue,
+            },
+            None => 0,
+        };
+        if let Some(m) = declaration.find(&content[start..]) {
+            return Some(WHITESPACE.replace_all(m.as_str().trim(), " ").into_owned());
+        }
+    }
+    None
+}
+
+/// Signatures of the std and dependency items a Rust file uses, as a section
+/// to attach to its block. None when nothing could be looked up.
+pub fn external_signatures(source: &str) -> Option<String> {
+    let mut lines = Vec::new();
+    let mut found = 0;
+    for item in external_items(source) {
+        if found >= MAX_SIGNATURES {
+            break;
+        }
+        let cached = FOUND.lock().unwrap().get(&item).cloned();
+        let signature = match cached {
+            Some(signature) => signature,
+            None => {
+                let root = item.split("::").next().unwrap_or_default();
+                let signature = find_signature(&crate_sources(root), &item);
+                FOUND.lock().unwrap().insert(item.clone(), signature.clone());
+                signature
+            }
+        };
+        if let Some(signature) = signature {
+            lines.push(format!("// {}", item));
+            lines.push(signature);
+            found += 1;
+        }
+    }
+
+    if lines.is_empty() {
+        return None;
+    }
+    Some(format!("External signatures:\n```rust\n{}\n```\n", lines.join("\n")))
+}
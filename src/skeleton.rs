@@ -0,0 +1,668 @@
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::BTreeSet;
+
+use crate::cfg::{Cfg, cfg_attribute};
+
+/// Placeholder left where a body was removed
+const ELIDED: &str = "...";
+
+lazy_static! {
+    /// Blocks whose contents are declarations worth keeping
+    static ref CONTAINER: Regex = Regex::new(
+        r"\b(class|interface|impl|trait|mod|struct|enum|union|namespace|module|extern)\b"
+    )
+    .unwrap();
+    /// Blocks that are bodies of code
+    static ref BODY: Regex =
+        Regex::new(r"\)|=>|\b(fn|function|func|else|try|finally|do|loop|unsafe|async|get|set)\b").unwrap();
+    static ref PY_BLOCK: Regex = Regex::new(r"^\s*(?:async\s+)?(def|class)\s").unwrap();
+    /// Declarations by name, for symbol selection
+    static ref DECLARATION: Regex = Regex::new(
+        r"\b(?:fn|struct|enum|trait|type|union|mod|class|interface|record|def|func|function|const|static|let|var)\s+(?:\([^)]*\)\s*)?([A-Za-z_]\w*)"
+    )
+    .unwrap();
+    /// impl blocks, named by the type they implement for
+    static ref IMPL: Regex =
+        Regex::new(r"\bimpl(?:<[^>]*>)?\s+(?:[\w:]+(?:<[^>]*>)?\s+for\s+)?([A-Za-z_]\w*)").unwrap();
+    /// Keywords opening a function, whose return type may mention a container
+    static ref FUNCTION: Regex = Regex::new(r"\b(fn|function|func|def)\b").unwrap();
+    static ref WHERE: Regex = Regex::new(r"\bwhere\b").unwrap();
+    static ref STRING: Regex = Regex::new(r#""(?:[^"\\]|\\.)*"|`(?:[^`\\]|\\.)*`|'[{}]'"#).unwrap();
+}
+
+/// Signatures, doc comments and declarations of a source file with function
+/// bodies replaced by `...`. None for languages without a skeleton.
+pub fn skeleton(ext: &str, content: &str) -> Option<String> {
+    match ext.trim_start_matches('.') {
+        "py" | "pyi" => Some(python_skeleton(content)),
+        "rs" | "go" | "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" | "java" | "kt" | "cs" => {
+            Some(brace_skeleton(content))
+        }
+        _ => None,
+    }
+}
+
+/// Blank out string literals and comments so braces in them aren't counted,
+/// keeping byte offsets unchanged
+fn code_only(line: &str, in_comment: &mut bool) -> String {
+    let mut code = STRING
+        .replace_all(line, |c: &regex::Captures| " ".repeat(c[0].len()))
+        .into_owned();
+
+    let mut start = 0;
+    loop {
+        if *in_comment {
+            match code[start..].find("*/") {
+                Some(end) => {
+                    let end = start + end + 2;
+                    code.replace_range(start..end, &" ".repeat(end - start));
+                    *in_comment = false;
+                    start = end;
+                }
+                None => {
+                    let len = code.len() - start;
+                    code.replace_range(start.., &" ".repeat(len));
+                    return code;
+                }
+            }
+        }
+        let line_comment = code[start..].find("//").map(|i| start + i);
+        let block_comment = code[start..].find("/*").map(|i| start + i);
+        match (line_comment, block_comment) {
+            (Some(l), b) if b.is_none_or(|b| l < b) => {
+                let len = code.len() - l;
+                code.replace_range(l.., &" ".repeat(len));
+                return code;
+            }
+            (_, Some(b)) => {
+                *in_comment = true;
+                start = b;
+            }
+            _ => return code,
+        }
+    }
+}
+
+/// Whether a block opening after `head` is a code body rather than a
+/// container of declarations. A container keyword counts only before any
+/// function keyword, so `fn iter() -> impl Iterator` is still a body.
+fn is_body(head: &str) -> bool {
+    match (CONTAINER.find(head), FUNCTION.find(head)) {
+        (Some(container), Some(function)) => function.start() < container.start(),
+        (Some(_), None) => false,
+        (None, _) => BODY.is_match(head),
+    }
+}
+
+/// The lines of a declaration before its block opens, gathered so that
+/// multi-line signatures and `where` clauses are judged and kept whole
+#[derive(Default)]
+struct Header {
+    code: String,
+    /// Original lines, and whether each has been written out already
+    lines: Vec<(String, bool)>,
+}
+
+impl Header {
+    /// Add a line, starting over where a statement, block or list item ends
+    /// (a comma inside parentheses or a `where` clause continues it)
+    fn push(&mut self, line: &str, code: &str, written: bool) {
+        self.code.push(' ');
+        self.code.push_str(code.trim());
+        self.lines.push((line.to_string(), written));
+        let end = code.trim_end();
+        let open_parens = self.code.matches('(').count() > self.code.matches(')').count();
+        if end.trim().is_empty()
+            || end.ends_with([';', '{', '}'])
+            || (end.ends_with(',') && !open_parens && !WHERE.is_match(&self.code))
+        {
+            self.clear();
+        }
+    }
+
+    /// The whole header of a block opening at `brace` on this line
+    fn head(&self, code: &str, brace: usize) -> String {
+        format!("{} {}", self.code, &code[..brace])
+    }
+
+    fn clear(&mut self) {
+        self.code.clear();
+        self.lines.clear();
+    }
+}
+
+/// Skeleton for brace-delimited languages: blocks that look like code
+/// bodies are collapsed onto their opening line, and impls, classes and
+/// modules keep their whole header (generics, bounds, `where` clauses) above
+/// the declarations inside them
+fn brace_skeleton(content: &str) -> String {
+    let mut out = Vec::new();
+    let mut in_comment = false;
+    let mut depth: i64 = 0;
+    // Depth to return to before the body being skipped ends
+    let mut skip_until: Option<i64> = None;
+    let mut header = Header::default();
+
+    for line in content.lines() {
+        let code = code_only(line, &mut in_comment);
+        let opens = code.matches('{').count() as i64;
+        let closes = code.matches('}').count() as i64;
+
+        if let Some(target) = skip_until {
+            depth += opens - closes;
+            if depth <= target {
+                skip_until = None;
+            }
+            continue;
+        }
+
+        if opens > closes
+            && let Some(brace) = code.find('{')
+        {
+            let head = header.head(&code, brace);
+            if is_body(&head) {
+                out.push(format!("{}{{ {} }}", &line[..brace], ELIDED));
+                header.clear();
+                skip_until = Some(depth);
+                depth += opens - closes;
+                continue;
+            }
+        }
+
+        depth += opens - closes;
+        out.push(line.to_string());
+        header.push(line, &code, true);
+    }
+
+    let mut skeleton = out.join("\n");
+    skeleton.push('\n');
+    skeleton
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Skeleton for Python: each def keeps its signature and docstring, and its
+/// body becomes `...`; classes keep their docstrings, attributes and methods
+fn python_skeleton(content: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut out = Vec::new();
+    let mut i = 0;
+    // Indentation of the def whose body is being skipped
+    let mut skip_deeper_than: Option<usize> = None;
+
+    while i < lines.len() {
+        let line = lines[i];
+        if let Some(indent) = skip_deeper_than {
+            if line.trim().is_empty() || indent_of(line) > indent {
+                i += 1;
+                continue;
+            }
+            skip_deeper_than = None;
+        }
+
+        let Some(kind) = PY_BLOCK.captures(line).map(|c| c[1].to_string()) else {
+            out.push(line.to_string());
+            i += 1;
+            continue;
+        };
+
+        // The signature runs until the line ending in ':' with brackets closed
+        let indent = indent_of(line);
+        let mut balance = 0i64;
+        loop {
+            let current = lines[i];
+            out.push(current.to_string());
+            balance += current.matches(['(', '[']).count() as i64;
+            balance -= current.matches([')', ']']).count() as i64;
+            i += 1;
+            if (balance <= 0 && current.contains(':')) || i >= lines.len() {
+                break;
+            }
+        }
+        // One-line definitions have nothing to elide
+        if !lines[i - 1].trim_end().ends_with(':') {
+            continue;
+        }
+
+        // Keep a docstring directly under the signature
+        let body_start = (i..lines.len()).find(|&j| !lines[j].trim().is_empty());
+        if let Some(j) = body_start {
+            let first = lines[j].trim_start();
+            let unprefixed = first.trim_start_matches(['r', 'R', 'u', 'U', 'b', 'B']);
+            let quote = ["\"\"\"", "'''"].into_iter().find(|q| unprefixed.starts_with(q));
+            if let Some(quote) = quote {
+                let opening = first.len() - unprefixed.len() + quote.len();
+                let mut end = j;
+                if !first[opening..].contains(quote) {
+                    end = (j + 1..lines.len())
+                        .find(|&k| lines[k].contains(quote))
+                        .unwrap_or(lines.len() - 1);
+                }
+                out.extend(lines[i..=end].iter().map(|l| l.to_string()));
+                i = end + 1;
+            }
+        }
+
+        if kind == "def" {
+            let body_indent = body_start
+                .map(|j| indent_of(lines[j]))
+                .filter(|&b| b > indent)
+                .unwrap_or(indent + 4);
+            out.push(format!("{}{}", " ".repeat(body_indent), ELIDED));
+            skip_deeper_than = Some(indent);
+        }
+    }
+
+    let mut skeleton = out.join("\n");
+    skeleton.push('\n');
+    skeleton
+}
+
+/// Mark the items of a Rust skeleton gated by `#[cfg]` with their whole
+/// gate, including those of the modules and impls around them. With a
+/// feature set, items it gates off are dropped and the others marked active
+/// or dependent on the target.
+pub fn annotate_cfg_gates(skeleton: &str, features: Option<&BTreeSet<String>>) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let mut in_comment = false;
+    let mut depth: i64 = 0;
+    let mut skip_until: Option<i64> = None;
+    // Gates of the open blocks, with the depth outside each
+    let mut blocks: Vec<(i64, Vec<Cfg>)> = Vec::new();
+    // Doc comments and attributes waiting for their item, and its gates
+    let mut held: Vec<String> = Vec::new();
+    let mut pending: Vec<Cfg> = Vec::new();
+    let mut dropped = 0;
+
+    for line in skeleton.lines() {
+        let code = code_only(line, &mut in_comment);
+        let change = code.matches('{').count() as i64 - code.matches('}').count() as i64;
+        if let Some(target) = skip_until {
+            depth += change;
+            if depth <= target {
+                skip_until = None;
+            }
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+        match cfg_attribute(line) {
+            // A gate on the whole file or module, whose items aren't dropped one by one
+            Some((cfg, true)) => {
+                let note = match features.and_then(|f| cfg.eval(f)) {
+                    Some(true) => " // active",
+                    Some(false) => " // inactive",
+                    None => "",
+                };
+                out.push(format!("{}{}", line, note));
+                continue;
+            }
+            Some((cfg, false)) => {
+                pending.push(cfg);
+                held.push(line.to_string());
+                continue;
+            }
+            None if trimmed.starts_with("#[") || trimmed.starts_with("///") => {
+                held.push(line.to_string());
+                continue;
+            }
+            None => {}
+        }
+
+        if pending.is_empty() {
+            out.append(&mut held);
+            out.push(line.to_string());
+        } else {
+            let gates = blocks.iter().flat_map(|(_, gates)| gates).chain(&pending).cloned().collect();
+            let gate = Cfg::all(gates);
+            let active = features.map(|f| gate.eval(f));
+            if active == Some(Some(false)) {
+                dropped += 1;
+                held.clear();
+                pending.clear();
+                if change > 0 {
+                    skip_until = Some(depth);
+                    depth += change;
+                }
+                continue;
+            }
+            let note = match active {
+                Some(Some(true)) => ", active",
+                Some(None) => ", depends on the target",
+                _ => "",
+            };
+            out.append(&mut held);
+            out.push(format!("{} // cfg: {}{}", line, gate, note));
+        }
+
+        if change > 0 {
+            blocks.push((depth, std::mem::take(&mut pending)));
+        }
+        pending.clear();
+        depth += change;
+        while blocks.last().is_some_and(|(outer, _)| depth <= *outer) {
+            blocks.pop();
+        }
+    }
+
+    if let Some(features) = features
+        && dropped > 0
+    {
+        let features: Vec<&str> = features.iter().map(String::as_str).collect();
+        out.push(format!(
+            "// {} items gated off with features: {}",
+            dropped,
+            if features.is_empty() { "none".to_string() } else { features.join(", ") }
+        ));
+    }
+    let mut annotated = out.join("\n");
+    annotated.push('\n');
+    annotated
+}
+
+/// Whether a doc line documents the item after it, rather than its parent
+fn is_outer_doc(trimmed: &str) -> bool {
+    trimmed.starts_with("///") && !trimmed.starts_with("////")
+}
+
+/// Doc comments of a Rust file with the items they document, like a rustdoc
+/// page: signatures keep no bodies, and impls, traits and modules are kept
+/// around the documented items inside them
+pub fn rust_docs(content: &str) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let mut in_comment = false;
+    let mut depth: i64 = 0;
+    let mut skip_until: Option<i64> = None;
+    // Open blocks: depth outside them, where their lines start and end in
+    // `out`, and whether they were documented themselves
+    let mut containers: Vec<(i64, usize, usize, bool)> = Vec::new();
+    // The next item follows doc comments
+    let mut documented = false;
+    // A documented item's signature continues on the next line
+    let mut in_signature = false;
+    // Parentheses left open in that signature
+    let mut parens: i64 = 0;
+    let mut header = Header::default();
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        let code = code_only(line, &mut in_comment);
+        let opens = code.matches('{').count() as i64;
+        let closes = code.matches('}').count() as i64;
+
+        if let Some(target) = skip_until {
+            depth += opens - closes;
+            if depth <= target {
+                skip_until = None;
+            }
+            continue;
+        }
+
+        if trimmed.starts_with("//!") || is_outer_doc(trimmed) {
+            // Separate each documented item from what came before
+            if !documented && out.last().is_some_and(|l| !l.trim_end().ends_with('{')) {
+                out.push(String::new());
+            }
+            out.push(line.to_string());
+            documented = is_outer_doc(trimmed);
+            continue;
+        }
+        if documented && !in_signature && trimmed.starts_with("#[") {
+            out.push(line.to_string());
+            continue;
+        }
+
+        let keep = documented || in_signature;
+        if opens > closes
+            && let Some(brace) = code.find('{')
+        {
+            // A kept signature keeps the rest of its header, such as a
+            // `where` clause after its parameters
+            let kept_header = keep || header.lines.iter().any(|(_, written)| *written);
+            let unwritten = header.lines.iter().filter(|(_, written)| !written).map(|(l, _)| l.clone());
+            if is_body(&header.head(&code, brace)) {
+                if kept_header {
+                    out.extend(unwritten);
+                    out.push(format!("{}{{ {} }}", &line[..brace], ELIDED));
+                }
+                skip_until = Some(depth);
+            } else {
+                let start = out.len();
+                if !kept_header && out.last().is_some_and(|l| !l.trim_end().ends_with('{')) {
+                    out.push(String::new());
+                }
+                out.extend(unwritten);
+                out.push(line.to_string());
+                containers.push((depth, start, out.len(), kept_header));
+            }
+            depth += opens - closes;
+            documented = false;
+            in_signature = false;
+            parens = 0;
+            header.clear();
+            continue;
+        }
+
+        depth += opens - closes;
+        let mut closed = false;
+        while let Some(&(outer, start, end, kept)) = containers.last()
+            && depth <= outer
+        {
+            containers.pop();
+            // Undocumented blocks are dropped when nothing inside was documented
+            if !kept && out.len() == end {
+                out.truncate(start);
+            } else if !closed {
+                out.push(line.to_string());
+                closed = true;
+            }
+        }
+        let written = !closed && keep && !trimmed.is_empty();
+        if written {
+            out.push(line.to_string());
+            parens += code.matches('(').count() as i64 - code.matches(')').count() as i64;
+            in_signature = parens > 0 || !code.trim_end().ends_with([';', ',', '}']);
+            if !in_signature {
+                parens = 0;
+            }
+            documented = false;
+        }
+        header.push(line, &code, written || closed);
+    }
+
+    let mut docs = out.join("\n");
+    docs.push('\n');
+    docs
+}
+
+/// Marker left in place of a folded body, with the folded line range
+pub fn fold_marker(lines: usize, start: usize, end: usize) -> String {
+    format!("… {} lines: {}-{} …", lines, start, end)
+}
+
+/// Line and byte column where the block opened at `brace` on line `start` closes
+fn block_end(codes: &[String], start: usize, brace: usize) -> Option<(usize, usize)> {
+    let mut depth = 0i64;
+    for (idx, code) in codes.iter().enumerate().skip(start) {
+        let from = if idx == start { brace } else { 0 };
+        for (col, c) in code[from..].char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some((idx, from + col));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+/// Source with function bodies of at least `min_lines` lines replaced by a
+/// marker naming the folded lines (1-based). None for languages that can't
+/// be folded.
+pub fn fold_bodies(ext: &str, content: &str, min_lines: usize) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut out = Vec::new();
+
+    match ext.trim_start_matches('.') {
+        "py" | "pyi" => {
+            let mut i = 0;
+            while i < lines.len() {
+                let line = lines[i];
+                out.push(line.to_string());
+                i += 1;
+                let is_def = PY_BLOCK.captures(line).is_some_and(|c| &c[1] == "def");
+                if !is_def || !line.trim_end().ends_with(':') {
+                    continue;
+                }
+                let indent = indent_of(line);
+                let end = (i..lines.len())
+                    .find(|&j| !lines[j].trim().is_empty() && indent_of(lines[j]) <= indent)
+                    .unwrap_or(lines.len());
+                // Trailing blank lines stay outside the fold
+                let end = (i..end).rev().find(|&j| !lines[j].trim().is_empty()).map_or(i, |j| j + 1);
+                if end - i >= min_lines {
+                    let body_indent = lines[i..end]
+                        .iter()
+                        .find(|l| !l.trim().is_empty())
+                        .map_or(indent + 4, |l| indent_of(l));
+                    out.push(format!("{}{}", " ".repeat(body_indent), fold_marker(end - i, i + 1, end)));
+                    i = end;
+                }
+            }
+        }
+        "rs" | "go" | "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" | "java" | "kt" | "cs" => {
+            let mut in_comment = false;
+            let codes: Vec<String> = lines.iter().map(|l| code_only(l, &mut in_comment)).collect();
+            let mut i = 0;
+            while i < lines.len() {
+                let code = &codes[i];
+                if code.matches('{').count() > code.matches('}').count()
+                    && let Some(brace) = code.find('{')
+                {
+                    let head = match &code[..brace] {
+                        head if head.trim().is_empty() && i > 0 => codes[i - 1].as_str(),
+                        head => head,
+                    };
+                    if !CONTAINER.is_match(head)
+                        && BODY.is_match(head)
+                        && let Some((end, col)) = block_end(&codes, i, brace)
+                        && end - i > min_lines
+                    {
+                        out.push(format!(
+                            "{}{{ {} }}{}",
+                            &lines[i][..brace],
+                            fold_marker(end - i - 1, i + 2, end),
+                            &lines[end][col + 1..]
+                        ));
+                        i = end + 1;
+                        continue;
+                    }
+                }
+                out.push(lines[i].to_string());
+                i += 1;
+            }
+        }
+        _ => return None,
+    }
+
+    let mut folded = out.join("\n");
+    if content.ends_with('\n') {
+        folded.push('\n');
+    }
+    Some(folded)
+}
+
+/// Matcher for symbol name patterns such as `Parser*` and `parse_*`
+pub fn symbol_matcher(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).with_context(|| format!("Invalid symbol pattern: {}", pattern))?);
+    }
+    builder.build().context("Failed to build symbol patterns")
+}
+
+/// Whether a line is a doc comment, comment, attribute or decorator that
+/// belongs to the declaration below it
+fn is_preamble(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    ["//", "/*", "*", "#[", "@"].iter().any(|p| trimmed.starts_with(p))
+        || (trimmed.starts_with('#') && !trimmed.starts_with("#!"))
+}
+
+/// Line ranges (1-based, inclusive, unmerged) of the declarations whose names
+/// match `symbols`, with the doc comments and attributes above them. None for
+/// languages that can't be scanned.
+pub fn symbol_ranges(ext: &str, content: &str, symbols: &GlobSet) -> Option<Vec<(usize, usize)>> {
+    let ranges = declarations(ext, content)?
+        .into_iter()
+        .filter(|(name, _, _)| symbols.is_match(name))
+        .map(|(_, start, end)| (start, end))
+        .collect();
+    Some(ranges)
+}
+
+/// Every declaration in a file with its line range (1-based, inclusive),
+/// doc comments and attributes included. None for languages that can't be
+/// scanned.
+pub fn declarations(ext: &str, content: &str) -> Option<Vec<(String, usize, usize)>> {
+    let python = match ext.trim_start_matches('.') {
+        "py" | "pyi" => true,
+        "rs" | "go" | "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" | "java" | "kt" | "cs" => false,
+        _ => return None,
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    let mut in_comment = false;
+    let codes: Vec<String> = lines.iter().map(|l| code_only(l, &mut in_comment)).collect();
+
+    let mut found = Vec::new();
+    for (i, code) in codes.iter().enumerate() {
+        let Some(name) = IMPL
+            .captures(code)
+            .or_else(|| DECLARATION.captures(code))
+            .map(|c| c[1].to_string())
+        else {
+            continue;
+        };
+
+        let end = if python {
+            let indent = indent_of(lines[i]);
+            let end = (i + 1..lines.len())
+                .find(|&j| !lines[j].trim().is_empty() && indent_of(lines[j]) <= indent)
+                .unwrap_or(lines.len());
+            (i..end).rev().find(|&j| !lines[j].trim().is_empty()).unwrap_or(i)
+        } else {
+            // The item ends at its semicolon, or where its block closes
+            let mut end = i;
+            for (j, code) in codes.iter().enumerate().skip(i) {
+                let brace = code.find('{');
+                let semicolon = code.find(';');
+                match (brace, semicolon) {
+                    (Some(b), s) if s.is_none_or(|s| b < s) => {
+                        end = block_end(&codes, j, b).map_or(lines.len() - 1, |(e, _)| e);
+                        break;
+                    }
+                    (_, Some(_)) => {
+                        end = j;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            end
+        };
+
+        let start = (0..i).rev().take_while(|&j| is_preamble(lines[j])).last().unwrap_or(i);
+        found.push((name, start + 1, end + 1));
+    }
+    Some(found)
+}
@@ -0,0 +1,219 @@
+use anyhow::{Context, Result};
+use log::warn;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use crate::cache::CacheMap;
+use crate::file_analysis::DirectoryMap;
+use crate::manifest::Manifest;
+use crate::skeleton::declarations;
+
+/// Snapshots of the files last included in full, stored in the start directory
+pub const SNAPSHOT_FILE_NAME: &str = ".claude-snapshots";
+
+/// A file as it was last read into the context: hashes of its lines and of
+/// its declarations, enough to tell how it changed without keeping the source
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Snapshot {
+    pub lines: Vec<u64>,
+    /// Declaration names in order with the hash of their text, for languages
+    /// the skeleton scanner reads
+    pub declarations: Option<Vec<(String, u64)>>,
+}
+
+impl Snapshot {
+    pub fn new(ext: &str, content: &str) -> Self {
+        let lines: Vec<&str> = content.lines().collect();
+        let declarations = declarations(ext, content).map(|found| {
+            found
+                .into_iter()
+                .map(|(name, start, end)| (name, hash(&lines[start - 1..end])))
+                .collect()
+        });
+        Snapshot {
+            lines: lines.iter().map(hash).collect(),
+            declarations,
+        }
+    }
+}
+
+fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Snapshots of the files last read into the context, by path
+pub type SnapshotMap = HashMap<PathBuf, Snapshot>;
+
+/// Load the snapshots if they exist
+pub fn load_snapshots(directory: &Path) -> Result<SnapshotMap> {
+    let path = directory.join(SNAPSHOT_FILE_NAME);
+    if !path.exists() {
+        return Ok(SnapshotMap::new());
+    }
+
+    let content = fs::read_to_string(&path).context("Failed to read snapshots")?;
+    match serde_json::from_str(&content) {
+        Ok(snapshots) => Ok(snapshots),
+        Err(e) => {
+            warn!("Invalid snapshot file format. Creating a new one: {}", e);
+            Ok(SnapshotMap::new())
+        }
+    }
+}
+
+/// Save the snapshots
+pub fn save_snapshots(directory: &Path, snapshots: &SnapshotMap) -> Result<()> {
+    let content = serde_json::to_string(snapshots).context("Failed to serialize snapshots")?;
+    fs::write(directory.join(SNAPSHOT_FILE_NAME), content).context("Failed to write snapshots")
+}
+
+/// Text files at or below a path
+fn text_files_under<'a>(dir_info: &'a DirectoryMap, path: &'a Path) -> impl Iterator<Item = &'a Path> {
+    dir_info
+        .values()
+        .flat_map(|info| info.files.iter())
+        .filter(move |f| !f.binary && f.path.starts_with(path))
+        .map(|f| f.path.as_path())
+}
+
+/// Replace the snapshots of the files a manifest reads in full with ones of
+/// their current content
+pub fn record_reads(snapshots: &mut SnapshotMap, manifest: &Manifest, start_dir: &Path, dir_info: &DirectoryMap) {
+    for entry in manifest.entries.iter().filter(|e| e.action == "read") {
+        let path = start_dir.join(&entry.path);
+        for file in text_files_under(dir_info, &path) {
+            if let Ok(content) = fs::read_to_string(file) {
+                let ext = file.extension().and_then(|e| e.to_str()).unwrap_or("");
+                snapshots.insert(file.to_path_buf(), Snapshot::new(ext, &content));
+            }
+        }
+    }
+}
+
+/// How a file changed since its snapshot
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeSummary {
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// Declarations whose text changed, with "+" or "-" in front of those
+    /// added or removed
+    pub symbols: Vec<String>,
+}
+
+impl ChangeSummary {
+    /// One line for the prompt, e.g. `+12 -3 lines; parse, +render`
+    pub fn describe(&self) -> String {
+        let mut line = format!("+{} -{} lines", self.lines_added, self.lines_removed);
+        if !self.symbols.is_empty() {
+            line.push_str("; ");
+            line.push_str(&self.symbols.join(", "));
+        }
+        line
+    }
+}
+
+/// Compare two snapshots of a file: lines added and removed (matching the
+/// unchanged start and end, then counting the lines in between), and for
+/// languages the skeleton scanner reads, the declarations that changed
+pub fn summarize_change(old: &Snapshot, new: &Snapshot) -> ChangeSummary {
+    let (old_lines, new_lines) = (&old.lines, &new.lines);
+    let prefix = old_lines.iter().zip(new_lines).take_while(|(a, b)| a == b).count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old_lines[prefix..old_lines.len() - suffix];
+    let new_middle = &new_lines[prefix..new_lines.len() - suffix];
+
+    // Lines only moved within the changed region count as unchanged
+    let mut counts: HashMap<u64, isize> = HashMap::new();
+    for line in new_middle {
+        *counts.entry(*line).or_default() += 1;
+    }
+    for line in old_middle {
+        *counts.entry(*line).or_default() -= 1;
+    }
+    let lines_added = counts.values().filter(|&&n| n > 0).sum::<isize>() as usize;
+    let lines_removed = counts.values().filter(|&&n| n < 0).map(|n| -n).sum::<isize>() as usize;
+
+    // Declarations sharing a name (methods of different impls, overloads)
+    // are kept in order and compared one by one
+    let bodies = |declarations: &[(String, u64)]| -> BTreeMap<String, Vec<u64>> {
+        let mut bodies: BTreeMap<String, Vec<u64>> = BTreeMap::new();
+        for (name, body) in declarations {
+            bodies.entry(name.clone()).or_default().push(*body);
+        }
+        bodies
+    };
+    let mut symbols = Vec::new();
+    if let (Some(before), Some(after)) = (&old.declarations, &new.declarations) {
+        let (before, after) = (bodies(before), bodies(after));
+        let none = Vec::new();
+        let removed = before.keys().filter(|n| !after.contains_key(*n));
+        for name in after.keys().chain(removed) {
+            let (old_bodies, new_bodies) = (before.get(name).unwrap_or(&none), after.get(name).unwrap_or(&none));
+            for i in 0..old_bodies.len().max(new_bodies.len()) {
+                let symbol = match (old_bodies.get(i), new_bodies.get(i)) {
+                    (Some(old_body), Some(new_body)) if old_body == new_body => continue,
+                    (Some(_), Some(_)) => name.clone(),
+                    (None, _) => format!("+{}", name),
+                    (_, None) => format!("-{}", name),
+                };
+                if !symbols.contains(&symbol) {
+                    symbols.push(symbol);
+                }
+            }
+        }
+    }
+
+    ChangeSummary {
+        lines_added,
+        lines_removed,
+        symbols,
+    }
+}
+
+/// Cached reads whose files changed since they were last read, each with
+/// the changed files under it and how they changed
+pub fn changed_reads(
+    cache: &CacheMap,
+    snapshots: &SnapshotMap,
+    dir_info: &DirectoryMap,
+) -> Vec<(PathBuf, Vec<(PathBuf, ChangeSummary)>)> {
+    let mut reads: Vec<&PathBuf> = cache
+        .iter()
+        .filter(|(_, action)| *action == "read")
+        .map(|(path, _)| path)
+        .collect();
+    reads.sort();
+
+    let mut changed = Vec::new();
+    for read in reads {
+        let mut files = Vec::new();
+        for file in text_files_under(dir_info, read) {
+            let Some(snapshot) = snapshots.get(file) else {
+                continue;
+            };
+            let Ok(content) = fs::read_to_string(file) else {
+                continue;
+            };
+            let ext = file.extension().and_then(|e| e.to_str()).unwrap_or("");
+            let current = Snapshot::new(ext, &content);
+            if current != *snapshot {
+                files.push((file.to_path_buf(), summarize_change(snapshot, &current)));
+            }
+        }
+        if !files.is_empty() {
+            files.sort_by(|a, b| a.0.cmp(&b.0));
+            changed.push((read.clone(), files));
+        }
+    }
+    changed
+}
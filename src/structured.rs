@@ -0,0 +1,372 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::path::Path;
+
+use crate::changelog::{RECENT_RELEASES, is_changelog, recent_releases};
+use crate::markup::{is_markup, summarize_markup};
+
+/// Extensions of structured config files that get a schema-style summary
+pub const STRUCTURED_EXTENSIONS: [&str; 4] = [".json", ".yaml", ".yml", ".toml"];
+
+/// Maximum nesting depth rendered in a key tree
+const MAX_TREE_DEPTH: usize = 6;
+/// Maximum keys shown per object before collapsing the rest
+const MAX_KEYS_PER_OBJECT: usize = 25;
+/// Maximum length of a scalar value shown next to its key
+const MAX_VALUE_LEN: usize = 40;
+
+/// Check if a file is a structured config file (YAML/JSON/TOML)
+pub fn is_structured_file(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    STRUCTURED_EXTENSIONS.contains(&format!(".{}", ext).as_str())
+}
+
+/// Parse a structured file into JSON values, one per document
+/// (YAML files such as k8s manifests may contain several documents)
+pub fn parse_structured(path: &Path, content: &str) -> Result<Vec<Value>> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    match ext.as_str() {
+        "json" => Ok(vec![
+            serde_json::from_str(content).context("Failed to parse JSON")?,
+        ]),
+        "toml" => {
+            let value: toml::Value = toml::from_str(content).context("Failed to parse TOML")?;
+            Ok(vec![toml_to_json(value)])
+        }
+        "yaml" | "yml" => {
+            let mut documents = Vec::new();
+            for document in serde_yaml::Deserializer::from_str(content) {
+                let value: Value = serde::Deserialize::deserialize(document)
+                    .context("Failed to parse YAML")?;
+                if !value.is_null() {
+                    documents.push(value);
+                }
+            }
+            Ok(documents)
+        }
+        _ => anyhow::bail!("Unsupported structured file type: .{}", ext),
+    }
+}
+
+/// Convert a TOML value into a JSON value (datetimes become strings)
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => Value::from(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(k, v)| (k, toml_to_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Render a schema-style summary of a structured file: a collapsed key tree
+/// with value types, counts and notable scalar values
+pub fn summarize_structured(path: &Path, content: &str) -> Result<String> {
+    let documents = parse_structured(path, content)?;
+
+    let mut output = String::new();
+    for (idx, document) in documents.iter().enumerate() {
+        if documents.len() > 1 {
+            output.push_str(&format!("--- document {} of {}\n", idx + 1, documents.len()));
+        }
+        render_key_tree(document, 0, &mut output);
+    }
+
+    if documents.is_empty() {
+        output.push_str("(empty document)\n");
+    }
+
+    Ok(output)
+}
+
+/// Render a value as an indented key tree
+pub fn render_key_tree(value: &Value, depth: usize, output: &mut String) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map.iter().take(MAX_KEYS_PER_OBJECT) {
+                render_entry(key, child, depth, output);
+            }
+            if map.len() > MAX_KEYS_PER_OBJECT {
+                output.push_str(&format!(
+                    "{}... and {} more keys\n",
+                    "  ".repeat(depth),
+                    map.len() - MAX_KEYS_PER_OBJECT
+                ));
+            }
+        }
+        Value::Array(_) => render_entry("(root)", value, depth, output),
+        _ => output.push_str(&format!(
+            "{}{}\n",
+            "  ".repeat(depth),
+            describe_scalar(value)
+        )),
+    }
+}
+
+/// Render a single key and its value
+fn render_entry(key: &str, value: &Value, depth: usize, output: &mut String) {
+    let indent = "  ".repeat(depth);
+
+    match value {
+        Value::Object(map) => {
+            output.push_str(&format!(
+                "{}- {}: object ({} keys)\n",
+                indent,
+                key,
+                map.len()
+            ));
+            if depth + 1 < MAX_TREE_DEPTH {
+                render_key_tree(value, depth + 1, output);
+            } else if !map.is_empty() {
+                output.push_str(&format!("{}  ...\n", indent));
+            }
+        }
+        Value::Array(items) => {
+            output.push_str(&format!(
+                "{}- {}: array of {} ({} items)\n",
+                indent,
+                key,
+                array_element_type(items),
+                items.len()
+            ));
+            // Collapse arrays by rendering the first element as a representative
+            if let Some(first @ Value::Object(_)) = items.first() {
+                if depth + 1 < MAX_TREE_DEPTH {
+                    render_key_tree(first, depth + 1, output);
+                } else {
+                    output.push_str(&format!("{}  ...\n", indent));
+                }
+            }
+        }
+        _ => output.push_str(&format!("{}- {}: {}\n", indent, key, describe_scalar(value))),
+    }
+}
+
+/// Name of the JSON type of a value
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(n) if n.is_f64() => "float",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Describe the element type(s) of an array, e.g. "string" or "integer|null"
+fn array_element_type(items: &[Value]) -> String {
+    let mut types: Vec<&str> = Vec::new();
+    for item in items {
+        let name = type_name(item);
+        if !types.contains(&name) {
+            types.push(name);
+        }
+    }
+
+    if types.is_empty() {
+        "unknown".to_string()
+    } else {
+        types.join("|")
+    }
+}
+
+/// Describe a scalar value as its type plus a short rendering of the value
+fn describe_scalar(value: &Value) -> String {
+    let rendered = match value {
+        Value::String(s) => format!("{:?}", s),
+        _ => value.to_string(),
+    };
+
+    if rendered.chars().count() > MAX_VALUE_LEN {
+        let truncated: String = rendered.chars().take(MAX_VALUE_LEN).collect();
+        format!("{} = {}...", type_name(value), truncated)
+    } else {
+        format!("{} = {}", type_name(value), rendered)
+    }
+}
+
+/// Number of lines kept when sampling a plain text data file
+const SAMPLE_HEAD_LINES: usize = 20;
+
+/// Schema inferred from a JSON value, merged across array elements
+#[derive(Debug, Default)]
+pub struct Schema {
+    /// Observed JSON types, in first-seen order
+    types: Vec<&'static str>,
+    /// Number of values this schema was inferred from
+    occurrences: usize,
+    /// First scalar value seen, used as an example
+    example: Option<String>,
+    /// Number of values that were objects
+    object_count: usize,
+    /// Object fields, in first-seen order
+    fields: Vec<(String, Schema)>,
+    /// Schema of array elements
+    items: Option<Box<Schema>>,
+    /// Largest array length seen
+    max_items: usize,
+}
+
+impl Schema {
+    /// Infer the schema of a JSON value
+    pub fn infer(value: &Value) -> Self {
+        let mut schema = Schema::default();
+        schema.merge(value);
+        schema
+    }
+
+    /// Merge another observed value into this schema
+    fn merge(&mut self, value: &Value) {
+        self.occurrences += 1;
+        let name = type_name(value);
+        if !self.types.contains(&name) {
+            self.types.push(name);
+        }
+
+        match value {
+            Value::Object(map) => {
+                self.object_count += 1;
+                for (key, child) in map {
+                    match self.fields.iter_mut().find(|(k, _)| k == key) {
+                        Some((_, field)) => field.merge(child),
+                        None => self.fields.push((key.clone(), Schema::infer(child))),
+                    }
+                }
+            }
+            Value::Array(items) => {
+                self.max_items = self.max_items.max(items.len());
+                let element = self.items.get_or_insert_with(Box::default);
+                for item in items {
+                    element.merge(item);
+                }
+            }
+            Value::Null => {}
+            _ => {
+                if self.example.is_none() {
+                    let rendered = describe_scalar(value);
+                    let example = rendered.split_once(" = ").map_or(rendered.as_str(), |(_, v)| v);
+                    self.example = Some(example.to_string());
+                }
+            }
+        }
+    }
+
+    /// Render the schema as an indented field list
+    pub fn render(&self, name: &str, parent_occurrences: usize, depth: usize, output: &mut String) {
+        let indent = "  ".repeat(depth);
+        let mut line = format!("{}- {}: {}", indent, name, self.describe_type());
+
+        if self.occurrences < parent_occurrences {
+            line.push_str(&format!(" (in {}/{})", self.occurrences, parent_occurrences));
+        }
+        if let Some(example) = &self.example {
+            line.push_str(&format!(" e.g. {}", example));
+        }
+        output.push_str(&line);
+        output.push('\n');
+
+        if depth + 1 >= MAX_TREE_DEPTH {
+            return;
+        }
+
+        for (field, schema) in self.fields.iter().take(MAX_KEYS_PER_OBJECT) {
+            schema.render(field, self.object_count, depth + 1, output);
+        }
+        if self.fields.len() > MAX_KEYS_PER_OBJECT {
+            output.push_str(&format!(
+                "{}  ... and {} more fields\n",
+                indent,
+                self.fields.len() - MAX_KEYS_PER_OBJECT
+            ));
+        }
+
+        if let Some(items) = &self.items
+            && !items.fields.is_empty()
+        {
+            for (field, schema) in items.fields.iter().take(MAX_KEYS_PER_OBJECT) {
+                schema.render(field, items.object_count, depth + 1, output);
+            }
+        }
+    }
+
+    /// Describe the type(s) of this schema, e.g. "array[<=50] of object" or "string|null"
+    fn describe_type(&self) -> String {
+        self.types
+            .iter()
+            .map(|t| match (*t, &self.items) {
+                ("array", Some(items)) if !items.types.is_empty() => {
+                    format!("array[<={}] of {}", self.max_items, items.types.join("|"))
+                }
+                _ => t.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("|")
+    }
+}
+
+/// Infer and render the schema of a JSON data file
+pub fn infer_json_schema(content: &str) -> Result<String> {
+    let value: Value = serde_json::from_str(content).context("Failed to parse JSON")?;
+    let mut output = String::new();
+    Schema::infer(&value).render("(root)", 1, 0, &mut output);
+    Ok(output)
+}
+
+/// Sample a data file: JSON gets an inferred schema, anything else
+/// the first few lines plus a line count
+pub fn sample_content(path: &Path, content: &str) -> String {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    if is_changelog(path)
+        && let Some(recent) = recent_releases(content, RECENT_RELEASES)
+    {
+        return recent;
+    }
+
+    if is_markup(path)
+        && let Some(summary) = summarize_markup(content)
+    {
+        return summary.render();
+    }
+
+    if ext == "json" {
+        match infer_json_schema(content) {
+            Ok(schema) => return format!("Schema (fields, types and examples):\n{}", schema),
+            Err(e) => log::warn!("Failed to infer schema for {}: {}", path.display(), e),
+        }
+    }
+
+    let total_lines = content.lines().count();
+    let mut output = format!(
+        "First {} of {} lines:\n",
+        SAMPLE_HEAD_LINES.min(total_lines),
+        total_lines
+    );
+    for line in content.lines().take(SAMPLE_HEAD_LINES) {
+        output.push_str(line);
+        output.push('\n');
+    }
+    output
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::file_analysis::DirectoryMap;
+
+/// A default action proposed for a path before the user has chosen one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// Action name, as stored in the cache (e.g. "exclude")
+    pub action: String,
+    /// Short explanation shown next to the prompt
+    pub reason: String,
+}
+
+pub type SuggestionMap = HashMap<PathBuf, Suggestion>;
+
+/// Get the suggested action for a path if there is one
+pub fn get_suggestion_for_path<'a>(
+    path: &Path,
+    suggestions: &'a SuggestionMap,
+) -> Option<&'a Suggestion> {
+    suggestions.get(path)
+}
+
+/// Add suggestions from another source without overriding existing ones
+pub fn merge_suggestions(suggestions: &mut SuggestionMap, extra: SuggestionMap) {
+    for (path, suggestion) in extra {
+        suggestions.entry(path).or_insert(suggestion);
+    }
+}
+
+/// Suggest the same action for directories whose files all share one suggestion,
+/// so whole generated/vendored trees can be handled with a single decision
+pub fn propagate_to_directories(suggestions: &mut SuggestionMap, dir_info: &DirectoryMap) {
+    // Deepest directories first so parents can reuse their children's results
+    let mut dirs: Vec<&PathBuf> = dir_info.keys().collect();
+    dirs.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
+
+    for dir in dirs {
+        if suggestions.contains_key(dir) {
+            continue;
+        }
+        let info = &dir_info[dir];
+
+        let children: Vec<&PathBuf> = info
+            .files
+            .iter()
+            .map(|f| &f.path)
+            .chain(info.subdirs.iter().filter(|d| dir_info.contains_key(*d)))
+            .collect();
+
+        let mut shared: Option<&Suggestion> = None;
+        let mut uniform = !children.is_empty();
+        for child in children {
+            match (suggestions.get(child), shared) {
+                (Some(s), None) => shared = Some(s),
+                (Some(s), Some(prev)) if s.action == prev.action => {}
+                _ => {
+                    uniform = false;
+                    break;
+                }
+            }
+        }
+
+        if uniform && let Some(suggestion) = shared.cloned() {
+            suggestions.insert(dir.clone(), suggestion);
+        }
+    }
+}
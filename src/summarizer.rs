@@ -0,0 +1,139 @@
+use log::{info, warn};
+use std::sync::OnceLock;
+
+use crate::config::SummarizerConfig;
+use crate::launcher::{CLAUDE_LAUNCHER, find_on_path};
+use crate::ledger::SUMMARY_MODEL;
+use crate::llm::{
+    AnthropicApi, CLAUDE_CONTEXT, ClaudeCli, DEFAULT_OLLAMA_MODEL, DEFAULT_OPENAI_MODEL, LlmProvider,
+    OLLAMA_CONTEXT, OPENAI_CONTEXT, OPENAI_KEY_VAR, Ollama, OpenAi, RetryPolicy,
+};
+use crate::token_count::API_KEY_VAR;
+
+/// Ollama server used when neither the config nor OLLAMA_HOST names one
+pub const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+/// Environment variable Ollama itself reads for its address
+pub const OLLAMA_HOST_VAR: &str = "OLLAMA_HOST";
+
+/// Summarizer chosen with --summarizer or the config's `backend`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SummarizerKind {
+    /// The Anthropic API when a key is set, else the claude CLI
+    #[default]
+    Claude,
+    /// The OpenAI API, with the key in OPENAI_API_KEY
+    #[value(name = "openai")]
+    OpenAi,
+    /// A local model served by Ollama, for summaries generated offline
+    Ollama,
+}
+
+impl SummarizerKind {
+    /// What this backend needs before summaries can be generated with it
+    pub fn requirement(self) -> String {
+        match self {
+            SummarizerKind::Claude => format!("set {} or install the claude CLI", API_KEY_VAR),
+            SummarizerKind::OpenAi => format!("set {}", OPENAI_KEY_VAR),
+            SummarizerKind::Ollama => "start an Ollama server".to_string(),
+        }
+    }
+}
+
+/// The provider summaries of this run are generated with
+static SUMMARIZER: OnceLock<Box<dyn LlmProvider>> = OnceLock::new();
+/// The backend this run asked for, whether or not it could be set up
+static BACKEND: OnceLock<SummarizerKind> = OnceLock::new();
+
+/// An environment variable's value, unless it is unset or empty
+fn env_value(var: &str) -> Option<String> {
+    std::env::var(var).ok().filter(|v| !v.is_empty())
+}
+
+/// Base URL of the Ollama server: the config's, else OLLAMA_HOST's (which
+/// may leave out the scheme), else the default
+pub fn ollama_url(config: &SummarizerConfig) -> String {
+    let url = config
+        .ollama_url
+        .clone()
+        .or_else(|| env_value(OLLAMA_HOST_VAR))
+        .unwrap_or_else(|| DEFAULT_OLLAMA_URL.to_string());
+    let url = url.trim_end_matches('/');
+    if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("http://{}", url)
+    }
+}
+
+/// The provider a summarizer config asks for. Claude summaries use the API
+/// when a key is set in the environment or the config, else the claude CLI
+/// if it is on PATH; OpenAI summaries need OPENAI_API_KEY. None when the
+/// provider can't be reached this way.
+pub fn summary_provider(config: &SummarizerConfig) -> Option<Box<dyn LlmProvider>> {
+    let retry = RetryPolicy::from_config(config);
+    let model = |default: &str| config.model.clone().unwrap_or_else(|| default.to_string());
+    let context = |default: usize| config.context_window.unwrap_or(default);
+    match config.backend.unwrap_or_default() {
+        SummarizerKind::Claude => match env_value(API_KEY_VAR).or_else(|| config.api_key.clone()) {
+            Some(key) => Some(Box::new(AnthropicApi {
+                key,
+                model: model(SUMMARY_MODEL),
+                context: context(CLAUDE_CONTEXT),
+                retry,
+            })),
+            None => {
+                let Some(path) = find_on_path(CLAUDE_LAUNCHER) else {
+                    info!("No {} and no claude CLI; summaries won't be generated", API_KEY_VAR);
+                    return None;
+                };
+                Some(Box::new(ClaudeCli {
+                    path,
+                    model: model(SUMMARY_MODEL),
+                    context: context(CLAUDE_CONTEXT),
+                    retry,
+                }))
+            }
+        },
+        SummarizerKind::OpenAi => {
+            let Some(key) = env_value(OPENAI_KEY_VAR) else {
+                info!("No {}; summaries won't be generated", OPENAI_KEY_VAR);
+                return None;
+            };
+            Some(Box::new(OpenAi {
+                key,
+                model: model(DEFAULT_OPENAI_MODEL),
+                context: context(OPENAI_CONTEXT),
+                retry,
+            }))
+        }
+        SummarizerKind::Ollama => Some(Box::new(Ollama {
+            url: ollama_url(config),
+            model: model(DEFAULT_OLLAMA_MODEL),
+            context: context(OLLAMA_CONTEXT),
+            retry,
+        })),
+    }
+}
+
+/// Generate this run's summaries with the provider the config asks for
+pub fn configure_summarizer(config: &SummarizerConfig) {
+    let _ = BACKEND.set(config.backend.unwrap_or_default());
+    let Some(provider) = summary_provider(config) else {
+        return;
+    };
+    if SUMMARIZER.set(provider).is_err() {
+        warn!("The summarizer was already configured");
+    }
+}
+
+/// The provider set up for this run, if any
+pub fn summarizer() -> Option<&'static dyn LlmProvider> {
+    SUMMARIZER.get().map(Box::as_ref)
+}
+
+/// What the backend this run asked for still needs, for errors when there
+/// is no provider
+pub fn summarizer_requirement() -> String {
+    BACKEND.get().copied().unwrap_or_default().requirement()
+}
@@ -1,38 +1,104 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use log::{info, warn};
+use std::cell::Cell;
 use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::DefaultHasher;
 use std::fs;
 use std::hash::{Hash, Hasher};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use crate::anchors::git_blob_hash;
+use crate::encryption::{CACHE_KEY_VAR, cache_cipher, decrypt, encrypt, is_encrypted};
+use crate::normalize::{is_doc_file, normalize_doc};
+
+/// Format version of summary exports
+pub const EXPORT_VERSION: u32 = 1;
 
 /// Cache of file summaries
 #[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct SummaryCache {
     /// Map from file path hash to summary info
     entries: HashMap<String, SummaryEntry>,
+    /// Lookups over all runs that saved the cache
+    #[serde(default)]
+    totals: Cell<CacheStats>,
+    /// Lookups since the cache was loaded
+    #[serde(skip)]
+    run: Cell<CacheStats>,
+}
+
+/// Hits and misses of summary lookups
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    pub fn lookups(&self) -> u64 {
+        self.hits + self.misses
+    }
+
+    /// Share of lookups that were hits, as a percentage
+    pub fn hit_rate(&self) -> Option<u64> {
+        (self.hits * 100).checked_div(self.lookups())
+    }
+
+    fn record(&mut self, hit: bool) {
+        if hit {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+    }
+}
+
+/// Summaries shared between machines, keyed by the content they summarize
+/// rather than by local paths
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SummaryExport {
+    pub version: u32,
+    pub summaries: Vec<ExportedSummary>,
+}
+
+/// One summary in an export
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ExportedSummary {
+    /// Path relative to the exporting project's root, for reference only
+    pub path: PathBuf,
+    /// Git blob hash of the summarized content (normalized for documentation)
+    pub blob: String,
+    pub timestamp: u64,
+    pub summary: String,
 }
 
 /// Entry in the summary cache
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct SummaryEntry {
+    /// The summarized file; missing from entries cached before it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
     /// Content hash of the file when it was summarized
     pub content_hash: String,
     /// Timestamp when the summary was created
     pub timestamp: u64,
     /// The generated summary
     pub summary: String,
+    /// Timestamp of the last lookup that used the summary
+    #[serde(default)]
+    pub accessed_at: Cell<u64>,
+    /// Lookups that used the summary
+    #[serde(default)]
+    pub access_count: Cell<u64>,
 }
 
 impl SummaryCache {
     /// Create a new empty cache
     pub fn new() -> Self {
-        Self {
-            entries: HashMap::new(),
-        }
+        Self::default()
     }
 
-    /// Get a summary from the cache
+    /// Get a summary from the cache, without counting it as a lookup
     pub fn get_summary(&self, file_path: &Path, content_hash: &str) -> Option<&str> {
         let path_hash = hash_path(file_path);
         match self.entries.get(&path_hash) {
@@ -41,20 +107,161 @@ impl SummaryCache {
         }
     }
     
+    /// Get a summary from the cache for use in the context, recording the
+    /// hit or miss
+    pub fn lookup_summary(&self, file_path: &Path, content_hash: &str) -> Option<&str> {
+        let entry = self
+            .entries
+            .get(&hash_path(file_path))
+            .filter(|entry| entry.content_hash == content_hash);
+        if let Some(entry) = entry {
+            entry.accessed_at.set(now());
+            entry.access_count.set(entry.access_count.get() + 1);
+        }
+        self.record_lookup(entry.is_some());
+        entry.map(|entry| entry.summary.as_str())
+    }
+
+    /// Count a lookup made without `lookup_summary`
+    pub fn record_lookup(&self, hit: bool) {
+        for stats in [&self.run, &self.totals] {
+            let mut updated = stats.get();
+            updated.record(hit);
+            stats.set(updated);
+        }
+    }
+
+    /// Lookups since the cache was loaded
+    pub fn run_stats(&self) -> CacheStats {
+        self.run.get()
+    }
+
+    /// Lookups over all runs, including this one
+    pub fn total_stats(&self) -> CacheStats {
+        self.totals.get()
+    }
+
+    /// Number of cached summaries
+    pub fn summary_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Cached summaries that were never used by a lookup
+    pub fn unused(&self) -> usize {
+        self.entries.values().filter(|e| e.access_count.get() == 0).count()
+    }
+
+    /// Whether any summary, current or stale, is cached for a file
+    pub fn contains(&self, file_path: &Path) -> bool {
+        self.entries.contains_key(&hash_path(file_path))
+    }
+
+    /// The cached entry for a file, current or stale
+    pub fn entry(&self, file_path: &Path) -> Option<&SummaryEntry> {
+        self.entries.get(&hash_path(file_path))
+    }
+
+    /// Cached summaries whose text or file path contains a term, ignoring
+    /// case, sorted by path. Paths of entries that don't record one are found
+    /// by walking the project.
+    pub fn search(&self, term: &str, base_dir: &Path) -> Vec<(PathBuf, &SummaryEntry)> {
+        let mut walked: HashMap<String, PathBuf> = HashMap::new();
+        if self.entries.values().any(|e| e.path.is_none()) {
+            for entry in walkdir::WalkDir::new(base_dir).follow_links(true).into_iter().flatten() {
+                if entry.file_type().is_file() {
+                    walked.insert(hash_path(entry.path()), entry.into_path());
+                }
+            }
+        }
+
+        let term = term.to_lowercase();
+        let mut found: Vec<(PathBuf, &SummaryEntry)> = self
+            .entries
+            .iter()
+            .filter_map(|(path_hash, entry)| {
+                let path = entry.path.clone().or_else(|| walked.get(path_hash).cloned())?;
+                let matches = entry.summary.to_lowercase().contains(&term)
+                    || path.to_string_lossy().to_lowercase().contains(&term);
+                matches.then_some((path, entry))
+            })
+            .collect();
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+        found
+    }
+
     /// Insert a summary into the cache
     pub fn insert_summary(&mut self, file_path: &Path, content_hash: &str, summary: String) {
         let path_hash = hash_path(file_path);
         let entry = SummaryEntry {
+            path: Some(canonical(file_path)),
             content_hash: content_hash.to_string(),
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs(),
+            timestamp: now(),
             summary,
+            ..Default::default()
         };
         self.entries.insert(path_hash, entry);
     }
 
+    /// Current summaries of the given files, for sharing. Stale summaries are left out.
+    pub fn export(&self, files: &[PathBuf], base_dir: &Path) -> SummaryExport {
+        let mut summaries = Vec::new();
+        for file in files {
+            let Some(entry) = self.entries.get(&hash_path(file)) else {
+                continue;
+            };
+            let Ok(bytes) = fs::read(file) else {
+                continue;
+            };
+            let content = String::from_utf8_lossy(&bytes);
+            if entry.content_hash != hash_file_content(file, &content) {
+                continue;
+            }
+            summaries.push(ExportedSummary {
+                path: file.strip_prefix(base_dir).unwrap_or(file).to_path_buf(),
+                blob: share_key(file, &bytes),
+                timestamp: entry.timestamp,
+                summary: entry.summary.clone(),
+            });
+        }
+        SummaryExport {
+            version: EXPORT_VERSION,
+            summaries,
+        }
+    }
+
+    /// Take exported summaries for the given files whose content matches,
+    /// wherever the files are. Current local summaries are kept.
+    /// Returns how many summaries were imported.
+    pub fn import(&mut self, export: &SummaryExport, files: &[PathBuf]) -> usize {
+        let by_blob: HashMap<&str, &ExportedSummary> =
+            export.summaries.iter().map(|s| (s.blob.as_str(), s)).collect();
+
+        let mut imported = 0;
+        for file in files {
+            let Ok(bytes) = fs::read(file) else {
+                continue;
+            };
+            let Some(exported) = by_blob.get(share_key(file, &bytes).as_str()) else {
+                continue;
+            };
+            let content_hash = hash_file_content(file, &String::from_utf8_lossy(&bytes));
+            if self.get_summary(file, &content_hash).is_some() {
+                continue;
+            }
+            self.entries.insert(
+                hash_path(file),
+                SummaryEntry {
+                    path: Some(canonical(file)),
+                    content_hash,
+                    timestamp: exported.timestamp,
+                    summary: exported.summary.clone(),
+                    ..Default::default()
+                },
+            );
+            imported += 1;
+        }
+        imported
+    }
 
     /// Cleans up summaries that no longer exist in the filesystem
     /// This preserves all valid summaries regardless of age
@@ -87,6 +294,14 @@ impl SummaryCache {
     }
 }
 
+/// Seconds since the Unix epoch
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
 /// Calculate a hash for any hashable value
 fn calculate_hash<T: Hash>(value: T) -> String {
     let mut hasher = DefaultHasher::new();
@@ -94,10 +309,14 @@ fn calculate_hash<T: Hash>(value: T) -> String {
     format!("{:x}", hasher.finish())
 }
 
+/// A path with symlinks and relative parts resolved, when it exists
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
 /// Calculate a hash for a file path
 fn hash_path(path: &Path) -> String {
-    let canonical_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
-    calculate_hash(canonical_path)
+    calculate_hash(canonical(path))
 }
 
 /// Calculate a hash for file content
@@ -105,12 +324,45 @@ pub fn hash_content(content: &str) -> String {
     calculate_hash(content)
 }
 
+/// Cache key for a file's content. Documentation is hashed after
+/// normalization, so reformatted copies and other releases of the same
+/// document share summaries.
+pub fn hash_file_content(path: &Path, content: &str) -> String {
+    if is_doc_file(path) {
+        hash_content(&normalize_doc(path, content))
+    } else {
+        hash_content(content)
+    }
+}
+
+/// Key matching a file's content across machines in exports
+fn share_key(path: &Path, bytes: &[u8]) -> String {
+    if is_doc_file(path) {
+        git_blob_hash(normalize_doc(path, &String::from_utf8_lossy(bytes)).as_bytes())
+    } else {
+        git_blob_hash(bytes)
+    }
+}
+
 /// Load summary cache from disk
 pub fn load_summary_cache(base_dir: &Path) -> Result<SummaryCache> {
     let cache_path = base_dir.join(".claude-summaries");
 
     if cache_path.exists() {
-        let cache_content = fs::read_to_string(&cache_path).context("Failed to read summary cache file")?;
+        let mut cache_content = fs::read_to_string(&cache_path).context("Failed to read summary cache file")?;
+
+        // An encrypted cache can't be read, and mustn't be replaced, without its key
+        if is_encrypted(&cache_content) {
+            let Some(cipher) = cache_cipher()? else {
+                bail!(
+                    "Summary cache {} is encrypted; set {} to use it",
+                    cache_path.display(),
+                    CACHE_KEY_VAR
+                );
+            };
+            cache_content = decrypt(&cipher, &cache_content)
+                .with_context(|| format!("Failed to decrypt {}", cache_path.display()))?;
+        }
 
         match serde_json::from_str(&cache_content) {
             Ok(cache) => {
@@ -127,15 +379,40 @@ pub fn load_summary_cache(base_dir: &Path) -> Result<SummaryCache> {
     }
 }
 
-/// Save summary cache to disk
+/// Save summary cache to disk, encrypted when CACHE_KEY_VAR is set
 pub fn save_summary_cache(base_dir: &Path, cache: &SummaryCache) -> Result<()> {
     let cache_path = base_dir.join(".claude-summaries");
 
-    let cache_content = serde_json::to_string_pretty(cache).context("Failed to serialize summary cache")?;
+    let mut cache_content = serde_json::to_string_pretty(cache).context("Failed to serialize summary cache")?;
+    if let Some(cipher) = cache_cipher()? {
+        cache_content = encrypt(&cipher, &cache_content)?;
+    }
 
     fs::write(&cache_path, cache_content).context("Failed to write summary cache file")?;
 
     info!("Summary cache saved to {}", cache_path.display());
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Write a summary export as JSON
+pub fn write_summary_export(path: &Path, export: &SummaryExport) -> Result<()> {
+    let content = serde_json::to_string_pretty(export).context("Failed to serialize summary export")?;
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Read a summary export written by `write_summary_export`
+pub fn read_summary_export(path: &Path) -> Result<SummaryExport> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let export: SummaryExport = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid summary export {}", path.display()))?;
+    if export.version != EXPORT_VERSION {
+        bail!(
+            "Unsupported summary export version {} in {} (expected {})",
+            export.version,
+            path.display(),
+            EXPORT_VERSION
+        );
+    }
+    Ok(export)
+}
@@ -0,0 +1,76 @@
+use anyhow::{Result, bail};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::config::{Config, TaskPreset};
+
+/// Task presets that ship with the tool
+pub const BUILT_IN_TASKS: [&str; 3] = ["review", "onboarding", "bugfix"];
+
+/// A built-in task preset
+fn built_in_task(name: &str) -> Option<TaskPreset> {
+    let class_actions = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+        pairs.iter().map(|(c, a)| (c.to_string(), a.to_string())).collect()
+    };
+    let preset = match name {
+        // Code and its tests in full; everything around them kept brief
+        "review" => TaskPreset {
+            budget: Some("source=65,tests=25,docs+config=10".to_string()),
+            class_actions: class_actions(&[("docs", "summarize"), ("vendored", "exclude"), ("unknown", "stats")]),
+            preamble: Some(
+                "You are reviewing {project}. Look for bugs, unclear code and missing tests, \
+                 and cite the file and lines for each finding. Read every context file, then say 'Ready'."
+                    .to_string(),
+            ),
+            ..Default::default()
+        },
+        // Docs and the shape of the code rather than every line of it
+        "onboarding" => TaskPreset {
+            budget: Some("source=50,docs=35,tests+config=15".to_string()),
+            class_actions: class_actions(&[("tests", "skeleton"), ("vendored", "exclude"), ("unknown", "stats")]),
+            preamble: Some(
+                "You are new to {project}. Build a picture of its architecture, main flows and \
+                 conventions before answering questions about it. Read every context file, then say 'Ready'."
+                    .to_string(),
+            ),
+            ..Default::default()
+        },
+        // The code paths and the tests that exercise them
+        "bugfix" => TaskPreset {
+            budget: Some("source=60,tests=30,docs+config=10".to_string()),
+            class_actions: class_actions(&[("docs", "stats"), ("vendored", "exclude"), ("unknown", "stats")]),
+            preamble: Some(
+                "You are fixing a bug in {project}. Trace the relevant code paths and the tests that \
+                 cover them before proposing a change. Read every context file, then say 'Ready'."
+                    .to_string(),
+            ),
+            ..Default::default()
+        },
+        _ => return None,
+    };
+    Some(preset)
+}
+
+/// The task preset with this name: a built-in one with the config's
+/// `[tasks.NAME]` fields over it, or one defined only in the config
+pub fn task_preset(config: &Config, name: &str) -> Result<TaskPreset> {
+    match (built_in_task(name), config.tasks.get(name)) {
+        (Some(built_in), Some(configured)) => Ok(built_in.overridden_by(configured)),
+        (Some(built_in), None) => Ok(built_in),
+        (None, Some(configured)) => Ok(configured.clone()),
+        (None, None) => {
+            let mut known: Vec<&str> = BUILT_IN_TASKS.to_vec();
+            known.extend(config.tasks.keys().map(String::as_str).filter(|k| !BUILT_IN_TASKS.contains(k)));
+            bail!("Unknown task preset '{}' (known: {})", name, known.join(", "))
+        }
+    }
+}
+
+/// A preamble template with `{project}` filled in
+pub fn fill_preamble(template: &str, start_dir: &Path) -> String {
+    let project = start_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "the project".to_string());
+    template.replace("{project}", &project)
+}
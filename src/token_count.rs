@@ -0,0 +1,254 @@
+use anyhow::{Context, Result, anyhow};
+use log::{info, warn};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use crate::anchors::git_blob_hash;
+use crate::config::SummarizerConfig;
+use crate::file_analysis::DirectoryMap;
+use crate::llm::{CallError, RetryPolicy, http_agent, post_json};
+
+/// Anthropic token counting endpoint
+pub const COUNT_TOKENS_URL: &str = "https://api.anthropic.com/v1/messages/count_tokens";
+/// API version sent with each request
+pub const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// Model whose tokenizer is used when none is given
+pub const DEFAULT_COUNT_MODEL: &str = "claude-sonnet-4-5";
+/// Environment variable holding the API key
+pub const API_KEY_VAR: &str = "ANTHROPIC_API_KEY";
+/// Cache file for counted tokens, stored in the start directory
+pub const TOKEN_CACHE_FILE_NAME: &str = ".claude-token-counts";
+/// Format of the cache; counts of older formats include the message wrapper
+const TOKEN_CACHE_VERSION: u32 = 2;
+/// Requests sent at once
+pub const COUNT_BATCH_SIZE: usize = 8;
+/// Cache key of a model's per-request overhead, which no file hash can take
+const OVERHEAD_KEY: &str = "overhead";
+
+/// Exact token counts keyed by model and content hash
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct TokenCountCache {
+    #[serde(default)]
+    version: u32,
+    entries: HashMap<String, usize>,
+}
+
+impl Default for TokenCountCache {
+    fn default() -> Self {
+        Self {
+            version: TOKEN_CACHE_VERSION,
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl TokenCountCache {
+    fn key(model: &str, hash: &str) -> String {
+        format!("{}:{}", model, hash)
+    }
+
+    /// Drop the counts of content no file has any more, keeping each
+    /// model's overhead
+    pub fn retain_hashes(&mut self, hashes: &HashSet<String>) {
+        self.entries.retain(|key, _| {
+            let hash = key.rsplit(':').next().unwrap_or("");
+            hash == OVERHEAD_KEY || hashes.contains(hash)
+        });
+    }
+
+    /// Get the cached count for content with this hash
+    pub fn get(&self, model: &str, hash: &str) -> Option<usize> {
+        self.entries.get(&Self::key(model, hash)).copied()
+    }
+
+    /// Record the count for content with this hash
+    pub fn insert(&mut self, model: &str, hash: &str, tokens: usize) {
+        self.entries.insert(Self::key(model, hash), tokens);
+    }
+}
+
+/// Load the token count cache if it exists
+pub fn load_token_cache(directory: &Path) -> Result<TokenCountCache> {
+    let cache_path = directory.join(TOKEN_CACHE_FILE_NAME);
+    if !cache_path.exists() {
+        return Ok(TokenCountCache::default());
+    }
+
+    let content = fs::read_to_string(&cache_path).context("Failed to read token count cache")?;
+    match serde_json::from_str::<TokenCountCache>(&content) {
+        Ok(cache) if cache.version == TOKEN_CACHE_VERSION => Ok(cache),
+        Ok(_) => {
+            info!("Token count cache is from an older version; counting again");
+            Ok(TokenCountCache::default())
+        }
+        Err(e) => {
+            warn!("Invalid token count cache format. Creating a new one: {}", e);
+            Ok(TokenCountCache::default())
+        }
+    }
+}
+
+/// Save the token count cache
+pub fn save_token_cache(directory: &Path, cache: &TokenCountCache) -> Result<()> {
+    let cache_path = directory.join(TOKEN_CACHE_FILE_NAME);
+    let content =
+        serde_json::to_string_pretty(cache).context("Failed to serialize token count cache")?;
+    fs::write(&cache_path, content).context("Failed to write token count cache")?;
+    Ok(())
+}
+
+/// Extract the token count from a count_tokens response body
+pub fn parse_count_response(body: &str) -> Result<usize> {
+    let value: serde_json::Value =
+        serde_json::from_str(body).context("Invalid count_tokens response")?;
+    value
+        .get("input_tokens")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as usize)
+        .ok_or_else(|| anyhow!("count_tokens response has no input_tokens: {}", body))
+}
+
+/// Client for the count_tokens API
+pub struct TokenCounter {
+    api_key: String,
+    model: String,
+    retry: RetryPolicy,
+}
+
+impl TokenCounter {
+    /// Create a counter if an API key is set in the environment
+    pub fn new(api_key: &str, model: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            retry: RetryPolicy::from_config(&SummarizerConfig::default()),
+        }
+    }
+
+    pub fn from_env(model: &str) -> Option<Self> {
+        let api_key = std::env::var(API_KEY_VAR).ok().filter(|k| !k.is_empty())?;
+        Some(Self::new(&api_key, model))
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Count the tokens in a piece of text sent as a single user message,
+    /// retrying rate limits and server errors
+    pub fn count(&self, text: &str) -> Result<usize> {
+        let body = serde_json::json!({
+            "model": self.model,
+            "messages": [{"role": "user", "content": text}],
+        });
+        self.retry.run(|| {
+            let request = http_agent()
+                .post(COUNT_TOKENS_URL)
+                .set("x-api-key", &self.api_key)
+                .set("anthropic-version", ANTHROPIC_VERSION);
+            let response = post_json(request, &body, "count_tokens", false)?;
+            parse_count_response(&response).map_err(CallError::fatal)
+        })
+    }
+
+    /// Tokens every request adds around its message, counted once with a
+    /// one-token text
+    pub fn overhead(&self) -> Result<usize> {
+        Ok(self.count("x")?.saturating_sub(1))
+    }
+}
+
+/// Count files the cache doesn't have, given as (content hash, path),
+/// COUNT_BATCH_SIZE requests at a time, and cache their counts less the
+/// per-request overhead. Stops at the first error, keeping what was counted.
+fn count_missing(
+    missing: &[(&String, &PathBuf)],
+    counter: &TokenCounter,
+    cache: &mut TokenCountCache,
+    requests: &mut usize,
+) -> Result<()> {
+    let overhead = match cache.get(counter.model(), OVERHEAD_KEY) {
+        Some(overhead) => overhead,
+        None => {
+            *requests += 1;
+            let overhead = counter.overhead()?;
+            cache.insert(counter.model(), OVERHEAD_KEY, overhead);
+            overhead
+        }
+    };
+
+    for batch in missing.chunks(COUNT_BATCH_SIZE) {
+        let results: Vec<Result<usize>> = thread::scope(|scope| {
+            let handles: Vec<_> = batch
+                .iter()
+                .map(|(_, path)| {
+                    scope.spawn(move || counter.count(&String::from_utf8_lossy(&fs::read(path)?)))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|_| Err(anyhow!("Counting thread panicked"))))
+                .collect()
+        });
+        *requests += results.len();
+        for ((hash, _), result) in batch.iter().zip(results) {
+            cache.insert(counter.model(), hash, result?.saturating_sub(overhead));
+        }
+    }
+    Ok(())
+}
+
+/// Replace estimated token counts for all text files with exact counts,
+/// using the cache where possible. Files the cache doesn't have are counted
+/// COUNT_BATCH_SIZE requests at a time, less the per-request overhead; at
+/// the first error the remaining files keep their estimates. Counts of
+/// content no file has any more are dropped from the cache. Returns the
+/// number of files counted.
+pub fn apply_exact_counts(
+    dir_info: &mut DirectoryMap,
+    counter: &TokenCounter,
+    cache: &mut TokenCountCache,
+) -> usize {
+    let hashes: HashMap<PathBuf, String> = dir_info
+        .values()
+        .flat_map(|info| info.files.iter())
+        .filter(|f| !f.binary)
+        .filter_map(|f| Some((f.path.clone(), git_blob_hash(&fs::read(&f.path).ok()?))))
+        .collect();
+    cache.retain_hashes(&hashes.values().cloned().collect());
+
+    let mut requests = 0;
+    let mut missing: Vec<(&String, &PathBuf)> = Vec::new();
+    let mut seen = HashSet::new();
+    for (path, hash) in &hashes {
+        if cache.get(counter.model(), hash).is_none() && seen.insert(hash) {
+            missing.push((hash, path));
+        }
+    }
+    missing.sort();
+    if !missing.is_empty()
+        && let Err(e) = count_missing(&missing, counter, cache, &mut requests)
+    {
+        warn!("Falling back to estimated token counts: {:#}", e);
+    }
+
+    let mut counted = 0;
+    for info in dir_info.values_mut() {
+        for file in info.files.iter_mut().filter(|f| !f.binary) {
+            if let Some(tokens) = hashes.get(&file.path).and_then(|hash| cache.get(counter.model(), hash)) {
+                file.tokens = tokens;
+                counted += 1;
+            }
+        }
+        // Directory totals cover the files directly inside each directory
+        info.tokens = info.files.iter().filter(|f| !f.binary).map(|f| f.tokens).sum();
+    }
+
+    info!(
+        "Counted exact tokens for {} files ({} API requests)",
+        counted, requests
+    );
+    counted
+}
@@ -0,0 +1,35 @@
+use tiktoken_rs::{CoreBPE, cl100k_base_singleton};
+
+/// Counts the tokens in text
+pub trait Tokenizer {
+    fn count(&self, text: &str) -> usize;
+}
+
+/// Byte-pair encoding with a real vocabulary. Claude's own vocabulary isn't
+/// published, so cl100k_base stands in for it; `--exact-tokens` asks the
+/// API for the model's count.
+#[derive(Clone, Copy)]
+pub struct BpeTokenizer {
+    bpe: &'static CoreBPE,
+}
+
+impl BpeTokenizer {
+    /// The cl100k_base vocabulary, loaded once per process
+    pub fn cl100k() -> Self {
+        Self {
+            bpe: cl100k_base_singleton(),
+        }
+    }
+}
+
+impl Tokenizer for BpeTokenizer {
+    fn count(&self, text: &str) -> usize {
+        // Special tokens such as <|endoftext|> are counted as plain text
+        self.bpe.encode_ordinary(text).len()
+    }
+}
+
+/// Tokens in some text under the BPE vocabulary
+pub fn count_tokens(text: &str) -> usize {
+    BpeTokenizer::cl100k().count(text)
+}
@@ -0,0 +1,89 @@
+use std::fs;
+use std::path::Path;
+
+use crate::cache::CacheMap;
+use crate::file_analysis::{DirectoryMap, tree_totals};
+use crate::summary_cache::{SummaryCache, hash_file_content};
+
+/// Status markers for a node: its cached action, and for files whether a
+/// summary is cached for the current content
+fn markers(path: &Path, cache: &CacheMap, summaries: &SummaryCache) -> String {
+    let mut markers = String::new();
+    if let Some(action) = cache.get(path) {
+        markers.push_str(&format!(" [{}]", action));
+    }
+    if path.is_file() && summaries.contains(path) {
+        let fresh = fs::read_to_string(path)
+            .is_ok_and(|content| summaries.get_summary(path, &hash_file_content(path, &content)).is_some());
+        markers.push_str(if fresh { " (summary cached)" } else { " (summary stale)" });
+    }
+    markers
+}
+
+/// Render the analyzed project as an indented tree. With `tokens`, each
+/// directory shows the tokens of everything below it and each file its own.
+/// Nodes with a cached action or summary are marked.
+pub fn render_tree(
+    start_dir: &Path,
+    dir_info: &DirectoryMap,
+    cache: &CacheMap,
+    summaries: &SummaryCache,
+    tokens: bool,
+) -> String {
+    let mut output = String::new();
+    let name = start_dir.file_name().unwrap_or(start_dir.as_os_str()).to_string_lossy();
+    output.push_str(&format!("{}/", name));
+    if tokens {
+        output.push_str(&format!(" (~{} tokens)", tree_totals(dir_info, start_dir).tokens));
+    }
+    output.push('\n');
+    render_children(start_dir, dir_info, cache, summaries, tokens, "", &mut output);
+    output
+}
+
+fn render_children(
+    dir: &Path,
+    dir_info: &DirectoryMap,
+    cache: &CacheMap,
+    summaries: &SummaryCache,
+    tokens: bool,
+    prefix: &str,
+    output: &mut String,
+) {
+    let Some(info) = dir_info.get(dir) else {
+        return;
+    };
+
+    let mut subdirs = info.subdirs.clone();
+    subdirs.sort();
+    let mut files: Vec<_> = info.files.iter().collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let count = subdirs.len() + files.len();
+
+    for (idx, sub) in subdirs.iter().enumerate() {
+        let last = idx + 1 == count;
+        let name = sub.file_name().unwrap_or(sub.as_os_str()).to_string_lossy();
+        output.push_str(&format!("{}{}{}/", prefix, if last { "└── " } else { "├── " }, name));
+        if tokens {
+            output.push_str(&format!(" (~{} tokens)", tree_totals(dir_info, sub).tokens));
+        }
+        output.push_str(&markers(sub, cache, summaries));
+        output.push('\n');
+
+        let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+        render_children(sub, dir_info, cache, summaries, tokens, &child_prefix, output);
+    }
+
+    for (idx, file) in files.iter().enumerate() {
+        let last = subdirs.len() + idx + 1 == count;
+        let name = file.path.file_name().unwrap_or(file.path.as_os_str()).to_string_lossy();
+        output.push_str(&format!("{}{}{}", prefix, if last { "└── " } else { "├── " }, name));
+        if file.binary {
+            output.push_str(" (binary)");
+        } else if tokens {
+            output.push_str(&format!(" (~{} tokens)", file.tokens));
+        }
+        output.push_str(&markers(&file.path, cache, summaries));
+        output.push('\n');
+    }
+}
@@ -0,0 +1,574 @@
+use anyhow::{Context, Result, bail};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+use termion::{color, style};
+
+/// Width of the label column in aligned key/value output
+const LABEL_WIDTH: usize = 18;
+/// Width of header rules
+const RULE_WIDTH: usize = 60;
+
+/// Lines of stdin, read on a thread once a prompt has had a timeout; every
+/// later prompt reads from here so no answer is lost to the thread
+static STDIN_LINES: OnceLock<Mutex<Receiver<String>>> = OnceLock::new();
+
+/// Set for --non-interactive runs, where no prompt waits for an answer
+static NON_INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Set when stdout carries the run's output, so messages go to stderr
+static MESSAGES_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Lines of the answer script read so far, for pointing at a bad answer
+static SCRIPT_LINE: AtomicUsize = AtomicUsize::new(0);
+
+/// How answers are scripted when stdin isn't a terminal, shown in --help
+pub const SCRIPTED_ANSWERS_HELP: &str = "\
+Scripted answers:
+  When stdin is not a terminal, each prompt takes the next answer from it, one
+  per line: a command key (1-9, q, ...), an action name (read, exclude, enter,
+  summarize, stats, sample, skeleton, docs), y or n, or - for the prompt's
+  default. Text after # is a comment; blank and comment-only lines are skipped.
+  Answers are echoed after their prompts. An answer the choice prompt doesn't
+  accept stops the run, naming its line. Once the script runs out, every later
+  prompt takes its default, as with --non-interactive.";
+
+lazy_static! {
+    /// Colors are used only on a terminal and when NO_COLOR is not set
+    static ref COLOR_ENABLED: bool =
+        std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && if messages_to_stderr() {
+            termion::is_tty(&io::stderr())
+        } else {
+            termion::is_tty(&io::stdout())
+        };
+}
+
+/// Kinds of terminal output that get distinct styling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// Node headers (FILE/DIR)
+    Header,
+    /// Questions waiting for input
+    Prompt,
+    /// Problems the user should notice
+    Warning,
+    /// Token counts, sizes and other figures
+    Number,
+    /// Cached/suggested defaults and other secondary information
+    Muted,
+    /// Section titles in summaries
+    Title,
+}
+
+/// Whether colored output is enabled
+pub fn color_enabled() -> bool {
+    *COLOR_ENABLED
+}
+
+/// Apply a style to text (a no-op when colors are disabled)
+pub fn paint(text: impl Display, kind: Style) -> String {
+    if !color_enabled() {
+        return text.to_string();
+    }
+
+    let prefix = match kind {
+        Style::Header => format!("{}{}", style::Bold, color::Fg(color::Cyan)),
+        Style::Prompt => format!("{}{}", style::Bold, color::Fg(color::Green)),
+        Style::Warning => format!("{}", color::Fg(color::Yellow)),
+        Style::Number => format!("{}", color::Fg(color::Magenta)),
+        Style::Muted => format!("{}", color::Fg(color::LightBlack)),
+        Style::Title => format!("{}", style::Bold),
+    };
+    format!("{}{}{}", prefix, text, style::Reset)
+}
+
+/// Send every later message, menu and prompt to stderr, leaving stdout to
+/// the output the run was asked for
+pub fn set_messages_to_stderr() {
+    MESSAGES_TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+/// Whether messages go to stderr
+pub fn messages_to_stderr() -> bool {
+    MESSAGES_TO_STDERR.load(Ordering::Relaxed)
+}
+
+/// Print a line of a message or menu, on stderr when stdout carries output
+pub fn say(line: impl Display) {
+    if messages_to_stderr() {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Print a node header framed by rules, e.g. `FILE: src/main.rs`
+pub fn print_header(kind: &str, path: &Path, rule: char) {
+    let line = rule.to_string().repeat(RULE_WIDTH);
+    say(format!("\n{}", paint(&line, Style::Muted)));
+    say(paint(format!("{}: {}", kind, path.display()), Style::Header));
+    say(paint(&line, Style::Muted));
+}
+
+/// Print an aligned `label: value` line with the value highlighted
+pub fn print_field(label: &str, value: impl Display) {
+    say(format!(
+        "{:<width$} {}",
+        format!("{}:", label),
+        paint(value, Style::Number),
+        width = LABEL_WIDTH
+    ));
+}
+
+/// Print a section title
+pub fn print_title(title: &str) {
+    say(format!("\n{}", paint(title, Style::Title)));
+}
+
+/// Print a warning line
+pub fn print_warning(message: &str) {
+    say(paint(message, Style::Warning));
+}
+
+/// Print the cached actions grouped by action, showing a few paths for each
+pub fn print_cache_summary(cache: &HashMap<PathBuf, String>, start_dir: &Path) {
+    let mut action_paths: HashMap<&str, Vec<&PathBuf>> = HashMap::new();
+    for (path, action) in cache {
+        action_paths.entry(action.as_str()).or_default().push(path);
+    }
+
+    print_title("Cache summary:");
+    for (action, paths) in action_paths.iter() {
+        say(format!(
+            "\n  {}: {} items",
+            paint(action, Style::Header),
+            paint(paths.len(), Style::Number)
+        ));
+        for path in paths.iter().take(5) {
+            // Only show first 5 for brevity
+            say(format!(
+                "    - {}",
+                path.strip_prefix(start_dir).unwrap_or(path).display()
+            ));
+        }
+        if paths.len() > 5 {
+            say(format!(
+                "    - {}",
+                paint(format!("... and {} more", paths.len() - 5), Style::Muted)
+            ));
+        }
+    }
+}
+
+/// A command available at the node prompt
+pub struct Command {
+    /// Key(s) typed to run the command
+    pub key: &'static str,
+    /// Short label shown in the options list
+    pub label: &'static str,
+    /// One-line explanation shown in the help screen
+    pub help: &'static str,
+    /// Whether the command only applies to directories
+    pub dirs_only: bool,
+    /// Whether the command is listed in the numbered options
+    pub in_options: bool,
+}
+
+/// All commands understood by the interactive loop
+pub const COMMANDS: [Command; 13] = [
+    Command {
+        key: "1",
+        label: "Read (include full content)",
+        help: "Include the full content of the file, or of every file in the directory",
+        dirs_only: false,
+        in_options: true,
+    },
+    Command {
+        key: "2",
+        label: "Exclude (skip this node)",
+        help: "Leave the node out of the context entirely",
+        dirs_only: false,
+        in_options: true,
+    },
+    Command {
+        key: "3",
+        label: "Enter (this level only)",
+        help: "Add a directory header and decide on each child file/directory individually",
+        dirs_only: true,
+        in_options: true,
+    },
+    Command {
+        key: "4",
+        label: "Summarize (create summary)",
+        help: "Include a summary instead of the content (config files get a key tree)",
+        dirs_only: false,
+        in_options: true,
+    },
+    Command {
+        key: "5",
+        label: "Stats only (just include statistics)",
+        help: "Include only size, token estimate and extension statistics",
+        dirs_only: false,
+        in_options: true,
+    },
+    Command {
+        key: "6",
+        label: "Sample (schema or first lines of data files)",
+        help: "Include an inferred schema for JSON data, or the first lines of other files",
+        dirs_only: false,
+        in_options: true,
+    },
+    Command {
+        key: "7",
+        label: "Enter to depth N (decide deeper directories as a whole)",
+        help: "Enter subdirectories N levels down without asking; directories at that depth can't be entered",
+        dirs_only: true,
+        in_options: true,
+    },
+    Command {
+        key: "8",
+        label: "Skeleton (signatures and docs, bodies removed)",
+        help: "Include declarations, signatures and doc comments without function bodies",
+        dirs_only: false,
+        in_options: true,
+    },
+    Command {
+        key: "9",
+        label: "API docs (Rust doc comments and the items they document)",
+        help: "Include only doc comments with their items' signatures; a directory holding a crate gets a crate section",
+        dirs_only: false,
+        in_options: true,
+    },
+    Command {
+        key: "l",
+        label: "Line ranges (chosen lines only)",
+        help: "Include only the given line ranges of a file, e.g. 120-240,400-450, marking what is left out",
+        dirs_only: false,
+        in_options: true,
+    },
+    Command {
+        key: "o",
+        label: "Open in pager or editor",
+        help: "View the file in $PAGER (or $VISUAL/$EDITOR), or a directory's tree, then choose",
+        dirs_only: false,
+        in_options: true,
+    },
+    Command {
+        key: "?/h",
+        label: "Help",
+        help: "Show this command reference",
+        dirs_only: false,
+        in_options: false,
+    },
+    Command {
+        key: "q",
+        label: "Quit",
+        help: "Stop prompting and finalize the context files written so far",
+        dirs_only: false,
+        in_options: true,
+    },
+];
+
+/// Choices left unanswered for a while fall back to an action, so an
+/// attended run keeps moving
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTimeout {
+    pub after: Duration,
+    /// Action for choices with no cached or suggested default
+    pub action: String,
+    /// Enter directories that can be entered rather than apply `action` to
+    /// them, so their files fall back one by one
+    pub enter_dirs: bool,
+}
+
+/// Prompt shown when asking for a command
+pub const CHOICE_PROMPT: &str = "\nEnter choice [1-9, l, o, q, ? for help]: ";
+
+/// Map a cached action name to its command key
+pub fn choice_for_action(action: &str) -> Option<&'static str> {
+    match action {
+        "read" => Some("1"),
+        "exclude" => Some("2"),
+        "enter" => Some("3"),
+        "summarize" => Some("4"),
+        "stats" => Some("5"),
+        "sample" => Some("6"),
+        "skeleton" => Some("8"),
+        "docs" => Some("9"),
+        _ => None,
+    }
+}
+
+/// Map a command key to the action it applies to a file
+pub fn action_for_choice(choice: &str) -> Option<&'static str> {
+    match choice {
+        "1" => Some("read"),
+        "2" => Some("exclude"),
+        "4" => Some("summarize"),
+        "5" => Some("stats"),
+        "6" => Some("sample"),
+        "8" => Some("skeleton"),
+        "9" => Some("docs"),
+        _ => None,
+    }
+}
+
+/// Print the numbered options for a node; leaves (files, and directories that
+/// can only be decided as a whole) get no enter options
+pub fn print_options(leaf: bool) {
+    print_title("Options:");
+    for command in COMMANDS.iter().filter(|c| c.in_options) {
+        if leaf && command.dirs_only {
+            continue;
+        }
+        say(format!("  {}. {}", paint(command.key, Style::Prompt), command.label));
+    }
+}
+
+/// Print the full command reference
+pub fn print_help() {
+    print_title("Commands:");
+    for command in &COMMANDS {
+        let scope = if command.dirs_only {
+            " (directories only)"
+        } else {
+            ""
+        };
+        say(format!(
+            "  {} {}{}",
+            paint(format!("{:<5}", command.key), Style::Prompt),
+            command.help,
+            paint(scope, Style::Muted)
+        ));
+    }
+    say(format!(
+        "\n{}",
+        paint(
+            "A cached or suggested action is offered first; answer 'n' to choose another.",
+            Style::Muted
+        )
+    ));
+}
+
+/// Leave every later prompt unanswered instead of reading stdin, so each
+/// takes its default
+pub fn set_non_interactive() {
+    NON_INTERACTIVE.store(true, Ordering::Relaxed);
+}
+
+/// Whether prompts go unanswered
+pub fn is_non_interactive() -> bool {
+    NON_INTERACTIVE.load(Ordering::Relaxed)
+}
+
+/// Whether prompts read a script of answers rather than a person's typing
+pub fn is_scripted() -> bool {
+    static STDIN_IS_TTY: OnceLock<bool> = OnceLock::new();
+    !is_non_interactive() && !*STDIN_IS_TTY.get_or_init(|| termion::is_tty(&io::stdin()))
+}
+
+/// Line of the answer script the last answer came from
+pub fn script_line() -> usize {
+    SCRIPT_LINE.load(Ordering::Relaxed)
+}
+
+/// An answer script line without its comment, None when nothing is left
+pub fn scripted_answer(line: &str) -> Option<&str> {
+    let answer = line.split('#').next().unwrap_or("").trim();
+    (!answer.is_empty()).then_some(answer)
+}
+
+/// The next answer from the script on stdin, echoed after its prompt; at
+/// the end of the script the run goes on non-interactively
+fn next_scripted_answer() -> Result<Option<String>> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if io::stdin().read_line(&mut line)? == 0 {
+            say(paint("(end of answer script: taking defaults)", Style::Muted));
+            set_non_interactive();
+            return Ok(None);
+        }
+        SCRIPT_LINE.fetch_add(1, Ordering::Relaxed);
+        if let Some(answer) = scripted_answer(&line) {
+            say(answer);
+            // `-` leaves the prompt unanswered, taking its default
+            return Ok((answer != "-").then(|| answer.to_string()));
+        }
+    }
+}
+
+/// Print a prompt and read a trimmed line from stdin
+pub fn prompt(message: &str) -> Result<String> {
+    Ok(prompt_timeout(message, None)?.unwrap_or_default())
+}
+
+/// Print a prompt and read a trimmed line from stdin, giving up after
+/// `timeout`. None when nothing was answered in time, and at once in a
+/// non-interactive run.
+pub fn prompt_timeout(message: &str, timeout: Option<Duration>) -> Result<Option<String>> {
+    // Keep leading newlines outside the styled region
+    let text = message.trim_start_matches('\n');
+    let message = format!("{}{}", &message[..message.len() - text.len()], paint(text, Style::Prompt));
+    if messages_to_stderr() {
+        eprint!("{}", message);
+        io::stderr().flush()?;
+    } else {
+        print!("{}", message);
+        io::stdout().flush()?;
+    }
+    if is_non_interactive() {
+        say("");
+        return Ok(None);
+    }
+    // A script answers at once, so there is nothing to time out
+    if is_scripted() {
+        return next_scripted_answer();
+    }
+
+    let lines = match (timeout, STDIN_LINES.get()) {
+        (None, None) => {
+            let mut response = String::new();
+            io::stdin().read_line(&mut response)?;
+            return Ok(Some(response.trim().to_string()));
+        }
+        (_, Some(lines)) => lines,
+        (Some(_), None) => STDIN_LINES.get_or_init(|| {
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                let mut line = String::new();
+                while io::stdin().read_line(&mut line).is_ok_and(|n| n > 0) {
+                    if sender.send(std::mem::take(&mut line)).is_err() {
+                        break;
+                    }
+                }
+            });
+            Mutex::new(receiver)
+        }),
+    };
+
+    let lines = lines.lock().map_err(|_| anyhow::anyhow!("stdin reader failed"))?;
+    let response = match timeout {
+        Some(timeout) => match lines.recv_timeout(timeout) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => {
+                say("");
+                return Ok(None);
+            }
+            // Standard input closed, as when reading it directly
+            Err(RecvTimeoutError::Disconnected) => String::new(),
+        },
+        None => lines.recv().unwrap_or_default(),
+    };
+    Ok(Some(response.trim().to_string()))
+}
+
+/// Parse a prompt timeout such as `15s`, `2m`, `500ms` or `30` (seconds)
+pub fn parse_timeout(spec: &str) -> Result<Duration, String> {
+    let spec = spec.trim();
+    let split = spec.find(|c: char| !c.is_ascii_digit()).unwrap_or(spec.len());
+    let (number, unit) = spec.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid timeout '{}' (e.g. 15s, 2m)", spec))?;
+    match unit {
+        "ms" => Ok(Duration::from_millis(number)),
+        "" | "s" => Ok(Duration::from_secs(number)),
+        "m" => Ok(Duration::from_secs(number * 60)),
+        _ => Err(format!("invalid timeout unit '{}' (use ms, s or m)", unit)),
+    }
+}
+
+/// Editor from $VISUAL or $EDITOR, falling back to vi
+pub fn editor_command() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|cmd| !cmd.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// Run a user-configured command on a file and wait for it; the command may
+/// carry arguments, e.g. `code --wait`
+fn run_on_file(command: &str, path: &Path) -> Result<()> {
+    let mut parts = command.split_whitespace();
+    let Some(program) = parts.next() else {
+        bail!("No command to open {}", path.display());
+    };
+    let status = std::process::Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to start '{}'", command))?;
+    if !status.success() {
+        bail!("'{}' exited with {}", command, status);
+    }
+    Ok(())
+}
+
+/// Let the user edit some text in their editor and return the result
+pub fn edit_text(text: &str) -> Result<String> {
+    let mut file = tempfile::Builder::new().suffix(".md").tempfile()?;
+    file.write_all(text.as_bytes())?;
+    file.flush()?;
+
+    run_on_file(&editor_command(), file.path())?;
+    std::fs::read_to_string(file.path()).context("Failed to read edited text")
+}
+
+/// Open a file for viewing in $PAGER, or the editor when no pager is set
+pub fn open_in_viewer(path: &Path) -> Result<()> {
+    let viewer = std::env::var("PAGER")
+        .ok()
+        .filter(|cmd| !cmd.trim().is_empty())
+        .unwrap_or_else(editor_command);
+    run_on_file(&viewer, path)
+}
+
+/// View some text in $PAGER or the editor
+pub fn view_text(text: &str) -> Result<()> {
+    let mut file = tempfile::Builder::new().suffix(".txt").tempfile()?;
+    file.write_all(text.as_bytes())?;
+    file.flush()?;
+    open_in_viewer(file.path())
+}
+
+/// Show a new summary and let the user accept it, regenerate it with an
+/// extra instruction, or edit it before it is written and cached
+pub fn review_summary(
+    rel_path: &Path,
+    summary: String,
+    regenerate: impl Fn(&str) -> Result<String>,
+) -> Result<String> {
+    let mut summary = summary;
+    loop {
+        print_title(&format!("Summary of {}:", rel_path.display()));
+        say(summary.trim_end());
+
+        let choice = prompt("[a]ccept, [r]egenerate with a tweak, [e]dit in $EDITOR: ")?;
+        match choice.to_lowercase().as_str() {
+            "" | "a" => break,
+            "r" => {
+                let tweak = prompt("Instruction for the summarizer: ")?;
+                summary = regenerate(&tweak)?;
+            }
+            "e" => match edit_text(&summary) {
+                Ok(edited) => summary = edited,
+                Err(e) => print_warning(&e.to_string()),
+            },
+            _ => print_warning("Invalid choice."),
+        }
+    }
+
+    if !summary.ends_with('\n') {
+        summary.push('\n');
+    }
+    Ok(summary)
+}
@@ -0,0 +1,71 @@
+use std::path::{Path, PathBuf};
+
+use crate::file_analysis::DirectoryMap;
+use crate::suggestions::{Suggestion, SuggestionMap};
+
+/// Metadata directories of version control systems, never walked
+pub const VCS_METADATA_DIRS: [&str; 4] = [".git", ".hg", ".svn", ".jj"];
+
+/// Default action for a repository nested inside the start directory
+pub const NESTED_REPO_ACTION: &str = "stats";
+
+/// Exclude patterns for the metadata directories, matching whole components
+pub fn vcs_excludes() -> Vec<String> {
+    VCS_METADATA_DIRS.iter().map(|dir| format!("{}/", dir)).collect()
+}
+
+/// Whether a directory is the inside of a bare git repository (HEAD,
+/// objects/ and refs/ with no working tree), which holds only history
+pub fn is_bare_repo(dir: &Path) -> bool {
+    dir.join("HEAD").is_file() && dir.join("objects").is_dir() && dir.join("refs").is_dir()
+}
+
+/// The version control system whose metadata a directory holds, if any. A
+/// `.git` file counts too, as in submodules and worktrees.
+pub fn repo_kind(dir: &Path) -> Option<&'static str> {
+    VCS_METADATA_DIRS
+        .iter()
+        .find(|name| dir.join(name).exists())
+        .map(|name| name.trim_start_matches('.'))
+}
+
+/// Directories below the start directory that are repositories of their
+/// own, outermost only. Old Subversion checkouts have `.svn` in every
+/// directory, so one whose parent is from the same system isn't counted.
+pub fn nested_repos(start_dir: &Path, dir_info: &DirectoryMap) -> Vec<(PathBuf, &'static str)> {
+    let mut repos: Vec<(PathBuf, &'static str)> = dir_info
+        .keys()
+        .filter(|dir| dir.as_path() != start_dir)
+        .filter_map(|dir| {
+            let kind = repo_kind(dir)?;
+            let parent_kind = dir.parent().and_then(repo_kind);
+            (kind != "svn" || parent_kind != Some("svn")).then(|| (dir.clone(), kind))
+        })
+        .collect();
+    repos.sort();
+
+    let mut outermost: Vec<(PathBuf, &'static str)> = Vec::new();
+    for (dir, kind) in repos {
+        if !outermost.iter().any(|(outer, _)| dir.starts_with(outer)) {
+            outermost.push((dir, kind));
+        }
+    }
+    outermost
+}
+
+/// Suggest collapsing each nested repository into one node; entering it
+/// instead shows its files as usual
+pub fn nested_repo_suggestions(start_dir: &Path, dir_info: &DirectoryMap) -> SuggestionMap {
+    nested_repos(start_dir, dir_info)
+        .into_iter()
+        .map(|(dir, kind)| {
+            (
+                dir,
+                Suggestion {
+                    action: NESTED_REPO_ACTION.to_string(),
+                    reason: format!("nested {} repository", kind),
+                },
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,110 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::file_analysis::DirectoryMap;
+use crate::suggestions::{Suggestion, SuggestionMap, propagate_to_directories};
+
+/// Directory names that hold third-party code
+pub const VENDOR_DIRS: [&str; 7] = [
+    "vendor",
+    "vendors",
+    "third_party",
+    "third-party",
+    "thirdparty",
+    "bower_components",
+    "node_modules",
+];
+/// Default action for files under a vendor directory
+pub const VENDOR_DIR_ACTION: &str = "stats";
+/// Default action for files whose license header names another project
+pub const FOREIGN_HEADER_ACTION: &str = "summarize";
+
+/// Lines read from the top of a file when looking for a license header
+const HEADER_LINES: usize = 20;
+/// License files whose copyright holder identifies the project
+const LICENSE_FILE_NAMES: [&str; 4] = ["LICENSE", "LICENSE.md", "LICENSE.txt", "COPYING"];
+
+lazy_static! {
+    static ref COPYRIGHT: Regex =
+        Regex::new(r"(?i)copyright\s+(?:\(c\)\s*|©\s*)?(?:\d{4}(?:\s*[-,]\s*\d{4})*\s+)?(?:by\s+)?([^\n<]+)").unwrap();
+}
+
+/// The vendor directory component of a path relative to the start directory
+pub fn vendor_dir(rel_path: &Path) -> Option<&str> {
+    rel_path
+        .parent()?
+        .components()
+        .filter_map(|c| c.as_os_str().to_str())
+        .find(|c| VENDOR_DIRS.contains(c))
+}
+
+/// Copyright holder named in some text, normalized for comparison
+pub fn copyright_holder(text: &str) -> Option<String> {
+    let holder = COPYRIGHT.captures(text)?[1]
+        .trim_end_matches(['.', ',', '*', '/', '-', ' ', '\t', '\r'])
+        .trim()
+        .to_lowercase();
+    (!holder.is_empty() && !holder.starts_with("notice")).then_some(holder)
+}
+
+/// Copyright holder from the project's own license file
+fn project_holder(start_dir: &Path) -> Option<String> {
+    LICENSE_FILE_NAMES
+        .iter()
+        .find_map(|name| fs::read_to_string(start_dir.join(name)).ok())
+        .and_then(|text| copyright_holder(&text))
+}
+
+/// Copyright holder in the header of a source file
+fn header_holder(path: &Path) -> Option<String> {
+    let file = fs::File::open(path).ok()?;
+    let header: Vec<String> = BufReader::new(file)
+        .lines()
+        .take(HEADER_LINES)
+        .map_while(Result::ok)
+        .collect();
+    copyright_holder(&header.join("\n"))
+}
+
+/// Suggest stats for files under vendor directories, and summaries for files
+/// whose license header names a different copyright holder than the
+/// project's own license
+pub fn vendored_suggestions(start_dir: &Path, dir_info: &DirectoryMap) -> SuggestionMap {
+    let project = project_holder(start_dir);
+    let mut suggestions = SuggestionMap::new();
+
+    for file in dir_info.values().flat_map(|info| info.files.iter()) {
+        if file.binary {
+            continue;
+        }
+        let rel_path = file.path.strip_prefix(start_dir).unwrap_or(&file.path);
+
+        let suggestion = if let Some(dir) = vendor_dir(rel_path) {
+            Some(Suggestion {
+                action: VENDOR_DIR_ACTION.to_string(),
+                reason: format!("vendored ({}/)", dir),
+            })
+        } else if let Some(project) = &project
+            && let Some(holder) = header_holder(&file.path)
+            && !holder.contains(project.as_str())
+            && !project.contains(holder.as_str())
+        {
+            Some(Suggestion {
+                action: FOREIGN_HEADER_ACTION.to_string(),
+                reason: format!("third-party license header ({})", holder),
+            })
+        } else {
+            None
+        };
+
+        if let Some(suggestion) = suggestion {
+            suggestions.insert(file.path.clone(), suggestion);
+        }
+    }
+
+    propagate_to_directories(&mut suggestions, dir_info);
+    suggestions
+}
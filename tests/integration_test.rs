@@ -75,7 +75,7 @@ fn test_subtract() {
     "#);
     
     // Create a binary file 
-    create_binary_file(&temp_dir.path(), "binary.dat");
+    create_binary_file(temp_dir.path(), "binary.dat");
     
     // Create a file in node_modules that should be excluded
     let node_modules_dir = temp_dir.path().join("node_modules");
@@ -99,7 +99,6 @@ fn test_subtract() {
     
     // This test just sets up the structure, it doesn't actually verify anything
     // and serves as a utility for manual testing
-    assert!(true);
 }
 
 fn create_dir(parent: &Path, name: &str) {
@@ -0,0 +1,41 @@
+use llm_context_loader::analysis_cache::{AnalysisCache, analyze_directory_cached};
+use llm_context_loader::file_analysis::{DirectoryMap, analyze_directory};
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+/// Each file with its tokens, sorted
+fn files(dir_info: &DirectoryMap) -> Vec<(PathBuf, usize)> {
+    let mut files: Vec<(PathBuf, usize)> = dir_info
+        .values()
+        .flat_map(|info| info.files.iter().map(|f| (f.path.clone(), f.tokens)))
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_cached_analysis_matches_fresh_one() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join("src/util")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+    fs::write(root.join("src/util/mod.rs"), "pub fn help() {}").unwrap();
+
+    let mut cache = AnalysisCache::default();
+    let first = analyze_directory_cached(&root, &[], &mut cache).unwrap();
+    assert_eq!(files(&first), files(&analyze_directory(&root, &[]).unwrap()));
+    let again = analyze_directory_cached(&root, &[], &mut cache).unwrap();
+    assert_eq!(files(&again), files(&first));
+
+    // A file saved through a rename, as editors do, and a new directory are picked up
+    fs::write(root.join("src/util/.mod.rs.swp"), "pub fn help() {}\npub fn more() {}\n").unwrap();
+    fs::rename(root.join("src/util/.mod.rs.swp"), root.join("src/util/mod.rs")).unwrap();
+    fs::create_dir_all(root.join("docs")).unwrap();
+    fs::write(root.join("docs/guide.md"), "# Guide").unwrap();
+    let changed = analyze_directory_cached(&root, &[], &mut cache).unwrap();
+    let fresh = analyze_directory(&root, &[]).unwrap();
+    assert_eq!(files(&changed), files(&fresh));
+    assert_eq!(changed[&root.join("src")].tokens, fresh[&root.join("src")].tokens);
+    assert_eq!(changed[&root].subdirs.len(), 2);
+}
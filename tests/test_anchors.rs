@@ -0,0 +1,113 @@
+use llm_context_loader::anchors::{
+    action_breakdown, citation_id, format_anchor, git_blob_hash, parse_anchor, update_context_file,
+};
+use llm_context_loader::context_files::{OutputSettings, create_context_file};
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::processing::{Action, process_node};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+#[test]
+fn test_anchor_roundtrip() {
+    let line = format_anchor(Path::new("src/my file.rs"), "1234abcd", &Action::Summarize);
+    let anchor = parse_anchor(&line).unwrap();
+
+    assert_eq!(anchor.hash, "1234abcd");
+    assert_eq!(anchor.action, Action::Summarize);
+    assert_eq!(anchor.path, Path::new("src/my file.rs"));
+    assert!(parse_anchor("===== FILE START: x =====").is_none());
+}
+
+#[test]
+fn test_citation_ids() {
+    let id = citation_id(Path::new("src/main.rs"));
+    assert_eq!(id.len(), 7);
+    assert!(id.starts_with('F'));
+    // The same path always gets the same ID, and other paths another
+    assert_eq!(citation_id(Path::new("src/main.rs")), id);
+    assert_ne!(citation_id(Path::new("src/lib.rs")), id);
+
+    let line = format_anchor(Path::new("src/main.rs"), "1234abcd", &Action::Read);
+    assert!(line.contains(&format!("[{}]", id)));
+    assert_eq!(parse_anchor(&line).unwrap().citation, Some(id));
+
+    // Anchors from before citations still parse
+    let old = parse_anchor("===== ANCHOR: 1234abcd read src/main.rs =====").unwrap();
+    assert_eq!(old.path, Path::new("src/main.rs"));
+    assert_eq!(old.citation, None);
+}
+
+#[test]
+fn test_update_changed_blocks() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::write(root.join("a.txt"), "alpha v1\n").unwrap();
+    fs::write(root.join("b.txt"), "beta v1\n").unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let out_dir = root.join("out");
+    let output = OutputSettings {
+        dir: Some(out_dir.clone()),
+        ..Default::default()
+    };
+    let mut context_file = create_context_file(1, 1, &root, &output).unwrap();
+    process_node(
+        &root,
+        &dir_info,
+        &mut context_file,
+        100000,
+        0,
+        &HashSet::new(),
+        &HashSet::new(),
+        Action::Read,
+        1,
+        &root,
+        &output,
+        None,
+    )
+    .unwrap();
+
+    // Nothing changed yet
+    assert_eq!(update_context_file(&context_file.path, None).unwrap(), 0);
+
+    fs::write(root.join("a.txt"), "alpha v2\n").unwrap();
+    assert_eq!(update_context_file(&context_file.path, None).unwrap(), 1);
+
+    let content = fs::read_to_string(&context_file.path).unwrap();
+    assert!(content.contains("alpha v2"));
+    assert!(!content.contains("alpha v1"));
+    assert!(content.contains("beta v1"));
+    assert_eq!(content.matches("===== ANCHOR: ").count(), 2);
+
+    // The refreshed anchor records the new hash
+    assert_eq!(update_context_file(&context_file.path, None).unwrap(), 0);
+}
+
+#[test]
+fn test_git_blob_hash() {
+    // Matches `printf 'hello\n' | git hash-object --stdin`
+    assert_eq!(
+        git_blob_hash(b"hello\n"),
+        "ce013625030ba8dba906f756967f9e9ca394464a"
+    );
+}
+
+#[test]
+fn test_action_breakdown() {
+    let read = format_anchor(Path::new("a.rs"), "1111", &Action::Read);
+    let stats = format_anchor(Path::new("b.rs"), "2222", &Action::Stats);
+    let content = format!(
+        "Header line\n\n\n{}\n{}\n\n## DIRECTORY: src\n\n{}\n{}\n",
+        read,
+        "x".repeat(400),
+        stats,
+        "Size: 10 bytes"
+    );
+
+    let breakdown = action_breakdown(&content);
+    assert_eq!(breakdown.keys().copied().collect::<Vec<_>>(), vec!["headers", "read", "stats"]);
+    assert!(breakdown["read"] > breakdown["stats"]);
+    assert!(breakdown["read"] > breakdown["headers"]);
+}
@@ -0,0 +1,75 @@
+use llm_context_loader::autoselect::{auto_select, largest_files};
+use llm_context_loader::budget::{BudgetPlan, Ladder};
+use llm_context_loader::coverage::{CoverageMap, FileCoverage};
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::recency::RecencyMap;
+use llm_context_loader::suggestions::{Suggestion, SuggestionMap};
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+#[test]
+fn test_auto_select_budget_and_coverage() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::write(root.join("README.md"), "# Project\n").unwrap();
+    fs::write(root.join("big.py"), "x = 1\n".repeat(100)).unwrap();
+    fs::write(root.join("small.py"), "y = 2\n").unwrap();
+    fs::write(root.join("gen.py"), "z = 3\n").unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let priority = vec![root.join("README.md")];
+    let mut suggestions = SuggestionMap::new();
+    suggestions.insert(
+        root.join("gen.py"),
+        Suggestion {
+            action: "exclude".to_string(),
+            reason: "generated".to_string(),
+        },
+    );
+
+    // big.py (~400 tokens) doesn't fit as a read and falls back to a summary
+    let manifest = auto_select(&root, &dir_info, &suggestions, &priority, &RecencyMap::new(), None, 200, &BudgetPlan::default(), &Ladder::default());
+    let selected: Vec<(PathBuf, &str)> = manifest
+        .entries
+        .iter()
+        .map(|e| (e.path.clone(), e.action.as_str()))
+        .collect();
+    assert_eq!(
+        selected,
+        vec![
+            (PathBuf::from("README.md"), "read"),
+            (PathBuf::from("big.py"), "summarize"),
+            (PathBuf::from("small.py"), "read"),
+        ]
+    );
+
+    assert_eq!(manifest.entries[1].options["requested"], "read");
+
+    // With coverage only the files that ran are considered
+    let mut coverage = CoverageMap::new();
+    coverage.insert(root.join("small.py"), FileCoverage { lines_found: 1, lines_hit: 1 });
+    coverage.insert(root.join("big.py"), FileCoverage { lines_found: 100, lines_hit: 0 });
+    let manifest = auto_select(&root, &dir_info, &suggestions, &priority, &RecencyMap::new(), Some(&coverage), 200, &BudgetPlan::default(), &Ladder::default());
+    let paths: Vec<PathBuf> = manifest.entries.iter().map(|e| e.path.clone()).collect();
+    assert_eq!(paths, vec![PathBuf::from("README.md"), PathBuf::from("small.py")]);
+}
+
+#[test]
+fn test_largest_files_order() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/big.rs"), "x".repeat(900)).unwrap();
+    fs::write(root.join("mid.rs"), "y".repeat(300)).unwrap();
+    fs::write(root.join("a.rs"), "z".repeat(30)).unwrap();
+    fs::write(root.join("b.rs"), "z".repeat(30)).unwrap();
+    fs::write(root.join("image.bin"), [0u8, 159, 146, 150]).unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let files: Vec<PathBuf> = largest_files(&dir_info).into_iter().map(|(p, _)| p).collect();
+    assert_eq!(
+        files,
+        vec![root.join("src/big.rs"), root.join("mid.rs"), root.join("a.rs"), root.join("b.rs")]
+    );
+}
@@ -0,0 +1,63 @@
+use llm_context_loader::budget::{BudgetPlan, Category, Ladder, ReadImpact};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn test_category_of() {
+    assert_eq!(Category::of(Path::new("src/main.rs")), Category::Source);
+    assert_eq!(Category::of(Path::new("tests/test_ui.rs")), Category::Tests);
+    assert_eq!(Category::of(Path::new("src/app.spec.ts")), Category::Tests);
+    assert_eq!(Category::of(Path::new("README.md")), Category::Docs);
+    assert_eq!(Category::of(Path::new("docs/guide.html")), Category::Docs);
+    assert_eq!(Category::of(Path::new("Cargo.toml")), Category::Config);
+    assert_eq!(Category::of(Path::new(".gitignore")), Category::Config);
+}
+
+#[test]
+fn test_budget_plan() {
+    let root = PathBuf::from("/repo");
+    let mut plan = BudgetPlan::parse("source=70,docs=20%,tests+config=10", 1000, &root).unwrap();
+    assert_eq!(plan.buckets.len(), 3);
+    assert_eq!(plan.buckets[2].limit, 100);
+
+    plan.record(&root.join("Cargo.toml"), 60);
+    assert!(plan.fits(&root.join("tests/a.rs"), 40));
+    assert!(!plan.fits(&root.join("tests/a.rs"), 41));
+    // Several files are checked together against their buckets
+    let costs = vec![(root.join("src/a.rs"), 400), (root.join("src/b.rs"), 301)];
+    assert_eq!(plan.overflow(&costs).unwrap().name, "source");
+
+    assert!(BudgetPlan::parse("source=80,docs=30", 1000, &root).is_err());
+    assert!(BudgetPlan::parse("vendor=10", 1000, &root).is_err());
+}
+
+#[test]
+fn test_ladder_downgrade() {
+    let ladder = Ladder::default();
+    assert_eq!(ladder.downgrade("read", |_| true), "read");
+    assert_eq!(ladder.downgrade("read", |a| a == "summarize" || a == "stats"), "summarize");
+    assert_eq!(ladder.downgrade("sample", |a| a == "read"), "exclude");
+
+    // Custom ladders skip rungs that aren't listed
+    let ladder = Ladder::parse("read,truncate,exclude").unwrap();
+    assert_eq!(ladder.downgrade("read", |a| a == "sample" || a == "stats"), "sample");
+    // ...and never step up to a costlier action
+    assert_eq!(ladder.downgrade("summarize", |a| a == "read" || a == "sample"), "exclude");
+    assert!(Ladder::parse("read,delete").is_err());
+}
+
+#[test]
+fn test_read_impact() {
+    // 31,000 of the 100,000 tokens left, in a part with 150,000 of 180,000
+    let impact = ReadImpact::new(31_000, 0, 100_000, 150_000, 180_000);
+    assert_eq!(impact.percent_of_remaining, 31);
+    assert_eq!(impact.new_parts, 1);
+    assert_eq!(impact.describe(), "this will consume 31% of remaining budget and force a new part");
+
+    let impact = ReadImpact::new(400_000, 100_000, 1_000_000, 0, 180_000);
+    assert_eq!(impact.percent_of_remaining, 45);
+    assert_eq!(impact.new_parts, 2);
+    assert!(impact.describe().ends_with("and force 2 new parts"));
+
+    let impact = ReadImpact::new(10, 50, 100, 10, 180_000);
+    assert_eq!(impact.describe(), "this will consume 20% of remaining budget");
+}
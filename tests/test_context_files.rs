@@ -1,6 +1,12 @@
-use llm_context_loader::context_files::{create_context_file, append_to_file, get_or_rotate_file};
+use llm_context_loader::context_files::{OutputSettings, create_context_file, append_to_file, get_or_rotate_file, prepare_output_dir, archived_runs, clean_context_dir, PartExtension, PartNaming, create_session_dir, latest_session, session_dirs, set_latest_session, emit_parts, OutputTarget, check_continuations, finalize_context_files, split_block, TempWorkspace, renumber_parts};
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::formats::OutputFormat;
+use llm_context_loader::processing::{Action, process_node};
+use std::collections::HashSet;
+use llm_context_loader::anchors::context_part_paths;
 use tempfile::TempDir;
 use std::fs;
+use std::path::Path;
 
 #[test]
 fn test_create_context_file() {
@@ -8,9 +14,13 @@ fn test_create_context_file() {
     let base_dir = temp_dir.path();
     let output_dir = temp_dir.path().join("output");
     fs::create_dir(&output_dir).unwrap();
+    let output = OutputSettings {
+        dir: Some(output_dir.clone()),
+        ..Default::default()
+    };
     
     // Create a context file
-    let context_file = create_context_file(1, 2, base_dir, Some(&output_dir)).unwrap();
+    let context_file = create_context_file(1, 2, base_dir, &output).unwrap();
     
     // Verify the file exists
     let expected_path = output_dir.join("context-001.txt");
@@ -46,12 +56,16 @@ fn test_get_or_rotate_file() {
     let base_dir = temp_dir.path();
     let output_dir = temp_dir.path().join("output");
     fs::create_dir(&output_dir).unwrap();
+    let output = OutputSettings {
+        dir: Some(output_dir.clone()),
+        ..Default::default()
+    };
     
     // Create initial context file
-    let context_file = create_context_file(1, 2, base_dir, Some(&output_dir)).unwrap();
+    let context_file = create_context_file(1, 2, base_dir, &output).unwrap();
     
     // Test with tokens under limit
-    let context_file1 = get_or_rotate_file(&context_file, 2, base_dir, Some(&output_dir)).unwrap();
+    let context_file1 = get_or_rotate_file(&context_file, 2, base_dir, &output).unwrap();
     assert_eq!(context_file.path, context_file1.path);
     
     // Modify token count to exceed limit
@@ -59,7 +73,265 @@ fn test_get_or_rotate_file() {
     context_file_over_limit.current_tokens = 21000; // Over the CLAUDE_TOKEN_LIMIT
     
     // Test rotation
-    let context_file2 = get_or_rotate_file(&context_file_over_limit, 2, base_dir, Some(&output_dir)).unwrap();
+    let context_file2 = get_or_rotate_file(&context_file_over_limit, 2, base_dir, &output).unwrap();
     assert_ne!(context_file_over_limit.path, context_file2.path);
     assert_eq!(context_file2.file_num, 2);
-}
\ No newline at end of file
+}
+#[test]
+fn test_output_dir_lifecycle() {
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path();
+    let output = OutputSettings { dir: Some(dir.to_path_buf()), ..Default::default() };
+
+    // A three-part run followed by a one-part run leaves no stale parts
+    for num in 1..=3 {
+        create_context_file(num, 3, dir, &output).unwrap();
+    }
+    fs::write(dir.join("index.json"), "{}").unwrap();
+    prepare_output_dir(dir, 2).unwrap();
+    create_context_file(1, 1, dir, &output).unwrap();
+
+    assert!(dir.join("context-001.txt").exists());
+    assert!(!dir.join("context-002.txt").exists());
+    let runs = archived_runs(dir).unwrap();
+    assert_eq!(runs.len(), 1);
+    assert!(runs[0].join("context-003.txt").exists());
+    assert!(runs[0].join("index.json").exists());
+
+    // Without retention the previous run is deleted, and so are old archives
+    prepare_output_dir(dir, 0).unwrap();
+    assert!(!dir.join("context-001.txt").exists());
+    assert!(archived_runs(dir).unwrap().is_empty());
+
+    create_context_file(1, 1, dir, &output).unwrap();
+    assert_eq!(clean_context_dir(dir, 0).unwrap(), (1, 0));
+    assert!(!dir.join("context-001.txt").exists());
+}
+
+#[test]
+fn test_part_naming_template() {
+    let temp_dir = TempDir::new().unwrap();
+    let base_dir = temp_dir.path().join("myproj");
+    fs::create_dir(&base_dir).unwrap();
+    let out_dir = temp_dir.path().join("out");
+    let output = OutputSettings {
+        dir: Some(out_dir.clone()),
+        naming: PartNaming::new("{project}-{date}-part{num}.{ext}", PartExtension::Md).unwrap(),
+        ..Default::default()
+    };
+
+    let second = create_context_file(2, 2, &base_dir, &output).unwrap();
+    let first = create_context_file(1, 2, &base_dir, &output).unwrap();
+    fs::write(out_dir.join("notes.md"), "unrelated\n").unwrap();
+
+    let name = first.path.file_name().unwrap().to_string_lossy().to_string();
+    assert!(name.starts_with("myproj-"));
+    assert!(name.ends_with("-part001.md"));
+    // Parts are recognized by their header, whatever they are named
+    assert_eq!(context_part_paths(&out_dir).unwrap(), vec![first.path, second.path]);
+
+    assert!(PartNaming::new("context.txt", PartExtension::Txt).is_err());
+}
+
+#[test]
+fn test_sessions_and_latest() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+
+    let first = create_session_dir(root).unwrap();
+    let second = create_session_dir(root).unwrap();
+    assert_ne!(first, second);
+    set_latest_session(root, &second).unwrap();
+
+    assert_eq!(latest_session(root), Some(second.clone()));
+    assert_eq!(session_dirs(root).unwrap(), vec![second.clone(), first.clone()]);
+
+    // Cleaning down to one session keeps the newest and leaves latest on it
+    assert_eq!(clean_context_dir(root, 1).unwrap(), (0, 1));
+    assert!(!first.exists());
+    assert_eq!(latest_session(root), Some(second));
+
+    clean_context_dir(root, 0).unwrap();
+    assert_eq!(latest_session(root), None);
+}
+
+#[test]
+fn test_emit_parts() {
+    let temp_dir = TempDir::new().unwrap();
+    let part = temp_dir.path().join("context-001.txt");
+    fs::write(&part, "part one\n").unwrap();
+
+    emit_parts(std::slice::from_ref(&part), &[OutputTarget::File, OutputTarget::Stdout]).unwrap();
+    assert!(emit_parts(&[temp_dir.path().join("missing.txt")], &[OutputTarget::Stdout]).is_err());
+    // File-only output never reads the parts back
+    emit_parts(&[temp_dir.path().join("missing.txt")], &[OutputTarget::File]).unwrap();
+}
+
+#[test]
+fn test_oversized_block_is_split_with_paired_markers() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    let line = "a line of a very large text file that does not fit in one part\n";
+    fs::write(root.join("big.txt"), line.repeat(3000)).unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let output = OutputSettings {
+        dir: Some(root.join("out")),
+        ..Default::default()
+    };
+    let mut context_file = create_context_file(1, 1, &root, &output).unwrap();
+    let result = process_node(
+        &root.join("big.txt"),
+        &dir_info,
+        &mut context_file,
+        1000000,
+        0,
+        &HashSet::new(),
+        &HashSet::new(),
+        Action::Read,
+        1,
+        &root,
+        &output,
+        None,
+    )
+    .unwrap();
+
+    let parts: Vec<_> = result.context_files.iter().map(|f| f.path.clone()).collect();
+    assert!(parts.len() > 1);
+    let first = fs::read_to_string(&parts[0]).unwrap();
+    let second = fs::read_to_string(&parts[1]).unwrap();
+    assert!(first.contains("===== CONTINUED IN context-002.txt AT MARKER "));
+    assert!(second.contains("===== CONTINUATION OF big.txt FROM context-001.txt AT MARKER "));
+    check_continuations(&parts).unwrap();
+
+    // A missing continuation is caught before the parts are finalized
+    fs::write(&parts[1], second.replace("CONTINUATION OF", "CONTINUING")).unwrap();
+    assert!(finalize_context_files(&result.context_files, 1, None, output.format.formatter()).is_err());
+}
+
+#[test]
+fn test_markers_in_file_content_are_not_continuations() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    let markers = "===== CONTINUATION OF a.txt FROM context-000.txt AT MARKER x-1 =====\n\
+                   ===== CONTINUED IN context-009.txt AT MARKER y-1 =====\n";
+    fs::write(root.join("markers.txt"), markers).unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    for format in [OutputFormat::Text, OutputFormat::Markdown, OutputFormat::Xml] {
+        let output = OutputSettings {
+            dir: Some(root.join(format!("out-{:?}", format))),
+            format,
+            ..Default::default()
+        };
+        let mut context_file = create_context_file(1, 1, &root, &output).unwrap();
+        let result = process_node(
+            &root.join("markers.txt"),
+            &dir_info,
+            &mut context_file,
+            1000000,
+            0,
+            &HashSet::new(),
+            &HashSet::new(),
+            Action::Read,
+            1,
+            &root,
+            &output,
+            None,
+        )
+        .unwrap();
+        assert_eq!(result.context_files.len(), 1);
+        finalize_context_files(&result.context_files, 1, None, format.formatter()).unwrap();
+        check_continuations(&[context_file.path]).unwrap();
+    }
+}
+
+#[test]
+fn test_split_block_overlap() {
+    let block = "anchor\nline 1\nline 2\nline 3\nline 4\nline 5\n";
+    let chunks = split_block(block, 8, 8, 4);
+    assert!(chunks.len() > 1);
+    assert_eq!(chunks[0].overlap_lines, 0);
+    assert!(chunks[1..].iter().all(|c| c.overlap_lines > 0));
+    assert!(chunks[0].text.starts_with("anchor\n"));
+    for pair in chunks.windows(2) {
+        let repeated: Vec<&str> = pair[1].text.lines().take(pair[1].overlap_lines).collect();
+        assert!(pair[0].text.lines().rev().take(repeated.len()).eq(repeated.iter().rev().copied()));
+        assert!(!pair[1].text.contains("anchor"));
+    }
+    // Without overlap, the chunks are exactly the block
+    let plain = split_block(block, 8, 8, 0);
+    assert_eq!(plain.iter().map(|c| c.text.as_str()).collect::<String>(), block);
+}
+
+#[test]
+fn test_temp_workspace_cleanup() {
+    let workspace = TempWorkspace::new(false).unwrap();
+    let output = OutputSettings {
+        dir: Some(workspace.path().to_path_buf()),
+        ..Default::default()
+    };
+    let part = create_context_file(1, 1, workspace.path(), &output).unwrap().path;
+    assert!(part.exists());
+    drop(workspace);
+    assert!(!part.exists());
+
+    let kept = TempWorkspace::new(true).unwrap();
+    let kept_dir = kept.path().to_path_buf();
+    drop(kept);
+    assert!(kept_dir.is_dir());
+    fs::remove_dir_all(kept_dir).unwrap();
+
+    // Without a directory there is nowhere to write parts
+    assert!(create_context_file(1, 1, Path::new("."), &OutputSettings::default()).is_err());
+}
+
+#[test]
+fn test_preamble_and_epilogue_replace_ready() {
+    let temp_dir = TempDir::new().unwrap();
+    let output = OutputSettings {
+        dir: Some(temp_dir.path().join("output")),
+        preamble: Some("Follow the conventions in CONTRIBUTING.md.\n".to_string()),
+        epilogue: Some("Answer with a unified diff.".to_string()),
+        ..Default::default()
+    };
+    let first = create_context_file(1, 2, temp_dir.path(), &output).unwrap();
+    let second = create_context_file(2, 2, temp_dir.path(), &output).unwrap();
+    finalize_context_files(
+        &[first.clone(), second.clone()],
+        3,
+        output.epilogue.as_deref(),
+        output.format.formatter(),
+    )
+    .unwrap();
+
+    let first = fs::read_to_string(&first.path).unwrap();
+    let second = fs::read_to_string(&second.path).unwrap();
+    assert!(first.contains("START and END tag.\nFollow the conventions in CONTRIBUTING.md.\n====="));
+    assert!(!second.contains("CONTRIBUTING"));
+    assert!(second.trim_end().ends_with("Answer with a unified diff."));
+    assert!(!first.contains("'Ready'") && !second.contains("'Ready'"));
+}
+
+#[test]
+fn test_renumber_parts() {
+    let temp_dir = TempDir::new().unwrap();
+    let output = OutputSettings {
+        dir: Some(temp_dir.path().to_path_buf()),
+        ..Default::default()
+    };
+    // An earlier run of one part, then two appended parts
+    let first = create_context_file(1, 1, temp_dir.path(), &output).unwrap();
+    let second = create_context_file(2, 2, temp_dir.path(), &output).unwrap();
+    let third = create_context_file(3, 2, temp_dir.path(), &output).unwrap();
+    append_to_file(&first.path, "kept\n").unwrap();
+
+    let parts = vec![first.path.clone(), second.path.clone(), third.path.clone()];
+    renumber_parts(&parts).unwrap();
+
+    for (idx, part) in parts.iter().enumerate() {
+        let content = fs::read_to_string(part).unwrap();
+        assert!(content.lines().next().unwrap().ends_with(&format!("(Part {} of 3).", idx + 1)));
+    }
+    assert!(fs::read_to_string(&first.path).unwrap().ends_with("kept\n"));
+}
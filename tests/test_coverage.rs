@@ -0,0 +1,55 @@
+use llm_context_loader::coverage::{FileCoverage, parse_cobertura, parse_lcov};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_parse_lcov() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src").join("a.rs"), "").unwrap();
+
+    let lcov = "TN:\nSF:src/a.rs\nDA:1,3\nDA:2,0\nDA:3,1\nend_of_record\n";
+    let coverage = parse_lcov(lcov, &root);
+
+    assert_eq!(
+        coverage[&root.join("src").join("a.rs")],
+        FileCoverage {
+            lines_found: 3,
+            lines_hit: 2
+        }
+    );
+}
+
+#[test]
+fn test_parse_cobertura() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir(root.join("pkg")).unwrap();
+    fs::write(root.join("pkg").join("mod.py"), "").unwrap();
+
+    let xml = r#"<?xml version="1.0" ?>
+<coverage>
+  <sources><source>pkg</source></sources>
+  <packages><package><classes>
+    <class name="mod" filename="mod.py">
+      <lines>
+        <line number="1" hits="1"/>
+        <line number="2" hits="0"/>
+      </lines>
+    </class>
+    <class name="mod2" filename="mod.py">
+      <lines><line number="2" hits="4"/><line number="5" hits="0"/></lines>
+    </class>
+  </classes></package></packages>
+</coverage>"#;
+    let coverage = parse_cobertura(xml, &root);
+
+    assert_eq!(
+        coverage[&root.join("pkg").join("mod.py")],
+        FileCoverage {
+            lines_found: 3,
+            lines_hit: 2
+        }
+    );
+}
@@ -0,0 +1,57 @@
+use llm_context_loader::daemon::{DAEMON_OUTPUT_DIR, Daemon};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_daemon_builds_and_refreshes() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+    let daemon = Daemon::new(root.clone(), vec![".claude-context".to_string(), ".claude-summaries".to_string()], String::new()).unwrap();
+
+    let response = daemon.respond("/build?paths=main.rs&action=read");
+    assert_eq!(response.status, 200, "{}", response.body);
+    assert!(response.body.contains("\"files_included\":1"));
+    assert!(fs::read_to_string(root.join(DAEMON_OUTPUT_DIR).join("context-001.txt")).unwrap().contains("fn main() {}"));
+
+    // Builds go to an excluded directory, so they don't count as changes
+    assert!(!daemon.refresh().unwrap());
+    fs::write(root.join("lib.rs"), "pub fn lib() {}\n").unwrap();
+    assert!(daemon.refresh().unwrap());
+    assert!(daemon.respond("/status").body.contains("\"files\":2"));
+
+    assert_eq!(daemon.respond("/build?paths=../outside").status, 400);
+}
+
+#[test]
+fn test_daemon_refuses_paths_outside_start_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap().join("project");
+    let elsewhere = temp_dir.path().canonicalize().unwrap().join("elsewhere");
+    fs::create_dir_all(&root).unwrap();
+    fs::create_dir_all(&elsewhere).unwrap();
+    fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+    fs::write(elsewhere.join("context-001.txt"), "keep me\n").unwrap();
+    fs::write(elsewhere.join("secret.rs"), "fn key() {}\n").unwrap();
+    std::os::unix::fs::symlink(&elsewhere, root.join("link")).unwrap();
+    fs::write(
+        root.join("escape.json"),
+        r#"{"version":1,"entries":[{"path":"../elsewhere/secret.rs","action":"read"}]}"#,
+    )
+    .unwrap();
+    fs::write(elsewhere.join("outside.json"), r#"{"version":1,"entries":[]}"#).unwrap();
+    let daemon = Daemon::new(root.clone(), vec![".claude-context".to_string()], String::new()).unwrap();
+
+    for out in ["../elsewhere", "link", "link/new", elsewhere.to_str().unwrap()] {
+        let response = daemon.respond(&format!("/build?paths=main.rs&out={}", out));
+        assert_eq!(response.status, 400, "out={}: {}", out, response.body);
+    }
+    assert_eq!(fs::read_to_string(elsewhere.join("context-001.txt")).unwrap(), "keep me\n");
+
+    assert_eq!(daemon.respond("/build?manifest=escape.json").status, 400);
+    assert_eq!(daemon.respond("/build?manifest=link/outside.json").status, 400);
+
+    let response = daemon.respond("/build?paths=main.rs&out=build/ctx");
+    assert_eq!(response.status, 200, "{}", response.body);
+    assert!(root.join("build/ctx/context-001.txt").exists());
+}
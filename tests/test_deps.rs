@@ -0,0 +1,36 @@
+use llm_context_loader::deps::{direct_dependencies, used_paths};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_used_paths() {
+    let source = "use serde::{Deserialize, de::{self, Visitor as V}};\n\
+        // serde::Commented\n\
+        fn load() -> serde_json::Value { serde_json::from_str(\"{}\").unwrap() }\n";
+    let serde: Vec<String> = used_paths("serde", source).into_iter().collect();
+    assert_eq!(serde, ["serde::Deserialize", "serde::de", "serde::de::Visitor"]);
+    let json: Vec<String> = used_paths("serde_json", source).into_iter().collect();
+    assert_eq!(json, ["serde_json::Value", "serde_json::from_str"]);
+}
+
+#[test]
+fn test_direct_dependencies_use_locked_versions() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(
+        temp_dir.path().join("Cargo.toml"),
+        "[package]\nname = \"demo\"\n\n[dependencies]\nserde-json = { package = \"serde_json\", version = \"1\" }\nregex = \"1.9\"\n",
+    )
+    .unwrap();
+    fs::write(
+        temp_dir.path().join("Cargo.lock"),
+        "[[package]]\nname = \"serde_json\"\nversion = \"1.0.140\"\n",
+    )
+    .unwrap();
+
+    let deps = direct_dependencies(temp_dir.path()).unwrap();
+    let summary: Vec<(&str, &str, &str)> = deps
+        .iter()
+        .map(|d| (d.package.as_str(), d.ident.as_str(), d.version.as_str()))
+        .collect();
+    assert_eq!(summary, [("serde_json", "serde_json", "1.0.140"), ("regex", "regex", "1.9")]);
+}
@@ -0,0 +1,49 @@
+use llm_context_loader::ecosystem::{Ecosystem, detect_ecosystems, ecosystem_excludes};
+use llm_context_loader::file_analysis::{analyze_directory, is_skipped, matches_exclude};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+#[test]
+fn test_detect_ecosystems() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("Cargo.toml"), "[package]\n").unwrap();
+    fs::create_dir(temp_dir.path().join("web")).unwrap();
+    fs::write(temp_dir.path().join("web").join("package.json"), "{}").unwrap();
+
+    let ecosystems = detect_ecosystems(temp_dir.path());
+
+    assert_eq!(ecosystems, vec![Ecosystem::Rust, Ecosystem::Node]);
+
+    let excludes = ecosystem_excludes(&ecosystems);
+    assert!(excludes.contains(&"target/".to_string()));
+    assert!(excludes.contains(&"dist/".to_string()));
+}
+
+#[test]
+fn test_matches_exclude_components() {
+    assert!(matches_exclude(Path::new("/proj/web/dist"), "dist/"));
+    assert!(matches_exclude(Path::new("/proj/web/dist/app.js"), "dist/"));
+    assert!(!matches_exclude(Path::new("/proj/src/distance.rs"), "dist/"));
+    assert!(matches_exclude(Path::new("/proj/vendor/bundle/gem.rb"), "vendor/bundle/"));
+
+    // Patterns without a trailing slash keep substring semantics
+    assert!(matches_exclude(Path::new("/proj/src/distance.rs"), "dist"));
+}
+
+#[test]
+fn test_excludes_ignore_the_root_and_its_ancestors() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap().join("build").join("app");
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::create_dir_all(root.join("dist")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("dist/app.js"), "bundle\n").unwrap();
+
+    let excludes = vec!["build/".to_string(), "dist/".to_string(), "app".to_string()];
+    let dir_info = analyze_directory(&root, &excludes).unwrap();
+    assert!(dir_info.contains_key(&root.join("src")));
+    assert!(!dir_info.contains_key(&root.join("dist")));
+    assert!(!is_skipped(&root, &root, true, &excludes));
+    assert!(is_skipped(&root.join("dist"), &root, true, &excludes));
+}
@@ -0,0 +1,20 @@
+use llm_context_loader::encryption::{decrypt, encrypt, generate_key, is_encrypted, parse_key};
+
+#[test]
+fn test_encrypt_round_trip() {
+    let cipher = parse_key(&generate_key()).unwrap();
+    let sealed = encrypt(&cipher, "{\"entries\": {}}").unwrap();
+    assert!(is_encrypted(&sealed));
+    assert!(!sealed.contains("entries"));
+    assert_eq!(decrypt(&cipher, &sealed).unwrap(), "{\"entries\": {}}");
+
+    // Another key can't read it
+    let other = parse_key(&generate_key()).unwrap();
+    assert!(decrypt(&other, &sealed).is_err());
+}
+
+#[test]
+fn test_parse_key_checks_length() {
+    assert!(parse_key("c2hvcnQ=").is_err());
+    assert!(parse_key("not base64!").is_err());
+}
@@ -0,0 +1,52 @@
+use llm_context_loader::entrypoints::{entry_point_files, first_degree_imports};
+use llm_context_loader::file_analysis::analyze_directory;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_rust_entry_points_and_mods() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    let src = root.join("src");
+    fs::create_dir_all(src.join("net")).unwrap();
+    fs::create_dir_all(src.join("bin")).unwrap();
+    fs::write(src.join("main.rs"), "mod config;\npub(crate) mod net;\nfn main() {}\n").unwrap();
+    fs::write(src.join("config.rs"), "mod nested;\n").unwrap();
+    fs::write(src.join("net").join("mod.rs"), "").unwrap();
+    fs::write(src.join("unused.rs"), "").unwrap();
+    fs::write(src.join("bin").join("tool.rs"), "fn main() {}\n").unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let (entries, imports) = entry_point_files(&root, &dir_info);
+
+    assert_eq!(entries, vec![src.join("main.rs"), src.join("bin").join("tool.rs")]);
+    assert_eq!(imports, vec![src.join("config.rs"), src.join("net").join("mod.rs")]);
+}
+
+#[test]
+fn test_js_and_python_imports() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join("lib").join("util")).unwrap();
+    fs::write(
+        root.join("server.js"),
+        "import { a } from './lib/a';\nconst u = require(\"./lib/util\");\nimport x from 'express';\n",
+    )
+    .unwrap();
+    fs::write(root.join("lib").join("a.ts"), "").unwrap();
+    fs::write(root.join("lib").join("util").join("index.js"), "").unwrap();
+
+    assert_eq!(
+        first_degree_imports(&root.join("server.js"), &root),
+        vec![root.join("lib").join("a.ts"), root.join("lib").join("util").join("index.js")]
+    );
+
+    fs::create_dir_all(root.join("pkg")).unwrap();
+    fs::write(root.join("main.py"), "import os\nfrom pkg import helpers\nimport settings\n").unwrap();
+    fs::write(root.join("settings.py"), "").unwrap();
+    fs::write(root.join("pkg").join("__init__.py"), "").unwrap();
+    assert_eq!(
+        first_degree_imports(&root.join("main.py"), &root),
+        vec![root.join("pkg").join("__init__.py"), root.join("settings.py")]
+    );
+}
@@ -0,0 +1,39 @@
+use llm_context_loader::export::{ExportFormat, render_export, selected_files};
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::manifest::Manifest;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_selected_files_and_export() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src").join("lib.rs"), "pub fn lib() {}\n").unwrap();
+    fs::write(root.join("src").join("old.rs"), "fn old() {}\n").unwrap();
+    fs::write(root.join("it's.md"), "# notes\n").unwrap();
+
+    let mut manifest = Manifest::new();
+    manifest.record(&root.join("src").join("old.rs"), &root, "exclude");
+    manifest.record(&root.join("src"), &root, "summarize");
+    manifest.record(&root.join("it's.md"), &root, "read");
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let files = selected_files(&manifest, &root, &dir_info);
+
+    // The earlier exclude wins over the directory-wide summarize
+    let actions: Vec<(&str, &str)> = files
+        .iter()
+        .map(|f| (f.path.file_name().unwrap().to_str().unwrap(), f.action.as_str()))
+        .collect();
+    assert_eq!(actions, vec![("lib.rs", "summarize"), ("it's.md", "read")]);
+
+    let list = render_export(&files, &root, ExportFormat::List);
+    assert_eq!(list.lines().count(), 2);
+    assert!(list.starts_with(&root.join("src").join("lib.rs").display().to_string()));
+
+    let script = render_export(&files, &root, ExportFormat::Script);
+    assert!(script.starts_with("#!/bin/sh\n"));
+    assert!(script.contains("# action: summarize"));
+    assert!(script.contains(r"'it'\''s.md'"));
+}
@@ -1,4 +1,5 @@
-use llm_context_loader::file_analysis::{analyze_directory, is_binary, TOKENS_PER_BYTE};
+use llm_context_loader::file_analysis::{analyze_directory, is_binary, largest_subtrees, tree_totals};
+use llm_context_loader::tokenizer::count_tokens;
 use tempfile::TempDir;
 use std::fs::{self, File};
 use std::io::Write;
@@ -67,12 +68,33 @@ fn test_analyze_directory() {
     assert!(!dir_info.contains_key(&excluded_dir));
     
     // Check token calculations for text file
-    let text_file_size = text_content.len() as f64;
-    let expected_tokens = (text_file_size * TOKENS_PER_BYTE).ceil() as usize;
+    let expected_tokens = count_tokens(text_content);
     
     let main_dir_files = &main_dir_info.files;
     assert!(main_dir_files.iter().any(|f| f.path == text_path));
     
     let text_file_info = main_dir_files.iter().find(|f| f.path == text_path).unwrap();
     assert_eq!(text_file_info.tokens, expected_tokens);
-}
\ No newline at end of file
+}
+#[test]
+fn test_tree_totals() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join("big").join("deep")).unwrap();
+    fs::create_dir(root.join("small")).unwrap();
+    fs::write(root.join("top.txt"), "x".repeat(10)).unwrap();
+    fs::write(root.join("big").join("a.txt"), "x".repeat(100)).unwrap();
+    fs::write(root.join("big").join("deep").join("b.txt"), "x".repeat(100)).unwrap();
+    fs::write(root.join("small").join("c.txt"), "x".repeat(10)).unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let totals = tree_totals(&dir_info, &root);
+    assert_eq!(totals.files, 4);
+    assert_eq!(totals.dirs, 3);
+    assert_eq!(totals.tokens, 2 * count_tokens(&"x".repeat(10)) + 2 * count_tokens(&"x".repeat(100)));
+
+    let subtrees = largest_subtrees(&dir_info, &root, 1);
+    assert_eq!(subtrees.len(), 1);
+    assert_eq!(subtrees[0].0, root.join("big"));
+    assert_eq!(subtrees[0].1.files, 2);
+}
@@ -0,0 +1,195 @@
+use llm_context_loader::anchors::{format_anchor, parse_anchor, update_context_file};
+use llm_context_loader::context_files::{OutputSettings, create_context_file, finalize_context_files};
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::formats::{
+    OutputFormat, PartItem, json_entries, language_tag, parse_part, part_format, parts_format,
+};
+use llm_context_loader::processing::{Action, process_node};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::{TempDir, tempdir};
+
+fn text_part() -> String {
+    format!(
+        "The following content is a collection of files and directories (Part 1 of 1).
+Each file is clearly marked with a START and END tag.
+===== DIRECTORY CONTENT=====
+Source directory: /project
+Generated by: llm-context-loader 0.1.0
+
+## DIRECTORY: src
+
+{}
+===== FILE START: src/main.rs [blob 0123456789ab] =====
+```rs
+fn main() {{
+    let fence = r#\"
+```
+## DIRECTORY: not/a/directory
+\"#;
+}}
+```
+===== FILE END: src/main.rs =====
+
+{}
+# Summary of notes.txt
+A list of things to do.
+
+
+===== END OF FILE COLLECTION =====
+
+Total files included: 2
+",
+        format_anchor(Path::new("src/main.rs"), "0123456789ab", &Action::Read),
+        format_anchor(Path::new("notes.txt"), "ba9876543210", &Action::Summarize),
+    )
+}
+
+/// A project with a source file, written into one part in a format
+fn write_part(format: OutputFormat) -> (TempDir, PathBuf, PathBuf) {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    let source = "fn main() {\n    let fence = r#\"\n```\n## DIRECTORY: not/a/directory\n\"#;\n}\n";
+    fs::write(root.join("src/main.rs"), source).unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let output = OutputSettings {
+        dir: Some(root.join("out")),
+        format,
+        ..Default::default()
+    };
+    let mut context_file = create_context_file(1, 1, &root, &output).unwrap();
+    let result = process_node(
+        &root.join("src"),
+        &dir_info,
+        &mut context_file,
+        usize::MAX,
+        0,
+        &HashSet::new(),
+        &HashSet::new(),
+        Action::Read,
+        1,
+        &root,
+        &output,
+        None,
+    )
+    .unwrap();
+    finalize_context_files(&result.context_files, 1, None, format.formatter()).unwrap();
+    (temp_dir, root, context_file.path)
+}
+
+#[test]
+fn test_parse_text_part() {
+    let part = parse_part(&text_part());
+    assert_eq!(part.details[0], ("Source directory".to_string(), "/project".to_string()));
+    // The DIRECTORY line inside the file's content doesn't start an item
+    assert_eq!(part.items.len(), 3);
+    assert!(matches!(&part.items[0], PartItem::Directory { path, .. } if path == "src"));
+    let PartItem::File(read) = &part.items[1] else {
+        panic!("expected a file block");
+    };
+    assert_eq!(read.blob.as_deref(), Some("0123456789ab"));
+    assert_eq!(read.fence.as_deref(), Some("rs"));
+    assert!(read.content.starts_with("fn main()") && read.content.ends_with('}'));
+    assert!(read.content.contains("\n## DIRECTORY: not/a/directory\n"));
+    assert_eq!(part.closing, vec!["Total files included: 2".to_string()]);
+}
+
+#[test]
+fn test_markdown_part() {
+    let (_temp_dir, root, path) = write_part(OutputFormat::Markdown);
+    let markdown = fs::read_to_string(&path).unwrap();
+    assert_eq!(part_format(&markdown), OutputFormat::Markdown);
+    assert!(markdown.starts_with("The following content is a collection of files and directories (Part 1 of 1)."));
+    assert!(markdown.contains("\n## src/\n"));
+    assert!(markdown.contains("## Contents\n\n- [src/](#dir-"));
+    assert!(markdown.contains("\n  - [src/main.rs](#"));
+    // The content holds a line opening with three backticks, so the fence is longer
+    assert!(markdown.contains("\n````rust\nfn main() {"));
+    assert!(markdown.contains("\n## DIRECTORY: not/a/directory\n"));
+    assert!(markdown.contains("**End of file collection.**"));
+    assert_eq!(language_tag("yml"), "yaml");
+
+    // Anchors survive as comments, so the index still finds every file
+    assert_eq!(markdown.lines().filter_map(parse_anchor).count(), 1);
+
+    // Changed files are updated in place, in the part's format
+    fs::write(root.join("src/main.rs"), "fn main() {\n    println!(\"updated\");\n}\n").unwrap();
+    assert_eq!(update_context_file(&path, None).unwrap(), 1);
+    let updated = fs::read_to_string(&path).unwrap();
+    assert!(updated.contains("```rust\nfn main() {\n    println!(\"updated\");\n}\n```\n"));
+    assert!(!updated.contains("not/a/directory"));
+    assert!(updated.contains("**End of file collection.**"));
+    assert_eq!(updated.lines().filter_map(parse_anchor).count(), 1);
+}
+
+#[test]
+fn test_xml_part() {
+    let (_temp_dir, root, path) = write_part(OutputFormat::Xml);
+    let xml = fs::read_to_string(&path).unwrap();
+    assert_eq!(part_format(&xml), OutputFormat::Xml);
+    assert!(xml.contains("<directory path=\"src\" />"));
+    assert!(xml.contains("action=\"read\" blob=\""));
+    // Contents go in as written, without the text format's fence
+    assert!(xml.contains("<source>src/main.rs</source>\n<document_contents>\nfn main() {"));
+    assert!(xml.contains("\n## DIRECTORY: not/a/directory\n"));
+    assert_eq!(xml.matches("<document ").count(), 1);
+    assert!(xml.contains("</documents>\n<closing>\n"));
+    assert_eq!(xml.lines().filter_map(parse_anchor).count(), 1);
+
+    fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+    assert_eq!(update_context_file(&path, None).unwrap(), 1);
+    let updated = fs::read_to_string(&path).unwrap();
+    assert!(updated.contains("<document_contents>\nfn main() {}\n</document_contents>\n</document>\n"));
+    assert_eq!(updated.matches("<document ").count(), 1);
+}
+
+#[test]
+fn test_parts_format() {
+    let (_markdown_dir, _, markdown) = write_part(OutputFormat::Markdown);
+    let (_text_dir, _, text) = write_part(OutputFormat::Text);
+    assert_eq!(parts_format(std::slice::from_ref(&markdown)).unwrap(), OutputFormat::Markdown);
+    assert_eq!(parts_format(std::slice::from_ref(&text)).unwrap(), OutputFormat::Text);
+    assert!(parts_format(&[markdown, text]).is_err());
+}
+
+#[test]
+fn test_json_entries_join_split_blocks() {
+    let dir = tempdir().unwrap();
+    let header = |num: usize| {
+        format!(
+            "The following content is a collection of files and directories (Part {} of 2).\n===== DIRECTORY CONTENT=====\nSource directory: /project\n",
+            num
+        )
+    };
+    let first = dir.path().join("context-001.txt");
+    let second = dir.path().join("context-002.txt");
+    fs::write(
+        &first,
+        format!(
+            "{}\n\n{}\n===== FILE START: big.rs [blob 0123456789ab] =====\n```rs\nline 1\nline 2\n===== CONTINUED IN context-002.txt AT MARKER 0123456789ab-1 =====\n",
+            header(1),
+            format_anchor(Path::new("big.rs"), "0123456789ab", &Action::Read)
+        ),
+    )
+    .unwrap();
+    fs::write(
+        &second,
+        format!(
+            "{}\n\n===== CONTINUATION OF big.rs FROM context-001.txt AT MARKER 0123456789ab-1 =====\n(The first 1 lines repeat the end of the previous part.)\nline 2\nline 3\n```\n===== FILE END: big.rs =====\n\n{}\n# Summary of notes.txt\nThings to do.\n",
+            header(2),
+            format_anchor(Path::new("notes.txt"), "ba9876543210", &Action::Summarize)
+        ),
+    )
+    .unwrap();
+
+    let entries = json_entries(&[first, second]).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].content.as_deref(), Some("line 1\nline 2\nline 3"));
+    assert_eq!(entries[0].context_file, "context-001.txt");
+    assert_eq!(entries[1].summary.as_deref(), Some("Things to do."));
+    assert_eq!(entries[1].content, None);
+    assert_eq!(entries[1].context_file, "context-002.txt");
+}
@@ -0,0 +1,40 @@
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::gitattributes::linguist_suggestions;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_linguist_suggestions() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+
+    fs::create_dir(root.join("gen")).unwrap();
+    fs::create_dir(root.join("vendor")).unwrap();
+    fs::write(root.join("gen").join("api.rs"), "// generated\n").unwrap();
+    fs::write(root.join("vendor").join("lib.js"), "var x = 1;\n").unwrap();
+    fs::write(root.join("vendor").join("keep.js"), "var y = 2;\n").unwrap();
+    fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("schema.pb.go"), "package x\n").unwrap();
+    fs::write(
+        root.join(".gitattributes"),
+        "# linguist overrides\n\
+         gen/** linguist-generated\n\
+         *.pb.go linguist-generated=true\n\
+         vendor/** linguist-vendored\n\
+         vendor/keep.js -linguist-vendored\n",
+    )
+    .unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let suggestions = linguist_suggestions(&dir_info);
+
+    assert_eq!(suggestions[&root.join("gen").join("api.rs")].action, "exclude");
+    assert_eq!(suggestions[&root.join("schema.pb.go")].action, "exclude");
+    assert_eq!(suggestions[&root.join("vendor").join("lib.js")].action, "stats");
+    assert!(!suggestions.contains_key(&root.join("vendor").join("keep.js")));
+    assert!(!suggestions.contains_key(&root.join("main.rs")));
+
+    // A directory whose files all share a suggestion gets it too
+    assert_eq!(suggestions[&root.join("gen")].action, "exclude");
+    assert!(!suggestions.contains_key(&root.join("vendor")));
+}
@@ -0,0 +1,31 @@
+use llm_context_loader::config::load_config_file;
+use llm_context_loader::hooks::{HookEnv, run_hook};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_hooks_see_run_paths() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    let config_path = root.join("config.toml");
+    fs::write(
+        &config_path,
+        "[hooks]\npre_build = \"echo \\\"$LLM_CONTEXT_MANIFEST\\\" > seen.txt\"\npost_build = \"exit 3\"\n",
+    )
+    .unwrap();
+    let config = load_config_file(&config_path).unwrap();
+
+    let env = HookEnv {
+        start_dir: root.clone(),
+        manifest: root.join(".claude-manifest.json"),
+        ..Default::default()
+    };
+    run_hook("pre_build", config.hooks.pre_build.as_deref().unwrap(), &env).unwrap();
+    assert_eq!(
+        fs::read_to_string(root.join("seen.txt")).unwrap().trim(),
+        root.join(".claude-manifest.json").display().to_string()
+    );
+
+    let error = run_hook("post_build", config.hooks.post_build.as_deref().unwrap(), &env).unwrap_err();
+    assert!(error.to_string().contains("post_build hook failed"));
+}
@@ -0,0 +1,46 @@
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::include::{include_matcher, restrict_to_includes};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_restrict_to_includes() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join("src/parser")).unwrap();
+    fs::create_dir_all(root.join("docs")).unwrap();
+    fs::create_dir_all(root.join("assets")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("src/parser/lex.rs"), "fn lex() {}\n").unwrap();
+    fs::write(root.join("src/parser/grammar.txt"), "expr := term\n").unwrap();
+    fs::write(root.join("docs/guide.md"), "# Guide\n").unwrap();
+    fs::write(root.join("docs/deep.txt"), "notes\n").unwrap();
+    fs::write(root.join("assets/logo.txt"), "logo\n").unwrap();
+    fs::write(root.join("README.md"), "# Readme\n").unwrap();
+
+    let mut dir_info = analyze_directory(&root, &["lex.rs".to_string()]).unwrap();
+    let includes = include_matcher(&["src/**/*.rs".to_string(), "docs/*.md".to_string()]).unwrap();
+    let dropped = restrict_to_includes(&mut dir_info, &root, &includes);
+    assert_eq!(dropped, 4);
+
+    let files: Vec<_> = dir_info.values().flat_map(|info| info.files.iter()).map(|f| f.path.clone()).collect();
+    assert_eq!(files.len(), 2);
+    assert!(files.contains(&root.join("src/main.rs")));
+    assert!(files.contains(&root.join("docs/guide.md")));
+
+    // The excluded file stays out, and directories left empty aren't offered
+    assert!(!dir_info.contains_key(&root.join("src/parser")));
+    assert!(!dir_info.contains_key(&root.join("assets")));
+    assert!(!dir_info[&root.join("src")].subdirs.contains(&root.join("src/parser")));
+    assert_eq!(dir_info[&root].total_files, 0);
+    assert_eq!(dir_info[&root.join("docs")].total_files, 1);
+}
+
+#[test]
+fn test_include_patterns_without_slash_match_at_any_depth() {
+    let includes = include_matcher(&["*.md".to_string()]).unwrap();
+    assert!(includes.is_match("README.md"));
+    assert!(includes.is_match("docs/guide/intro.md"));
+    assert!(!includes.is_match("src/main.rs"));
+    assert!(include_matcher(&["src/[".to_string()]).is_err());
+}
@@ -0,0 +1,92 @@
+use llm_context_loader::anchors::file_hash;
+use llm_context_loader::context_files::{OutputSettings, create_context_file};
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::index::{FoldedRegion, build_index, load_index, write_index};
+use llm_context_loader::processing::{Action, process_node};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+#[test]
+fn test_index_lists_file_hashes() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::write(root.join("notes.txt"), "some notes\n").unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let out_dir = root.join("out");
+    let output = OutputSettings {
+        dir: Some(out_dir.clone()),
+        ..Default::default()
+    };
+    let mut context_file = create_context_file(1, 1, &root, &output).unwrap();
+    process_node(
+        &root.join("notes.txt"),
+        &dir_info,
+        &mut context_file,
+        100000,
+        0,
+        &HashSet::new(),
+        &HashSet::new(),
+        Action::Read,
+        1,
+        &root,
+        &output,
+        None,
+    )
+    .unwrap();
+
+    let hash = file_hash(&root.join("notes.txt"));
+    let content = fs::read_to_string(&context_file.path).unwrap();
+    assert!(content.contains(&format!("[blob {}] =====", hash)));
+
+    let index = build_index(std::slice::from_ref(&context_file.path)).unwrap();
+    assert_eq!(index.files.len(), 1);
+    assert_eq!(index.files[0].path, Path::new("notes.txt"));
+    assert_eq!(index.files[0].action, "read");
+    assert_eq!(index.files[0].hash, hash);
+    assert_eq!(index.files[0].part, "context-001.txt");
+
+    write_index(&out_dir, &[context_file.path]).unwrap();
+    assert_eq!(load_index(&out_dir.join("index.json")).unwrap(), index);
+}
+
+#[test]
+fn test_index_lists_folded_bodies() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    let source = "fn short() {\n    1\n}\n\nfn long() -> u32 {\n    let a = 1;\n    let b = 2;\n    let c = 3;\n    a + b + c\n}\n";
+    fs::write(root.join("lib.rs"), source).unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let output = OutputSettings {
+        dir: Some(root.join("out")),
+        fold_bodies: Some(3),
+        ..Default::default()
+    };
+    let mut context_file = create_context_file(1, 1, &root, &output).unwrap();
+    process_node(
+        &root.join("lib.rs"),
+        &dir_info,
+        &mut context_file,
+        100000,
+        0,
+        &HashSet::new(),
+        &HashSet::new(),
+        Action::Read,
+        1,
+        &root,
+        &output,
+        None,
+    )
+    .unwrap();
+
+    let content = fs::read_to_string(&context_file.path).unwrap();
+    assert!(content.contains("[bodies over 3 lines folded]"));
+    assert!(content.contains("fn short() {\n    1\n}"));
+    assert!(content.contains("fn long() -> u32 { … 4 lines: 6-9 … }\n"));
+
+    let index = build_index(std::slice::from_ref(&context_file.path)).unwrap();
+    assert_eq!(index.files[0].folds, vec![FoldedRegion { start: 6, end: 9 }]);
+}
@@ -0,0 +1,33 @@
+use llm_context_loader::launcher::{find_in, shell_quote};
+use std::env;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_find_in_path() {
+    let temp_dir = TempDir::new().unwrap();
+    let bin = temp_dir.path().join("bin");
+    let shims = temp_dir.path().join("shims");
+    fs::create_dir_all(&bin).unwrap();
+    fs::create_dir_all(&shims).unwrap();
+    fs::write(shims.join("claude.cmd"), "@echo off").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(shims.join("claude.cmd"), fs::Permissions::from_mode(0o755)).unwrap();
+        // Not executable, so not a match
+        fs::write(bin.join("claude"), "").unwrap();
+    }
+
+    let path = env::join_paths([&bin, &shims]).unwrap();
+    let extensions = vec![".exe".to_string(), ".cmd".to_string()];
+    assert_eq!(find_in("claude", &path, &extensions), Some(shims.join("claude.cmd")));
+    assert_eq!(find_in("claude", &path, &[]), None);
+    assert_eq!(find_in("missing", &path, &extensions), None);
+}
+
+#[test]
+fn test_shell_quote() {
+    assert_eq!(shell_quote("say 'Ready'", false), r"'say '\''Ready'\'''");
+    assert_eq!(shell_quote(r#"say "Ready""#, true), r#""say \"Ready\"""#);
+}
@@ -0,0 +1,38 @@
+use llm_context_loader::layout::ordered_manifest;
+use llm_context_loader::manifest::Manifest;
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+#[test]
+fn test_ordered_manifest_puts_overview_first_and_imports_adjacent() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("README.md"), "# Project\n").unwrap();
+    fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+    fs::write(root.join("src/main.rs"), "mod util;\nfn main() {}\n").unwrap();
+    fs::write(root.join("src/other.rs"), "pub fn other() {}\n").unwrap();
+    fs::write(root.join("src/util.rs"), "pub fn util() {}\n").unwrap();
+
+    let mut manifest = Manifest::new();
+    for (path, action) in [
+        ("src", "enter"),
+        ("src/main.rs", "read"),
+        ("src/other.rs", "summarize"),
+        ("Cargo.toml", "read"),
+        ("src/util.rs", "read"),
+        ("README.md", "read"),
+    ] {
+        manifest.record(&root.join(path), &root, action);
+    }
+
+    let ordered = ordered_manifest(&manifest, &root);
+    let paths: Vec<PathBuf> = ordered.entries.iter().map(|e| e.path.clone()).collect();
+    let expected: Vec<PathBuf> = ["README.md", "Cargo.toml", "src/main.rs", "src/util.rs", "src/other.rs"]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+    assert_eq!(paths, expected);
+    assert_eq!(ordered.entries[4].action, "summarize");
+}
@@ -0,0 +1,49 @@
+use llm_context_loader::ledger::{
+    CostGrouping, LedgerEntry, cost_report, estimate_cost, flush_ledger, load_ledger, record_call,
+};
+use tempfile::TempDir;
+
+fn entry(time: &str, run: &str, model: &str, input_tokens: usize, output_tokens: usize) -> LedgerEntry {
+    LedgerEntry {
+        time: time.to_string(),
+        run: run.to_string(),
+        model: model.to_string(),
+        input_tokens,
+        output_tokens,
+        cost_usd: estimate_cost(model, input_tokens, output_tokens),
+    }
+}
+
+#[test]
+fn test_cost_report() {
+    let entries = vec![
+        entry("2026-03-01T10:00:00+00:00", "run-a", "claude-haiku-4-5", 1_000_000, 0),
+        entry("2026-03-01T10:00:05+00:00", "run-a", "claude-haiku-4-5", 0, 200_000),
+        entry("2026-03-02T09:00:00+00:00", "run-b", "local-model", 500, 50),
+    ];
+
+    let by_day = cost_report(&entries, CostGrouping::Day);
+    assert_eq!(by_day.len(), 2);
+    let first = &by_day["2026-03-01"];
+    assert_eq!((first.calls, first.input_tokens, first.output_tokens), (2, 1_000_000, 200_000));
+    assert!((first.cost_usd - 2.0).abs() < 1e-9);
+
+    let by_model = cost_report(&entries, CostGrouping::Model);
+    assert_eq!(by_model["local-model"].unpriced_calls, 1);
+    assert_eq!(by_model["local-model"].cost_usd, 0.0);
+    assert_eq!(cost_report(&entries, CostGrouping::Run).len(), 2);
+}
+
+#[test]
+fn test_flush_ledger() {
+    let temp_dir = TempDir::new().unwrap();
+    record_call("claude-haiku-4-5", 100, 10);
+    record_call("claude-haiku-4-5", 200, 20);
+    assert_eq!(flush_ledger(temp_dir.path()).unwrap(), 2);
+    assert_eq!(flush_ledger(temp_dir.path()).unwrap(), 0);
+
+    let entries = load_ledger(temp_dir.path()).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].run, entries[1].run);
+    assert_eq!(entries[1].input_tokens, 200);
+}
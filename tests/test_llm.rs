@@ -0,0 +1,54 @@
+use llm_context_loader::config::SummarizerConfig;
+use llm_context_loader::llm::{
+    DEFAULT_MAX_RETRIES, RetryPolicy, is_transient_cli_error, parse_messages_response, parse_ollama_response,
+    parse_openai_response,
+};
+use std::time::Duration;
+
+#[test]
+fn test_parse_responses() {
+    let body = r#"{"content":[{"type":"text","text":"  Parses the config file.\n"}],"usage":{"input_tokens":812,"output_tokens":41}}"#;
+    let completion = parse_messages_response(body).unwrap();
+    assert_eq!(completion.text, "Parses the config file.");
+    assert_eq!((completion.input_tokens, completion.output_tokens), (812, 41));
+    assert!(parse_messages_response(r#"{"content":[],"usage":{}}"#).is_err());
+    assert!(parse_messages_response("overloaded").is_err());
+
+    let body = r#"{"choices":[{"message":{"role":"assistant","content":"Routes requests."}}],"usage":{"prompt_tokens":90,"completion_tokens":4}}"#;
+    let completion = parse_openai_response(body).unwrap();
+    assert_eq!(completion.text, "Routes requests.");
+    assert_eq!((completion.input_tokens, completion.output_tokens), (90, 4));
+
+    let body = r#"{"model":"qwen2.5-coder","response":"Reads the manifest.\n","done":true,"prompt_eval_count":230,"eval_count":12}"#;
+    let completion = parse_ollama_response(body).unwrap();
+    assert_eq!(completion.text, "Reads the manifest.");
+    assert_eq!((completion.input_tokens, completion.output_tokens), (230, 12));
+    assert!(parse_ollama_response(r#"{"error":"model not found"}"#).is_err());
+}
+
+#[test]
+fn test_retry_policy_from_config() {
+    let policy = RetryPolicy::from_config(&SummarizerConfig::default());
+    assert_eq!(policy.max_retries, DEFAULT_MAX_RETRIES);
+    assert_eq!(policy.backoff(0), Duration::from_secs(1));
+    assert_eq!(policy.backoff(2), Duration::from_secs(4));
+
+    let config = SummarizerConfig {
+        max_retries: Some(1),
+        backoff_multiplier: Some(1.5),
+        ..Default::default()
+    };
+    let policy = RetryPolicy::from_config(&config);
+    assert_eq!(policy.max_retries, 1);
+    assert_eq!(policy.backoff(1), Duration::from_millis(1500));
+}
+
+#[test]
+fn test_cli_errors_are_only_retried_when_transient() {
+    assert!(is_transient_cli_error("API Error: 529 {\"type\":\"overloaded_error\"}"));
+    assert!(is_transient_cli_error("Rate limit reached, please retry"));
+    assert!(is_transient_cli_error("Request timed out."));
+    assert!(!is_transient_cli_error("Invalid API key · Please run /login"));
+    assert!(!is_transient_cli_error("error: unknown option '--modle'"));
+    assert!(!is_transient_cli_error(""));
+}
@@ -0,0 +1,34 @@
+use llm_context_loader::lock::{LOCK_FILE_NAME, acquire_lock};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_acquire_lock() {
+    let temp_dir = TempDir::new().unwrap();
+    let lock_path = temp_dir.path().join(LOCK_FILE_NAME);
+
+    let lock = acquire_lock(temp_dir.path(), false).unwrap();
+    assert_eq!(
+        fs::read_to_string(&lock_path).unwrap(),
+        std::process::id().to_string()
+    );
+
+    // A second run is refused while the first holds the lock, unless forced
+    let err = acquire_lock(temp_dir.path(), false).unwrap_err();
+    assert!(err.to_string().contains("--force"));
+    let forced = acquire_lock(temp_dir.path(), true).unwrap();
+    drop(lock);
+    drop(forced);
+    assert!(!lock_path.exists());
+}
+
+#[test]
+fn test_stale_lock_is_replaced() {
+    if !std::path::Path::new("/proc").is_dir() {
+        return;
+    }
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join(LOCK_FILE_NAME), "4294967295").unwrap();
+
+    let _lock = acquire_lock(temp_dir.path(), false).unwrap();
+}
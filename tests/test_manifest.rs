@@ -0,0 +1,102 @@
+use llm_context_loader::context_files::{OutputSettings, create_context_file};
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::manifest::{
+    EntryOptions, MANIFEST_VERSION, Manifest, apply_manifest, load_manifest, save_manifest,
+};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_manifest_roundtrip() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().to_path_buf();
+
+    let mut manifest = Manifest::new();
+    manifest.record(&root.join("src"), &root, "enter");
+    manifest.record(&root.join("src").join("main.rs"), &root, "read");
+
+    let path = root.join("manifest.json");
+    save_manifest(&path, &manifest).unwrap();
+    let loaded = load_manifest(&path).unwrap();
+
+    assert_eq!(loaded, manifest);
+    assert_eq!(loaded.entries[1].path, std::path::Path::new("src/main.rs"));
+
+    // Manifests from newer versions are rejected
+    let mut newer = manifest.clone();
+    newer.version = MANIFEST_VERSION + 1;
+    save_manifest(&path, &newer).unwrap();
+    assert!(load_manifest(&path).is_err());
+}
+
+#[test]
+fn test_apply_manifest() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src").join("main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("src").join("skip.rs"), "fn skip() {}\n").unwrap();
+
+    let mut manifest = Manifest::new();
+    manifest.record(&root.join("src"), &root, "enter");
+    manifest.record(&root.join("src").join("main.rs"), &root, "read");
+    manifest.record(&root.join("src").join("skip.rs"), &root, "exclude");
+    manifest.record(&root.join("gone.rs"), &root, "read");
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let out_dir = root.join("out");
+    let output = OutputSettings {
+        dir: Some(out_dir.clone()),
+        ..Default::default()
+    };
+    let mut context_file = create_context_file(1, 1, &root, &output).unwrap();
+
+    let result = apply_manifest(
+        &manifest,
+        &dir_info,
+        &mut context_file,
+        100000,
+        1,
+        &root,
+        &output,
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(result.included_files.len(), 1);
+    let content = fs::read_to_string(&context_file.path).unwrap();
+    assert!(content.contains("## DIRECTORY: src"));
+    assert!(content.contains("fn main() {}"));
+    assert!(!content.contains("fn skip() {}"));
+}
+
+#[test]
+fn test_entry_options_are_replayed() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::write(root.join("lib.rs"), "pub fn keep() {}\n\npub fn drop_me() {}\n").unwrap();
+
+    let mut manifest = Manifest::new();
+    manifest.record(&root, &root, "enter");
+    manifest.record(&root.join("lib.rs"), &root, "read");
+    manifest.fill_options(&EntryOptions {
+        symbols: vec!["keep".to_string()],
+        ..Default::default()
+    });
+
+    // Options only go on the entries they apply to
+    assert!(manifest.entries[0].options.is_empty());
+    assert_eq!(manifest.entries[1].options["symbols"], "keep");
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let output = OutputSettings {
+        dir: Some(root.join("out")),
+        ..Default::default()
+    };
+    let mut context_file = create_context_file(1, 1, &root, &output).unwrap();
+    apply_manifest(&manifest, &dir_info, &mut context_file, 100000, 1, &root, &output, None).unwrap();
+
+    let content = fs::read_to_string(&context_file.path).unwrap();
+    assert!(content.contains("pub fn keep()"));
+    assert!(!content.contains("pub fn drop_me()"));
+}
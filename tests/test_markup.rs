@@ -0,0 +1,28 @@
+use llm_context_loader::markup::{is_markup, summarize_markup};
+use llm_context_loader::structured::sample_content;
+use std::path::Path;
+
+#[test]
+fn test_svg_outline() {
+    let svg = r#"<?xml version="1.0"?>
+<!-- drawn by hand -->
+<svg xmlns="http://www.w3.org/2000/svg" width="24" height="24" viewBox="0 0 24 24">
+  <g id="a"><path d="M0 0"/><path d="M1 1"/><circle r="2"/></g>
+  <g id="b"><path d="M2 2"/></g>
+  <rect width="1" height="1"/>
+</svg>"#;
+    let summary = summarize_markup(svg).unwrap();
+    assert_eq!(summary.root, "svg");
+    assert_eq!(summary.element_count(), 8);
+    assert_eq!(
+        summary.render(),
+        "Root: <svg width=\"24\" height=\"24\" viewBox=\"0 0 24 24\">\n\
+         Elements: 8 (path 3, g 2, circle 1, rect 1, svg 1)\n\
+         Outline:\n\
+         svg\n  g (x2)\n    path (x2)\n    circle\n  rect\n"
+    );
+
+    assert!(is_markup(Path::new("icons/logo.SVG")));
+    assert!(sample_content(Path::new("logo.svg"), svg).starts_with("Root: <svg"));
+    assert!(summarize_markup("plain text").is_none());
+}
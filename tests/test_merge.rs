@@ -0,0 +1,79 @@
+use llm_context_loader::anchors::{context_part_paths, format_anchor};
+use llm_context_loader::context_files::{
+    OutputSettings, PartNaming, append_to_file, create_context_file, finalize_context_files,
+};
+use llm_context_loader::formats::OutputFormat;
+use llm_context_loader::index::load_index;
+use llm_context_loader::merge::{MergeSummary, merge_bundles};
+use llm_context_loader::processing::Action;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Write a one-part bundle with a block per (path, hash)
+fn write_bundle(dir: &Path, source: &Path, files: &[(&str, &str)], format: OutputFormat) {
+    let output = OutputSettings {
+        dir: Some(dir.to_path_buf()),
+        format,
+        ..Default::default()
+    };
+    let part = create_context_file(1, 1, source, &output).unwrap();
+    for (path, hash) in files {
+        let anchor = format_anchor(Path::new(path), hash, &Action::Read);
+        let block = format.formatter().file(&format!("{}\nbody of {}\n", anchor, path), None);
+        append_to_file(&part.path, &block).unwrap();
+    }
+    finalize_context_files(&[part], files.len(), None, output.format.formatter()).unwrap();
+}
+
+#[test]
+fn test_merge_bundles() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    let (app, lib, merged) = (root.join("app"), root.join("lib"), root.join("merged"));
+    write_bundle(&app, Path::new("/src/app"), &[("main.rs", "aaaa"), ("util.rs", "bbbb")], OutputFormat::Text);
+    write_bundle(&lib, Path::new("/src/lib"), &[("util.rs", "bbbb"), ("lib.rs", "cccc")], OutputFormat::Text);
+
+    let summary = merge_bundles(&[app, lib], &merged, &PartNaming::default()).unwrap();
+    assert_eq!(summary, MergeSummary { parts: 2, files: 4, duplicates: 1 });
+
+    let parts = context_part_paths(&merged).unwrap();
+    assert_eq!(parts.len(), 2);
+    let second = fs::read_to_string(&parts[1]).unwrap();
+    assert!(second.contains("(Part 2 of 2)"));
+    assert!(second.contains("Source directory: /src/lib"));
+    assert!(second.contains("body of lib.rs"));
+    assert!(!second.contains("body of util.rs"));
+    assert_eq!(second.matches("END OF FILE COLLECTION").count(), 1);
+
+    // The dropped copy is still indexed, pointing at the kept block
+    let index = load_index(&merged.join("index.json")).unwrap();
+    let duplicate = index.files.last().unwrap();
+    assert_eq!(duplicate.path, PathBuf::from("util.rs"));
+    assert_eq!(duplicate.source, Some(PathBuf::from("/src/lib")));
+    assert_eq!(duplicate.part, "context-001.txt");
+}
+
+#[test]
+fn test_merge_markdown_bundles() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    let (app, lib, text, merged) = (root.join("app"), root.join("lib"), root.join("text"), root.join("merged"));
+    write_bundle(&app, Path::new("/src/app"), &[("main.rs", "aaaa"), ("util.rs", "bbbb")], OutputFormat::Markdown);
+    write_bundle(&lib, Path::new("/src/lib"), &[("util.rs", "bbbb"), ("lib.rs", "cccc")], OutputFormat::Markdown);
+    write_bundle(&text, Path::new("/src/text"), &[("text.rs", "dddd")], OutputFormat::Text);
+
+    // Blocks are kept as written, so bundles in different formats don't mix
+    assert!(merge_bundles(&[app.clone(), text], &merged, &PartNaming::default()).is_err());
+
+    let summary = merge_bundles(&[app, lib], &merged, &PartNaming::default()).unwrap();
+    assert_eq!(summary, MergeSummary { parts: 2, files: 4, duplicates: 1 });
+    let second = fs::read_to_string(&context_part_paths(&merged).unwrap()[1]).unwrap();
+    assert!(second.contains("(Part 2 of 2)"));
+    assert!(second.contains("body of lib.rs"));
+    assert!(!second.contains("body of util.rs"));
+    // The table of contents lists what the merged part holds
+    assert!(second.contains("- [lib.rs](#"));
+    assert!(!second.contains("- [util.rs](#"));
+    assert_eq!(second.matches("**End of file collection.**").count(), 1);
+}
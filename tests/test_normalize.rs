@@ -0,0 +1,63 @@
+use llm_context_loader::normalize::{
+    Normalization, collapse_whitespace, html_to_markdown, html_to_text, normalize_doc, normalize_text, read_text,
+};
+use llm_context_loader::processing::render_read_block;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+#[test]
+fn test_html_to_text() {
+    let html = "<html><head><style>p { color: red; }</style><script>init();</script></head>\
+        <body><h1>Usage</h1><!-- nav --><p>Call <code>run()</code> &amp; wait&#33;</p></body></html>";
+    assert_eq!(collapse_whitespace(&html_to_text(html)), "Usage\n\nCall run() & wait!\n");
+}
+
+#[test]
+fn test_copies_of_a_doc_normalize_equal() {
+    let fetched = "<div class=\"docblock\"><p>Parses   input.</p>\n<p>Since serde 1.0.188.</p></div>";
+    let vendored = "Parses input.\n\n\nSince serde 1.0.197.  \n";
+    assert_eq!(
+        normalize_doc(Path::new("docs.rs/serde/index.html"), fetched),
+        normalize_doc(Path::new("vendor/serde/README.md"), vendored)
+    );
+    assert_eq!(
+        normalize_doc(Path::new("README.md"), vendored),
+        "Parses input.\n\nSince serde <version>.\n"
+    );
+}
+
+#[test]
+fn test_html_to_markdown() {
+    let html = r#"<html><head><style>body { color: red; }</style><script>alert(1)</script></head>
+<body>
+<h1>Report</h1>
+<p>See <a href="https://example.com/docs">the <b>docs</b></a> and run <code>make</code>.</p>
+<ul><li>First &amp; foremost</li><li><em>Second</em></li></ul>
+<pre>fn main() {
+    println!("&lt;hi&gt;");
+}</pre>
+</body></html>"#;
+    assert_eq!(
+        html_to_markdown(html),
+        "# Report\n\nSee [the **docs**](https://example.com/docs) and run `make`.\n\n- First & foremost\n- *Second*\n\n```\nfn main() {\n    println!(\"<hi>\");\n}\n```\n"
+    );
+}
+
+#[test]
+fn test_output_normalization() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("notes.txt");
+    fs::write(&path, b"caf\xe9\r\nend").unwrap();
+
+    // Without normalization the content can't be read
+    let plain = render_read_block(&path, Path::new("notes.txt"), None, &[]);
+    assert!(plain.contains("Error reading file"));
+
+    let all = [Normalization::LineEndings, Normalization::FinalNewline, Normalization::Utf8];
+    let block = render_read_block(&path, Path::new("notes.txt"), None, &all);
+    assert!(block.ends_with("caf\u{fffd}\nend\n===== FILE END: notes.txt =====\n"));
+
+    assert_eq!(normalize_text("a\r\nb".to_string(), &[Normalization::LineEndings]), "a\nb");
+    assert_eq!(read_text(&path, true).unwrap(), "caf\u{fffd}\r\nend");
+}
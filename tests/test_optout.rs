@@ -0,0 +1,27 @@
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::optout::{DEFAULT_OPT_OUT_MARKER, remove_opted_out};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_marked_files_are_removed() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::write(root.join("secret.rs"), "// llm-context: ignore\nfn key() {}\n").unwrap();
+    fs::write(root.join("notes.md"), "---\ntitle: Notes\nllm-context: ignore\n---\nPrivate\n").unwrap();
+    fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+    let late = format!("{}// llm-context: ignore\n", "fn f() {}\n".repeat(40));
+    fs::write(root.join("late.rs"), late).unwrap();
+
+    let mut dir_info = analyze_directory(&root, &[]).unwrap();
+    let removed = remove_opted_out(&mut dir_info, DEFAULT_OPT_OUT_MARKER);
+    assert_eq!(removed, vec![root.join("notes.md"), root.join("secret.rs")]);
+
+    let info = &dir_info[&root];
+    assert_eq!(info.total_files, 2);
+    assert_eq!(info.tokens, info.files.iter().map(|f| f.tokens).sum::<usize>());
+
+    // An empty marker opts nothing out
+    let mut dir_info = analyze_directory(&root, &[]).unwrap();
+    assert!(remove_opted_out(&mut dir_info, "").is_empty());
+}
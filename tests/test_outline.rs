@@ -0,0 +1,31 @@
+use llm_context_loader::outline::{MAX_CARD_ITEMS, item_card_line, public_items};
+
+#[test]
+fn test_public_items() {
+    let rust = "pub fn run() {}\nfn private() {}\npub(crate) struct Hidden;\npub async fn fetch() {}\npub struct Config {\n    pub fn nested() {}\n}\npub trait Emit {}\n";
+    assert_eq!(
+        public_items("rs", rust),
+        vec!["fn run", "fn fetch", "struct Config", "trait Emit"]
+    );
+
+    let python = "import os\n\nclass Loader:\n    def load(self): ...\n\ndef _helper(): ...\nasync def main(): ...\n";
+    assert_eq!(public_items(".py", python), vec!["class Loader", "def main"]);
+
+    let ts = "export default function App() {}\nexport const VERSION = 1;\nconst local = 2;\nexport interface Props {}\n";
+    assert_eq!(
+        public_items("tsx", ts),
+        vec!["function App", "const VERSION", "interface Props"]
+    );
+
+    assert!(public_items("txt", "pub fn not_code() {}").is_empty());
+}
+
+#[test]
+fn test_item_card_line() {
+    assert_eq!(item_card_line(&[]), None);
+
+    let items: Vec<String> = (0..MAX_CARD_ITEMS + 3).map(|i| format!("fn f{}", i)).collect();
+    let line = item_card_line(&items).unwrap();
+    assert!(line.starts_with(&format!("Public items ({}): fn f0, fn f1", MAX_CARD_ITEMS + 3)));
+    assert!(line.ends_with(", +3 more"));
+}
@@ -0,0 +1,37 @@
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::priority::{overview_rank, priority_files};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+#[test]
+fn test_overview_rank() {
+    assert_eq!(overview_rank(Path::new("README.md")), Some(0));
+    assert!(overview_rank(Path::new("Cargo.toml")) < overview_rank(Path::new("LICENSE")));
+    assert_eq!(overview_rank(Path::new("main.rs")), None);
+}
+
+#[test]
+fn test_priority_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+
+    fs::write(root.join("LICENSE"), "MIT\n").unwrap();
+    fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+    fs::write(root.join("README.md"), "# Demo\n").unwrap();
+    fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+    fs::create_dir(root.join("docs")).unwrap();
+    fs::write(root.join("docs").join("README.md"), "# Nested\n").unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let files = priority_files(&root, &dir_info);
+
+    assert_eq!(
+        files,
+        vec![
+            root.join("README.md"),
+            root.join("Cargo.toml"),
+            root.join("LICENSE")
+        ]
+    );
+}
@@ -1,6 +1,12 @@
-use llm_context_loader::processing::{Action, process_directory_content};
-use llm_context_loader::context_files::ContextFile;
-use llm_context_loader::file_analysis::{DirInfo, FileInfo};
+use llm_context_loader::processing::{
+    Action, apply_cached_actions, parse_line_ranges, process_directory_content, render_file_block, summarize_files,
+    summary_targets,
+};
+use llm_context_loader::context_files::{ContextFile, OutputSettings, create_context_file};
+use llm_context_loader::config::rule_matcher;
+use llm_context_loader::file_analysis::{DirInfo, FileInfo, CLAUDE_TOKEN_LIMIT, analyze_directory};
+use llm_context_loader::optout::DEFAULT_OPT_OUT_MARKER;
+use llm_context_loader::tokenizer::count_tokens;
 use tempfile::TempDir;
 use std::collections::HashMap;
 use std::fs::{self, File};
@@ -12,6 +18,7 @@ fn test_action_parse_str() {
     assert_eq!(Action::parse_str("enter"), Some(Action::Enter));
     assert_eq!(Action::parse_str("summarize"), Some(Action::Summarize));
     assert_eq!(Action::parse_str("stats"), Some(Action::Stats));
+    assert_eq!(Action::parse_str("sample"), Some(Action::Sample));
     assert_eq!(Action::parse_str("invalid"), None);
 }
 
@@ -31,10 +38,12 @@ fn test_process_directory_content() {
     
     // Create directory info
     let mut dir_info = HashMap::new();
-    let mut info = DirInfo::default();
-    info.total_files = 2;
-    info.binary_files = 1;
-    info.tokens = 500;
+    let mut info = DirInfo {
+        total_files: 2,
+        binary_files: 1,
+        tokens: 500,
+        ..Default::default()
+    };
     
     // Add file info
     let file_info = FileInfo {
@@ -66,7 +75,7 @@ fn test_process_directory_content() {
         &Action::Stats,
         1,
         temp_dir.path(),
-        None
+        &OutputSettings::default()
     ).unwrap();
     
     // Verify result
@@ -90,4 +99,154 @@ fn test_action_from_str() {
     assert_eq!(Action::from_str("read").unwrap(), Action::Read);
     assert_eq!(Action::from_str("exclude").unwrap(), Action::Exclude);
     assert!(Action::from_str("invalid").is_err());
-}
\ No newline at end of file
+}
+#[test]
+fn test_cached_reads_over_file_cap_are_summarized() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::write(root.join("small.txt"), "short\n").unwrap();
+    fs::write(root.join("huge.txt"), "grown far too large\n".repeat(500)).unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let output = OutputSettings {
+        dir: Some(root.join("out")),
+        ..Default::default()
+    };
+    let mut context_file = create_context_file(1, 1, &root, &output).unwrap();
+    let cache: HashMap<_, _> = [
+        (root.join("small.txt"), "read".to_string()),
+        (root.join("huge.txt"), "read".to_string()),
+    ]
+    .into();
+
+    let (result, downgrades) =
+        apply_cached_actions(&dir_info, &mut context_file, 100000, 100, &cache, 1, &root, &output, None).unwrap();
+
+    assert_eq!(downgrades.len(), 1);
+    assert_eq!(downgrades[0].path, root.join("huge.txt"));
+    assert_eq!(downgrades[0].applied, Action::Summarize);
+    assert!(result.processed.contains(&root.join("small.txt")));
+
+    let content = fs::read_to_string(&context_file.path).unwrap();
+    assert!(content.contains("===== FILE START: "));
+    assert!(content.contains("# Summary of "));
+    assert!(!content.contains("grown far too large"));
+}
+
+#[test]
+fn test_line_ranges_action() {
+    assert_eq!(parse_line_ranges("400-450, 120-240,230-260,7"), Some(vec![(7, 7), (120, 260), (400, 450)]));
+    assert_eq!(parse_line_ranges("5-3"), None);
+    assert_eq!(parse_line_ranges("0-3"), None);
+
+    let action = Action::parse_str("lines:2-3,5-5").unwrap();
+    assert_eq!(action, Action::Lines(vec![(2, 3), (5, 5)]));
+    assert_eq!(action.spec(), "lines:2-3,5-5");
+
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("notes.txt");
+    fs::write(&path, "one\ntwo\nthree\nfour\nfive\nsix\n").unwrap();
+    let block = render_file_block(&path, std::path::Path::new("notes.txt"), &action, None).unwrap();
+    assert!(block.contains("[lines 2-3,5-5 of 6] =====\n"));
+    assert!(block.contains("… line 1 elided …\ntwo\nthree\n… line 4 elided …\nfive\n… line 6 elided …\n===== FILE END"));
+}
+
+#[test]
+fn test_paths_are_project_relative_unless_absolute() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/lib.rs"), "pub fn f() {}\n").unwrap();
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+
+    for absolute_paths in [false, true] {
+        let output = OutputSettings {
+            dir: Some(root.join(format!("out-{}", absolute_paths))),
+            absolute_paths,
+            ..Default::default()
+        };
+        let mut context_file = create_context_file(1, 1, &root, &output).unwrap();
+        let cache: HashMap<_, _> = [(root.join("src"), "read".to_string())].into();
+        apply_cached_actions(&dir_info, &mut context_file, 100000, 100000, &cache, 1, &root, &output, None).unwrap();
+
+        let content = fs::read_to_string(&context_file.path).unwrap();
+        let shown = if absolute_paths { root.join("src/lib.rs") } else { "src/lib.rs".into() };
+        assert!(content.contains(&format!("===== FILE START: {} [blob", shown.display())));
+        let shown = if absolute_paths { root.join("src") } else { "src".into() };
+        assert!(content.contains(&format!("## DIRECTORY: {}\n", shown.display())));
+    }
+}
+
+#[test]
+fn test_summarize_files_reports_every_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let files: Vec<_> = (0..5)
+        .map(|i| {
+            let path = temp_dir.path().join(format!("f{}.rs", i));
+            fs::write(&path, format!("fn f{}() {{}}\n", i)).unwrap();
+            path
+        })
+        .chain([temp_dir.path().join("missing.rs")])
+        .collect();
+
+    // No summarizer is configured here, so each file comes back with an error
+    let mut reported = Vec::new();
+    summarize_files(&files, 3, |path, result| {
+        let error = format!("{:#}", result.err().unwrap());
+        reported.push((path.to_path_buf(), error));
+    });
+    reported.sort();
+    assert_eq!(reported.len(), files.len());
+    assert!(reported[0].1.contains("no summarizer available"));
+    assert!(reported[5].1.starts_with("Failed to read"));
+}
+
+#[test]
+fn test_summary_targets_skip_opted_out_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("src/secret.rs"), "// llm-context: ignore\nfn key() {}\n").unwrap();
+    fs::write(root.join("config.json"), "{}\n").unwrap();
+    fs::write(root.join("README.md"), "# Readme\n").unwrap();
+
+    let mut dir_info = analyze_directory(&root, &[]).unwrap();
+    let targets = summary_targets(&root, &mut dir_info, &[], DEFAULT_OPT_OUT_MARKER);
+    assert_eq!(targets, vec![root.join("README.md"), root.join("src/main.rs")]);
+
+    let mut dir_info = analyze_directory(&root, &[]).unwrap();
+    let filters = [rule_matcher("src/**").unwrap()];
+    let targets = summary_targets(&root, &mut dir_info, &filters, "");
+    assert_eq!(targets, vec![root.join("src/main.rs"), root.join("src/secret.rs")]);
+}
+
+#[test]
+fn test_parts_stay_within_budget() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    let text: String = (0..3000).map(|i| format!("entry{} value\n", i)).collect();
+    for name in ["a.txt", "b.txt", "c.txt", "d.txt"] {
+        fs::write(root.join(name), &text).unwrap();
+    }
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let output = OutputSettings {
+        dir: Some(root.join("out")),
+        ..Default::default()
+    };
+    let mut context_file = create_context_file(1, 1, &root, &output).unwrap();
+    let cache: HashMap<_, _> = ["a.txt", "b.txt", "c.txt", "d.txt"]
+        .iter()
+        .map(|name| (root.join(name), "read".to_string()))
+        .collect();
+
+    let (result, _) =
+        apply_cached_actions(&dir_info, &mut context_file, 100000, 100000, &cache, 1, &root, &output, None).unwrap();
+
+    assert!(result.context_files.len() > 1);
+    for part in &result.context_files {
+        let tokens = count_tokens(&fs::read_to_string(&part.path).unwrap());
+        assert!(tokens <= CLAUDE_TOKEN_LIMIT, "part {} has {} tokens", part.file_num, tokens);
+    }
+}
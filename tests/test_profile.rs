@@ -0,0 +1,25 @@
+use llm_context_loader::profile::{Phase, profile_directory};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_profile_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("notes.md"), "# Notes\n").unwrap();
+    fs::create_dir(root.join("skip")).unwrap();
+    fs::write(root.join("skip").join("ignored.rs"), "fn x() {}\n").unwrap();
+
+    let report = profile_directory(&root, &["skip/".to_string()], false).unwrap();
+
+    assert_eq!(report.files.len(), 2);
+    assert_eq!(report.binary_files, 0);
+    for phase in Phase::ALL {
+        assert!(report.phases.contains_key(&phase), "{:?} not timed", phase);
+    }
+
+    let slowest = report.slowest_files(1);
+    assert_eq!(slowest.len(), 1);
+    assert!(slowest[0].total() >= report.files.iter().map(|f| f.total()).min().unwrap());
+}
@@ -0,0 +1,45 @@
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::recency::{collect_recency, dirty_files, recency_suggestions, sort_by_recency};
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn git(dir: &std::path::Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .env("GIT_COMMITTER_DATE", "2001-01-01T00:00:00")
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn test_recency_from_git() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::write(root.join("old.rs"), "fn old() {}\n").unwrap();
+    fs::write(root.join("active.rs"), "fn active() {}\n").unwrap();
+
+    git(&root, &["init", "-q"]);
+    git(&root, &["add", "."]);
+    git(&root, &["commit", "-q", "-m", "initial"]);
+    fs::write(root.join("active.rs"), "fn active() { todo!() }\n").unwrap();
+
+    let dirty = dirty_files(&root);
+    assert!(dirty.contains(&root.join("active.rs")));
+    assert!(!dirty.contains(&root.join("old.rs")));
+
+    let dir_info = analyze_directory(&root, &[".git".to_string()]).unwrap();
+    let recency = collect_recency(&root, &dir_info, &dirty);
+    let mut paths = vec![root.join("old.rs"), root.join("active.rs")];
+    sort_by_recency(&mut paths, &recency);
+    assert_eq!(paths[0], root.join("active.rs"));
+    assert_eq!(recency[&root], recency[&root.join("active.rs")]);
+
+    let suggestions = recency_suggestions(&dir_info, &dirty);
+    assert_eq!(suggestions[&root.join("active.rs")].action, "read");
+    assert_eq!(suggestions.len(), 1);
+}
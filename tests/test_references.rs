@@ -0,0 +1,68 @@
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::references::unreferenced_files;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_unreferenced_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    let src = root.join("src");
+    fs::create_dir_all(&src).unwrap();
+    fs::write(src.join("main.rs"), "mod used;\nfn main() {}\n").unwrap();
+    fs::write(src.join("used.rs"), "mod inner;\n").unwrap();
+    fs::create_dir_all(src.join("used")).unwrap();
+    fs::write(src.join("used").join("inner.rs"), "").unwrap();
+    fs::write(src.join("orphan.rs"), "fn dead() {}\n").unwrap();
+    fs::write(root.join("notes.md"), "# not source\n").unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    assert_eq!(unreferenced_files(&root, &dir_info), vec![src.join("orphan.rs")]);
+}
+
+#[test]
+fn test_tests_examples_and_scripts_are_roots() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    let files = [
+        ("src/lib.rs", "pub fn lib() {}\n"),
+        ("src/orphan.rs", "fn dead() {}\n"),
+        ("build.rs", "fn main() {}\n"),
+        ("tests/api.rs", "mod common;\n"),
+        ("tests/common/mod.rs", "pub fn setup() {}\n"),
+        ("examples/demo.rs", "fn main() {}\n"),
+        ("benches/speed.rs", "fn main() {}\n"),
+        ("tools/test_tool.py", "def test_it(): pass\n"),
+        ("tools/run.py", "if __name__ == \"__main__\":\n    pass\n"),
+        ("tools/helper.py", "def helper(): pass\n"),
+        ("web/app.test.js", "test('x', () => {});\n"),
+        ("web/vite.config.js", "export default {};\n"),
+        ("web/unused.js", "export const x = 1;\n"),
+    ];
+    for (path, content) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    assert_eq!(
+        unreferenced_files(&root, &dir_info),
+        vec![root.join("src/orphan.rs"), root.join("tools/helper.py"), root.join("web/unused.js")]
+    );
+}
+
+#[test]
+fn test_languages_with_unresolved_imports_are_skipped() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("tsconfig.json"), r#"{"compilerOptions":{"paths":{"@/*":["src/*"]}}}"#).unwrap();
+    fs::write(root.join("src/index.ts"), "import { x } from '@/util';\n").unwrap();
+    fs::write(root.join("src/util.ts"), "export const x = 1;\n").unwrap();
+    fs::write(root.join("src/tool.go"), "package tool\n").unwrap();
+    fs::write(root.join("src/orphan.py"), "x = 1\n").unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    assert_eq!(unreferenced_files(&root, &dir_info), vec![root.join("src/orphan.py")]);
+}
@@ -0,0 +1,53 @@
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::index::{ContextIndex, IndexEntry};
+use llm_context_loader::manifest::Manifest;
+use llm_context_loader::review::{apply_review_command, review_rows};
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+#[test]
+fn test_review_rows_and_commands() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/lib.rs"), "x".repeat(4000)).unwrap();
+    fs::write(root.join("notes.md"), "y".repeat(400)).unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let mut manifest = Manifest::new();
+    manifest.record(&root.join("src"), &root, "enter");
+    manifest.record(&root.join("src/lib.rs"), &root, "read");
+    manifest.record_with_options(
+        &root.join("notes.md"),
+        &root,
+        "stats",
+        [("requested".to_string(), "read".to_string())].into(),
+    );
+    let index = ContextIndex {
+        files: vec![IndexEntry {
+            path: PathBuf::from("src/lib.rs"),
+            citation: None,
+            action: "read".to_string(),
+            hash: "abc".to_string(),
+            part: "context-002.txt".to_string(),
+            source: None,
+            folds: Vec::new(),
+        }],
+    };
+
+    let rows = review_rows(&manifest, &root, &dir_info, &index);
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].path, PathBuf::from("src/lib.rs"));
+    assert_eq!(rows[0].part.as_deref(), Some("context-002.txt"));
+    assert_eq!(rows[1].tokens, 0);
+
+    apply_review_command(&mut manifest, &rows, "1 flip").unwrap();
+    assert_eq!(manifest.entries[1].action, "summarize");
+    apply_review_command(&mut manifest, &rows, "2 drop").unwrap();
+    assert_eq!(manifest.entries[2].action, "exclude");
+    assert!(manifest.entries[2].options.is_empty());
+
+    assert!(apply_review_command(&mut manifest, &rows, "3 read").is_err());
+    assert!(apply_review_command(&mut manifest, &rows, "1 enter").is_err());
+}
@@ -0,0 +1,38 @@
+use anyhow::anyhow;
+use llm_context_loader::anchors::format_anchor;
+use llm_context_loader::manifest::Manifest;
+use llm_context_loader::processing::Action;
+use llm_context_loader::run_summary::RunSummary;
+use llm_context_loader::summary_cache::CacheStats;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+#[test]
+fn test_run_summary_counts_and_json() {
+    let temp_dir = TempDir::new().unwrap();
+    let part = temp_dir.path().join("context_1.txt");
+    let read = format_anchor(Path::new("a.rs"), "1111", &Action::Read);
+    fs::write(&part, format!("Header\n\n{}\n{}\n", read, "x".repeat(400))).unwrap();
+
+    let root = Path::new("/project");
+    let mut manifest = Manifest::new();
+    manifest.record(&root.join("a.rs"), root, "read");
+    manifest.record(&root.join("b.rs"), root, "summarize");
+    manifest.record(&root.join("target"), root, "exclude");
+
+    let stats = CacheStats { hits: 2, misses: 1 };
+    let summary = RunSummary::new(vec![part.clone()], &manifest, 2, 150, stats, Vec::new());
+    assert_eq!(summary.status, "ok");
+    assert_eq!((summary.files_summarized, summary.files_excluded), (1, 1));
+    assert!(summary.tokens_by_action["read"] > 0);
+
+    let json: serde_json::Value = serde_json::from_str(&summary.to_json_line().unwrap()).unwrap();
+    assert_eq!(json["parts"][0], part.display().to_string());
+    assert_eq!(json["cache_hits"], 2);
+    assert!(!summary.to_json_line().unwrap().contains('\n'));
+
+    let failed = RunSummary::failed(&anyhow!("no such directory"));
+    assert_eq!(failed.status, "error");
+    assert_eq!(failed.errors, vec!["no such directory"]);
+}
@@ -0,0 +1,38 @@
+use llm_context_loader::index::{ContextIndex, IndexEntry};
+use llm_context_loader::serve::respond;
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+#[test]
+fn test_serves_indexed_files_and_line_ranges() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/lib.rs"), "line 1\nline 2\nline 3\nline 4\n").unwrap();
+    fs::write(root.join("secret.txt"), "not indexed\n").unwrap();
+
+    let index = ContextIndex {
+        files: vec![IndexEntry {
+            path: PathBuf::from("src/lib.rs"),
+            citation: None,
+            action: "summarize".to_string(),
+            hash: "abc".to_string(),
+            part: "context-001.txt".to_string(),
+            source: Some(root.to_path_buf()),
+            folds: Vec::new(),
+        }],
+    };
+    let elsewhere = PathBuf::from("/nonexistent");
+
+    let full = respond(&index, &elsewhere, "/file?path=src%2Flib.rs");
+    assert_eq!(full.status, 200);
+    assert_eq!(full.body, "line 1\nline 2\nline 3\nline 4\n");
+
+    let region = respond(&index, &elsewhere, "/file?path=src/lib.rs&lines=2-3");
+    assert_eq!(region.body, "line 2\nline 3\n");
+
+    assert_eq!(respond(&index, &elsewhere, "/file?path=secret.txt").status, 404);
+    assert_eq!(respond(&index, &elsewhere, "/file?path=src/lib.rs&lines=3-1").status, 400);
+    assert!(respond(&index, &elsewhere, "/index").body.contains("\"src/lib.rs\""));
+}
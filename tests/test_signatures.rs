@@ -0,0 +1,39 @@
+use llm_context_loader::signatures::{external_items, find_signature};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_external_items() {
+    let source = "use std::collections::{HashMap, hash_map::Entry};\nuse crate::config::Config;\n\
+        use tokio::select;\nfn f() { let m = HashMap::new(); let c: HashMap::<u8, u8> = HashMap::with_capacity(2); }\n";
+    assert_eq!(
+        external_items(source),
+        [
+            "std::collections::HashMap",
+            "std::collections::hash_map::Entry",
+            "tokio::select",
+            "std::collections::HashMap::new",
+            "std::collections::HashMap::with_capacity",
+        ]
+    );
+}
+
+#[test]
+fn test_find_signature() {
+    let temp_dir = TempDir::new().unwrap();
+    let lib = temp_dir.path().join("lib.rs");
+    fs::write(
+        &lib,
+        "pub fn entry() {}\n\npub struct Map<K, V> {\n    inner: Vec<(K, V)>,\n}\n\nimpl<K, V> Map<K, V> {\n    pub fn entry(\n        &mut self,\n        key: K,\n    ) -> Entry<'_, K, V> {\n        todo!()\n    }\n}\n\n#[macro_export]\nmacro_rules! select { () => {} }\n",
+    )
+    .unwrap();
+    let files = vec![lib];
+
+    assert_eq!(find_signature(&files, "demo::Map").unwrap(), "pub struct Map<K, V>");
+    assert_eq!(
+        find_signature(&files, "demo::Map::entry").unwrap(),
+        "pub fn entry( &mut self, key: K, ) -> Entry<'_, K, V>"
+    );
+    assert_eq!(find_signature(&files, "demo::select").unwrap(), "macro_rules! select");
+    assert_eq!(find_signature(&files, "demo::Missing"), None);
+}
@@ -0,0 +1,232 @@
+use llm_context_loader::skeleton::{annotate_cfg_gates, fold_bodies, rust_docs, skeleton, symbol_matcher, symbol_ranges};
+use std::collections::BTreeSet;
+
+#[test]
+fn test_python_skeleton_keeps_docstrings() {
+    let source = r#"class Greeter:
+    """Says hello."""
+
+    def greet(self, name: str) -> str:
+        """Greeting for a name."""
+        message = f"Hello {name}"
+        return message
+
+def main(
+    args,
+):
+    Greeter().greet(args[0])
+"#;
+    let expected = r#"class Greeter:
+    """Says hello."""
+
+    def greet(self, name: str) -> str:
+        """Greeting for a name."""
+        ...
+def main(
+    args,
+):
+    ...
+"#;
+    assert_eq!(skeleton("py", source).unwrap(), expected);
+}
+
+#[test]
+fn test_brace_skeleton_collapses_bodies() {
+    let source = r#"/// A point
+struct Point {
+    x: i32,
+}
+
+impl Point {
+    /// Distance from the origin
+    fn norm(&self) -> i32
+    {
+        let s = "{";
+        self.x.abs()
+    }
+}
+"#;
+    let expected = r#"/// A point
+struct Point {
+    x: i32,
+}
+
+impl Point {
+    /// Distance from the origin
+    fn norm(&self) -> i32
+    { ... }
+}
+"#;
+    assert_eq!(skeleton("rs", source).unwrap(), expected);
+
+    let js = "// Adds\nexport function add(a, b) {\n  return a + b;\n}\n";
+    assert_eq!(skeleton("js", js).unwrap(), "// Adds\nexport function add(a, b) { ... }\n");
+    assert_eq!(skeleton("md", "# Title\n"), None);
+}
+
+#[test]
+fn test_methods_stay_under_their_impl() {
+    let source = r#"impl<K, V> Extend<(K, V)> for Cache<K, V>
+where
+    K: Hash + Eq,
+    V: Fn(u32) -> u32,
+{
+    /// Insert every pair
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.map.insert(k, v);
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.map.keys()
+    }
+}
+"#;
+    let expected = r#"impl<K, V> Extend<(K, V)> for Cache<K, V>
+where
+    K: Hash + Eq,
+    V: Fn(u32) -> u32,
+{
+    /// Insert every pair
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) { ... }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> { ... }
+}
+"#;
+    assert_eq!(skeleton("rs", source).unwrap(), expected);
+
+    // The docs keep the whole header of an undocumented impl around its documented methods
+    let docs = rust_docs(source);
+    assert!(docs.starts_with("impl<K, V> Extend<(K, V)> for Cache<K, V>\nwhere\n    K: Hash + Eq,\n"));
+    assert!(docs.contains("    V: Fn(u32) -> u32,\n{\n    /// Insert every pair\n"));
+    assert!(!docs.contains("keys"));
+}
+
+#[test]
+fn test_rust_docs_keeps_documented_items() {
+    let source = r#"//! Geometry.
+
+use std::fmt;
+
+/// A point
+pub struct Point {
+    /// Horizontal
+    pub x: i32,
+    y: i32,
+}
+
+impl Point {
+    /// Builds one
+    pub fn new(
+        x: i32,
+    ) -> Self {
+        Point { x, y: 0 }
+    }
+
+    fn private(&self) {}
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.x)
+    }
+}
+"#;
+    let expected = r#"//! Geometry.
+
+/// A point
+pub struct Point {
+    /// Horizontal
+    pub x: i32,
+}
+
+impl Point {
+    /// Builds one
+    pub fn new(
+        x: i32,
+    ) -> Self { ... }
+}
+"#;
+    assert_eq!(rust_docs(source), expected);
+}
+
+#[test]
+fn test_fold_python_bodies() {
+    let source = "def short():\n    return 1\n\n\ndef long(x):\n    y = x + 1\n    z = y * 2\n    return z\n\n\nclass A:\n    pass\n";
+    let folded = fold_bodies("py", source, 3).unwrap();
+    assert_eq!(
+        folded,
+        "def short():\n    return 1\n\n\ndef long(x):\n    … 3 lines: 6-8 …\n\n\nclass A:\n    pass\n"
+    );
+    assert!(fold_bodies("toml", source, 3).is_none());
+}
+
+#[test]
+fn test_symbol_ranges_cover_docs_and_items() {
+    let source = r#"use std::fmt;
+
+/// Parses input
+#[derive(Debug)]
+pub struct Parser {
+    pos: usize,
+}
+
+impl Parser {
+    fn advance(&mut self) {
+        self.pos += 1;
+    }
+}
+
+fn helper() {}
+
+/// Entry point
+pub fn parse_all(input: &str) -> Parser {
+    Parser { pos: input.len() }
+}
+
+const LIMIT: usize = 4;
+"#;
+    let symbols = symbol_matcher(&["Parser*".to_string(), "parse_*".to_string()]).unwrap();
+    assert_eq!(symbol_ranges("rs", source, &symbols).unwrap(), vec![(3, 7), (9, 13), (17, 20)]);
+
+    let none = symbol_matcher(&["Missing".to_string()]).unwrap();
+    assert_eq!(symbol_ranges("rs", source, &none).unwrap(), vec![]);
+    assert!(symbol_ranges("txt", source, &symbols).is_none());
+}
+
+#[test]
+fn test_cfg_gates_in_skeletons() {
+    let source = r#"/// Always there
+pub fn core() {
+    work();
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    #[cfg(unix)]
+    pub fn to_socket() {
+        connect();
+    }
+}
+
+#[cfg(not(feature = "std"))]
+pub fn alloc_only() {}
+"#;
+    let outline = skeleton("rs", source).unwrap();
+    let annotated = annotate_cfg_gates(&outline, None);
+    assert!(annotated.contains("mod serde_impls { // cfg: feature = \"serde\"\n"));
+    assert!(annotated.contains("pub fn to_socket() { ... } // cfg: all(feature = \"serde\", unix)\n"));
+
+    let features: BTreeSet<String> = ["std".to_string()].into();
+    let filtered = annotate_cfg_gates(&outline, Some(&features));
+    assert!(filtered.contains("pub fn core() { ... }\n"));
+    assert!(!filtered.contains("serde_impls") && !filtered.contains("to_socket"));
+    assert!(!filtered.contains("alloc_only"));
+    assert!(filtered.ends_with("// 2 items gated off with features: std\n"));
+
+    let features: BTreeSet<String> = ["serde".to_string()].into();
+    let filtered = annotate_cfg_gates(&outline, Some(&features));
+    assert!(filtered.contains("pub fn to_socket() { ... } // cfg: all(feature = \"serde\", unix), depends on the target\n"));
+    assert!(filtered.contains("pub fn alloc_only() {} // cfg: not(feature = \"std\"), active\n"));
+}
@@ -0,0 +1,66 @@
+use llm_context_loader::cache::CacheMap;
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::manifest::Manifest;
+use llm_context_loader::snapshots::{
+    SNAPSHOT_FILE_NAME, Snapshot, SnapshotMap, changed_reads, load_snapshots, record_reads, save_snapshots,
+    summarize_change,
+};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_summarize_change() {
+    let old = "fn parse() {\n    1\n}\n\nfn render() {\n    2\n}\n\nfn old() {}\n";
+    let new = "fn parse() {\n    1\n}\n\nfn render() {\n    3\n}\n\nfn added() {}\n";
+    let change = summarize_change(&Snapshot::new("rs", old), &Snapshot::new("rs", new));
+    assert_eq!(change.lines_added, 2);
+    assert_eq!(change.lines_removed, 2);
+    assert_eq!(change.symbols, vec!["+added", "render", "-old"]);
+    assert_eq!(change.describe(), "+2 -2 lines; +added, render, -old");
+
+    // Methods sharing a name in different impls are compared one by one
+    let old = "impl A {\n    fn new() {}\n}\n\nimpl B {\n    fn new() {}\n}\n";
+    let new = "impl A {\n    fn new() { 1 }\n}\n\nimpl B {\n    fn new() {}\n}\n";
+    assert_eq!(summarize_change(&Snapshot::new("rs", old), &Snapshot::new("rs", new)).symbols, vec!["A", "new"]);
+
+    let plain = summarize_change(&Snapshot::new("txt", "a\nb\n"), &Snapshot::new("txt", "a\nc\nb\n"));
+    assert_eq!((plain.lines_added, plain.lines_removed), (1, 0));
+    assert!(plain.symbols.is_empty());
+}
+
+#[test]
+fn test_changed_reads() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/lib.rs"), "fn a() {}\n").unwrap();
+    fs::write(root.join("notes.txt"), "notes\n").unwrap();
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+
+    let mut manifest = Manifest::new();
+    manifest.record(&root.join("src"), &root, "read");
+    manifest.record(&root.join("notes.txt"), &root, "summarize");
+    let mut snapshots = SnapshotMap::new();
+    record_reads(&mut snapshots, &manifest, &root, &dir_info);
+    assert_eq!(snapshots.len(), 1);
+
+    // Only hashes of the source are kept on disk
+    save_snapshots(&root, &snapshots).unwrap();
+    assert!(!fs::read_to_string(root.join(SNAPSHOT_FILE_NAME)).unwrap().contains("fn a"));
+    assert_eq!(load_snapshots(&root).unwrap(), snapshots);
+
+    let cache: CacheMap = [
+        (root.join("src"), "read".to_string()),
+        (root.join("notes.txt"), "summarize".to_string()),
+    ]
+    .into();
+    assert!(changed_reads(&cache, &snapshots, &dir_info).is_empty());
+
+    fs::write(root.join("src/lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+    fs::write(root.join("notes.txt"), "changed\n").unwrap();
+    let changed = changed_reads(&cache, &snapshots, &dir_info);
+    assert_eq!(changed.len(), 1);
+    assert_eq!(changed[0].0, root.join("src"));
+    assert_eq!(changed[0].1[0].0, root.join("src/lib.rs"));
+    assert_eq!(changed[0].1[0].1.symbols, vec!["+b"]);
+}
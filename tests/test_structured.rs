@@ -0,0 +1,88 @@
+use llm_context_loader::structured::{
+    infer_json_schema, is_structured_file, sample_content, summarize_structured,
+};
+use std::path::Path;
+
+#[test]
+fn test_is_structured_file() {
+    assert!(is_structured_file(Path::new("config.yaml")));
+    assert!(is_structured_file(Path::new("deploy.YML")));
+    assert!(is_structured_file(Path::new("Cargo.toml")));
+    assert!(is_structured_file(Path::new("package.json")));
+    assert!(!is_structured_file(Path::new("main.rs")));
+}
+
+#[test]
+fn test_summarize_yaml_key_tree() {
+    let content = r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+spec:
+  replicas: 3
+  containers:
+    - name: app
+      image: nginx
+    - name: sidecar
+      image: envoy
+---
+apiVersion: v1
+kind: Service
+"#;
+
+    let summary = summarize_structured(Path::new("deploy.yaml"), content).unwrap();
+
+    assert!(summary.contains("--- document 1 of 2"));
+    assert!(summary.contains("- kind: string = \"Deployment\""));
+    assert!(summary.contains("- metadata: object (1 keys)"));
+    assert!(summary.contains("- replicas: integer = 3"));
+    assert!(summary.contains("- containers: array of object (2 items)"));
+    assert!(summary.contains("- image: string = \"nginx\""));
+}
+
+#[test]
+fn test_summarize_toml_key_tree() {
+    let content = r#"
+[package]
+name = "demo"
+version = "0.1.0"
+
+[dependencies]
+serde = "1.0"
+"#;
+
+    let summary = summarize_structured(Path::new("Cargo.toml"), content).unwrap();
+
+    assert!(summary.contains("- package: object (2 keys)"));
+    assert!(summary.contains("  - name: string = \"demo\""));
+    assert!(summary.contains("- dependencies: object (1 keys)"));
+}
+
+#[test]
+fn test_infer_json_schema() {
+    let content = r#"[
+        {"id": 1, "name": "Alice", "tags": ["admin"], "address": {"city": "Paris"}},
+        {"id": 2, "tags": [], "address": null}
+    ]"#;
+
+    let schema = infer_json_schema(content).unwrap();
+
+    assert!(schema.contains("- (root): array[<=2] of object"));
+    assert!(schema.contains("  - id: integer e.g. 1"));
+    assert!(schema.contains("  - name: string (in 1/2) e.g. \"Alice\""));
+    assert!(schema.contains("  - tags: array[<=1] of string"));
+    assert!(schema.contains("  - address: object|null"));
+    assert!(schema.contains("    - city: string e.g. \"Paris\""));
+}
+
+#[test]
+fn test_sample_content_falls_back_to_head() {
+    let content = (1..=30).map(|i| format!("{},row", i)).collect::<Vec<_>>().join("\n");
+
+    let sample = sample_content(Path::new("data.csv"), &content);
+
+    assert!(sample.starts_with("First 20 of 30 lines:"));
+    assert!(sample.contains("20,row"));
+    assert!(!sample.contains("21,row"));
+}
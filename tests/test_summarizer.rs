@@ -0,0 +1,41 @@
+use llm_context_loader::config::SummarizerConfig;
+use llm_context_loader::llm::{DEFAULT_OLLAMA_MODEL, OLLAMA_CONTEXT};
+use llm_context_loader::summarizer::{SummarizerKind, ollama_url, summary_provider};
+
+#[test]
+fn test_ollama_provider() {
+    let config = SummarizerConfig {
+        backend: Some(SummarizerKind::Ollama),
+        ..Default::default()
+    };
+    let provider = summary_provider(&config).unwrap();
+    assert_eq!(provider.model(), DEFAULT_OLLAMA_MODEL);
+    assert_eq!(provider.max_context(), OLLAMA_CONTEXT);
+
+    let config = SummarizerConfig {
+        backend: Some(SummarizerKind::Ollama),
+        model: Some("llama3.1:8b".to_string()),
+        context_window: Some(8192),
+        ..Default::default()
+    };
+    let provider = summary_provider(&config).unwrap();
+    assert_eq!(provider.model(), "llama3.1:8b");
+    assert_eq!(provider.max_context(), 8192);
+}
+
+#[test]
+fn test_ollama_url() {
+    // OLLAMA_HOST style addresses get a scheme
+    let config = SummarizerConfig {
+        ollama_url: Some("127.0.0.1:11500/".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(ollama_url(&config), "http://127.0.0.1:11500");
+}
+
+#[test]
+fn test_requirements_name_the_backend() {
+    assert!(SummarizerKind::Claude.requirement().contains("ANTHROPIC_API_KEY"));
+    assert_eq!(SummarizerKind::OpenAi.requirement(), "set OPENAI_API_KEY");
+    assert!(!SummarizerKind::OpenAi.requirement().contains("ANTHROPIC"));
+}
@@ -1,4 +1,5 @@
-use llm_context_loader::summary_cache::{SummaryCache, hash_content};
+use llm_context_loader::summary_cache::{SummaryCache, hash_content, load_summary_cache, save_summary_cache};
+use std::path::PathBuf;
 use tempfile::TempDir;
 use std::fs::{self, File};
 use std::io::Write;
@@ -56,4 +57,88 @@ fn test_summary_cache_cleanup() {
     // Verify file1 is still in cache but file2 is gone
     assert!(cache.get_summary(&file1_path, &content_hash).is_some());
     assert!(cache.get_summary(&file2_path, &content_hash).is_none());
-}
\ No newline at end of file
+}
+#[test]
+fn test_summary_export_matches_content_not_path() {
+    let source = TempDir::new().unwrap();
+    let original = source.path().join("lib.rs");
+    fs::write(&original, "fn shared() {}\n").unwrap();
+    let stale = source.path().join("old.rs");
+    fs::write(&stale, "fn changed() {}\n").unwrap();
+
+    let mut cache = SummaryCache::new();
+    cache.insert_summary(&original, &hash_content("fn shared() {}\n"), "Shared helper".to_string());
+    cache.insert_summary(&stale, &hash_content("fn before() {}\n"), "Outdated".to_string());
+
+    let export = cache.export(&[original.clone(), stale], source.path());
+    assert_eq!(export.summaries.len(), 1);
+    assert_eq!(export.summaries[0].path, PathBuf::from("lib.rs"));
+
+    // The same content under another name on another machine
+    let target = TempDir::new().unwrap();
+    let moved = target.path().join("src").join("helpers.rs");
+    fs::create_dir_all(moved.parent().unwrap()).unwrap();
+    fs::write(&moved, "fn shared() {}\n").unwrap();
+
+    let mut imported = SummaryCache::new();
+    let files = vec![moved.clone()];
+    assert_eq!(imported.import(&export, &files), 1);
+    assert_eq!(imported.get_summary(&moved, &hash_content("fn shared() {}\n")), Some("Shared helper"));
+    // Already current, so nothing more to import
+    assert_eq!(imported.import(&export, &files), 0);
+}
+
+#[test]
+fn test_lookups_are_counted() {
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = temp_dir.path().join("main.py");
+    fs::write(&file_path, "print('hi')\n").unwrap();
+    let hash = hash_content("print('hi')\n");
+
+    let mut cache = SummaryCache::new();
+    assert_eq!(cache.lookup_summary(&file_path, &hash), None);
+    cache.insert_summary(&file_path, &hash, "Prints a greeting".to_string());
+    assert_eq!(cache.unused(), 1);
+    assert_eq!(cache.lookup_summary(&file_path, &hash), Some("Prints a greeting"));
+    // Peeking is not a lookup
+    cache.get_summary(&file_path, &hash);
+
+    let stats = cache.run_stats();
+    assert_eq!((stats.hits, stats.misses, stats.hit_rate()), (1, 1, Some(50)));
+    assert_eq!(cache.unused(), 0);
+
+    // Totals survive a save and load; the run's counts start over
+    save_summary_cache(temp_dir.path(), &cache).unwrap();
+    let reloaded = load_summary_cache(temp_dir.path()).unwrap();
+    assert_eq!(reloaded.total_stats(), stats);
+    assert_eq!(reloaded.run_stats().lookups(), 0);
+}
+
+#[test]
+fn test_search_summaries() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::write(root.join("parser.rs"), "fn parse() {}").unwrap();
+    fs::write(root.join("lexer.rs"), "fn lex() {}").unwrap();
+
+    let mut cache = SummaryCache::new();
+    cache.insert_summary(&root.join("parser.rs"), &hash_content("fn parse() {}"), "Builds the AST from tokens.".to_string());
+    cache.insert_summary(&root.join("lexer.rs"), &hash_content("fn lex() {}"), "Splits input into Tokens.".to_string());
+
+    let found = cache.search("TOKENS", &root);
+    let paths: Vec<PathBuf> = found.iter().map(|(path, _)| path.clone()).collect();
+    assert_eq!(paths, vec![root.join("lexer.rs"), root.join("parser.rs")]);
+    assert_eq!(cache.search("lexer", &root).len(), 1);
+    assert_eq!(cache.entry(&root.join("parser.rs")).unwrap().summary, "Builds the AST from tokens.");
+
+    // Entries cached before paths were recorded are found by walking the project
+    save_summary_cache(&root, &cache).unwrap();
+    let cache_path = root.join(".claude-summaries");
+    let mut value: serde_json::Value = serde_json::from_str(&fs::read_to_string(&cache_path).unwrap()).unwrap();
+    for entry in value["entries"].as_object_mut().unwrap().values_mut() {
+        entry.as_object_mut().unwrap().remove("path");
+    }
+    fs::write(&cache_path, value.to_string()).unwrap();
+    let loaded = load_summary_cache(&root).unwrap();
+    assert_eq!(loaded.search("ast", &root)[0].0, root.join("parser.rs"));
+}
@@ -0,0 +1,41 @@
+use llm_context_loader::budget::BudgetPlan;
+use llm_context_loader::config::Config;
+use llm_context_loader::tasks::{BUILT_IN_TASKS, fill_preamble, task_preset};
+use std::path::Path;
+
+#[test]
+fn test_built_in_tasks() {
+    let config = Config::default();
+    for name in BUILT_IN_TASKS {
+        let task = task_preset(&config, name).unwrap();
+        BudgetPlan::parse(task.budget.as_deref().unwrap(), 100000, Path::new("/project")).unwrap();
+        let preamble = fill_preamble(task.preamble.as_deref().unwrap(), Path::new("/work/widget"));
+        assert!(preamble.contains("widget"));
+    }
+    assert!(task_preset(&config, "refactor").is_err());
+}
+
+#[test]
+fn test_config_overrides_task() {
+    let config: Config = toml::from_str(
+        r#"
+[tasks.review]
+max_tokens = 50000
+class_actions = { docs = "exclude" }
+
+[tasks.audit]
+budget = "source=100"
+"#,
+    )
+    .unwrap();
+
+    let review = task_preset(&config, "review").unwrap();
+    assert_eq!(review.max_tokens, Some(50000));
+    assert_eq!(review.class_actions["docs"], "exclude");
+    assert_eq!(review.class_actions["vendored"], "exclude");
+    assert!(review.preamble.is_some());
+
+    let audit = task_preset(&config, "audit").unwrap();
+    assert_eq!(audit.budget.as_deref(), Some("source=100"));
+    assert!(audit.preamble.is_none());
+}
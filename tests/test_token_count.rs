@@ -0,0 +1,62 @@
+use llm_context_loader::anchors::git_blob_hash;
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::token_count::{
+    TOKEN_CACHE_FILE_NAME, TokenCountCache, TokenCounter, apply_exact_counts, load_token_cache,
+    parse_count_response, save_token_cache,
+};
+use std::collections::HashSet;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_parse_count_response() {
+    assert_eq!(parse_count_response(r#"{"input_tokens": 1234}"#).unwrap(), 1234);
+    assert!(parse_count_response(r#"{"type": "error"}"#).is_err());
+    assert!(parse_count_response("not json").is_err());
+}
+
+#[test]
+fn test_token_cache_roundtrip() {
+    let temp_dir = TempDir::new().unwrap();
+
+    let mut cache = TokenCountCache::default();
+    cache.insert("model-a", "abc123", 42);
+    save_token_cache(temp_dir.path(), &cache).unwrap();
+
+    let loaded = load_token_cache(temp_dir.path()).unwrap();
+    assert_eq!(loaded.get("model-a", "abc123"), Some(42));
+    // Counts are specific to the model's tokenizer
+    assert_eq!(loaded.get("model-b", "abc123"), None);
+}
+
+#[test]
+fn test_old_token_caches_are_discarded() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join(TOKEN_CACHE_FILE_NAME), r#"{"entries":{"model-a:abc123":42}}"#).unwrap();
+    assert_eq!(load_token_cache(temp_dir.path()).unwrap().get("model-a", "abc123"), None);
+}
+
+#[test]
+fn test_exact_counts_use_and_prune_the_cache() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+    let hash = git_blob_hash(b"fn main() {}\n");
+
+    let mut cache = load_token_cache(&root).unwrap();
+    cache.insert("model-a", &hash, 5);
+    cache.insert("model-a", "deleted", 99);
+    cache.insert("model-b", &hash, 6);
+
+    // Everything is cached, so no request is made with this key
+    let counter = TokenCounter::new("not-a-key", "model-a");
+    let mut dir_info = analyze_directory(&root, &[]).unwrap();
+    assert_eq!(apply_exact_counts(&mut dir_info, &counter, &mut cache), 1);
+    assert_eq!(dir_info[&root].files[0].tokens, 5);
+    assert_eq!(dir_info[&root].tokens, 5);
+
+    assert_eq!(cache.get("model-a", "deleted"), None);
+    assert_eq!(cache.get("model-b", &hash), Some(6));
+    cache.retain_hashes(&HashSet::new());
+    assert_eq!(cache.get("model-a", &hash), None);
+}
@@ -0,0 +1,21 @@
+use llm_context_loader::tokenizer::{BpeTokenizer, Tokenizer, count_tokens};
+
+#[test]
+fn test_count_tokens() {
+    assert_eq!(count_tokens(""), 0);
+    assert_eq!(count_tokens("the quick brown fox"), 4);
+    assert_eq!(BpeTokenizer::cl100k().count("hello world"), 2);
+    // Special tokens are plain text here
+    assert!(count_tokens("<|endoftext|>") > 1);
+}
+
+#[test]
+fn test_counts_track_content_not_size() {
+    // Deep indentation costs far less than its bytes
+    let indented = format!("{}x\n", " ".repeat(32)).repeat(50);
+    assert!(count_tokens(&indented) < indented.len() / 4);
+
+    // Minified code is dense with punctuation
+    let minified = "function(a,b){return a&&b?a(b):!1}".repeat(20);
+    assert!(count_tokens(&minified) > minified.len() / 4);
+}
@@ -0,0 +1,28 @@
+use llm_context_loader::cache::CacheMap;
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::summary_cache::SummaryCache;
+use llm_context_loader::tree::render_tree;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_render_tree_with_tokens_and_markers() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/main.rs"), "x".repeat(400)).unwrap();
+    fs::write(root.join("README.md"), "y".repeat(40)).unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let mut cache = CacheMap::new();
+    cache.insert(root.join("src/main.rs"), "read".to_string());
+
+    let tree = render_tree(&root, &dir_info, &cache, &SummaryCache::new(), true);
+    let lines: Vec<&str> = tree.lines().collect();
+
+    let main_tokens = dir_info[&root.join("src")].tokens;
+    assert!(lines[0].ends_with(&format!("/ (~{} tokens)", main_tokens + dir_info[&root].tokens)));
+    assert_eq!(lines[1], format!("├── src/ (~{} tokens)", main_tokens));
+    assert_eq!(lines[2], format!("│   └── main.rs (~{} tokens) [read]", main_tokens));
+    assert!(lines[3].starts_with("└── README.md (~"));
+}
@@ -0,0 +1,77 @@
+use llm_context_loader::ui::{
+    COMMANDS, Style, action_for_choice, choice_for_action, color_enabled, edit_text, open_in_viewer, paint,
+    parse_timeout, prompt, prompt_timeout, scripted_answer, set_non_interactive,
+};
+use std::time::Duration;
+
+#[test]
+fn test_paint_without_color() {
+    // Test output is captured rather than written to a terminal
+    if !color_enabled() {
+        assert_eq!(paint("Tokens", Style::Header), "Tokens");
+        assert_eq!(paint(42, Style::Number), "42");
+    }
+}
+
+#[test]
+fn test_choice_for_action() {
+    assert_eq!(choice_for_action("read"), Some("1"));
+    assert_eq!(choice_for_action("sample"), Some("6"));
+    assert_eq!(choice_for_action("bogus"), None);
+}
+
+#[test]
+fn test_action_for_choice_round_trip() {
+    for action in ["read", "exclude", "summarize", "stats", "sample"] {
+        assert_eq!(action_for_choice(choice_for_action(action).unwrap()), Some(action));
+    }
+    // Enter commands apply to directories only and map to no file action
+    for key in ["3", "7"] {
+        assert!(COMMANDS.iter().any(|c| c.key == key && c.dirs_only));
+        assert_eq!(action_for_choice(key), None);
+    }
+}
+
+#[test]
+fn test_edit_text_with_editor_command() {
+    // Editor commands may carry arguments; sed stands in for an interactive editor
+    unsafe { std::env::set_var("VISUAL", "sed -i s/draft/final/") };
+    assert_eq!(edit_text("A draft summary\n").unwrap(), "A final summary\n");
+}
+
+#[test]
+fn test_open_in_viewer_uses_pager() {
+    // `test -f` stands in for a pager: it succeeds only for an existing file
+    unsafe { std::env::set_var("PAGER", "test -f") };
+    let file = tempfile::NamedTempFile::new().unwrap();
+    assert!(open_in_viewer(file.path()).is_ok());
+    assert!(open_in_viewer(std::path::Path::new("/nonexistent/file")).is_err());
+}
+
+#[test]
+fn test_parse_timeout() {
+    assert_eq!(parse_timeout("15s"), Ok(Duration::from_secs(15)));
+    assert_eq!(parse_timeout("2m"), Ok(Duration::from_secs(120)));
+    assert_eq!(parse_timeout("500ms"), Ok(Duration::from_millis(500)));
+    assert_eq!(parse_timeout("30"), Ok(Duration::from_secs(30)));
+    assert!(parse_timeout("15h").is_err());
+    assert!(parse_timeout("soon").is_err());
+}
+
+#[test]
+fn test_non_interactive_prompts_take_defaults() {
+    // No prompt may wait on stdin once the run is non-interactive
+    set_non_interactive();
+    assert_eq!(prompt("Use existing cache file? [Y/n]: ").unwrap(), "");
+    assert_eq!(prompt_timeout("Choice: ", None).unwrap(), None);
+    assert_eq!(prompt_timeout("Choice: ", Some(Duration::from_secs(60))).unwrap(), None);
+}
+
+#[test]
+fn test_scripted_answer_lines() {
+    assert_eq!(scripted_answer("read\n"), Some("read"));
+    assert_eq!(scripted_answer("  4   # summarize the fixtures\n"), Some("4"));
+    assert_eq!(scripted_answer("# the cache prompt comes first\n"), None);
+    assert_eq!(scripted_answer("   \n"), None);
+    assert_eq!(scripted_answer("-"), Some("-"));
+}
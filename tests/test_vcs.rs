@@ -0,0 +1,54 @@
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::vcs::{is_bare_repo, nested_repo_suggestions, nested_repos, vcs_excludes};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_metadata_and_bare_repos_are_not_walked() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join(".hg/store")).unwrap();
+    fs::write(root.join(".hg/store/data"), "history\n").unwrap();
+    fs::write(root.join(".hgignore"), "target\n").unwrap();
+    fs::create_dir_all(root.join("mirror.git/objects/ab")).unwrap();
+    fs::create_dir_all(root.join("mirror.git/refs/heads")).unwrap();
+    fs::write(root.join("mirror.git/HEAD"), "ref: refs/heads/main\n").unwrap();
+    fs::write(root.join("mirror.git/objects/ab/cdef"), "blob\n").unwrap();
+    fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+
+    assert!(is_bare_repo(&root.join("mirror.git")));
+    assert!(!is_bare_repo(&root));
+
+    let dir_info = analyze_directory(&root, &vcs_excludes()).unwrap();
+    assert!(!dir_info.contains_key(&root.join(".hg")));
+    assert!(!dir_info.keys().any(|d| d.starts_with(root.join("mirror.git"))));
+    // Only whole `.hg` components are skipped
+    let files: Vec<_> = dir_info[&root].files.iter().map(|f| f.path.clone()).collect();
+    assert!(files.contains(&root.join(".hgignore")));
+    assert!(files.contains(&root.join("main.rs")));
+}
+
+#[test]
+fn test_nested_repos_collapse() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join(".git")).unwrap();
+    fs::create_dir_all(root.join("libs/engine/.git")).unwrap();
+    fs::create_dir_all(root.join("libs/engine/sub/.jj")).unwrap();
+    fs::write(root.join("libs/engine/sub/lib.rs"), "pub fn f() {}\n").unwrap();
+    fs::create_dir_all(root.join("tools/widget")).unwrap();
+    // A submodule's .git is a file pointing at the superproject's metadata
+    fs::write(root.join("tools/widget/.git"), "gitdir: ../../.git/modules/widget\n").unwrap();
+    fs::write(root.join("tools/widget/main.rs"), "fn main() {}\n").unwrap();
+
+    let dir_info = analyze_directory(&root, &[".git/".to_string(), ".jj/".to_string()]).unwrap();
+    let repos = nested_repos(&root, &dir_info);
+    assert_eq!(
+        repos,
+        vec![(root.join("libs/engine"), "git"), (root.join("tools/widget"), "git")]
+    );
+
+    let suggestions = nested_repo_suggestions(&root, &dir_info);
+    assert_eq!(suggestions[&root.join("tools/widget")].action, "stats");
+    assert!(!suggestions.contains_key(&root));
+}
@@ -0,0 +1,48 @@
+use llm_context_loader::budget::Category;
+use llm_context_loader::file_analysis::analyze_directory;
+use llm_context_loader::vendored::{copyright_holder, vendor_dir, vendored_suggestions};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+#[test]
+fn test_vendor_dirs_and_holders() {
+    assert_eq!(vendor_dir(Path::new("third_party/zlib/inflate.c")), Some("third_party"));
+    assert_eq!(vendor_dir(Path::new("src/vendor.rs")), None);
+    assert_eq!(Category::of(Path::new("web/vendor/jquery.js")), Category::Vendored);
+
+    assert_eq!(
+        copyright_holder("MIT License\n\nCopyright (c) 2019-2023 Jane Doe\n").as_deref(),
+        Some("jane doe")
+    );
+    assert_eq!(
+        copyright_holder("/*\n * Copyright 2004 The Other Project Authors.\n */").as_deref(),
+        Some("the other project authors")
+    );
+    assert_eq!(copyright_holder("fn main() {}"), None);
+}
+
+#[test]
+fn test_vendored_suggestions() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join("vendor/lib")).unwrap();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("LICENSE"), "MIT License\n\nCopyright (c) 2024 Jane Doe\n").unwrap();
+    fs::write(root.join("vendor/lib/a.js"), "var a;\n").unwrap();
+    fs::write(root.join("src/own.rs"), "// Copyright 2024 Jane Doe\nfn a() {}\n").unwrap();
+    fs::write(root.join("src/md5.c"), "/* Copyright (C) 1999 Acme Corp. */\nint x;\n").unwrap();
+
+    let dir_info = analyze_directory(&root, &[]).unwrap();
+    let suggestions = vendored_suggestions(&root, &dir_info);
+
+    assert_eq!(suggestions[&root.join("vendor/lib/a.js")].action, "stats");
+    assert_eq!(suggestions[&root.join("vendor")].action, "stats");
+    assert_eq!(suggestions[&root.join("src/md5.c")].action, "summarize");
+    assert_eq!(
+        suggestions[&root.join("src/md5.c")].reason,
+        "third-party license header (acme corp)"
+    );
+    assert!(!suggestions.contains_key(&root.join("src/own.rs")));
+    assert!(!suggestions.contains_key(&root.join("src")));
+}